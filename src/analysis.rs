@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::grid::Grid;
+use crate::pattern::Pattern;
+use crate::rule::{LifeLike, Rule};
+use crate::spaceship::{self, Kind};
+
+/// The still lifes and oscillators that the ash census names, with one phase
+/// of each in plaintext format. Spaceships are taken from [`Kind`].
+const OBJECTS: [(&str, &str); 10] = [
+    ("block", "OO\nOO"),
+    ("beehive", ".OO\nO..O\n.OO"),
+    ("loaf", ".OO\nO..O\n.O.O\n..O"),
+    ("boat", "OO\nO.O\n.O"),
+    ("ship", "OO\nO.O\n.OO"),
+    ("tub", ".O\nO.O\n.O"),
+    ("pond", ".OO\nO..O\nO..O\n.OO"),
+    ("blinker", "OOO"),
+    ("toad", ".OOO\nOOO"),
+    ("beacon", "OO\nOO\n..OO\n..OO"),
+];
+
+/// The longest period of the objects that [`apgcode`] identifies.
+const MAX_PERIOD: usize = 60;
+
+/// The digits of extended Wechsler format.
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// The size of a phase of an object, along with the coordinates of its live
+/// cells relative to the top-left corner of its bounding box, in order.
+type Shape = ((usize, usize), Vec<(usize, usize)>);
+
+/// The result of searching for a predecessor of a pattern.
+pub enum Predecessor {
+    /// A state that evolves into the pattern in one generation. It is one cell
+    /// larger than the pattern on every side.
+    Found(Grid),
+    /// No state evolves into the pattern, so it is a Garden of Eden.
+    None,
+    /// The search gave up after trying the maximum number of partial states.
+    GaveUp,
+}
+
+/// Search for a state one generation before the given pattern, where the cells
+/// around the pattern are free to evolve into anything.
+///
+/// Only the cells within one cell of the pattern can influence it, so the
+/// search assigns them one at a time, backtracking as soon as any cell of the
+/// pattern can no longer come out right. It gives up after assigning
+/// `max_nodes` cells.
+pub fn find_predecessor(target: &Grid, rule: &LifeLike, max_nodes: u64) -> Predecessor {
+    let (width, height) = (target.width() + 2, target.height() + 2);
+    let mut search = Search { target, rule, width, cells: vec![None; width * height], nodes: 0, max_nodes };
+    match search.assign() {
+        Some(true) => {
+            let mut grid = Grid::new(width, height);
+            for (index, alive) in search.cells.iter().enumerate() {
+                grid.set(index % width, index / width, alive.unwrap_or(false));
+            }
+            Predecessor::Found(grid)
+        },
+        Some(false) => Predecessor::None,
+        None => Predecessor::GaveUp,
+    }
+}
+
+/// The state of a predecessor search.
+struct Search<'a> {
+    target: &'a Grid,
+    rule: &'a LifeLike,
+    /// The width of the predecessor, which is one cell larger than the target
+    /// on every side.
+    width: usize,
+    /// The state of each cell of the predecessor, if it has been assigned, in
+    /// row-major order.
+    cells: Vec<Option<bool>>,
+    nodes: u64,
+    max_nodes: u64,
+}
+
+impl Search<'_> {
+    /// Try both states for every cell in order, backtracking to the last cell
+    /// that still has a state left to try whenever one can't come out right.
+    /// The cells are assigned in a loop rather than by recursion, since there
+    /// is one level for each of them. Returns whether a predecessor was
+    /// found, or `None` if the search gave up.
+    fn assign(&mut self) -> Option<bool> {
+        let mut index = 0;
+        while index < self.cells.len() {
+            // Dead cells come first, so that sparser predecessors are found.
+            let next = match self.cells[index] {
+                None => Some(false),
+                Some(false) => Some(true),
+                Some(true) => None,
+            };
+            let Some(alive) = next else {
+                self.cells[index] = None;
+                match index.checked_sub(1) {
+                    Some(previous) => index = previous,
+                    None => return Some(false),
+                }
+                continue;
+            };
+            self.nodes += 1;
+            if self.nodes > self.max_nodes {
+                return None;
+            }
+            self.cells[index] = Some(alive);
+            if self.consistent(index % self.width, index / self.width) {
+                index += 1;
+            }
+        }
+        Some(true)
+    }
+
+    /// Returns whether every cell of the target whose neighborhood includes
+    /// the given cell of the predecessor can still come out right.
+    fn consistent(&self, x: usize, y: usize) -> bool {
+        // The target cell at (tx, ty) is at (tx + 1, ty + 1) in the
+        // predecessor, so the ones around (x, y) are up to two cells up and
+        // to the left of it.
+        let columns = x.saturating_sub(2)..(x + 1).min(self.target.width());
+        let rows = y.saturating_sub(2)..(y + 1).min(self.target.height());
+        rows.flat_map(|ty| columns.clone().map(move |tx| (tx, ty))).all(|(tx, ty)| self.possible(tx, ty))
+    }
+
+    /// Returns whether some assignment of the predecessor's unassigned cells
+    /// around the given cell of the target would give it its state.
+    fn possible(&self, tx: usize, ty: usize) -> bool {
+        let (mut alive_neighbors, mut unknown_neighbors) = (0, 0);
+        for y in ty..ty + 3 {
+            for x in tx..tx + 3 {
+                if (x, y) == (tx + 1, ty + 1) {
+                    continue;
+                }
+                match self.cells[y * self.width + x] {
+                    Some(true) => alive_neighbors += 1,
+                    Some(false) => {},
+                    None => unknown_neighbors += 1,
+                }
+            }
+        }
+        let center = self.cells[(ty + 1) * self.width + tx + 1];
+        let wanted = self.target.get(tx, ty);
+        [false, true].into_iter().filter(|&alive| center.is_none_or(|center| center == alive)).any(|center| {
+            (alive_neighbors..=alive_neighbors + unknown_neighbors)
+                .any(|count| self.rule.next_state_for_count(center, count) == wanted)
+        })
+    }
+}
+
+/// Count the objects that the live cells of a grid have settled into, by their
+/// [`apgcode`] under the given rule. Cells are grouped into objects in the same
+/// way as for finding spaceships, and groups that don't settle on their own
+/// are counted as `other`. The counts are ordered from the most common object.
+pub fn census(grid: &Grid, rule: &dyn Rule) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut visited = vec![false; grid.width() * grid.height()];
+    for start in 0..visited.len() {
+        let (x, y) = (start % grid.width(), start / grid.width());
+        if visited[start] || !grid.get(x, y) {
+            continue;
+        }
+        let cells = spaceship::group(grid, (x, y), &mut visited);
+        let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        let width = cells.iter().map(|&(x, _)| x - min_x + 1).max().unwrap_or(0);
+        let height = cells.iter().map(|&(_, y)| y - min_y + 1).max().unwrap_or(0);
+        let mut object = Grid::new(width, height);
+        for (x, y) in cells {
+            object.set(x - min_x, y - min_y, true);
+        }
+        *counts.entry(apgcode(&object, rule).unwrap_or_else(|| "other".to_string())).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Returns the apgcode of the object formed by the live cells of a grid, which
+/// is how apgsearch and Catagolue name it: `xs` and its population for a still
+/// life, `xp` and its period for an oscillator, or `xq` and its period for a
+/// spaceship, then the shortest encoding in extended Wechsler format of any of
+/// its phases in any orientation. The block is `xs4_33`, for example, and the
+/// glider `xq4_153`. Objects that don't repeat on their own within 60
+/// generations have no code.
+pub fn apgcode(object: &Grid, rule: &dyn Rule) -> Option<String> {
+    let ((x0, y0), (width, height)) = object.bounds()?;
+    // Nothing moves faster than a cell a generation, so this leaves room for a
+    // spaceship to travel through a full period without reaching the edge.
+    let margin = MAX_PERIOD + 2;
+    let mut grid = object.crop(x0, y0, width, height).resized(width + 2 * margin, height + 2 * margin, margin, margin);
+    let (origin, first) = shape(&grid)?;
+    let mut phases = vec![first];
+    for period in 1..=MAX_PERIOD {
+        grid = grid.step(rule);
+        let (position, phase) = shape(&grid)?;
+        if phase != phases[0] {
+            phases.push(phase);
+            continue;
+        }
+        let prefix = match (position == origin, period) {
+            (false, _) => format!("xq{period}"),
+            (true, 1) => format!("xs{}", object.population()),
+            (true, _) => format!("xp{period}"),
+        };
+        let code = phases
+            .iter()
+            .flat_map(orientations)
+            .map(|phase| wechsler(&phase))
+            .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))?;
+        return Some(format!("{prefix}_{code}"));
+    }
+    None
+}
+
+/// Returns the common name of the object with the given apgcode in Conway's
+/// Game of Life, if it is one of the most common ones.
+pub fn common_name(apgcode: &str) -> Option<&'static str> {
+    static NAMES: OnceLock<HashMap<String, &'static str>> = OnceLock::new();
+    NAMES.get_or_init(build_names).get(apgcode).copied()
+}
+
+/// Work out the apgcode of each object with a common name.
+fn build_names() -> HashMap<String, &'static str> {
+    let spaceships = [Kind::Glider, Kind::Lwss, Kind::Mwss, Kind::Hwss].map(|kind| (kind.into(), kind.pattern()));
+    let others = OBJECTS.map(|(name, plaintext)| {
+        (name, Pattern::parse_plaintext(plaintext).expect("known objects are valid patterns"))
+    });
+    spaceships
+        .into_iter()
+        .chain(others)
+        .map(|(name, pattern)| (apgcode(&pattern.cells, &LifeLike::LIFE).expect("known objects repeat"), name))
+        .collect()
+}
+
+/// Returns the position of the grid's live cells along with their shape.
+fn shape(grid: &Grid) -> Option<((usize, usize), Shape)> {
+    let ((x0, y0), (width, height)) = grid.bounds()?;
+    let cells =
+        (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).filter(|&(x, y)| grid.get(x0 + x, y0 + y)).collect();
+    Some(((x0, y0), ((width, height), cells)))
+}
+
+/// Returns the shape in each of its eight orientations.
+fn orientations(shape: &Shape) -> impl Iterator<Item = Shape> + '_ {
+    let &((width, height), ref cells) = shape;
+    (0..8).map(move |orientation| {
+        let (transposed, flip_x, flip_y) = (orientation & 4 != 0, orientation & 2 != 0, orientation & 1 != 0);
+        let size = if transposed { (height, width) } else { (width, height) };
+        let mut cells: Vec<(usize, usize)> = cells
+            .iter()
+            .map(|&(x, y)| {
+                let (x, y) = if transposed { (y, x) } else { (x, y) };
+                (if flip_x { size.0 - 1 - x } else { x }, if flip_y { size.1 - 1 - y } else { y })
+            })
+            .collect();
+        cells.sort_unstable_by_key(|&(x, y)| (y, x));
+        (size, cells)
+    })
+}
+
+/// Encode a shape in extended Wechsler format. The rows are split into strips
+/// five cells tall, separated by `z`, and each column of a strip is a digit
+/// with the top cell in its lowest bit. Runs of empty columns are shortened to
+/// `0`, `w`, `x`, or `y` followed by the digit of how many more than four
+/// there are, and are left off the end of each strip.
+fn wechsler(shape: &Shape) -> String {
+    let &((width, height), ref cells) = shape;
+    let mut alive = vec![false; width * height];
+    for &(x, y) in cells {
+        alive[y * width + x] = true;
+    }
+    let mut code = String::new();
+    for strip in 0..height.div_ceil(5) {
+        if strip > 0 {
+            code.push('z');
+        }
+        let mut empty = 0;
+        for x in 0..width {
+            let column = (0..5)
+                .filter(|row| strip * 5 + row < height && alive[(strip * 5 + row) * width + x])
+                .fold(0, |column, row| column | 1 << row);
+            if column == 0 {
+                empty += 1;
+                continue;
+            }
+            while empty > 39 {
+                code.push_str("yz");
+                empty -= 39;
+            }
+            match empty {
+                0 => {},
+                1 => code.push('0'),
+                2 => code.push('w'),
+                3 => code.push('x'),
+                _ => {
+                    code.push('y');
+                    code.push(DIGITS[empty - 4] as char);
+                },
+            }
+            empty = 0;
+            code.push(DIGITS[column] as char);
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Pattern;
+
+    /// Returns the apgcode of a pattern given in plaintext, with its rows
+    /// separated by `/`, in Conway's Game of Life.
+    fn code(rows: &str) -> Option<String> {
+        let pattern = Pattern::parse_plaintext(&rows.replace('/', "\n")).unwrap();
+        apgcode(&pattern.cells, &LifeLike::LIFE)
+    }
+
+    #[test]
+    fn still_lifes() {
+        assert_eq!(code("OO/OO").as_deref(), Some("xs4_33"));
+        assert_eq!(code(".O./O.O/.O.").as_deref(), Some("xs4_252"));
+        assert_eq!(code("OO./O.O/.O.").as_deref(), Some("xs5_253"));
+        assert_eq!(code(".OO./O..O/.OO.").as_deref(), Some("xs6_696"));
+        assert_eq!(code(".OO./O..O/.O.O/..O.").as_deref(), Some("xs7_2596"));
+    }
+
+    #[test]
+    fn oscillators() {
+        assert_eq!(code("OOO").as_deref(), Some("xp2_7"));
+        assert_eq!(code("O/O/O").as_deref(), Some("xp2_7"));
+        assert_eq!(code(".OOO/OOO.").as_deref(), Some("xp2_7e"));
+    }
+
+    #[test]
+    fn spaceships() {
+        assert_eq!(code(".O./..O/OOO").as_deref(), Some("xq4_153"));
+        assert_eq!(code("O.O/.OO/.O.").as_deref(), Some("xq4_153"));
+        assert_eq!(code(".O..O/O..../O...O/OOOO.").as_deref(), Some("xq4_6frc"));
+    }
+
+    #[test]
+    fn objects_that_keep_changing_have_no_code() {
+        assert_eq!(code(".OO/OO./.O."), None);
+    }
+}
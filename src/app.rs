@@ -0,0 +1,1880 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cgol2::grid::Grid;
+use cgol2::pattern::Pattern;
+use cgol2::rule::{Registry, Rule};
+use cgol2::spaceship::{self, Spaceship};
+use clap::ValueEnum;
+use notify::RecommendedWatcher;
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use rand::seq::SliceRandom;
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::{
+    ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, StartCause, VirtualKeyCode,
+    WindowEvent,
+};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::{Fullscreen, Window, WindowBuilder};
+
+use crate::browser::Browser;
+use crate::camera::{Camera, Follow};
+use crate::console::{self, Console, Line};
+use crate::delta::Delta;
+use crate::error::Error;
+use crate::image::{FrameExport, Style};
+use crate::live::{self, LiveConfig};
+use crate::metrics::{self, FrameCounter, Metrics};
+#[cfg(feature = "midi")]
+use crate::midi::{Mapping, Midi};
+use crate::minimap::Minimap;
+use crate::overlay::Overlay;
+use crate::panel::{Action, Controls, Panel};
+use crate::profile::Profiler;
+use crate::recording::{Entry, Recorder, Replay};
+use crate::remote::Command;
+use crate::render::{Canvas, CellShape, GridCoords, PixelCoords, PixelRect};
+use crate::run::RunArgs;
+use crate::selection::Selection;
+use crate::simulation::{species_populations, End, Simulation, Soup};
+use crate::state::{State, WindowState};
+use crate::stats::{CsvLog, StatsStream};
+use crate::theme::{Ramp, Theme, ThemeSource};
+use crate::worker::Worker;
+use crate::{
+    diff, load_pattern, physical_cell_size, resolve_rule, ColorMode, OnEnd, UserEvent, MAX_BRUSH_RADIUS,
+    MAX_STEPS_PER_FRAME, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+
+/// The share of each frame that `--target-fps` fills with ticking and drawing,
+/// leaving the rest for handling input.
+const FRAME_BUDGET: f64 = 0.8;
+
+/// How many frames' worth of ticks can be made up for at once after frames ran
+/// late. Time beyond that is dropped, so that a stall isn't followed by a
+/// burst of ticks that stalls again.
+const MAX_LAG_FRAMES: u32 = 4;
+
+/// How long notices are shown in the HUD for.
+const NOTICE_DURATION: Duration = Duration::from_secs(3);
+
+/// How far the cursor has to move, in physical pixels, to wake the
+/// screensaver, so that imperceptible jitter doesn't.
+const WAKE_DISTANCE: f64 = 8.0;
+
+/// The keys and mouse buttons listed by the help overlay, with what they do.
+const KEYBINDINGS: [(&str, &str); 29] = [
+    ("space", "pause or resume"),
+    ("n", "reseed every board"),
+    ("c", "clear paused boards"),
+    ("1 2 3 4", "paint with draw, erase, toggle, or walls"),
+    ("left / right click", "paint / erase cells"),
+    ("ctrl+wheel", "resize the brush"),
+    ("shift+drag", "select a region"),
+    ("shift+right click", "clear the selection"),
+    ("ctrl+c / ctrl+v", "copy the selection / paste"),
+    ("p", "place the stamp"),
+    ("r", "rotate the stamp while placing it, else fill the selection randomly"),
+    ("f / v", "flip the stamp while placing it"),
+    ("b", "browse the bundled patterns"),
+    ("arrows", "pan"),
+    ("+ / - / wheel", "zoom"),
+    ("z / shift+z", "zoom in / out at the cursor"),
+    ("home", "reset the view"),
+    ("a", "zoom to fit the live cells"),
+    ("t", "follow the centroid / bounds / fit / nothing"),
+    (", / .", "halve / double generations per frame"),
+    ("m / i", "toggle the minimap / inspector"),
+    ("h", "toggle history"),
+    ("d", "toggle births and deaths"),
+    ("g", "toggle spaceships"),
+    ("f3", "toggle the profiling overlay"),
+    ("f11", "toggle fullscreen"),
+    ("? / f1", "toggle this help"),
+    ("`", "open the console"),
+    ("esc", "exit"),
+];
+
+/// What painting cells with the left mouse button does to them.
+#[derive(strum::Display, Clone, Copy, PartialEq)]
+#[strum(serialize_all = "lowercase")]
+enum Tool {
+    /// Bring cells to life.
+    Draw,
+    /// Kill cells.
+    Erase,
+    /// Flip the state of each cell once per stroke.
+    Toggle,
+    /// Build walls, which count as live neighbors but never change. Erasing
+    /// knocks them down.
+    Wall,
+}
+
+/// An independent universe, drawn in its own region of the window.
+pub struct Board {
+    pub simulation: Simulation,
+    /// The position of the top-left corner of the board's region of the
+    /// window, in cells at the default size.
+    origin: GridCoords,
+}
+
+/// Everything the windowed simulation needs between events.
+pub struct App {
+    window: Window,
+    pixels: Pixels,
+    pub boards: Vec<Board>,
+    /// How many boards are placed along the width and height of the window.
+    layout: (usize, usize),
+    /// Whether the boards are being compared, and so must always share a
+    /// seed.
+    comparing: bool,
+    /// Whether two compared boards are drawn over each other in one tile.
+    overlay: bool,
+    /// Whether the live cells of the first of two compared boards count as
+    /// live neighbors of the second's cells.
+    couple: bool,
+    /// How boards are refilled when they restart.
+    soup: Soup,
+    /// What to do when a board's run ends, if anything other than exiting
+    /// once every board is extinct.
+    on_end: Option<OnEnd>,
+    /// The number of generations after which a board's run ends.
+    max_generations: Option<u64>,
+    /// The default logical size of each cell, in pixels.
+    cell_size: usize,
+    /// The default physical size of each cell, in pixels.
+    pixel_cell_size: usize,
+    /// The number of physical pixels per logical pixel on the window's display.
+    scale_factor: f64,
+    /// The size of the window, in cells at the default size.
+    grid_width: usize,
+    grid_height: usize,
+    /// The size of each board's region of the window, in cells at the default
+    /// size.
+    tile_width: usize,
+    tile_height: usize,
+    camera: Camera,
+    /// What the camera keeps centered after every frame of ticks, if
+    /// anything.
+    follow: Option<Follow>,
+    /// Whether to show a minimap on each board when its universe doesn't fit
+    /// in view.
+    show_minimap: bool,
+    /// The position of the cursor in the window, if it is over it.
+    cursor: Option<PhysicalPosition<f64>>,
+    /// Whether to show the details of the cell under the cursor.
+    show_inspector: bool,
+    /// Whether to show the keybindings and current settings.
+    show_help: bool,
+    /// Whether to mark dead cells that have been alive before, like Golly's
+    /// LifeHistory rule.
+    show_history: bool,
+    /// The fraction of each birth and death that the delta view still shows
+    /// a generation later. The delta view is shown while boards track them.
+    delta_decay: f32,
+    /// Whether to outline the spaceships on boards that run Conway's Game of
+    /// Life, with an arrow in the direction they're moving.
+    show_spaceships: bool,
+    modifiers: ModifiersState,
+    /// The radius of the brush that cells are painted with, in cells.
+    brush_radius: usize,
+    /// The tool that the left mouse button paints with.
+    tool: Tool,
+    /// The tool being painted with while a mouse button is held.
+    painting: Option<Tool>,
+    /// The board and coordinates of the cells that have been toggled during
+    /// the current stroke.
+    toggled: HashSet<(usize, usize, usize)>,
+    selection: Option<Selection>,
+    /// Whether the selection is being dragged out.
+    selecting: bool,
+    /// The pattern that can be stamped onto boards.
+    stamp: Option<Pattern>,
+    /// Whether clicking stamps the pattern, rather than painting.
+    placing: bool,
+    /// A message shown in the HUD until the given time.
+    notice: Option<(String, Instant)>,
+    /// The board and cell the brush was last applied at, so that it isn't
+    /// applied repeatedly while the cursor stays within one cell.
+    last_painted: Option<(usize, usize, usize)>,
+    color_mode: ColorMode,
+    cell_shape: CellShape,
+    theme: Theme,
+    /// The theme and colors given as flags, which the theme is loaded from
+    /// again when its file or the config changes.
+    theme_source: ThemeSource,
+    config_path: Option<PathBuf>,
+    /// The config as it was last applied.
+    config: LiveConfig,
+    /// Watches the config and theme files for changes, until it's dropped.
+    _watcher: Option<RecommendedWatcher>,
+    /// Whether to draw the cells that differ between two compared boards in
+    /// red.
+    highlight_divergence: bool,
+    /// The state that every board is compared against, with the cells added
+    /// and removed since it drawn in the theme's birth and death colors.
+    reference: Option<Grid>,
+    tick_duration: Duration,
+    next_tick: Instant,
+    /// When timed ticks were last run.
+    last_update: Instant,
+    /// Time that has passed without being spent on ticks yet, which is spent in
+    /// whole frames of ticks.
+    lag: Duration,
+    /// Counts the ticks that are run, to measure the actual speed.
+    tick_counter: FrameCounter,
+    /// The number of generations computed per second, as last measured.
+    generations_per_second: Option<f64>,
+    /// The number of generations computed for each frame.
+    steps_per_frame: u64,
+    /// Whether the number of generations for each frame is adjusted to keep
+    /// up the frame rate of `--target-fps`.
+    adaptive: bool,
+    /// How long the last frame took to draw.
+    render_duration: Duration,
+    /// Measures where the time of each frame goes, while the profiling
+    /// overlay is shown.
+    profiler: Option<Profiler>,
+    /// Where to write the statistics of each tick, if anywhere.
+    stats_stream: Option<StatsStream>,
+    /// Where to append the statistics of each tick as CSV, if anywhere.
+    csv_log: Option<CsvLog>,
+    /// Where images of the boards are saved every few generations, if
+    /// anywhere.
+    frame_export: Option<FrameExport>,
+    /// Metrics shared with the metrics server, if it is running.
+    metrics: Option<Arc<Metrics>>,
+    /// Where the first board is played as notes, if anywhere.
+    #[cfg(feature = "midi")]
+    midi: Option<Midi>,
+    frame_counter: FrameCounter,
+    /// The number of timed ticks so far, which recorded events are stamped
+    /// with.
+    ticks: u64,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    /// Where the settings are saved on exit for the next run, if anywhere.
+    state_path: Option<PathBuf>,
+    /// Computes the next generation of each board while the current one is
+    /// drawn.
+    worker: Worker,
+    /// The console that commands are typed into, if it is open.
+    console: Option<Console>,
+    /// The browser of bundled patterns, if it is open.
+    browser: Option<Browser>,
+    /// The side panel of controls, if it is shown.
+    panel: Option<Panel>,
+    /// Whether the window is a screensaver, which exits on any input.
+    screensaver: bool,
+    /// Where the screensaver first saw the cursor, which it exits once the
+    /// cursor moves away from.
+    wake_anchor: Option<PhysicalPosition<f64>>,
+}
+
+impl App {
+    /// Open the window and generate the initial configuration of every board.
+    ///
+    /// When comparing boards, the second board uses `compare_rule` instead of
+    /// `rule` if one is given. Events from `replay` are applied as the run
+    /// reaches them.
+    pub fn new(
+        args: &RunArgs,
+        (rule, rule_name): (Arc<dyn Rule>, String),
+        compare_rule: Option<Arc<dyn Rule>>,
+        replay: Option<Replay>,
+        (saved, state_path): (&State, Option<PathBuf>),
+        config: LiveConfig,
+        event_loop: &EventLoop<UserEvent>,
+    ) -> Result<Self, Error> {
+        // The window and cell sizes are in logical pixels, so that the grid has
+        // the same dimensions regardless of the display's scale factor.
+        let size = LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64);
+        let mut builder = WindowBuilder::new()
+            .with_inner_size(size)
+            .with_fullscreen(args.fullscreen.then_some(Fullscreen::Borderless(None)));
+        if let Some(saved) = saved.window {
+            builder = builder
+                .with_inner_size(PhysicalSize::new(saved.width, saved.height))
+                .with_position(PhysicalPosition::new(saved.x, saved.y));
+        }
+        let window = builder.build(event_loop).map_err(Error::CreateWindow)?;
+        window.set_cursor_visible(!args.screensaver);
+
+        let cell_size = args.cell_size;
+        let grid_width = WINDOW_WIDTH / cell_size;
+        let grid_height = WINDOW_HEIGHT / cell_size;
+        let scale_factor = window.scale_factor();
+        let pixel_cell_size = physical_cell_size(cell_size, scale_factor);
+        let surface_size = window.inner_size();
+        let surface_texture = SurfaceTexture::new(surface_size.width, surface_size.height, &window);
+        // The buffer is scaled up to the window by whole multiples and
+        // centered in it, so the theme's background fills the margins of
+        // windows that don't fit a whole number of cells.
+        let theme_source = args.style.theme_source();
+        let theme = config.theme(&theme_source).load()?;
+        let pixels = PixelsBuilder::new(
+            (grid_width * pixel_cell_size) as u32,
+            (grid_height * pixel_cell_size) as u32,
+            surface_texture,
+        )
+        .clear_color(theme.background.to_linear())
+        .build()
+        .map_err(Error::CreatePixels)?;
+
+        let panel = args.panel.then(|| Panel::new(&window, &pixels, rule_name.clone()));
+
+        let comparing = args.comparing();
+        // Overlaid boards share a single tile.
+        let (columns, rows) = match (comparing, args.overlay) {
+            (true, true) => (1, 1),
+            (true, false) => (2, 1),
+            (false, _) => args.boards,
+        };
+        let count = if comparing { 2 } else { columns * rows };
+        let tile_width = grid_width / columns;
+        let tile_height = grid_height / rows;
+        let (universe_width, universe_height) = args.universe.unwrap_or((tile_width, tile_height));
+
+        // Patterns are placed at the center of the universe, the way
+        // `--pattern` places them, while snapshots are the whole universe.
+        let reference = args
+            .diff_against
+            .as_deref()
+            .map(|path| {
+                let (cells, generation) = diff::load_state(path)?;
+                if generation.is_some() {
+                    return Ok(cells);
+                }
+                let mut grid = Grid::new(universe_width, universe_height);
+                grid.paste(
+                    &cells,
+                    universe_width.saturating_sub(cells.width()) / 2,
+                    universe_height.saturating_sub(cells.height()) / 2,
+                );
+                Ok::<_, Error>(grid)
+            })
+            .transpose()?;
+
+        // Each board gets its own seed, derived from the first so that the whole
+        // layout can be reproduced from a single `--seed`. Compared boards all
+        // start from the same seed, so that only their settings differ.
+        let soup = args.simulation.soup()?;
+        let seed = args.simulation.seed.unwrap_or_else(rand::random);
+        log::info!("Using seed {seed}");
+        let mut boards = Vec::with_capacity(count);
+        for index in 0..count {
+            let board_seed = if comparing { seed } else { seed.wrapping_add(index as u64) };
+            if index > 0 && !comparing {
+                log::info!("Board {index} is using seed {board_seed}");
+            }
+            // The second of two compared boards is the one with different settings.
+            let second = comparing && index == 1;
+            let (rule, rule_name) = match (&compare_rule, &args.compare_rule) {
+                (Some(compare_rule), Some(name)) if second => (Arc::clone(compare_rule), name.clone()),
+                _ => (Arc::clone(&rule), rule_name.clone()),
+            };
+            let noise = args.compare_noise.filter(|_| second).unwrap_or(args.simulation.noise);
+            let universe = (universe_width, universe_height);
+            let mut simulation = Simulation::new(&args.simulation, universe, rule, rule_name, noise)?;
+            if let ColorMode::Lineage = args.color_mode {
+                simulation.track_lineage(board_seed);
+            }
+            if args.delta {
+                simulation.delta = Some(Delta::new(universe_width * universe_height, args.delta_decay as f32));
+            }
+            simulation.reseed(board_seed, &soup)?;
+            let tile = if args.overlay { 0 } else { index };
+            let origin = GridCoords { x: tile % columns * tile_width, y: tile / columns * tile_height };
+            boards.push(Board { simulation, origin });
+        }
+
+        let metrics = args.metrics_addr.map(|_| Arc::new(Metrics::default()));
+        if let (Some(addr), Some(metrics)) = (args.metrics_addr, &metrics) {
+            metrics::serve(addr, Arc::clone(metrics))?;
+        }
+        #[cfg(feature = "midi")]
+        let midi = match &args.midi {
+            Some(port) => {
+                let mapping = args.midi_map.as_deref().map(Mapping::load).transpose()?.unwrap_or_default();
+                Some(Midi::connect(port, mapping)?)
+            },
+            None => None,
+        };
+
+        let tick_duration = args
+            .target_fps
+            .or(args.fps)
+            .map_or(Duration::from_millis(100), |fps| Duration::from_secs_f64(1.0 / fps as f64));
+
+        // Editing the config or the theme file while running applies the
+        // changes, but not being able to watch them shouldn't stop the run.
+        let watched: Vec<PathBuf> =
+            args.config.iter().cloned().chain(theme_source.path().map(Path::to_path_buf)).collect();
+        let watcher = match watched.is_empty() {
+            true => None,
+            false => live::watch(watched, event_loop.create_proxy()).map_err(|err| log::warn!("{err}")).ok(),
+        };
+        Ok(Self {
+            window,
+            pixels,
+            boards,
+            layout: (columns, rows),
+            comparing,
+            overlay: args.overlay,
+            couple: args.couple,
+            soup,
+            on_end: args.on_end,
+            max_generations: args.simulation.max_generations,
+            cell_size,
+            pixel_cell_size,
+            scale_factor,
+            grid_width,
+            grid_height,
+            tile_width,
+            tile_height,
+            camera: Camera::new(
+                cell_size,
+                (tile_width * cell_size, tile_height * cell_size),
+                (universe_width, universe_height),
+            ),
+            follow: args.follow,
+            show_minimap: true,
+            cursor: None,
+            show_inspector: false,
+            show_help: false,
+            show_history: args.history,
+            delta_decay: args.delta_decay as f32,
+            show_spaceships: args.spaceships,
+            modifiers: ModifiersState::empty(),
+            brush_radius: args.brush_radius,
+            tool: Tool::Draw,
+            painting: None,
+            toggled: HashSet::new(),
+            selection: None,
+            selecting: false,
+            stamp: args.stamp.as_deref().map(load_pattern).transpose()?,
+            placing: false,
+            notice: None,
+            last_painted: None,
+            color_mode: args.color_mode.clone(),
+            cell_shape: args.cell_shape,
+            theme,
+            theme_source,
+            config_path: args.config.clone(),
+            config,
+            _watcher: watcher,
+            highlight_divergence: args.highlight_divergence,
+            reference,
+            tick_duration,
+            next_tick: Instant::now() + tick_duration,
+            last_update: Instant::now(),
+            lag: Duration::ZERO,
+            tick_counter: FrameCounter::new(),
+            generations_per_second: None,
+            steps_per_frame: args.steps_per_frame,
+            adaptive: args.target_fps.is_some(),
+            render_duration: Duration::ZERO,
+            profiler: None,
+            stats_stream: args.simulation.stats_stream.as_deref().map(StatsStream::create).transpose()?,
+            csv_log: args.simulation.log_csv.as_deref().map(CsvLog::open).transpose()?,
+            frame_export: args.simulation.frame_export(count)?,
+            metrics,
+            #[cfg(feature = "midi")]
+            midi,
+            frame_counter: FrameCounter::new(),
+            ticks: 0,
+            recorder: args
+                .record
+                .as_deref()
+                .map(|path| Recorder::create(path, seed, std::env::args().collect()))
+                .transpose()?,
+            replay,
+            state_path,
+            worker: Worker::spawn(),
+            console: None,
+            browser: None,
+            panel,
+            screensaver: args.screensaver,
+            wake_anchor: None,
+        })
+    }
+
+    /// Respond to an event from the event loop.
+    pub fn handle_event(&mut self, event: Event<'_, UserEvent>, control_flow: &mut ControlFlow) -> Result<(), Error> {
+        match event {
+            Event::NewEvents(StartCause::Init) => {
+                *control_flow = ControlFlow::WaitUntil(self.next_tick);
+            },
+            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
+                // Time accumulates between wakeups and is spent in whole frames
+                // of ticks, so that frames which ran late are made up for and
+                // the tick rate doesn't drift with how long ticking and drawing
+                // take.
+                let now = Instant::now();
+                self.lag = (self.lag + (now - self.last_update)).min(self.tick_duration * MAX_LAG_FRAMES);
+                self.last_update = now;
+                let frames = (self.lag.as_secs_f64() / self.tick_duration.as_secs_f64()) as u32;
+                self.lag = self.lag.saturating_sub(self.tick_duration * frames);
+                self.next_tick = now + self.tick_duration.saturating_sub(self.lag);
+                *control_flow = ControlFlow::WaitUntil(self.next_tick);
+                if frames == 0 {
+                    return Ok(());
+                }
+
+                // Commands are replayed between the ticks of a frame, so that
+                // replays don't depend on how many ticks each frame has.
+                let start = Instant::now();
+                for _ in 0..self.steps_per_frame * frames as u64 {
+                    self.replay_commands()?;
+                    log::trace!("Tick");
+                    self.ticks += 1;
+                    self.tick()?;
+                    if let Some(rate) = self.tick_counter.frame() {
+                        self.generations_per_second = Some(rate);
+                    }
+                    self.handle_ended_boards(control_flow)?;
+                    if *control_flow == ControlFlow::Exit {
+                        break;
+                    }
+                }
+                if self.adaptive {
+                    self.adapt_steps_per_frame(start.elapsed() / frames);
+                }
+                for board in self.boards.iter_mut().filter(|board| !board.simulation.paused) {
+                    board.simulation.prefetch(&self.worker);
+                }
+                self.follow_cells();
+                self.window.request_redraw();
+            },
+            Event::UserEvent(UserEvent::Remote(request)) => {
+                request.handle(self)?;
+                self.window.request_redraw();
+            },
+            Event::UserEvent(UserEvent::Reload) => {
+                self.reload()?;
+                self.window.request_redraw();
+            },
+            Event::WindowEvent { event, .. } if self.screensaver && self.wakes(&event) => {
+                *control_flow = ControlFlow::Exit;
+            },
+            Event::WindowEvent { event, .. } if self.panel_consumes(&event) => {},
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                WindowEvent::Resized(size) => {
+                    self.pixels.resize_surface(size.width, size.height).map_err(Error::Resize)?;
+                    if let Some(panel) = &mut self.panel {
+                        panel.resize(size.width, size.height);
+                    }
+                },
+                WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                    // Keep the grid dimensions the same, but draw each cell with
+                    // enough physical pixels to stay the same logical size.
+                    self.scale_factor = scale_factor;
+                    self.pixel_cell_size = physical_cell_size(self.cell_size, scale_factor);
+                    let (buffer_width, buffer_height) = self.buffer_size();
+                    self.pixels.resize_buffer(buffer_width as u32, buffer_height as u32).map_err(Error::Resize)?;
+                    self.pixels.resize_surface(new_inner_size.width, new_inner_size.height).map_err(Error::Resize)?;
+                    if let Some(panel) = &mut self.panel {
+                        panel.set_scale_factor(scale_factor);
+                        panel.resize(new_inner_size.width, new_inner_size.height);
+                    }
+                    self.window.request_redraw();
+                },
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(key), .. },
+                    ..
+                } => self.handle_key(key, control_flow)?,
+                WindowEvent::ReceivedCharacter(c) => {
+                    // The backtick that opened the console isn't part of the
+                    // line, and keys like backspace are handled as keys.
+                    if let Some(console) = self.console.as_mut().filter(|_| !c.is_control() && c != '`') {
+                        console.input.push(c);
+                        self.window.request_redraw();
+                    }
+                },
+                WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+                WindowEvent::CursorMoved { position, .. } => {
+                    self.cursor = Some(position);
+                    self.paint()?;
+                    self.extend_selection();
+                    if self.show_inspector {
+                        self.window.request_redraw();
+                    }
+                },
+                WindowEvent::CursorLeft { .. } => self.cursor = None,
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let scrolled = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y as f64,
+                        MouseScrollDelta::PixelDelta(position) => position.y,
+                    };
+                    if scrolled != 0.0 && self.modifiers.ctrl() {
+                        self.brush_radius = if scrolled > 0.0 {
+                            (self.brush_radius + 1).min(MAX_BRUSH_RADIUS)
+                        } else {
+                            self.brush_radius.saturating_sub(1)
+                        };
+                        self.window.request_redraw();
+                    } else if scrolled != 0.0 {
+                        self.zoom(scrolled > 0.0, true);
+                    }
+                },
+                WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => self.press(button)?,
+                WindowEvent::MouseInput { state: ElementState::Released, .. } => {
+                    self.painting = None;
+                    self.last_painted = None;
+                    self.toggled.clear();
+                    self.selecting = false;
+                },
+                _ => {},
+            },
+            Event::LoopDestroyed => {
+                // Every way of exiting ends up here, so this is the place to tear
+                // down anything that needs to outlive the last frame.
+                let generation = self.boards.iter().map(|board| board.simulation.generation).max().unwrap_or(0);
+                log::info!("Exiting at generation {generation}");
+                // Summaries go to stderr, since stdout may be the stats stream.
+                for (index, board) in self.boards.iter().enumerate() {
+                    match self.boards.len() {
+                        1 => eprintln!("{}", board.simulation.report()),
+                        _ => eprintln!("Board {index}:\n{}", board.simulation.report()),
+                    }
+                }
+                if let Some(stats_stream) = &mut self.stats_stream {
+                    stats_stream.flush()?;
+                }
+                if let Some(csv_log) = &mut self.csv_log {
+                    csv_log.flush()?;
+                }
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.flush()?;
+                }
+                #[cfg(feature = "midi")]
+                if let Some(midi) = &mut self.midi {
+                    midi.silence()?;
+                }
+                if let Some(path) = &self.state_path {
+                    if let Err(err) = self.state().save(path) {
+                        log::warn!("Couldn't save the settings to {}: {err}", path.display());
+                    }
+                }
+            },
+            Event::RedrawRequested(_) => {
+                self.render()?;
+                // Frames are drawn as fast as they can be until zooming has
+                // finished animating, rather than only after ticks.
+                if self.camera.rendered().is_some() {
+                    self.window.request_redraw();
+                }
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    /// Load the config and theme files again after one of them changed, and
+    /// apply the settings that changed to the running boards. If either can't
+    /// be loaded, the problem is shown and the previous settings are kept, so
+    /// that the display isn't interrupted.
+    fn reload(&mut self) -> Result<(), Error> {
+        let config = match self.config_path.as_deref().map(LiveConfig::load).transpose() {
+            Ok(config) => config.unwrap_or_default(),
+            Err(err) => {
+                log::warn!("{err}");
+                self.notify(&err.to_string());
+                return Ok(());
+            },
+        };
+        match config.theme(&self.theme_source).load() {
+            Ok(theme) => {
+                self.pixels.clear_color(theme.background.to_linear());
+                self.theme = theme;
+            },
+            Err(err) => {
+                log::warn!("{err}");
+                self.notify(&err.to_string());
+            },
+        }
+
+        let previous = &self.config;
+        let color_mode = config.color_mode.clone().filter(|_| config.color_mode != previous.color_mode);
+        let fps = config.frames_per_second.filter(|_| config.frames_per_second != previous.frames_per_second);
+        let steps = config.steps_per_frame.filter(|_| config.steps_per_frame != previous.steps_per_frame);
+        let rule = config.rule.clone().filter(|_| config.rule != previous.rule);
+        self.config = config;
+        if let Some(color_mode) = color_mode {
+            self.set_color_mode(color_mode);
+        }
+        // The speed adapts on its own to `--target-fps`.
+        if let Some(fps) = fps.filter(|_| !self.adaptive) {
+            self.tick_duration = Duration::from_secs_f64(1.0 / fps as f64);
+        }
+        if let Some(steps) = steps.filter(|_| !self.adaptive) {
+            self.steps_per_frame = steps.clamp(1, MAX_STEPS_PER_FRAME);
+        }
+        if let Some(rule) = rule {
+            match resolve_rule(&rule) {
+                Ok(_) => {
+                    for board in 0..self.boards.len() {
+                        Command::ChangeRule { rule: rule.clone() }.issue(self, board)?;
+                    }
+                },
+                Err(err) => self.notify(&err.to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the settings to start the next run with. The window's size and
+    /// position are left out if it is fullscreen, and the number of
+    /// generations for each frame if it is adapting to `--target-fps`.
+    fn state(&self) -> State {
+        let window = match (self.window.fullscreen(), self.window.outer_position()) {
+            (None, Ok(position)) => {
+                let size = self.window.inner_size();
+                Some(WindowState { width: size.width, height: size.height, x: position.x, y: position.y })
+            },
+            _ => None,
+        };
+        let rule = &self.boards[0].simulation.rule_name;
+        State {
+            window,
+            color_mode: Some(self.color_mode.clone()),
+            frames_per_second: Some((1.0 / self.tick_duration.as_secs_f64()).round() as u32),
+            steps_per_frame: (!self.adaptive).then_some(self.steps_per_frame),
+            // Rules loaded from files are left out, since they can't be
+            // restored by name.
+            rule: resolve_rule(rule).is_ok().then(|| rule.clone()),
+        }
+    }
+
+    /// Respond to a key being pressed while the pattern browser is open, which
+    /// takes every key until it's closed.
+    fn handle_browser_key(&mut self, key: VirtualKeyCode) -> Result<(), Error> {
+        let Some(browser) = &mut self.browser else { return Ok(()) };
+        match key {
+            VirtualKeyCode::B | VirtualKeyCode::Escape => self.browser = None,
+            VirtualKeyCode::Up => browser.select(-1),
+            VirtualKeyCode::Down => browser.select(1),
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter if self.modifiers.shift() => {
+                let entry = browser.entry();
+                self.browser = None;
+                for board in 0..self.boards.len() {
+                    Command::LoadPattern { pattern: entry.rle().to_string(), x: None, y: None }.issue(self, board)?;
+                }
+            },
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                self.stamp = Some(browser.entry().pattern());
+                self.placing = true;
+                self.browser = None;
+            },
+            _ => return Ok(()),
+        }
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Respond to a key being pressed while the console is open, which takes
+    /// every key until it's closed.
+    fn handle_console_key(&mut self, key: VirtualKeyCode) -> Result<(), Error> {
+        let Some(console) = &mut self.console else { return Ok(()) };
+        match key {
+            VirtualKeyCode::Grave | VirtualKeyCode::Escape => self.console = None,
+            VirtualKeyCode::Back => {
+                console.input.pop();
+            },
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                let line = std::mem::take(&mut console.input);
+                let output = self.enter(&line)?;
+                if let Some(console) = &mut self.console {
+                    console.output = output;
+                }
+            },
+            _ => return Ok(()),
+        }
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Run a line entered into the console, returning the response to show
+    /// under it.
+    fn enter(&mut self, line: &str) -> Result<String, Error> {
+        let line = match Line::parse(line) {
+            Ok(line) => line,
+            Err(message) => return Ok(message),
+        };
+        match line {
+            Line::Help => Ok(console::USAGE.to_string()),
+            Line::Speed(frames_per_second) => {
+                self.tick_duration = Duration::from_secs_f64(1.0 / frames_per_second as f64);
+                Ok(format!("{frames_per_second} frames per second"))
+            },
+            Line::Command(command) => {
+                // Compared boards are reseeded together, and the others get
+                // seeds derived from the given one, like at startup.
+                let boards = match command {
+                    Command::Reseed { .. } if self.comparing => 1,
+                    _ => self.boards.len(),
+                };
+                for board in 0..boards {
+                    let command = match &command {
+                        Command::Reseed { seed } => Command::Reseed { seed: seed.wrapping_add(board as u64) },
+                        command => command.clone(),
+                    };
+                    if let Err(message) = command.try_issue(self, board)? {
+                        return Ok(message);
+                    }
+                }
+                Ok("ok".to_string())
+            },
+        }
+    }
+
+    /// Returns whether the event is input that should wake the screensaver.
+    fn wakes(&mut self, event: &WindowEvent<'_>) -> bool {
+        match event {
+            WindowEvent::KeyboardInput { input: KeyboardInput { state: ElementState::Pressed, .. }, .. }
+            | WindowEvent::MouseInput { state: ElementState::Pressed, .. } => true,
+            WindowEvent::CursorMoved { position, .. } => {
+                let anchor = *self.wake_anchor.get_or_insert(*position);
+                (position.x - anchor.x).hypot(position.y - anchor.y) > WAKE_DISTANCE
+            },
+            _ => false,
+        }
+    }
+
+    /// Pass an event to the panel, if it is shown, returning whether the
+    /// panel took it so that the boards shouldn't respond to it too.
+    fn panel_consumes(&mut self, event: &WindowEvent<'_>) -> bool {
+        let Some(panel) = &mut self.panel else { return false };
+        let (consumed, repaint) = panel.handle_event(event);
+        if repaint {
+            self.window.request_redraw();
+        }
+        consumed
+    }
+
+    /// Lay out the panel, if it is shown, and apply the changes made with it
+    /// since the last frame.
+    fn update_from_panel(&mut self) -> Result<(), Error> {
+        let frames_per_second = (1.0 / self.tick_duration.as_secs_f64()).round() as u32;
+        let simulation = &self.boards[0].simulation;
+        let mut controls = Controls {
+            frames_per_second,
+            steps_per_frame: self.steps_per_frame,
+            adaptive: self.adaptive,
+            chance: self.soup.chance,
+            color_mode: self.color_mode.clone(),
+            paused: self.all_paused(),
+            rulestring: simulation.rule.rulestring().unwrap_or_else(|| simulation.rule_name.clone()),
+            life_like: simulation.rule.as_life_like(),
+        };
+        let Some(panel) = &mut self.panel else { return Ok(()) };
+        let actions = panel.prepare(&self.window, &mut controls);
+
+        if controls.frames_per_second != frames_per_second {
+            self.tick_duration = Duration::from_secs_f64(1.0 / controls.frames_per_second as f64);
+        }
+        self.steps_per_frame = controls.steps_per_frame;
+        self.soup.chance = controls.chance;
+        if controls.color_mode != self.color_mode {
+            self.set_color_mode(controls.color_mode);
+        }
+        for action in actions {
+            match action {
+                Action::TogglePause => self.toggle_pause()?,
+                Action::Step => {
+                    for board in 0..self.boards.len() {
+                        Command::Step { generations: 1 }.issue(self, board)?;
+                    }
+                },
+                Action::Reseed => self.reseed_all()?,
+                Action::ChangeRule(rule) => match resolve_rule(&rule) {
+                    Ok(_) => {
+                        for board in 0..self.boards.len() {
+                            Command::ChangeRule { rule: rule.clone() }.issue(self, board)?;
+                        }
+                    },
+                    Err(err) => self.notify(&err.to_string()),
+                },
+                Action::CopyRule(rulestring) => {
+                    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(&rulestring)) {
+                        Ok(()) => self.notify(&format!("copied {rulestring}")),
+                        Err(err) => {
+                            log::warn!("Failed to write to the clipboard: {err}");
+                            self.notify("couldn't write to the clipboard");
+                        },
+                    }
+                },
+                Action::LoadPattern(path) => {
+                    // Bundled patterns load by name here too, as with `--pattern`.
+                    let text = match load_pattern(Path::new(&path)) {
+                        Ok(pattern) => pattern.to_rle(),
+                        Err(err) => {
+                            self.notify(&err.to_string());
+                            continue;
+                        },
+                    };
+                    for board in 0..self.boards.len() {
+                        Command::LoadPattern { pattern: text.clone(), x: None, y: None }.issue(self, board)?;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Respond to a key being pressed.
+    fn handle_key(&mut self, key: VirtualKeyCode, control_flow: &mut ControlFlow) -> Result<(), Error> {
+        if self.console.is_some() {
+            return self.handle_console_key(key);
+        }
+        if self.browser.is_some() {
+            return self.handle_browser_key(key);
+        }
+        let (view, universe) = (self.view(), self.universe());
+        // Pan by a quarter of the view at a time.
+        let (columns, rows) = self.camera.view_cells(view);
+        let (dx, dy) = ((columns as isize / 4).max(1), (rows as isize / 4).max(1));
+        // Panning by hand stops the camera from following the cells, since it
+        // would be undone on the next frame.
+        if matches!(key, VirtualKeyCode::Left | VirtualKeyCode::Right | VirtualKeyCode::Up | VirtualKeyCode::Down) {
+            self.follow = None;
+        }
+        match key {
+            VirtualKeyCode::Escape => *control_flow = ControlFlow::Exit,
+            VirtualKeyCode::F11 => {
+                // The grid keeps its size and is letterboxed within the
+                // monitor by the pixel buffer's scaling.
+                let fullscreen = match self.window.fullscreen() {
+                    Some(_) => None,
+                    None => Some(Fullscreen::Borderless(None)),
+                };
+                self.window.set_fullscreen(fullscreen);
+            },
+            VirtualKeyCode::Left => self.camera.pan(-dx, 0, view, universe),
+            VirtualKeyCode::Right => self.camera.pan(dx, 0, view, universe),
+            VirtualKeyCode::Up => self.camera.pan(0, -dy, view, universe),
+            VirtualKeyCode::Down => self.camera.pan(0, dy, view, universe),
+            VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd => self.zoom(true, false),
+            VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => self.zoom(false, false),
+            VirtualKeyCode::Z => self.zoom(!self.modifiers.shift(), true),
+            VirtualKeyCode::Home => self.camera = Camera::new(self.cell_size, view, universe),
+            VirtualKeyCode::A => self.camera.fit(&self.boards[0].simulation.grid, view, universe),
+            VirtualKeyCode::T => {
+                self.follow = match self.follow {
+                    None => Some(Follow::Centroid),
+                    Some(Follow::Centroid) => Some(Follow::Bounds),
+                    Some(Follow::Bounds) => Some(Follow::Fit),
+                    Some(Follow::Fit) => None,
+                };
+                match self.follow {
+                    Some(Follow::Fit) => self.notify("fitting the live cells in view"),
+                    Some(follow) => self.notify(&format!("following the {follow}")),
+                    None => self.notify("not following the cells"),
+                }
+                self.follow_cells();
+            },
+            VirtualKeyCode::M => self.show_minimap = !self.show_minimap,
+            VirtualKeyCode::I => self.show_inspector = !self.show_inspector,
+            // `H` already toggles history, so the help is on `?` and F1.
+            VirtualKeyCode::Slash if self.modifiers.shift() => self.show_help = !self.show_help,
+            VirtualKeyCode::F1 => self.show_help = !self.show_help,
+            VirtualKeyCode::F3 => {
+                self.profiler = match self.profiler {
+                    Some(_) => None,
+                    None => Some(Profiler::new()),
+                };
+            },
+            VirtualKeyCode::Grave => self.console = Some(Console::default()),
+            VirtualKeyCode::B => self.browser = Some(Browser::default()),
+            VirtualKeyCode::H => self.show_history = !self.show_history,
+            VirtualKeyCode::D => self.toggle_delta(),
+            VirtualKeyCode::G => self.show_spaceships = !self.show_spaceships,
+            VirtualKeyCode::Comma | VirtualKeyCode::Period if self.adaptive => {
+                self.notify("generations per frame are adjusted to keep up --target-fps");
+            },
+            VirtualKeyCode::Comma | VirtualKeyCode::Period => {
+                self.steps_per_frame = match key {
+                    VirtualKeyCode::Comma => (self.steps_per_frame / 2).max(1),
+                    _ => (self.steps_per_frame * 2).min(MAX_STEPS_PER_FRAME),
+                };
+                self.notify(&format!("{} generations per frame", self.steps_per_frame));
+            },
+            VirtualKeyCode::Key1 => self.tool = Tool::Draw,
+            VirtualKeyCode::Key2 => self.tool = Tool::Erase,
+            VirtualKeyCode::Key3 => self.tool = Tool::Toggle,
+            VirtualKeyCode::Key4 => self.tool = Tool::Wall,
+            VirtualKeyCode::Space => self.toggle_pause()?,
+            VirtualKeyCode::C if self.modifiers.ctrl() => self.copy(),
+            VirtualKeyCode::C if !self.all_paused() => self.notify("pause with space before clearing"),
+            VirtualKeyCode::C => {
+                // Only paused boards can be cleared, since empty boards that
+                // are running have gone extinct.
+                for board in 0..self.boards.len() {
+                    Command::Clear.issue(self, board)?;
+                }
+            },
+            VirtualKeyCode::N => self.reseed_all()?,
+            VirtualKeyCode::V if self.modifiers.ctrl() => self.paste(),
+            VirtualKeyCode::P if self.stamp.is_none() => {
+                self.notify("no stamp; pick one with b, use --stamp, or paste one with ctrl+v")
+            },
+            VirtualKeyCode::P => self.placing = !self.placing,
+            VirtualKeyCode::R if self.placing => self.stamp = self.stamp.as_ref().map(Pattern::rotated_clockwise),
+            VirtualKeyCode::F if self.placing => self.stamp = self.stamp.as_ref().map(Pattern::flipped_horizontally),
+            VirtualKeyCode::V if self.placing => self.stamp = self.stamp.as_ref().map(Pattern::flipped_vertically),
+            VirtualKeyCode::R => {
+                let Some(selection) = self.selection else { return Ok(()) };
+                let ((x, y), (width, height)) = selection.bounds();
+                let chance = self.soup.chance;
+                Command::FillRandom { x, y, width, height, chance }.issue(self, selection.board)?;
+            },
+            _ => return Ok(()),
+        }
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Pause every board, unless every board is already paused, in which case
+    /// resume them.
+    fn toggle_pause(&mut self) -> Result<(), Error> {
+        let command = if self.all_paused() { Command::Resume } else { Command::Pause };
+        for board in 0..self.boards.len() {
+            command.clone().issue(self, board)?;
+        }
+        Ok(())
+    }
+
+    /// Refill every board with a random configuration from a new seed.
+    fn reseed_all(&mut self) -> Result<(), Error> {
+        // Compared boards are reseeded together, so only the first needs to be.
+        let boards = if self.comparing { 1 } else { self.boards.len() };
+        for board in 0..boards {
+            Command::Reseed { seed: rand::random() }.issue(self, board)?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether every board is paused.
+    fn all_paused(&self) -> bool {
+        self.boards.iter().all(|board| board.simulation.paused)
+    }
+
+    /// Show a message in the HUD for a few seconds.
+    fn notify(&mut self, message: &str) {
+        self.notice = Some((message.to_string(), Instant::now() + NOTICE_DURATION));
+    }
+
+    /// Respond to a mouse button being pressed at the cursor's position.
+    /// Dragging with the left button while Shift is held selects a region,
+    /// and clicking the right button while it's held clears the selection.
+    /// Clicking on a minimap moves the camera to the clicked cell. While
+    /// placing the stamp, the left button stamps it and the right button stops
+    /// placing it, and otherwise the left button paints with the current tool
+    /// and the right button erases.
+    fn press(&mut self, button: MouseButton) -> Result<(), Error> {
+        let Some(coords) = self.cursor_pixel() else { return Ok(()) };
+        if self.modifiers.shift() {
+            match button {
+                MouseButton::Left => {
+                    self.selection = self.cell_at(coords).map(|(board, x, y)| Selection::new(board, x, y));
+                    self.selecting = self.selection.is_some();
+                },
+                MouseButton::Right => self.selection = None,
+                _ => return Ok(()),
+            }
+            self.window.request_redraw();
+            return Ok(());
+        }
+
+        let target = self.boards.iter().find_map(|board| self.minimap(board)?.cell_at(coords));
+        if let (Some((x, y)), MouseButton::Left) = (target, button) {
+            self.follow = None;
+            self.camera.center_on(x, y, self.view(), self.universe());
+            self.window.request_redraw();
+            return Ok(());
+        }
+
+        if self.placing {
+            match (button, &self.stamp, self.cell_at(coords)) {
+                (MouseButton::Left, Some(stamp), Some((board, x, y))) => {
+                    Command::Stamp { cells: self.stamp_cells(stamp, x, y) }.issue(self, board)?;
+                },
+                (MouseButton::Right, ..) => self.placing = false,
+                _ => return Ok(()),
+            }
+            self.window.request_redraw();
+            return Ok(());
+        }
+
+        self.painting = match button {
+            MouseButton::Left => Some(self.tool),
+            MouseButton::Right => Some(Tool::Erase),
+            _ => return Ok(()),
+        };
+        self.paint()
+    }
+
+    /// Paint the cells around the cursor with the brush, if a mouse button
+    /// is held.
+    fn paint(&mut self) -> Result<(), Error> {
+        let Some(tool) = self.painting else { return Ok(()) };
+        let Some(cell) = self.cursor_pixel().and_then(|coords| self.cell_at(coords)) else { return Ok(()) };
+        if self.last_painted == Some(cell) {
+            return Ok(());
+        }
+        self.last_painted = Some(cell);
+        let (board, x, y) = cell;
+        let radius = self.brush_radius;
+        match tool {
+            Tool::Draw | Tool::Erase => {
+                Command::Paint { x, y, radius, alive: tool == Tool::Draw }.issue(self, board)?
+            },
+            Tool::Wall => Command::BuildWalls { x, y, radius }.issue(self, board)?,
+            Tool::Toggle => {
+                // Overlapping brush strokes would otherwise flip cells back.
+                let grid = &self.boards[board].simulation.grid;
+                let cells: Vec<_> = grid
+                    .disk(x, y, radius)
+                    .filter(|&(x, y)| self.toggled.insert((board, x, y)))
+                    .map(|(x, y)| (x, y, !grid.get(x, y)))
+                    .collect();
+                for (x, y, alive) in cells {
+                    Command::SetCell { x, y, alive }.issue(self, board)?;
+                }
+            },
+        }
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Put the selected region on the system clipboard as RLE.
+    fn copy(&mut self) {
+        let Some(selection) = self.selection else { return self.notify("select a region to copy with shift+drag") };
+        let ((x, y), (width, height)) = selection.bounds();
+        let simulation = &self.boards[selection.board].simulation;
+        let rule = simulation.rule.rulestring().unwrap_or_else(|| simulation.rule_name.clone());
+        let cells = simulation.grid.crop(x, y, width, height);
+        let walls = simulation.walls.as_ref().map(|walls| walls.crop(x, y, width, height));
+        let pattern = Pattern { name: None, rule: Some(rule), cells, walls };
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(pattern.to_rle())) {
+            Ok(()) => self.notify(&format!("copied {width}x{height} region")),
+            Err(err) => {
+                log::warn!("Failed to write to the clipboard: {err}");
+                self.notify("couldn't write to the clipboard");
+            },
+        }
+    }
+
+    /// Start placing the pattern on the system clipboard as the stamp, which
+    /// may be RLE or plaintext.
+    fn paste(&mut self) {
+        let text = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => text,
+            Err(err) => {
+                log::warn!("Failed to read the clipboard: {err}");
+                return self.notify("couldn't read the clipboard");
+            },
+        };
+        match Pattern::parse(&text) {
+            Ok(pattern) if pattern.cells.population() > 0 => {
+                self.stamp = Some(pattern);
+                self.placing = true;
+            },
+            Ok(_) => self.notify("the clipboard's pattern is empty"),
+            Err(err) => {
+                log::warn!("Failed to parse the clipboard as a pattern: {err}");
+                self.notify("the clipboard doesn't hold a pattern");
+            },
+        }
+    }
+
+    /// Returns the coordinates of the live cells of the stamp when it is
+    /// centered on the given cell, leaving out any outside of the universe.
+    fn stamp_cells(&self, stamp: &Pattern, x: usize, y: usize) -> Vec<[usize; 2]> {
+        let (width, height) = self.universe();
+        let cells = &stamp.cells;
+        let x0 = x as isize - cells.width() as isize / 2;
+        let y0 = y as isize - cells.height() as isize / 2;
+        (0..cells.height())
+            .flat_map(|dy| (0..cells.width()).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| cells.get(dx, dy))
+            .filter_map(|(dx, dy)| {
+                let x = usize::try_from(x0 + dx as isize).ok().filter(|&x| x < width)?;
+                let y = usize::try_from(y0 + dy as isize).ok().filter(|&y| y < height)?;
+                Some([x, y])
+            })
+            .collect()
+    }
+
+    /// Extend the selection to the cell under the cursor while it's being
+    /// dragged out, as long as the cursor stays on the same board.
+    fn extend_selection(&mut self) {
+        let Some((board, x, y)) = self.cursor_pixel().and_then(|coords| self.cell_at(coords)) else { return };
+        let Some(selection) = self.selection.as_mut().filter(|_| self.selecting) else { return };
+        if board == selection.board {
+            selection.extend_to(x, y);
+            self.window.request_redraw();
+        }
+    }
+
+    /// Returns the pixel of the pixel buffer under the cursor, if there is one.
+    fn cursor_pixel(&self) -> Option<PixelCoords> {
+        let position = self.cursor?;
+        let (x, y) = self.pixels.window_pos_to_pixel((position.x as f32, position.y as f32)).ok()?;
+        Some(PixelCoords { x, y })
+    }
+
+    /// Returns the board and universe coordinates of the cell drawn at the
+    /// given pixel, if there is one.
+    fn cell_at(&self, coords: PixelCoords) -> Option<(usize, usize, usize)> {
+        let cell_size = self.view_cell_size();
+        let index = self.boards.iter().position(|board| self.tile(board).contains(coords))?;
+        let tile = self.tile(&self.boards[index]);
+        let grid = &self.boards[index].simulation.grid;
+        let cells = self.camera.cells_per_pixel;
+        let x = self.camera.x + ((coords.x - tile.x) / cell_size * cells) as isize;
+        let y = self.camera.y + ((coords.y - tile.y) / cell_size * cells) as isize;
+        let x = usize::try_from(x).ok().filter(|&x| x < grid.width())?;
+        let y = usize::try_from(y).ok().filter(|&y| y < grid.height())?;
+        Some((index, x, y))
+    }
+
+    /// Returns a description of the cell under the cursor, if there is one.
+    fn inspect(&self) -> Option<String> {
+        let (index, x, y) = self.cell_at(self.cursor_pixel()?)?;
+        let simulation = &self.boards[index].simulation;
+        let board = if self.boards.len() > 1 { format!("board {index}\n") } else { String::new() };
+        let state =
+            if simulation.grid.get(x, y) { format!("alive, age {}", simulation.age(x, y)) } else { "dead".to_string() };
+        let neighbors = simulation.grid.alive_neighbors(x as i32, y as i32);
+        Some(format!("{board}{x}, {y}\n{state}\n{neighbors} neighbors"))
+    }
+
+    /// Returns the current settings followed by every keybinding, for the help
+    /// overlay.
+    fn help(&self) -> String {
+        let mut rules: Vec<&str> = Vec::new();
+        for board in &self.boards {
+            if !rules.contains(&board.simulation.rule_name.as_str()) {
+                rules.push(&board.simulation.rule_name);
+            }
+        }
+        let frames_per_second = 1.0 / self.tick_duration.as_secs_f64();
+        let adapting = if self.adaptive { ", adapting" } else { "" };
+        let mut help = format!("rule {}\n", rules.join(", "));
+        help.push_str(&format!(
+            "{frames_per_second:.0} frames per second, {} generations per frame{adapting}\n",
+            self.steps_per_frame
+        ));
+        help.push_str(&format!("{} colors, {} brush {}\n", self.color_mode, self.tool, self.brush_radius));
+        let width = KEYBINDINGS.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+        for (keys, action) in KEYBINDINGS {
+            help.push_str(&format!("\n{keys:width$}  {action}"));
+        }
+        help
+    }
+
+    /// Advance every board that isn't paused by one generation.
+    ///
+    /// Extinct boards keep ticking too, so that every board stays on the same
+    /// generation.
+    fn tick(&mut self) -> Result<(), Error> {
+        let start = Instant::now();
+        // A coupled board is stepped before the board driving it, so that it
+        // sees the same generation of it.
+        let mut order: Vec<usize> = (0..self.boards.len()).collect();
+        if self.couple {
+            order.reverse();
+        }
+        for index in order {
+            if !self.boards[index].simulation.paused {
+                self.step_board(index)?;
+            }
+        }
+        self.record_metrics(start.elapsed());
+        Ok(())
+    }
+
+    /// Choose how many generations to compute for the next frame, so that
+    /// ticking and drawing it fill the frame's share of time, given how long
+    /// the ticks of this frame took. It changes by at most a factor of two at
+    /// a time, so that one slow frame doesn't cause a stall.
+    fn adapt_steps_per_frame(&mut self, tick_duration: Duration) {
+        let budget = self.tick_duration.mul_f64(FRAME_BUDGET).saturating_sub(self.render_duration);
+        let per_step = tick_duration.as_secs_f64() / self.steps_per_frame as f64;
+        let fitting = if per_step > 0.0 { (budget.as_secs_f64() / per_step) as u64 } else { MAX_STEPS_PER_FRAME };
+        self.steps_per_frame =
+            fitting.clamp(self.steps_per_frame / 2, self.steps_per_frame * 2).clamp(1, MAX_STEPS_PER_FRAME);
+    }
+
+    /// Returns why the run on a board has ended, if it has.
+    ///
+    /// Stagnation only ends a run if an `--on-end` action was given, since
+    /// exiting as soon as a board settles would be surprising.
+    fn end(&self, simulation: &Simulation) -> Option<End> {
+        simulation.end(self.max_generations, self.on_end.is_some())
+    }
+
+    /// Apply the `--on-end` action to the boards whose runs have ended.
+    fn handle_ended_boards(&mut self, control_flow: &mut ControlFlow) -> Result<(), Error> {
+        let ends: Vec<Option<End>> = self.boards.iter().map(|board| self.end(&board.simulation)).collect();
+        for (index, end) in ends.iter().enumerate() {
+            let Some(end) = end else { continue };
+            let generation = self.boards[index].simulation.generation;
+            log::info!("Board {index} {end} at generation {generation}");
+            if *end == End::GenerationLimit {
+                match self.boards.len() {
+                    1 => eprintln!("Reached the generation limit at generation {generation}"),
+                    _ => eprintln!("Board {index} reached the generation limit at generation {generation}"),
+                }
+            }
+        }
+
+        match self.on_end.unwrap_or(OnEnd::Exit) {
+            OnEnd::Exit => {
+                // If there is nothing left to watch, there is nothing left to do but exit!
+                if ends.iter().all(Option::is_some) {
+                    *control_flow = ControlFlow::Exit;
+                }
+            },
+            OnEnd::Pause => {
+                for (board, end) in self.boards.iter_mut().zip(&ends) {
+                    if end.is_some() {
+                        board.simulation.paused = true;
+                    }
+                }
+            },
+            OnEnd::Restart => {
+                for (index, end) in ends.iter().enumerate() {
+                    // Compared boards restart together, so one of them may have
+                    // already been restarted along with the other.
+                    if end.is_some() && self.end(&self.boards[index].simulation).is_some() {
+                        log::info!("Board {index} is restarting after:\n{}", self.boards[index].simulation.report());
+                        self.restart_board(index)?;
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Refill a board with a random configuration from a new seed, after its
+    /// run ended. Compared boards are restarted together, so that they keep
+    /// sharing a seed.
+    fn restart_board(&mut self, index: usize) -> Result<(), Error> {
+        let seed = match &mut self.replay {
+            Some(replay) => replay.next_restart().unwrap_or_else(|| {
+                log::warn!("Board {index} restarted where the recording didn't, so the replay has diverged");
+                rand::random()
+            }),
+            None => rand::random(),
+        };
+        self.record(&Entry::Restart { ticks: self.ticks, board: index, seed })?;
+        if self.screensaver {
+            self.shuffle_board(index);
+        }
+        self.reseed_board(index, seed)
+    }
+
+    /// Switch a board to a random built-in rule, and every board to a random
+    /// color mode, so that the screensaver doesn't keep showing the same
+    /// thing.
+    fn shuffle_board(&mut self, index: usize) {
+        let mut rng = rand::thread_rng();
+        let registry = Registry::with_builtins();
+        let registrations: Vec<_> = registry.iter().collect();
+        if let Some(registration) = registrations.choose(&mut rng) {
+            let simulation = &mut self.boards[index].simulation;
+            simulation.rule = Arc::clone(&registration.rule);
+            simulation.rule_name = registration.name.clone();
+        }
+        if let Some(color_mode) = ColorMode::value_variants().choose(&mut rng) {
+            self.set_color_mode(color_mode.clone());
+        }
+        log::info!(
+            "Board {index} is now running {} in {} mode",
+            self.boards[index].simulation.rule_name,
+            self.color_mode
+        );
+    }
+
+    /// Switch to another way of coloring cells, tracking lineage only while
+    /// it's needed.
+    fn set_color_mode(&mut self, color_mode: ColorMode) {
+        self.color_mode = color_mode;
+        // Boards that weren't tracking lineage start with every live cell in
+        // its own family.
+        for board in &mut self.boards {
+            match self.color_mode {
+                ColorMode::Lineage if board.simulation.lineage.is_none() => {
+                    board.simulation.track_lineage(rand::random())
+                },
+                ColorMode::Lineage => {},
+                _ => board.simulation.lineage = None,
+            }
+        }
+    }
+
+    /// Show or hide the delta view, tracking births and deaths only while it's
+    /// shown.
+    fn toggle_delta(&mut self) {
+        for board in &mut self.boards {
+            let cells = board.simulation.grid.width() * board.simulation.grid.height();
+            board.simulation.delta = match board.simulation.delta {
+                Some(_) => None,
+                None => Some(Delta::new(cells, self.delta_decay)),
+            };
+        }
+    }
+
+    /// Refill a board with a random configuration from the given seed.
+    /// Compared boards are reseeded together.
+    pub fn reseed_board(&mut self, index: usize, seed: u64) -> Result<(), Error> {
+        let indices = if self.comparing { 0..self.boards.len() } else { index..index + 1 };
+        for index in indices {
+            log::info!("Board {index} is using seed {seed}");
+            self.boards[index].simulation.reseed(seed, &self.soup)?;
+        }
+        Ok(())
+    }
+
+    /// Run the commands from the replay that happened before the next tick.
+    fn replay_commands(&mut self) -> Result<(), Error> {
+        while let Some((board, command)) = self.replay.as_mut().and_then(|replay| replay.next_command(self.ticks)) {
+            command.replay(self, board)?;
+        }
+        Ok(())
+    }
+
+    /// Record a remote command that changed a board, if the run is being
+    /// recorded.
+    pub fn record_command(&mut self, board: usize, command: Command) -> Result<(), Error> {
+        self.record(&Entry::Command { ticks: self.ticks, board, command })
+    }
+
+    fn record(&mut self, entry: &Entry) -> Result<(), Error> {
+        match &mut self.recorder {
+            Some(recorder) => recorder.record(entry),
+            None => Ok(()),
+        }
+    }
+
+    /// Advance a single board by one generation, even if it is paused.
+    pub fn step_board(&mut self, index: usize) -> Result<(), Error> {
+        if self.couple && index == 1 {
+            self.boards[1].simulation.coupling = Some(self.boards[0].simulation.grid.clone());
+        }
+        let stats = self.boards[index].simulation.tick()?;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_tick(Duration::from_secs_f64(stats.tick_duration_ms / 1000.0), stats.step_duration);
+        }
+        if let Some(stats_stream) = &mut self.stats_stream {
+            stats_stream.write(index, &stats)?;
+        }
+        if let Some(csv_log) = &mut self.csv_log {
+            csv_log.write(index, &stats)?;
+        }
+        if self.boards[index].simulation.needs_expansion() {
+            self.expand_boards();
+        }
+        #[cfg(feature = "midi")]
+        if let Some(midi) = self.midi.as_mut().filter(|_| index == 0) {
+            midi.play(&self.boards[0].simulation.grid)?;
+        }
+        if let Some(frame_export) = &self.frame_export {
+            let style = Style {
+                theme: &self.theme,
+                color_mode: &self.color_mode,
+                cell_shape: self.cell_shape,
+                cell_size: self.cell_size,
+            };
+            frame_export.export(index, &self.boards[index].simulation, &style)?;
+        }
+        Ok(())
+    }
+
+    /// Zoom in or out by one level, keeping the cell under the cursor in place
+    /// if `at_cursor` and the cursor is over a board, or else the cell at the
+    /// center of the view. Zooming stops the camera from fitting the live
+    /// cells, since that would undo it on the next frame.
+    fn zoom(&mut self, zoom_in: bool, at_cursor: bool) {
+        if matches!(self.follow, Some(Follow::Fit)) {
+            self.follow = None;
+        }
+        let anchor = self.cursor_pixel().filter(|_| at_cursor).and_then(|coords| {
+            let tile = self.boards.iter().map(|board| self.tile(board)).find(|tile| tile.contains(coords))?;
+            // The camera works in logical pixels, and the buffer in physical
+            // ones.
+            let scale = self.pixel_cell_size as f64 / self.cell_size as f64;
+            Some(((coords.x - tile.x) as f64 / scale, (coords.y - tile.y) as f64 / scale))
+        });
+        self.camera.zoom(zoom_in, anchor, self.view(), self.universe());
+        self.window.request_redraw();
+    }
+
+    /// Center the view on the live cells of the first board, if the camera is
+    /// following them.
+    fn follow_cells(&mut self) {
+        let Some(follow) = self.follow else { return };
+        let (view, universe) = (self.view(), self.universe());
+        self.camera.follow(follow, &self.boards[0].simulation.grid, view, universe);
+    }
+
+    /// Double the size of every board's universe, so that they stay the same
+    /// size, and move the view and the selection along with the cells.
+    fn expand_boards(&mut self) {
+        let mut offset = (0, 0);
+        for board in &mut self.boards {
+            offset = board.simulation.expand();
+        }
+        if let Some(selection) = &mut self.selection {
+            selection.translate(offset.0, offset.1);
+        }
+        let (view, universe) = (self.view(), self.universe());
+        self.camera.pan(offset.0 as isize, offset.1 as isize, view, universe);
+    }
+
+    /// Update the metrics with the combined state of every board.
+    pub fn record_metrics(&self, tick_duration: Duration) {
+        if let Some(metrics) = &self.metrics {
+            let generation = self.boards.iter().map(|board| board.simulation.generation).max().unwrap_or(0);
+            let population = self.boards.iter().map(|board| board.simulation.grid.population()).sum();
+            metrics.record_tick(generation, population, tick_duration);
+        }
+    }
+
+    /// Returns the size of the pixel buffer, in physical pixels.
+    fn buffer_size(&self) -> (usize, usize) {
+        (self.grid_width * self.pixel_cell_size, self.grid_height * self.pixel_cell_size)
+    }
+
+    /// Returns the size of each board's region of the window, in logical
+    /// pixels.
+    fn view(&self) -> (usize, usize) {
+        (self.tile_width * self.cell_size, self.tile_height * self.cell_size)
+    }
+
+    /// Returns the size of every board's universe, in cells.
+    fn universe(&self) -> (usize, usize) {
+        let grid = &self.boards[0].simulation.grid;
+        (grid.width(), grid.height())
+    }
+
+    /// Returns the region of the pixel buffer a board is drawn in.
+    fn tile(&self, board: &Board) -> PixelRect {
+        let size = self.pixel_cell_size;
+        let (x, y) = (board.origin.x * size, board.origin.y * size);
+        PixelRect { x, y, w: self.tile_width * size, h: self.tile_height * size }
+    }
+
+    /// Returns the pixel at the top-left corner of a cell of a board, if the
+    /// cell is in view.
+    fn cell_origin(&self, board: &Board, x: usize, y: usize) -> Option<PixelCoords> {
+        let tile = self.tile(board);
+        let cell_size = self.view_cell_size();
+        let cells = self.camera.cells_per_pixel;
+        let column = usize::try_from(x as isize - self.camera.x).ok()? / cells;
+        let row = usize::try_from(y as isize - self.camera.y).ok()? / cells;
+        let origin = PixelCoords { x: tile.x + column * cell_size, y: tile.y + row * cell_size };
+        tile.contains(origin).then_some(origin)
+    }
+
+    /// Returns the physical size of each cell at the camera's zoom, in pixels.
+    fn view_cell_size(&self) -> usize {
+        physical_cell_size(self.camera.cell_size, self.scale_factor)
+    }
+
+    /// Returns the outline of the selection in the pixel buffer, cut off at
+    /// the edges of its board's region of the window.
+    fn selection_rect(&self, selection: &Selection) -> PixelRect {
+        let (origin, size) = selection.bounds();
+        self.region_rect(&self.boards[selection.board], origin, size)
+    }
+
+    /// Returns the outline of a region of cells of a board in the pixel buffer,
+    /// cut off at the edges of the board's region of the window.
+    fn region_rect(&self, board: &Board, (x, y): (usize, usize), (width, height): (usize, usize)) -> PixelRect {
+        let tile = self.tile(board);
+        let (cell_size, cells) = (self.view_cell_size() as isize, self.camera.cells_per_pixel as isize);
+        let pixel = |offset: isize| (offset * cell_size).div_euclid(cells);
+        let left = tile.x as isize + pixel(x as isize - self.camera.x);
+        let top = tile.y as isize + pixel(y as isize - self.camera.y);
+        let (right, bottom) = (left + pixel(width as isize).max(1), top + pixel(height as isize).max(1));
+        let clamp_x = |x: isize| x.clamp(tile.x as isize, (tile.x + tile.w) as isize) as usize;
+        let clamp_y = |y: isize| y.clamp(tile.y as isize, (tile.y + tile.h) as isize) as usize;
+        let (x, y) = (clamp_x(left), clamp_y(top));
+        PixelRect { x, y, w: clamp_x(right) - x, h: clamp_y(bottom) - y }
+    }
+
+    /// Returns the spaceships on a board along with their outlines in the
+    /// pixel buffer, if they are shown. Spaceships are only known for Conway's
+    /// Game of Life.
+    fn spaceships(&self, board: &Board) -> Vec<(PixelRect, Spaceship)> {
+        let simulation = &board.simulation;
+        if !self.show_spaceships || simulation.rule.rulestring().as_deref() != Some("B3/S23") {
+            return Vec::new();
+        }
+        spaceship::find_spaceships(&simulation.grid)
+            .into_iter()
+            .map(|spaceship| {
+                // Leave a cell of space between the spaceship and its outline.
+                let origin = (spaceship.x.saturating_sub(1), spaceship.y.saturating_sub(1));
+                let size = (spaceship.width + 2, spaceship.height + 2);
+                (self.region_rect(board, origin, size), spaceship)
+            })
+            .filter(|(rect, _)| rect.w > 0 && rect.h > 0)
+            .collect()
+    }
+
+    /// Returns the minimap of a board, if it is shown.
+    fn minimap(&self, board: &Board) -> Option<Minimap> {
+        let (columns, rows) = self.camera.view_cells(self.view());
+        let universe = self.universe();
+        let fits = columns >= universe.0 && rows >= universe.1;
+        (self.show_minimap && !fits).then(|| Minimap::new(self.tile(board), universe))
+    }
+
+    /// Draw every board to the window.
+    fn render(&mut self) -> Result<(), Error> {
+        let start = Instant::now();
+        self.update_from_panel()?;
+        let (buffer_width, buffer_height) = self.buffer_size();
+        let cell_size = self.view_cell_size();
+        let overlay = Overlay::new(self.scale_factor);
+        let mut hud = format!("{}, brush {}", self.tool, self.brush_radius);
+        if self.all_paused() {
+            hud.push_str(", paused");
+        }
+        if self.steps_per_frame > 1 {
+            hud.push_str(&format!(", {}x", self.steps_per_frame));
+        }
+        if let Some(rate) = self.generations_per_second.filter(|_| !self.all_paused()) {
+            hud.push_str(&format!(", {rate:.0} gen/s"));
+        }
+        if let Some(stamp) = self.stamp.as_ref().filter(|_| self.placing) {
+            hud.push_str(&format!(", placing {}", stamp.name.as_deref().unwrap_or("stamp")));
+        }
+        if let Some(selection) = &self.selection {
+            let (_, (width, height)) = selection.bounds();
+            hud.push_str(&format!(", selection {width}x{height}"));
+        }
+        for (index, board) in self.boards.iter().enumerate() {
+            let name = if self.boards.len() > 1 { format!("board {index} ") } else { String::new() };
+            if board.simulation.mutation.is_some() {
+                hud.push_str(&format!("\n{name}rule {}", board.simulation.rule_name));
+            }
+            if let Some(species) = &board.simulation.species {
+                hud.push_str(&format!("\n{name}{}", species_populations(species)));
+            }
+            if let Some(methuselah) = &board.simulation.methuselah {
+                hud.push_str(&format!("\n{name}{}", methuselah.metrics()));
+            }
+        }
+        if let Some((notice, _)) = self.notice.as_ref().filter(|(_, until)| Instant::now() < *until) {
+            hud.push('\n');
+            hud.push_str(notice);
+        }
+        let selection = self.selection.as_ref().map(|selection| self.selection_rect(selection));
+        // Preview where the stamp would be placed under the cursor.
+        let preview = match (&self.stamp, self.cursor_pixel().and_then(|coords| self.cell_at(coords))) {
+            (Some(stamp), Some((index, x, y))) if self.placing => {
+                let board = &self.boards[index];
+                let cells = self.stamp_cells(stamp, x, y);
+                let origins: Vec<_> = cells.into_iter().filter_map(|[x, y]| self.cell_origin(board, x, y)).collect();
+                Some((self.tile(board), origins))
+            },
+            _ => None,
+        };
+        let help = self.show_help.then(|| self.help());
+        let console = self.console.as_ref().map(|console| match console.output.as_str() {
+            "" => format!("> {}_", console.input),
+            output => format!("{output}\n> {}_", console.input),
+        });
+        let inspection = self.show_inspector.then(|| self.inspect().zip(self.cursor_pixel())).flatten();
+        let tiles: Vec<PixelRect> = self.boards.iter().map(|board| self.tile(board)).collect();
+        let spaceships: Vec<Vec<(PixelRect, Spaceship)>> =
+            self.boards.iter().map(|board| self.spaceships(board)).collect();
+        let minimaps: Vec<Option<Minimap>> = self.boards.iter().map(|board| self.minimap(board)).collect();
+        let view = ((self.camera.x, self.camera.y), self.camera.view_cells(self.view()));
+        let shading = Ramp::try_from(vec![self.theme.background, self.theme.alive]).expect("the ramp has two colors");
+        let mut canvas = Canvas::new(self.pixels.frame_mut(), buffer_width, buffer_height);
+
+        // Clear the screen to the color of dead cells.
+        let clear_start = Instant::now();
+        canvas.fill_rect(PixelRect { x: 0, y: 0, w: buffer_width, h: buffer_height }, self.theme.background);
+        let drawing_start = Instant::now();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_clear(drawing_start - clear_start);
+        }
+
+        // Draw the part of each board that is in view. When highlighting
+        // divergence, each of the two boards is compared against the other,
+        // and when diffing, every board is compared against the reference.
+        // Overlaid boards are both drawn in the first board's pass.
+        for (index, board) in self.boards.iter().enumerate() {
+            if self.overlay && index == 1 {
+                continue;
+            }
+            let tile = tiles[index];
+            canvas.set_clip(Some(tile));
+            let grid = &board.simulation.grid;
+            let other = self.highlight_divergence.then(|| &self.boards[1 - index].simulation.grid);
+            let overlaid = self.overlay.then(|| &self.boards[1].simulation.grid);
+            let origin = (self.camera.x, self.camera.y);
+            let rendered = self.camera.rendered();
+            if self.camera.cells_per_pixel > 1 {
+                // There are more cells in view than pixels, so each pixel is
+                // shaded by how many of the cells it covers are alive.
+                let cells = self.camera.cells_per_pixel;
+                canvas.draw_density(tile, grid, origin, cell_size, cells, |density| shading.sample(density));
+            } else {
+                let delta = board.simulation.delta.as_ref();
+                let reference = self.reference.as_ref();
+                let cycle = board.simulation.cycle_color();
+                let color = |x, y| {
+                    if board.simulation.is_wall(x, y) {
+                        Some(self.theme.wall)
+                    } else if reference.is_some_and(|reference| diff::alive(reference, x, y) != grid.get(x, y)) {
+                        Some(if grid.get(x, y) { self.theme.birth } else { self.theme.death })
+                    } else if let Some(second) = overlaid {
+                        self.theme.overlaid(grid.get(x, y), second.get(x, y))
+                    } else if !grid.get(x, y) {
+                        // Cells that just died fade out from the death color,
+                        // and cells that have been alive before stay marked
+                        // when showing history.
+                        let death = delta.map_or(0.0, |delta| delta.death(x, y, grid.width()));
+                        let history =
+                            (self.show_history && board.simulation.was_alive(x, y)).then_some(self.theme.history);
+                        let dead = history.unwrap_or(self.theme.background);
+                        (death > 0.0).then(|| dead.blend(self.theme.death, death)).or(history)
+                    } else if other.is_some_and(|other| !other.get(x, y)) {
+                        Some(self.theme.diverged)
+                    } else {
+                        let color = board.simulation.cell_color(x, y, &self.theme, &self.color_mode, cycle);
+                        let birth = delta.map_or(0.0, |delta| delta.birth(x, y, grid.width()));
+                        Some(color.blend(self.theme.birth, birth))
+                    }
+                };
+                match rendered {
+                    // Zooming is being animated, so the cells aren't on whole
+                    // pixels and grid lines wouldn't line up with them.
+                    Some(view) => {
+                        let size = view.cell_size * self.pixel_cell_size as f64 / self.cell_size as f64;
+                        canvas.draw_cells_scaled(tile, grid, (view.x, view.y), size, self.cell_shape, color);
+                    },
+                    None => {
+                        canvas.draw_cells(tile, grid, origin, cell_size, self.cell_shape, color);
+                        if let Some(grid_color) = self.theme.grid {
+                            canvas.draw_grid_lines(tile, cell_size, self.theme.grid_width, grid_color);
+                        }
+                    },
+                }
+            }
+
+            for (rect, spaceship) in &spaceships[index] {
+                canvas.stroke_rect(*rect, self.theme.spaceship);
+                // Point from the center of the outline in the direction the
+                // spaceship moves.
+                let center = ((rect.x + rect.w / 2) as isize, (rect.y + rect.h / 2) as isize);
+                let (dx, dy) = (spaceship.velocity.0 as f64, spaceship.velocity.1 as f64);
+                let length = rect.w.max(rect.h) as f64 / (dx * dx + dy * dy).sqrt();
+                let tip = (center.0 + (dx * length) as isize, center.1 + (dy * length) as isize);
+                canvas.draw_arrow(center, tip, self.theme.spaceship);
+            }
+
+            if let Some(minimap) = &minimaps[index] {
+                minimap.draw(&mut canvas, grid, view, &self.theme);
+            }
+        }
+        if let Some((tile, origins)) = preview {
+            canvas.set_clip(Some(tile));
+            for origin in origins {
+                canvas.fill_cell(origin, cell_size, self.cell_shape, self.theme.selection);
+            }
+        }
+        canvas.set_clip(None);
+
+        if let Some(rect) = selection.filter(|rect| rect.w > 0 && rect.h > 0) {
+            canvas.stroke_rect(rect, self.theme.selection);
+        }
+
+        // Separate the boards with thin lines along their shared edges.
+        let (columns, rows) = self.layout;
+        for column in 1..columns {
+            let x = column * self.tile_width * self.pixel_cell_size;
+            canvas.fill_rect(PixelRect { x, y: 0, w: 1, h: buffer_height }, self.theme.divider);
+        }
+        for row in 1..rows {
+            let y = row * self.tile_height * self.pixel_cell_size;
+            canvas.fill_rect(PixelRect { x: 0, y, w: buffer_width, h: 1 }, self.theme.divider);
+        }
+
+        if !self.screensaver {
+            overlay.draw(&mut canvas, PixelCoords { x: overlay.padding, y: overlay.padding }, &hud, &self.theme);
+        }
+
+        // Show the console along the bottom of the window.
+        if let Some(console) = &console {
+            let (_, h) = overlay.size(console);
+            let origin = PixelCoords { x: overlay.padding, y: buffer_height.saturating_sub(h + overlay.padding) };
+            overlay.draw(&mut canvas, origin, console, &self.theme);
+        }
+
+        // Show the help in the middle of the window, over everything else but
+        // the cursor's details.
+        if let Some(help) = &help {
+            let (w, h) = overlay.size(help);
+            let origin = PixelCoords { x: buffer_width.saturating_sub(w) / 2, y: buffer_height.saturating_sub(h) / 2 };
+            overlay.draw(&mut canvas, origin, help, &self.theme);
+        }
+
+        // Show the pattern browser in the middle of the window, over the help.
+        if let Some(browser) = &self.browser {
+            browser.draw(&mut canvas, &overlay, (buffer_width, buffer_height), &self.theme);
+        }
+
+        // Show the inspected cell's details beside the cursor, keeping them
+        // inside the window.
+        if let Some((text, cursor)) = inspection {
+            let (w, h) = overlay.size(&text);
+            let x = (cursor.x + 4 * overlay.padding).min(buffer_width.saturating_sub(w));
+            let y = (cursor.y + 4 * overlay.padding).min(buffer_height.saturating_sub(h));
+            overlay.draw(&mut canvas, PixelCoords { x, y }, &text, &self.theme);
+        }
+
+        // Show where the time of the last frame went in the top-right corner.
+        if let Some(profiler) = &mut self.profiler {
+            let text = profiler.text();
+            let (w, _) = overlay.size(&text);
+            let origin = PixelCoords { x: buffer_width.saturating_sub(w + overlay.padding), y: overlay.padding };
+            overlay.draw(&mut canvas, origin, &text, &self.theme);
+            profiler.record_drawing(drawing_start.elapsed());
+        }
+
+        let present_start = Instant::now();
+        match &mut self.panel {
+            Some(panel) => self.pixels.render_with(|encoder, target, context| {
+                context.scaling_renderer.render(encoder, target);
+                panel.render(encoder, target, context);
+                Ok(())
+            }),
+            None => self.pixels.render(),
+        }
+        .map_err(Error::Render)?;
+        self.render_duration = start.elapsed();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.finish_frame(present_start.elapsed());
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_frame();
+            if let Some(fps) = self.frame_counter.frame() {
+                metrics.record_fps(fps);
+            }
+        }
+        Ok(())
+    }
+}
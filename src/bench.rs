@@ -0,0 +1,80 @@
+use std::time::Instant;
+
+use cgol2::engine::Engine;
+use cgol2::grid::Grid;
+use cgol2::soup::{random_soup, Fill, FillKind, Symmetry};
+use cgol2::{parse_dimensions, parse_probability};
+use clap::{Args, ValueEnum};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::error::Error;
+use crate::resolve_rule;
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Number of generations to run for each engine and grid size.
+    #[arg(long, default_value_t = 1000)]
+    generations: u64,
+
+    /// Comma-separated grid sizes to benchmark, each given as `WIDTHxHEIGHT`.
+    #[arg(long, value_delimiter = ',', default_value = "64x64,256x256", value_parser = parse_dimensions)]
+    sizes: Vec<(usize, usize)>,
+
+    /// Comma-separated engines to benchmark. Defaults to every available
+    /// engine.
+    #[arg(long, value_delimiter = ',')]
+    engines: Vec<Engine>,
+
+    /// Seed for the initial random configuration, so that every engine starts
+    /// from the same state.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    #[arg(long, default_value_t = 0.25, value_parser = parse_probability)]
+    alive_random_chance: f64,
+
+    /// Name of a registered rule, or a rulestring in B/S notation.
+    #[arg(long, default_value = "life")]
+    rule: String,
+}
+
+/// Run every requested engine on every requested grid size and print how many
+/// generations per second each one achieved.
+pub fn run(args: BenchArgs) -> Result<(), Error> {
+    let rule = resolve_rule(&args.rule)?;
+    let engines = if args.engines.is_empty() { Engine::value_variants().to_vec() } else { args.engines.clone() };
+
+    println!("{:<12} {:>12} {:>12} {:>10} {:>14}", "engine", "size", "generations", "seconds", "gens/sec");
+    for &(width, height) in &args.sizes {
+        let mut initial = Grid::new(width, height);
+        let mut rng = StdRng::seed_from_u64(args.seed);
+        random_soup(
+            &mut initial,
+            None,
+            args.alive_random_chance,
+            Fill::Uniform,
+            FillKind::Bernoulli,
+            Symmetry::None,
+            &mut rng,
+        );
+
+        for &engine in &engines {
+            let mut grid = initial.clone();
+            let start = Instant::now();
+            for _ in 0..args.generations {
+                grid = engine.step(&grid, rule.as_ref());
+            }
+            let seconds = start.elapsed().as_secs_f64();
+            println!(
+                "{:<12} {:>12} {:>12} {:>10.3} {:>14.1}",
+                engine.to_string(),
+                format!("{width}x{height}"),
+                args.generations,
+                seconds,
+                args.generations as f64 / seconds,
+            );
+        }
+    }
+    Ok(())
+}
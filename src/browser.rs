@@ -0,0 +1,105 @@
+use cgol2::grid::Grid;
+use cgol2::library::{self, Entry};
+
+use crate::overlay::Overlay;
+use crate::render::{Canvas, PixelCoords, PixelRect, Rgb};
+use crate::theme::Theme;
+
+/// The keys that act on the selected pattern, shown under the list.
+const USAGE: &str = "up / down: select\nenter: stamp, shift+enter: load\nb / esc: close";
+
+/// A list of the bundled patterns to choose one to stamp or load from, with a
+/// thumbnail of the selected one.
+#[derive(Default)]
+pub struct Browser {
+    /// The index of the selected pattern in [`library::PATTERNS`].
+    selected: usize,
+}
+
+impl Browser {
+    /// Move the selection by the given number of entries, wrapping around the
+    /// ends of the list.
+    pub fn select(&mut self, offset: isize) {
+        let count = library::PATTERNS.len() as isize;
+        self.selected = (self.selected as isize + offset).rem_euclid(count) as usize;
+    }
+
+    /// Returns the selected pattern.
+    pub fn entry(&self) -> &'static Entry {
+        &library::PATTERNS[self.selected]
+    }
+
+    /// Returns the list of patterns, with the selected one marked and its
+    /// details below them.
+    fn text(&self) -> String {
+        let mut text = String::new();
+        for (index, entry) in library::PATTERNS.iter().enumerate() {
+            let marker = if index == self.selected { ">" } else { " " };
+            text.push_str(&format!("{marker} {}\n", entry.name));
+        }
+        let entry = self.entry();
+        let cells = entry.pattern().cells;
+        let period = entry.period.map_or_else(|| "no period".to_string(), |period| format!("period {period}"));
+        text.push_str(&format!(
+            "\n{}x{}, {period}\nfound by {}\n\n{USAGE}",
+            cells.width(),
+            cells.height(),
+            entry.discoverer
+        ));
+        text
+    }
+
+    /// Draw the list in the middle of the window, with the thumbnail in a
+    /// square panel of the same height beside it.
+    pub fn draw(&self, canvas: &mut Canvas, overlay: &Overlay, (width, height): (usize, usize), theme: &Theme) {
+        let text = self.text();
+        let (w, h) = overlay.size(&text);
+        let x = width.saturating_sub(w + h) / 2;
+        let y = height.saturating_sub(h) / 2;
+        overlay.draw(canvas, PixelCoords { x, y }, &text, theme);
+
+        let panel = PixelRect { x: x + w, y, w: h, h };
+        canvas.fill_rect(panel, theme.hud_background);
+        canvas.stroke_rect(panel, theme.divider);
+        let inset = 2 * overlay.padding;
+        let area = PixelRect {
+            x: panel.x + inset,
+            y: panel.y + inset,
+            w: panel.w.saturating_sub(2 * inset),
+            h: panel.h.saturating_sub(2 * inset),
+        };
+        draw_thumbnail(canvas, area, &self.entry().pattern().cells, theme.hud_text);
+    }
+}
+
+/// Draw the cells scaled to fit the area without stretching them, centered in
+/// it. Each pixel is lit if any of the cells it covers is alive, so that small
+/// patterns are drawn with square blocks and large ones keep thin features.
+fn draw_thumbnail(canvas: &mut Canvas, area: PixelRect, cells: &Grid, rgb: Rgb) {
+    if cells.width() == 0 || cells.height() == 0 {
+        return;
+    }
+    let scale = (area.w as f64 / cells.width() as f64).min(area.h as f64 / cells.height() as f64);
+    // Scaling up is kept to whole pixels per cell, so that every cell is the
+    // same size.
+    let scale = if scale >= 1.0 { scale.floor() } else { scale };
+    let w = ((cells.width() as f64 * scale).round() as usize).clamp(1, area.w.max(1));
+    let h = ((cells.height() as f64 * scale).round() as usize).clamp(1, area.h.max(1));
+    let (x0, y0) = (area.x + (area.w - w.min(area.w)) / 2, area.y + (area.h - h.min(area.h)) / 2);
+
+    // The cells covered by each pixel, rounded so that every pixel covers at
+    // least one cell when the pattern is scaled down.
+    let span = |pixel: usize, pixels: usize, cells: usize| {
+        let start = pixel * cells / pixels;
+        start..((pixel + 1) * cells / pixels).max(start + 1)
+    };
+    for py in 0..h {
+        let rows = span(py, h, cells.height());
+        for px in 0..w {
+            let columns = span(px, w, cells.width());
+            if rows.clone().any(|y| columns.clone().any(|x| cells.get(x, y))) {
+                canvas.fill_rect(PixelRect { x: x0 + px, y: y0 + py, w: 1, h: 1 }, rgb);
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::{Duration, Instant};
+
+use cgol2::grid::Grid;
+use clap::ValueEnum;
+
+/// The smallest size a cell can be zoomed out to, in logical pixels.
+const MIN_CELL_SIZE: usize = 1;
+/// The largest size a cell can be zoomed in to, in logical pixels.
+const MAX_CELL_SIZE: usize = 64;
+/// The most cells wide and tall that a logical pixel can cover when zoomed out
+/// past one pixel per cell.
+const MAX_CELLS_PER_PIXEL: usize = 4096;
+/// Fitting the live cells in view leaves this fraction of their size as a
+/// margin on each side, and at least one cell.
+const FIT_MARGIN: usize = 10;
+/// How long zooming takes to animate from one zoom level to the next.
+const ZOOM_DURATION: Duration = Duration::from_millis(150);
+
+/// What the camera keeps at the center of the view as the cells move.
+#[derive(ValueEnum, strum::Display, Clone, Copy)]
+#[strum(serialize_all = "lowercase")]
+pub enum Follow {
+    /// The average position of the live cells, which stays with the bulk of
+    /// a pattern as gliders escape from it.
+    Centroid,
+    /// The center of the smallest region that holds every live cell, which
+    /// keeps escaping gliders in view for as long as they fit.
+    Bounds,
+    /// The smallest region that holds every live cell, zooming out as it
+    /// grows so that all of it stays in view.
+    Fit,
+}
+
+/// A view of the universe at any position and zoom, rather than at whole
+/// cells and zoom levels, to draw the camera with while zooming is animated.
+#[derive(Clone, Copy)]
+pub struct View {
+    /// The universe coordinates of the top-left corner of the view.
+    pub x: f64,
+    pub y: f64,
+    /// How many logical pixels wide and tall each cell is drawn.
+    pub cell_size: f64,
+}
+
+/// An animated zoom from one view to the camera's current position and zoom,
+/// which keeps the cell under an anchor point of the view in place.
+struct Transition {
+    from: View,
+    /// The anchor point, in logical pixels from the top-left corner of the
+    /// view.
+    anchor: (f64, f64),
+    start: Instant,
+}
+
+/// Which part of the universe is visible in each board's region of the window,
+/// and how closely it is viewed.
+pub struct Camera {
+    /// The universe coordinates of the cell at the top-left corner of the
+    /// view. These are negative when the universe is smaller than the view,
+    /// so that it is centered.
+    pub x: isize,
+    pub y: isize,
+    /// How many logical pixels wide and tall each cell is drawn.
+    pub cell_size: usize,
+    /// How many cells wide and tall each logical pixel covers, once zoomed out
+    /// past one pixel per cell. This is 1 unless `cell_size` is.
+    pub cells_per_pixel: usize,
+    /// The zoom that is being animated, until it finishes.
+    transition: Option<Transition>,
+}
+
+impl Camera {
+    /// Create a camera centered on a universe of the given size.
+    pub fn new(cell_size: usize, view: (usize, usize), universe: (usize, usize)) -> Self {
+        let mut camera = Self { x: 0, y: 0, cell_size, cells_per_pixel: 1, transition: None };
+        camera.center_on(universe.0 as isize / 2, universe.1 as isize / 2, view, universe);
+        camera
+    }
+
+    /// Returns the number of cells that fit entirely in a view that is the
+    /// given number of logical pixels wide and tall.
+    pub fn view_cells(&self, view: (usize, usize)) -> (usize, usize) {
+        (view.0 / self.cell_size * self.cells_per_pixel, view.1 / self.cell_size * self.cells_per_pixel)
+    }
+
+    /// Returns the view the camera settles at once any zoom has finished.
+    fn settled(&self) -> View {
+        let cell_size = self.cell_size as f64 / self.cells_per_pixel as f64;
+        View { x: self.x as f64, y: self.y as f64, cell_size }
+    }
+
+    /// Returns the view to draw while a zoom is being animated, or `None` once
+    /// the camera has settled at its position and zoom.
+    ///
+    /// The cell size changes at an even rate relative to its size, so that
+    /// every step of the animation looks like the same amount of zoom, and
+    /// the anchor drifts from the cell that was under it to the one that is
+    /// once the camera has settled, which differ only by rounding.
+    pub fn rendered(&self) -> Option<View> {
+        let transition = self.transition.as_ref()?;
+        let t = transition.start.elapsed().as_secs_f64() / ZOOM_DURATION.as_secs_f64();
+        if t >= 1.0 {
+            return None;
+        }
+        let t = t * t * (3.0 - 2.0 * t);
+        let (from, to) = (transition.from, self.settled());
+        let (ax, ay) = transition.anchor;
+        let cell_size = from.cell_size * (to.cell_size / from.cell_size).powf(t);
+        let anchored = |from_x: f64, to_x: f64, anchor: f64| {
+            let (start, end) = (from_x + anchor / from.cell_size, to_x + anchor / to.cell_size);
+            start + (end - start) * t - anchor / cell_size
+        };
+        Some(View { x: anchored(from.x, to.x, ax), y: anchored(from.y, to.y, ay), cell_size })
+    }
+
+    /// Move the view so that the given cell is at its center.
+    pub fn center_on(&mut self, x: isize, y: isize, view: (usize, usize), universe: (usize, usize)) {
+        self.transition = None;
+        let (columns, rows) = self.view_cells(view);
+        self.x = x - columns as isize / 2;
+        self.y = y - rows as isize / 2;
+        self.clamp(view, universe);
+    }
+
+    /// Move the view so that the live cells of the grid stay centered in the
+    /// way given, leaving it where it is if there are none.
+    pub fn follow(&mut self, follow: Follow, grid: &Grid, view: (usize, usize), universe: (usize, usize)) {
+        let center = match follow {
+            Follow::Centroid => grid.centroid().map(|(x, y)| (x.round() as isize, y.round() as isize)),
+            Follow::Bounds => grid.bounds().map(|((x, y), (w, h))| ((x + w / 2) as isize, (y + h / 2) as isize)),
+            Follow::Fit => return self.fit(grid, view, universe),
+        };
+        if let Some((x, y)) = center {
+            self.center_on(x, y, view, universe);
+        }
+    }
+
+    /// Zoom and pan so that every live cell of the grid is in view with a
+    /// margin around them, as closely as they fit. Nothing changes if there
+    /// are none.
+    pub fn fit(&mut self, grid: &Grid, view: (usize, usize), universe: (usize, usize)) {
+        let Some(((x, y), (w, h))) = grid.bounds() else { return };
+        let with_margin = |size: usize| size + 2 * (size / FIT_MARGIN).max(1);
+        let (columns, rows) = (with_margin(w), with_margin(h));
+        // Use the largest cells that fit, and once even the smallest don't,
+        // as few cells per pixel as needed.
+        self.cell_size = (view.0 / columns).min(view.1 / rows).clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+        self.cells_per_pixel = 1;
+        loop {
+            let (visible_columns, visible_rows) = self.view_cells(view);
+            let fits = visible_columns >= columns && visible_rows >= rows;
+            if fits || self.cells_per_pixel >= MAX_CELLS_PER_PIXEL {
+                break;
+            }
+            self.cells_per_pixel *= 2;
+        }
+        self.center_on((x + w / 2) as isize, (y + h / 2) as isize, view, universe);
+    }
+
+    /// Move the view by the given number of cells.
+    pub fn pan(&mut self, dx: isize, dy: isize, view: (usize, usize), universe: (usize, usize)) {
+        self.transition = None;
+        self.x += dx;
+        self.y += dy;
+        self.clamp(view, universe);
+    }
+
+    /// Double the size of cells if `zoom_in`, or halve it otherwise, keeping
+    /// the same cell under the anchor, a point in logical pixels from the
+    /// top-left corner of the view, or else at the center of the view.
+    /// Zooming out past one pixel per cell doubles the number of cells each
+    /// pixel covers instead.
+    ///
+    /// The zoom is animated from wherever the last one had got to, unless
+    /// either end of it covers more than one cell per pixel, which snaps.
+    pub fn zoom(&mut self, zoom_in: bool, anchor: Option<(f64, f64)>, view: (usize, usize), universe: (usize, usize)) {
+        let from = self.rendered().unwrap_or_else(|| self.settled());
+        let (ax, ay) = anchor.unwrap_or((view.0 as f64 / 2.0, view.1 as f64 / 2.0));
+        let cell = (from.x + ax / from.cell_size, from.y + ay / from.cell_size);
+        let density = self.cells_per_pixel > 1;
+        match (zoom_in, self.cells_per_pixel > 1, self.cell_size > MIN_CELL_SIZE) {
+            (true, true, _) => self.cells_per_pixel /= 2,
+            (true, false, _) => self.cell_size = (self.cell_size * 2).min(MAX_CELL_SIZE),
+            (false, _, true) => self.cell_size /= 2,
+            (false, _, false) => self.cells_per_pixel = (self.cells_per_pixel * 2).min(MAX_CELLS_PER_PIXEL),
+        }
+        let cell_size = self.settled().cell_size;
+        self.x = (cell.0 - ax / cell_size).round() as isize;
+        self.y = (cell.1 - ay / cell_size).round() as isize;
+        self.clamp(view, universe);
+        let animated = !density && self.cells_per_pixel == 1;
+        self.transition = animated.then(|| Transition { from, anchor: (ax, ay), start: Instant::now() });
+    }
+
+    /// Keep as much of the universe in view as possible, centering it along
+    /// any dimension where it is smaller than the view.
+    fn clamp(&mut self, view: (usize, usize), universe: (usize, usize)) {
+        let (columns, rows) = self.view_cells(view);
+        let clamp_axis = |position: isize, visible: usize, size: usize| {
+            if size <= visible {
+                -((visible - size) as isize / 2)
+            } else {
+                position.clamp(0, (size - visible) as isize)
+            }
+        };
+        self.x = clamp_axis(self.x, columns, universe.0);
+        self.y = clamp_axis(self.y, rows, universe.1);
+    }
+}
@@ -0,0 +1,481 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::grid::Grid;
+
+/// Identifies a file as the index of a chunked world.
+const MAGIC: &[u8; 8] = b"CGOLCHNK";
+
+/// The version of the format, which changes whenever the layout does.
+const VERSION: u32 = 1;
+
+/// The size of the index's header: the magic bytes, the version, four bytes
+/// of padding, and then the chunk size, generation, and number of chunks as
+/// 64-bit integers.
+const HEADER_LEN: usize = 40;
+
+/// The size of each entry of the index: the signed column and row of a chunk,
+/// and the slot it is stored in, as 64-bit integers.
+const ENTRY_LEN: usize = 24;
+
+/// The width and height of a chunk, in cells. Each row of a chunk is one
+/// 64-bit word, with the leftmost cell in the lowest bit.
+pub const CHUNK_SIZE: usize = 64;
+
+/// The number of bytes a chunk takes on disk.
+const CHUNK_LEN: usize = CHUNK_SIZE * 8;
+
+/// The name of the index file in a world's directory.
+const INDEX: &str = "index";
+
+/// The name of the file the chunks are stored in, one after another.
+const CHUNKS: &str = "chunks";
+
+/// The rows of a chunk.
+type Rows = Box<[u64; CHUNK_SIZE]>;
+
+/// A chunk held in memory.
+struct Cached {
+    rows: Rows,
+    /// Whether the chunk has changed since it was loaded or last written.
+    dirty: bool,
+    /// When the chunk was last used, for evicting the least recently used.
+    used: u64,
+}
+
+/// A universe too large to hold in memory, stored on disk as fixed-size
+/// chunks that are loaded as they are needed and written back once they
+/// haven't been used in a while.
+///
+/// A world is a directory holding an index and a file of chunks. The index is
+/// a header followed by an entry for each stored chunk, saying which slot of
+/// the chunk file it is in. Chunks that have never had a live cell aren't
+/// stored at all, so a sparse universe takes little more room on disk than its
+/// live cells do. Cells are addressed by signed coordinates, so a world has no
+/// edges to run into.
+///
+/// At most a fixed number of chunks are held in memory, with the least
+/// recently used written back to disk when another is needed. Chunks are never
+/// written over the slots the index on disk refers to, so the world on disk
+/// is the one last flushed until [`World::flush`] is called again, and changes
+/// since then are lost if it isn't.
+pub struct World {
+    dir: PathBuf,
+    chunks: File,
+    /// The slots of the chunks stored on disk.
+    index: HashMap<(i64, i64), u64>,
+    /// The number of slots in the chunk file.
+    slots: u64,
+    /// Slots that no index refers to, which can be written over.
+    free: Vec<u64>,
+    /// Slots written since the index was last written, which only this
+    /// index refers to and so can be written over again.
+    written: HashSet<u64>,
+    /// Slots the index on disk still refers to, but this one no longer does,
+    /// which become free once the index is written.
+    retired: Vec<u64>,
+    cache: HashMap<(i64, i64), Cached>,
+    /// The most chunks to hold in memory at once.
+    capacity: usize,
+    /// Counts every use of a chunk, to order them by how recently they were
+    /// used.
+    clock: u64,
+    /// The generation the world was saved at.
+    pub generation: u64,
+}
+
+impl World {
+    /// Create an empty world in the given directory, replacing any world
+    /// already there, holding at most `capacity` chunks in memory at once.
+    pub fn create(dir: &Path, capacity: usize) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let chunks = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(dir.join(CHUNKS))?;
+        let world = Self::new(dir, chunks, HashMap::new(), 0, capacity, 0);
+        world.write_index()?;
+        Ok(world)
+    }
+
+    /// Open a world saved in the given directory, holding at most `capacity`
+    /// chunks in memory at once.
+    pub fn open(dir: &Path, capacity: usize) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut bytes = Vec::new();
+        File::open(dir.join(INDEX))?.read_to_end(&mut bytes)?;
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(invalid("not the index of a chunked world"));
+        }
+        let number = |start: usize| -> u64 {
+            u64::from_le_bytes(bytes[start..start + 8].try_into().expect("the index fields are eight bytes long"))
+        };
+        let version = u32::from_le_bytes(bytes[8..12].try_into().expect("the version is four bytes long"));
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported world version {version}")));
+        }
+        if number(16) != CHUNK_SIZE as u64 {
+            return Err(invalid(&format!("unsupported chunk size {}", number(16))));
+        }
+        let generation = number(24);
+        let count = number(32) as usize;
+        if count.checked_mul(ENTRY_LEN).and_then(|len| len.checked_add(HEADER_LEN)) != Some(bytes.len()) {
+            return Err(invalid("the index's size doesn't match its number of chunks"));
+        }
+
+        let chunks = OpenOptions::new().read(true).write(true).open(dir.join(CHUNKS))?;
+        let slots = chunks.metadata()?.len() / CHUNK_LEN as u64;
+        let mut index = HashMap::with_capacity(count);
+        for entry in 0..count {
+            let start = HEADER_LEN + entry * ENTRY_LEN;
+            let (column, row, slot) = (number(start) as i64, number(start + 8) as i64, number(start + 16));
+            if slot >= slots {
+                return Err(invalid("the index refers to a chunk past the end of the chunk file"));
+            }
+            index.insert((column, row), slot);
+        }
+        Ok(Self::new(dir, chunks, index, slots, capacity, generation))
+    }
+
+    fn new(
+        dir: &Path,
+        chunks: File,
+        index: HashMap<(i64, i64), u64>,
+        slots: u64,
+        capacity: usize,
+        generation: u64,
+    ) -> Self {
+        let used: HashSet<u64> = index.values().copied().collect();
+        Self {
+            dir: dir.to_path_buf(),
+            chunks,
+            index,
+            slots,
+            free: (0..slots).rev().filter(|slot| !used.contains(slot)).collect(),
+            written: HashSet::new(),
+            retired: Vec::new(),
+            cache: HashMap::new(),
+            capacity: capacity.max(1),
+            clock: 0,
+            generation,
+        }
+    }
+
+    /// Returns the bounds of the chunks that have ever had a live cell, as the
+    /// top-left cell of the first and the bottom-right cell of the last, or
+    /// `None` if there are none.
+    pub fn bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+        let live = |chunk: &Cached| chunk.rows.iter().any(|&row| row != 0);
+        let cached = self.cache.iter().filter(move |(_, chunk)| live(chunk)).map(|(position, _)| position);
+        let keys = || self.index.keys().chain(cached.clone());
+        let columns = keys().map(|&(column, _)| column);
+        let rows = keys().map(|&(_, row)| row);
+        let (left, right) = (columns.clone().min()?, columns.max()?);
+        let (top, bottom) = (rows.clone().min()?, rows.max()?);
+        let size = CHUNK_SIZE as i64;
+        Some(((left * size, top * size), (right * size + size - 1, bottom * size + size - 1)))
+    }
+
+    /// Returns whether the cell at the given coordinates is alive.
+    pub fn get(&mut self, x: i64, y: i64) -> io::Result<bool> {
+        let ((column, row), (x, y)) = locate(x, y);
+        Ok(self.chunk(column, row)?.rows[y] & (1 << x) != 0)
+    }
+
+    /// Set whether the cell at the given coordinates is alive.
+    pub fn set(&mut self, x: i64, y: i64, alive: bool) -> io::Result<()> {
+        let ((column, row), (x, y)) = locate(x, y);
+        let chunk = self.chunk(column, row)?;
+        let before = chunk.rows[y];
+        if alive {
+            chunk.rows[y] |= 1 << x;
+        } else {
+            chunk.rows[y] &= !(1 << x);
+        }
+        chunk.dirty |= chunk.rows[y] != before;
+        Ok(())
+    }
+
+    /// Load the region of the world with its top-left cell at the given
+    /// coordinates, such as the part of it in view of the camera.
+    pub fn load(&mut self, x0: i64, y0: i64, width: usize, height: usize) -> io::Result<Grid> {
+        let mut grid = Grid::new(width, height);
+        if width == 0 || height == 0 {
+            return Ok(grid);
+        }
+        for (column, row) in chunks_within(x0, y0, width, height) {
+            let rows = self.chunk(column, row)?.rows.clone();
+            for_cells_within(column, row, x0, y0, width, height, |(x, y), (cx, cy)| {
+                if rows[cy] & (1 << cx) != 0 {
+                    grid.set(x, y, true);
+                }
+            });
+        }
+        Ok(grid)
+    }
+
+    /// Write a region of the universe back to the world, with its top-left
+    /// cell at the given coordinates.
+    pub fn store(&mut self, grid: &Grid, x0: i64, y0: i64) -> io::Result<()> {
+        let (width, height) = (grid.width(), grid.height());
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        for (column, row) in chunks_within(x0, y0, width, height) {
+            // Chunks that aren't stored and would stay empty are left out.
+            if !self.index.contains_key(&(column, row)) && !self.cache.contains_key(&(column, row)) {
+                let mut empty = true;
+                for_cells_within(column, row, x0, y0, width, height, |(x, y), _| empty &= !grid.get(x, y));
+                if empty {
+                    continue;
+                }
+            }
+            let chunk = self.chunk(column, row)?;
+            let before = chunk.rows.clone();
+            for_cells_within(column, row, x0, y0, width, height, |(x, y), (cx, cy)| {
+                if grid.get(x, y) {
+                    chunk.rows[cy] |= 1 << cx;
+                } else {
+                    chunk.rows[cy] &= !(1 << cx);
+                }
+            });
+            chunk.dirty |= chunk.rows != before;
+        }
+        Ok(())
+    }
+
+    /// Write every changed chunk and the index to disk. The chunks are written
+    /// to slots the old index doesn't refer to, and the new index next to its
+    /// path and then moved over it, so that an interrupted flush leaves the
+    /// world as it was when it was last flushed.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let dirty: Vec<(i64, i64)> =
+            self.cache.iter().filter(|(_, chunk)| chunk.dirty).map(|(&position, _)| position).collect();
+        for position in dirty {
+            let rows = self.cache[&position].rows.clone();
+            self.write_chunk(position, &rows)?;
+            self.cache.get_mut(&position).expect("the chunk was just found in the cache").dirty = false;
+        }
+        self.chunks.sync_data()?;
+        self.write_index()?;
+        self.written.clear();
+        self.free.append(&mut self.retired);
+        Ok(())
+    }
+
+    /// Returns the chunk at the given chunk coordinates, loading it if it
+    /// isn't in memory, and evicting the least recently used chunk if there
+    /// isn't room for it.
+    fn chunk(&mut self, column: i64, row: i64) -> io::Result<&mut Cached> {
+        self.clock += 1;
+        if !self.cache.contains_key(&(column, row)) {
+            if self.cache.len() >= self.capacity {
+                self.evict()?;
+            }
+            let rows = self.read_chunk((column, row))?;
+            self.cache.insert((column, row), Cached { rows, dirty: false, used: 0 });
+        }
+        let chunk = self.cache.get_mut(&(column, row)).expect("the chunk was just loaded");
+        chunk.used = self.clock;
+        Ok(chunk)
+    }
+
+    /// Drop the least recently used chunk from memory, writing it first if it
+    /// has changed.
+    fn evict(&mut self) -> io::Result<()> {
+        let Some((&position, _)) = self.cache.iter().min_by_key(|(_, chunk)| chunk.used) else { return Ok(()) };
+        let chunk = self.cache.remove(&position).expect("the chunk was just found in the cache");
+        if chunk.dirty {
+            self.write_chunk(position, &chunk.rows)?;
+        }
+        Ok(())
+    }
+
+    /// Read a chunk from disk, or return an empty one if it isn't stored.
+    fn read_chunk(&mut self, position: (i64, i64)) -> io::Result<Rows> {
+        let mut rows: Rows = Box::new([0; CHUNK_SIZE]);
+        let Some(&slot) = self.index.get(&position) else { return Ok(rows) };
+        let mut bytes = [0; CHUNK_LEN];
+        self.chunks.seek(SeekFrom::Start(slot * CHUNK_LEN as u64))?;
+        self.chunks.read_exact(&mut bytes)?;
+        for (row, bytes) in rows.iter_mut().zip(bytes.chunks_exact(8)) {
+            *row = u64::from_le_bytes(bytes.try_into().expect("the rows are eight bytes long"));
+        }
+        Ok(rows)
+    }
+
+    /// Write a chunk to its slot if it was written there since the index was
+    /// last written, or otherwise to a free slot, or a new one at the end of
+    /// the chunk file if none are free.
+    fn write_chunk(&mut self, position: (i64, i64), rows: &[u64; CHUNK_SIZE]) -> io::Result<()> {
+        let slot = match self.index.get(&position) {
+            Some(&slot) if self.written.contains(&slot) => slot,
+            old => {
+                if let Some(&old) = old {
+                    self.retired.push(old);
+                }
+                let slot = self.free.pop().unwrap_or_else(|| {
+                    self.slots += 1;
+                    self.slots - 1
+                });
+                self.index.insert(position, slot);
+                self.written.insert(slot);
+                slot
+            },
+        };
+        let bytes: Vec<u8> = rows.iter().flat_map(|row| row.to_le_bytes()).collect();
+        self.chunks.seek(SeekFrom::Start(slot * CHUNK_LEN as u64))?;
+        self.chunks.write_all(&bytes)
+    }
+
+    fn write_index(&self) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.index.len() * ENTRY_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&(CHUNK_SIZE as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.generation.to_le_bytes());
+        bytes.extend_from_slice(&(self.index.len() as u64).to_le_bytes());
+        for (&(column, row), &slot) in &self.index {
+            bytes.extend_from_slice(&column.to_le_bytes());
+            bytes.extend_from_slice(&row.to_le_bytes());
+            bytes.extend_from_slice(&slot.to_le_bytes());
+        }
+
+        let path = self.dir.join(INDEX);
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut file = File::create(&partial)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&partial, path)
+    }
+}
+
+/// Returns the chunk a cell is in, and the cell's coordinates within it.
+fn locate(x: i64, y: i64) -> ((i64, i64), (usize, usize)) {
+    let size = CHUNK_SIZE as i64;
+    ((x.div_euclid(size), y.div_euclid(size)), (x.rem_euclid(size) as usize, y.rem_euclid(size) as usize))
+}
+
+/// Returns the chunks that overlap a region, row by row.
+fn chunks_within(x0: i64, y0: i64, width: usize, height: usize) -> impl Iterator<Item = (i64, i64)> {
+    let size = CHUNK_SIZE as i64;
+    let columns = x0.div_euclid(size)..=(x0 + width as i64 - 1).div_euclid(size);
+    let rows = y0.div_euclid(size)..=(y0 + height as i64 - 1).div_euclid(size);
+    rows.flat_map(move |row| columns.clone().map(move |column| (column, row)))
+}
+
+/// Call a function with the coordinates of each cell of a chunk that is within
+/// a region, both within the region and within the chunk.
+fn for_cells_within(
+    column: i64,
+    row: i64,
+    x0: i64,
+    y0: i64,
+    width: usize,
+    height: usize,
+    mut f: impl FnMut((usize, usize), (usize, usize)),
+) {
+    let size = CHUNK_SIZE as i64;
+    let (left, top) = (column * size, row * size);
+    let columns = left.max(x0)..(left + size).min(x0 + width as i64);
+    let rows = top.max(y0)..(top + size).min(y0 + height as i64);
+    for y in rows {
+        for x in columns.clone() {
+            f(((x - x0) as usize, (y - y0) as usize), ((x - left) as usize, (y - top) as usize));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty directory for a test's world.
+    fn directory(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cgol2-chunk-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn round_trips_cells_across_chunks() {
+        let dir = directory("round-trip");
+        let cells = [(0, 0), (63, 63), (64, 0), (-1, -1), (-65, 200), (1000, -3000)];
+        let mut world = World::create(&dir, 4).unwrap();
+        for &(x, y) in &cells {
+            world.set(x, y, true).unwrap();
+        }
+        world.generation = 42;
+        world.flush().unwrap();
+
+        let mut world = World::open(&dir, 4).unwrap();
+        assert_eq!(world.generation, 42);
+        for &(x, y) in &cells {
+            assert!(world.get(x, y).unwrap(), "({x}, {y}) should be alive");
+            assert!(!world.get(x + 1, y).unwrap(), "({}, {y}) should be dead", x + 1);
+        }
+        assert_eq!(world.bounds(), Some(((-128, -3008), (1023, 255))));
+
+        let mut grid = Grid::new(70, 3);
+        grid.set(0, 1, true);
+        grid.set(69, 2, true);
+        world.store(&grid, -3, 10).unwrap();
+        world.flush().unwrap();
+        let mut world = World::open(&dir, 4).unwrap();
+        assert!(world.load(-3, 10, 70, 3).unwrap() == grid, "a stored region should load back unchanged");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn evicted_chunks_are_read_back() {
+        let dir = directory("eviction");
+        let mut world = World::create(&dir, 1).unwrap();
+        for column in 0..5 {
+            world.set(column * 64 + column, 0, true).unwrap();
+        }
+        assert_eq!(world.cache.len(), 1);
+        for column in 0..5 {
+            assert!(world.get(column * 64 + column, 0).unwrap(), "chunk {column} should have been written back");
+            assert!(!world.get(column * 64, 1).unwrap());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unflushed_changes_leave_the_world_on_disk_as_it_was() {
+        let dir = directory("unflushed");
+        let mut world = World::create(&dir, 1).unwrap();
+        world.set(0, 0, true).unwrap();
+        world.flush().unwrap();
+
+        // Evicting the changed chunk writes it, but not over the flushed one.
+        world.set(0, 0, false).unwrap();
+        world.set(1, 0, true).unwrap();
+        world.set(64, 0, true).unwrap();
+        world.set(128, 0, true).unwrap();
+        let mut reopened = World::open(&dir, 1).unwrap();
+        assert!(reopened.get(0, 0).unwrap());
+        assert!(!reopened.get(1, 0).unwrap());
+        assert!(!reopened.get(64, 0).unwrap());
+
+        world.flush().unwrap();
+        let mut reopened = World::open(&dir, 1).unwrap();
+        assert!(!reopened.get(0, 0).unwrap());
+        assert!(reopened.get(1, 0).unwrap());
+        assert!(reopened.get(128, 0).unwrap());
+
+        // The slots the old index referred to are reused once it's replaced.
+        let slots = world.slots;
+        for _ in 0..4 {
+            world.set(2, 0, true).unwrap();
+            world.set(2, 0, false).unwrap();
+            world.set(65, 0, true).unwrap();
+            world.set(65, 0, false).unwrap();
+            world.flush().unwrap();
+        }
+        assert!(world.slots <= slots + 2, "the chunk file grew to {} slots from {slots}", world.slots);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,350 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use cgol2::grid::Boundary;
+use cgol2::pattern::Pattern;
+use cgol2::soup::{Fill, FillKind, Injection, Symmetry};
+use cgol2::{parse_dimensions, parse_probability};
+use clap::builder::RangedU64ValueParser;
+use clap::Args;
+
+use crate::error::Error;
+use crate::font::rasterize;
+use crate::hooks::Hook;
+use crate::image::{read_picture, FrameExport};
+use crate::simulation::Soup;
+use crate::species::SPECIES;
+use crate::zones::{Layout, Zones};
+use crate::{load_pattern, resolve_rule};
+
+/// Settings that decide how a universe starts and evolves, shared by every
+/// subcommand that runs a simulation.
+#[derive(Args, Clone)]
+pub struct SimulationArgs {
+    #[arg(long, default_value_t = 0.25, value_parser = parse_probability)]
+    pub alive_random_chance: f64,
+
+    /// Name of a registered rule, or a rulestring in B/S notation like
+    /// `B36/S23` or Hensel notation like `B2-a/S12`.
+    #[arg(long, default_value = "life")]
+    pub rule: String,
+
+    /// Golly `.rule` file to load the rule from, in place of `--rule`. Cells of
+    /// rules with more than two states are colored by their state.
+    #[arg(long, conflicts_with = "rule")]
+    pub rule_file: Option<PathBuf>,
+
+    /// TOML file defining a weighted 3x3 neighborhood kernel and the ranges of
+    /// weighted sums that cause births and survivals, in place of `--rule`,
+    /// e.g. `kernel = [[1, 2, 1], [2, 0, 2], [1, 2, 1]]`, `birth = [[5, 6]]`,
+    /// and `survival = [[4, 7]]`.
+    #[arg(long, conflicts_with_all = ["rule", "rule_file"])]
+    pub kernel: Option<PathBuf>,
+
+    /// Probability that any given cell flips its state after each tick.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_probability)]
+    pub noise: f64,
+
+    /// Only fill a region of this size at the center of the grid with the
+    /// initial random configuration, given as `WIDTHxHEIGHT`.
+    #[arg(long, value_parser = parse_dimensions)]
+    pub soup_size: Option<(usize, usize)>,
+
+    /// How the neighbors of cells at the edges of the universe are treated:
+    /// `dead`, `wrap`, `mirror`, or a topology in Golly's notation without
+    /// the sizes, such as `T+3` for a torus whose top and bottom edges are
+    /// joined three cells apart, `K*` for a Klein bottle, `C` for a
+    /// cross-surface, or `S` for a sphere.
+    #[arg(long, default_value_t = Boundary::Dead)]
+    pub boundary: Boundary,
+
+    /// Double the size of the universe, keeping its cells at the center,
+    /// whenever live cells come close to an edge, so that growing patterns
+    /// and spaceships are never cut off.
+    #[arg(long, conflicts_with = "boundary")]
+    pub expand: bool,
+
+    /// Place a pattern in the initial configuration, over the random cells,
+    /// given as `PATTERN@X,Y`. The pattern is a pattern file, `-` to read it
+    /// from standard input, or the name of a bundled pattern such as `glider`
+    /// (see `cgol2 patterns`), and its top-left corner goes at the
+    /// coordinates. Add `:rot90`, `:rot180`, `:rot270`, `:flipx`, or `:flipy`
+    /// to transform it, as in `glider@10,5:rot90`. Can be repeated, and
+    /// combined with `--alive-random-chance 0` to start from the patterns
+    /// alone.
+    #[arg(long, value_name = "PATTERN@X,Y")]
+    pub place: Vec<Placement>,
+
+    /// How the chance of each cell starting alive varies across the initial
+    /// random configuration.
+    #[arg(long, default_value_t = Fill::Uniform)]
+    pub fill: Fill,
+
+    /// How the live cells of the initial random configuration are arranged:
+    /// each independently at random with `bernoulli`, in clusters with voids
+    /// between them with `noise`, as small dots spread evenly apart with
+    /// `poisson-disc`, or as thin rings scattered at random with `rings`.
+    /// About `--alive-random-chance` of the cells start alive with each,
+    /// except that dots can't be packed closely enough for more than about a
+    /// fifth.
+    #[arg(long, default_value_t = FillKind::Bernoulli)]
+    pub fill_kind: FillKind,
+
+    /// Symmetry of the initial random configuration.
+    #[arg(long, default_value_t = Symmetry::None)]
+    pub symmetry: Symmetry,
+
+    /// PNG image to seed the initial configuration from, in place of random
+    /// cells. It is scaled to fit the grid, or `--soup-size`, and cells are
+    /// alive wherever it is bright.
+    #[arg(long, value_name = "PATH")]
+    pub seed_image: Option<PathBuf>,
+
+    /// Dither the image given to `--seed-image` rather than thresholding it,
+    /// so that its shading becomes the density of live cells.
+    #[arg(long, requires = "seed_image")]
+    pub dither: bool,
+
+    /// Pattern file to start from at the center of the grid, in place of
+    /// random cells, `-` to read the pattern from standard input, or the name
+    /// of a bundled pattern. The format is told from the contents. Walls in
+    /// the pattern, written as state `B` in RLE or `#` in plaintext, count as
+    /// live neighbors but never change, so that they can contain growth.
+    #[arg(long, value_name = "PATH", conflicts_with = "seed_image")]
+    pub pattern: Option<PathBuf>,
+
+    /// Text to write in live cells at the center of the grid, in place of
+    /// random cells. Lines are separated by newlines or `\n`.
+    #[arg(long, conflicts_with_all = ["seed_image", "pattern"])]
+    pub seed_text: Option<String>,
+
+    /// The size of each pixel of the text given to `--seed-text`, in cells.
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub text_scale: usize,
+
+    /// Split the live cells of the initial configuration into colonies of
+    /// this many rival species, drawn in their own colors. Cells are only born
+    /// when all of their parents are of the same species, and a surviving
+    /// cell joins whichever species holds the majority of it and its
+    /// neighbors.
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(2..=SPECIES.len() as u64))]
+    pub species: Option<usize>,
+
+    /// Seed for the random number generator. A random seed is chosen (and
+    /// logged) if none is given.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Move every cell of the universe by this many cells right and down
+    /// after each generation, or after every `N` generations, given as
+    /// `DX,DY` or `DX,DY/N`, like Golly's shifted universes. Drifting against
+    /// a spaceship's velocity keeps it still on screen, as with `-1,-1/4` for
+    /// a glider flying down and to the right. Cells that drift past an edge
+    /// come back through the edge it is joined to when the `--boundary` wraps
+    /// around, and are lost otherwise.
+    #[arg(long, value_name = "DX,DY[/N]", allow_hyphen_values = true)]
+    pub drift: Option<Drift>,
+
+    /// Divide the universe into zones that follow different rules: side by
+    /// side strips with `vertical`, stacked strips with `horizontal`, or the
+    /// path to a PNG mask stretched over the universe, where darker pixels
+    /// are in earlier zones. The first zone follows `--rule`, and the others
+    /// follow each `--zone-rule` in order.
+    #[arg(long, value_name = "LAYOUT", requires = "zone_rule")]
+    pub zones: Option<Layout>,
+
+    /// Rule of each zone after the first, as a registered rule or a B/S
+    /// rulestring. Can be repeated.
+    #[arg(long, value_name = "RULE", requires = "zones")]
+    pub zone_rule: Vec<String>,
+
+    /// Experimental: toggle a random birth or survival condition of the rule
+    /// every this many generations, so that it slowly evolves into other
+    /// rules. Only works with Life-like rules. Each new rule is logged, and
+    /// the report lists them along with the generation they took over.
+    #[arg(long, value_name = "GENERATIONS", value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    pub mutate_rule_every: Option<u64>,
+
+    /// Periodically inject a random patch of live cells, given as
+    /// `every=N,density=P,region=WxH`.
+    #[arg(long)]
+    pub inject: Option<Injection>,
+
+    /// Lua script defining `on_init`, `on_tick`, or `transition` hooks.
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+
+    /// End each board's run after this many generations. In the window, what
+    /// happens next is decided by `--on-end`.
+    #[arg(long, value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    pub max_generations: Option<u64>,
+
+    /// Time how long the pattern takes to stabilize, as for a methuselah such
+    /// as the R-pentomino, and count the gliders and other spaceships that
+    /// escape from it, which are removed from the universe once they are well
+    /// clear. The lifespan, final population, and escaped gliders are shown
+    /// below the status line in the window, and `sim` ends the run once the
+    /// pattern stabilizes and prints them as a JSON object on the last line
+    /// of its report.
+    #[arg(long)]
+    pub methuselah: bool,
+
+    /// Run a shell command whenever an event happens to a board, given as
+    /// `EVENT:COMMAND`, where the event is `extinct`, `stable` for settling
+    /// into a still life, `cycle` for settling into an oscillator, or `record`
+    /// for a new peak population, at most once every 100 generations. The
+    /// command gets the details in the `CGOL_EVENT`, `CGOL_GENERATION`,
+    /// `CGOL_POPULATION`, `CGOL_PERIOD`, and `CGOL_RULE` environment
+    /// variables, and isn't waited for. Can be repeated.
+    #[arg(long, value_name = "EVENT:COMMAND")]
+    pub exec_on: Vec<Hook>,
+
+    /// Write one JSON object per generation with statistics about it to this
+    /// file, or to stdout if it is `-`.
+    #[arg(long, value_name = "PATH")]
+    pub stats_stream: Option<PathBuf>,
+
+    /// Append one row per generation with statistics about it to this CSV
+    /// file, which is created with a header row if it doesn't exist.
+    #[arg(long, value_name = "PATH")]
+    pub log_csv: Option<PathBuf>,
+
+    /// Save a PNG image of each board to `--export-dir` every this many
+    /// generations, for assembling into a time-lapse video.
+    #[arg(long, requires = "export_dir", value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    pub export_every: Option<u64>,
+
+    /// Directory to save the images from `--export-every` to, which is created
+    /// if it doesn't exist.
+    #[arg(long, value_name = "DIR", requires = "export_every")]
+    pub export_dir: Option<PathBuf>,
+}
+
+impl SimulationArgs {
+    /// Lay out the zones of a universe of the given size, if it is divided
+    /// into any.
+    pub fn zones(&self, size: (usize, usize)) -> Result<Option<Zones>, Error> {
+        let Some(layout) = &self.zones else { return Ok(None) };
+        let rules = self.zone_rule.iter().map(|rule| resolve_rule(rule)).collect::<Result<_, _>>()?;
+        Zones::new(layout, rules, size).map(Some)
+    }
+
+    /// Returns how the initial configuration is generated, loading the
+    /// patterns it places.
+    pub fn soup(&self) -> Result<Soup, Error> {
+        let patterns = self.place.iter().map(Placement::load).collect::<Result<_, _>>()?;
+        let (centered, walls) = match (&self.pattern, &self.seed_text) {
+            (Some(path), _) => {
+                let pattern = load_pattern(path)?;
+                (Some(pattern.cells), pattern.walls)
+            },
+            (None, Some(text)) => (Some(rasterize(&text.replace("\\n", "\n"), self.text_scale)), None),
+            (None, None) => (None, None),
+        };
+        let picture = self
+            .seed_image
+            .as_ref()
+            .map(|path| read_picture(path).map_err(|err| Error::ReadImage { path: path.clone(), source: err }))
+            .transpose()?;
+        Ok(Soup {
+            size: self.soup_size,
+            chance: self.alive_random_chance,
+            fill: self.fill,
+            fill_kind: self.fill_kind,
+            symmetry: self.symmetry,
+            patterns,
+            picture,
+            dither: self.dither,
+            centered,
+            walls,
+        })
+    }
+
+    /// Returns where images of the boards are exported to, if anywhere,
+    /// creating the directory.
+    pub fn frame_export(&self, boards: usize) -> Result<Option<FrameExport>, Error> {
+        self.export_every
+            .zip(self.export_dir.as_deref())
+            .map(|(every, dir)| FrameExport::create(every, dir, boards))
+            .transpose()
+    }
+}
+
+/// How far every cell of a universe moves, and how often.
+#[derive(Clone, Copy)]
+pub struct Drift {
+    pub dx: i64,
+    pub dy: i64,
+    /// Number of generations between moves.
+    pub every: u64,
+}
+
+impl FromStr for Drift {
+    type Err = String;
+
+    /// Parse a drift like `-1,0` or `-1,-1/4`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (offset, every) = s.split_once('/').unwrap_or((s, "1"));
+        let (dx, dy) = offset.split_once(',').ok_or_else(|| format!("expected `DX,DY`, got `{offset}`"))?;
+        let dx = dx.trim().parse().map_err(|_| format!("`{dx}` is not a valid offset"))?;
+        let dy = dy.trim().parse().map_err(|_| format!("`{dy}` is not a valid offset"))?;
+        let every = every.trim().parse().map_err(|_| format!("`{every}` is not a valid generation count"))?;
+        if every == 0 {
+            return Err("the drift must happen at least every 1 generation".to_string());
+        }
+        Ok(Self { dx, dy, every })
+    }
+}
+
+/// A pattern to place in the initial configuration.
+#[derive(Clone)]
+pub struct Placement {
+    /// The name of a bundled pattern, or the path to a pattern file.
+    pattern: String,
+    x: usize,
+    y: usize,
+    /// Rotations and flips to apply to the pattern, in order.
+    transforms: Vec<Transform>,
+}
+
+/// A rotation or flip of a pattern.
+type Transform = fn(&Pattern) -> Pattern;
+
+impl Placement {
+    /// Load the pattern and transform it, returning it along with where its
+    /// top-left corner goes.
+    fn load(&self) -> Result<(Pattern, (usize, usize)), Error> {
+        let mut pattern = load_pattern(Path::new(&self.pattern))?;
+        for transform in &self.transforms {
+            pattern = transform(&pattern);
+        }
+        Ok((pattern, (self.x, self.y)))
+    }
+}
+
+impl FromStr for Placement {
+    type Err = String;
+
+    /// Parse a placement like `gun.rle@30,0:rot90:flipx`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, rest) = s.rsplit_once('@').ok_or_else(|| format!("expected `PATTERN@X,Y`, got `{s}`"))?;
+        let mut parts = rest.split(':');
+        let coordinates = parts.next().unwrap_or_default();
+        let (x, y) = coordinates.split_once(',').ok_or_else(|| format!("expected `X,Y`, got `{coordinates}`"))?;
+        let x = x.parse().map_err(|_| format!("`{x}` is not a valid x coordinate"))?;
+        let y = y.parse().map_err(|_| format!("`{y}` is not a valid y coordinate"))?;
+        let mut transforms = Vec::new();
+        for transform in parts {
+            let (transform, times): (Transform, usize) = match transform {
+                "rot90" => (Pattern::rotated_clockwise, 1),
+                "rot180" => (Pattern::rotated_clockwise, 2),
+                "rot270" => (Pattern::rotated_clockwise, 3),
+                "flipx" => (Pattern::flipped_horizontally, 1),
+                "flipy" => (Pattern::flipped_vertically, 1),
+                _ => return Err(format!("unknown transform `{transform}`")),
+            };
+            transforms.extend(std::iter::repeat_n(transform, times));
+        }
+        Ok(Self { pattern: pattern.to_string(), x, y, transforms })
+    }
+}
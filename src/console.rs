@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use cgol2::library;
+
+use crate::remote::Command;
+
+/// The commands the console understands, listed by `help`.
+pub const USAGE: &str = "help, pause, resume, step [N], clear, seed [N], rule RULE, load PATH [X Y], speed FPS";
+
+/// A line typed into the console, which runs once it's entered.
+#[derive(Default)]
+pub struct Console {
+    /// The line being typed.
+    pub input: String,
+    /// The response to the last line that was entered.
+    pub output: String,
+}
+
+/// What a line entered into the console asks for.
+pub enum Line {
+    /// A command to run on every board, in the same way as if a remote client
+    /// had sent it to each of them.
+    Command(Command),
+    /// Draw this many frames per second.
+    Speed(u32),
+    /// List the commands.
+    Help,
+}
+
+impl Line {
+    /// Parse a line like `rule B36/S23` or `seed 42`. Patterns given to `load`
+    /// are read from disk right away, unless they are the names of bundled
+    /// patterns, so that the command carries their contents like the remote
+    /// one.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else { return Err(format!("expected a command: {USAGE}")) };
+        let arguments: Vec<&str> = words.collect();
+        let number = |index: usize| -> Result<Option<u64>, String> {
+            arguments.get(index).map(|word| word.parse().map_err(|_| format!("`{word}` is not a number"))).transpose()
+        };
+        let line = match (name, arguments.len()) {
+            ("help", 0) => Self::Help,
+            ("pause", 0) => Self::Command(Command::Pause),
+            ("resume", 0) => Self::Command(Command::Resume),
+            ("step", 0..=1) => Self::Command(Command::Step { generations: number(0)?.unwrap_or(1) }),
+            ("clear", 0) => Self::Command(Command::Clear),
+            ("seed", 0..=1) => Self::Command(Command::Reseed { seed: number(0)?.unwrap_or_else(rand::random) }),
+            ("rule", 1) => Self::Command(Command::ChangeRule { rule: arguments[0].to_string() }),
+            ("load", 1 | 3) => {
+                let path = Path::new(arguments[0]);
+                let pattern = match library::find(arguments[0]) {
+                    Some(entry) => entry.rle().to_string(),
+                    None => std::fs::read_to_string(path)
+                        .map_err(|err| format!("couldn't read {}: {err}", path.display()))?,
+                };
+                let (x, y) = (number(1)?.map(|x| x as usize), number(2)?.map(|y| y as usize));
+                Self::Command(Command::LoadPattern { pattern, x, y })
+            },
+            ("speed", 1) => match number(0)? {
+                Some(fps @ 1..) => Self::Speed(fps.try_into().unwrap_or(u32::MAX)),
+                _ => return Err("the speed must be at least 1 frame per second".to_string()),
+            },
+            ("help" | "pause" | "resume" | "step" | "clear" | "seed" | "rule" | "load" | "speed", _) => {
+                return Err(format!("wrong number of arguments to {name}: {USAGE}"));
+            },
+            _ => return Err(format!("unknown command `{name}`: {USAGE}")),
+        };
+        Ok(line)
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use cgol2::pattern::{Format, Pattern};
+use clap::Args;
+
+use crate::error::Error;
+use crate::read_input;
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Pattern file to read, or `-` to read the pattern from standard input.
+    input: PathBuf,
+
+    /// File to write the converted pattern to.
+    output: PathBuf,
+
+    /// Format of the input file. Defaults to the format its extension is
+    /// usually used for, or else the one its contents look like.
+    #[arg(long)]
+    from: Option<Format>,
+
+    /// Format to write the output file in. Defaults to the format its
+    /// extension is usually used for.
+    #[arg(long)]
+    to: Option<Format>,
+}
+
+/// Read a pattern file in one format and write it out in another.
+pub fn run(args: ConvertArgs) -> Result<(), Error> {
+    let text = read_input(&args.input)?;
+    let from = args.from.or_else(|| Format::from_extension(&args.input)).unwrap_or_else(|| Format::detect(&text));
+    let to = args
+        .to
+        .or_else(|| Format::from_extension(&args.output))
+        .ok_or_else(|| Error::UnknownFormat { path: args.output.clone() })?;
+
+    let pattern =
+        Pattern::parse_as(&text, from).map_err(|err| Error::Pattern { path: args.input.clone(), source: err })?;
+    std::fs::write(&args.output, pattern.write_as(to))
+        .map_err(|err| Error::WriteFile { path: args.output.clone(), source: err })?;
+    log::info!("Converted {} from {from} to {to}", args.input.display());
+    Ok(())
+}
@@ -0,0 +1,876 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use ::core::fmt;
+use ::core::str::FromStr;
+
+/// How the neighbors of cells at the edges of a grid that fall outside of it
+/// are treated. This is the grid's topology, which decides the cell that each
+/// neighbor past an edge is taken from.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Boundary {
+    /// Neighbors outside of the grid are dead.
+    #[default]
+    Dead,
+    /// The grid wraps around, so neighbors past one edge are taken from the
+    /// opposite edge.
+    Wrap,
+    /// Neighbors outside of the grid are reflected back into it, so they have
+    /// the state of the cell at the edge.
+    Mirror,
+    /// The grid wraps around like a torus, but one pair of opposite edges is
+    /// joined with a shift, so that neighbors past one of them are taken from
+    /// the other edge this many cells along it.
+    Shifted { edges: Edges, shift: i64 },
+    /// The grid wraps around like a Klein bottle, with one pair of opposite
+    /// edges joined with a twist, so that neighbors past one of them are taken
+    /// from the other edge in reverse.
+    Klein { twisted: Edges },
+    /// Both pairs of opposite edges are joined with a twist, making the grid a
+    /// cross-surface.
+    CrossSurface,
+    /// The top edge is joined to the left edge and the bottom edge to the
+    /// right edge, making the grid a sphere. The corners have no neighbors
+    /// past both edges, and the edges only line up on square grids.
+    Sphere,
+}
+
+/// A pair of opposite edges of a grid.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edges {
+    TopBottom,
+    LeftRight,
+}
+
+impl Boundary {
+    /// Returns the cell of a grid of the given size that the given coordinates
+    /// refer to, if any. Boundaries that wrap around resolve coordinates any
+    /// distance outside of the grid, and a sphere only those one cell
+    /// outside.
+    fn resolve(self, x: i64, y: i64, width: usize, height: usize) -> Option<(usize, usize)> {
+        let (w, h) = (width as i64, height as i64);
+        if (0..w).contains(&x) && (0..h).contains(&y) {
+            return Some((x as usize, y as usize));
+        }
+        // How many times the coordinates cross each pair of edges.
+        let (across, down) = (x.div_euclid(w.max(1)), y.div_euclid(h.max(1)));
+        let (x, y) = match self {
+            Self::Dead => return None,
+            _ if w == 0 || h == 0 => return None,
+            Self::Wrap => (x, y),
+            Self::Mirror => (reflect(x, w), reflect(y, h)),
+            Self::Shifted { edges: Edges::TopBottom, shift } => (x + down * shift, y),
+            Self::Shifted { edges: Edges::LeftRight, shift } => (x, y + across * shift),
+            Self::Klein { twisted: Edges::TopBottom } => (if down % 2 != 0 { w - 1 - x } else { x }, y),
+            Self::Klein { twisted: Edges::LeftRight } => (x, if across % 2 != 0 { h - 1 - y } else { y }),
+            Self::CrossSurface => {
+                (if down % 2 != 0 { w - 1 - x } else { x }, if across % 2 != 0 { h - 1 - y } else { y })
+            },
+            Self::Sphere => {
+                let (x, y) = match (across, down) {
+                    (0, -1) => (0, x),
+                    (-1, 0) => (y, 0),
+                    (0, 1) => (w - 1, x),
+                    (1, 0) => (y, h - 1),
+                    _ => return None,
+                };
+                return ((0..w).contains(&x) && (0..h).contains(&y)).then_some((x as usize, y as usize));
+            },
+        };
+        Some((x.rem_euclid(w) as usize, y.rem_euclid(h) as usize))
+    }
+
+    /// Returns whether cells moved past an edge come back through the edge it
+    /// is joined to.
+    pub fn wraps(self) -> bool {
+        !matches!(self, Self::Dead | Self::Mirror | Self::Sphere)
+    }
+
+    /// Returns whether neighbors past each edge are resolved along each
+    /// dimension separately, so that the row above the top one is always a
+    /// whole row of the grid as it is, and likewise for columns.
+    pub fn is_separable(self) -> bool {
+        matches!(self, Self::Dead | Self::Wrap | Self::Mirror)
+    }
+}
+
+/// Reflect a coordinate along a dimension `len` cells long back into it.
+fn reflect(coordinate: i64, len: i64) -> i64 {
+    let reflected = coordinate.rem_euclid(2 * len);
+    if reflected < len {
+        reflected
+    } else {
+        2 * len - 1 - reflected
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dead => write!(f, "dead"),
+            Self::Wrap => write!(f, "wrap"),
+            Self::Mirror => write!(f, "mirror"),
+            Self::Shifted { edges: Edges::TopBottom, shift } => write!(f, "T{shift:+}"),
+            Self::Shifted { edges: Edges::LeftRight, shift } => write!(f, "T,{shift:+}"),
+            Self::Klein { twisted: Edges::TopBottom } => write!(f, "K*"),
+            Self::Klein { twisted: Edges::LeftRight } => write!(f, "K,*"),
+            Self::CrossSurface => write!(f, "C"),
+            Self::Sphere => write!(f, "S"),
+        }
+    }
+}
+
+impl FromStr for Boundary {
+    type Err = String;
+
+    /// Parse a boundary from its name, `dead`, `wrap`, or `mirror`, or from a
+    /// bounded-grid topology in Golly's notation with the sizes left out, since
+    /// they are those of the grid: `P` for a plane, `T` for a torus, `T+3` for
+    /// a torus with its top and bottom edges joined three cells apart (or
+    /// `T,+3` for its left and right edges), `K*` for a Klein bottle with its
+    /// top and bottom edges twisted (or `K,*` for its left and right edges),
+    /// `C` for a cross-surface, and `S` for a sphere.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let boundary = match s {
+            "dead" | "P" => Self::Dead,
+            "wrap" => Self::Wrap,
+            "mirror" => Self::Mirror,
+            "K*" | "K*," => Self::Klein { twisted: Edges::TopBottom },
+            "K,*" => Self::Klein { twisted: Edges::LeftRight },
+            "C" => Self::CrossSurface,
+            "S" => Self::Sphere,
+            _ => {
+                let invalid = || alloc::format!("`{s}` is not dead, wrap, mirror, or a topology like T+3 or K*");
+                let shifts = s.strip_prefix('T').ok_or_else(invalid)?;
+                let (width, height) = shifts.split_once(',').unwrap_or((shifts, ""));
+                let shift = |part: &str| match part {
+                    "" => Ok(0),
+                    _ if part.starts_with(['+', '-']) => part.parse::<i64>().map_err(|_| invalid()),
+                    _ => Err(invalid()),
+                };
+                match (shift(width)?, shift(height)?) {
+                    (0, 0) => Self::Wrap,
+                    (shift, 0) => Self::Shifted { edges: Edges::TopBottom, shift },
+                    (0, shift) => Self::Shifted { edges: Edges::LeftRight, shift },
+                    _ => return Err(alloc::format!("`{s}` shifts both pairs of edges, but only one can be")),
+                }
+            },
+        };
+        Ok(boundary)
+    }
+}
+
+/// A 2-dimensional state object that stores the alive / dead status of each of
+/// its cells.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+    boundary: Boundary,
+}
+
+impl Grid {
+    /// Create a grid of the given size with every cell dead, whose neighbors
+    /// outside of it are dead too.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, cells: vec![false; width * height], boundary: Boundary::Dead }
+    }
+
+    /// Returns the grid with cells at its edges treated according to the given
+    /// boundary.
+    pub fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// Returns how the neighbors of cells at the edges of the grid are
+    /// treated.
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    /// Kill every cell in the grid.
+    pub fn clear(&mut self) {
+        self.cells.fill(false);
+    }
+
+    /// Returns the number of cells along the x axis.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of cells along the y axis.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns whether the cell at the given coordinates is alive.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.cells[y * self.width + x]
+    }
+
+    /// Set whether the cell at the given coordinates is alive.
+    pub fn set(&mut self, x: usize, y: usize, alive: bool) {
+        self.cells[y * self.width + x] = alive;
+    }
+
+    /// Returns a mutable reference to every cell in the grid, in row-major
+    /// order.
+    pub fn cells_mut(&mut self) -> impl Iterator<Item = &mut bool> {
+        self.cells.iter_mut()
+    }
+
+    /// Fill the grid at random, bringing each cell to life with the given
+    /// chance. `random` is called once per cell and must return numbers spread
+    /// evenly over every `u32`, so that any random number generator, or a
+    /// hardware one, can drive it.
+    pub fn fill_random(&mut self, chance: f64, mut random: impl FnMut() -> u32) {
+        let threshold = (chance.clamp(0.0, 1.0) * (1u64 << 32) as f64) as u64;
+        for cell in &mut self.cells {
+            *cell = u64::from(random()) < threshold;
+        }
+    }
+
+    /// Returns the number of alive cells in the grid.
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&alive| alive).count()
+    }
+
+    /// Returns the coordinates of every cell within the given distance of a
+    /// cell, which are the cells inside a disk of that radius.
+    pub fn disk(&self, x: usize, y: usize, radius: usize) -> impl Iterator<Item = (usize, usize)> {
+        let columns = x.saturating_sub(radius)..x.saturating_add(radius).saturating_add(1).min(self.width);
+        let rows = y.saturating_sub(radius)..y.saturating_add(radius).saturating_add(1).min(self.height);
+        let square = |n: usize| n.saturating_mul(n);
+        rows.flat_map(move |cy| columns.clone().map(move |cx| (cx, cy)))
+            .filter(move |&(cx, cy)| square(cx.abs_diff(x)).saturating_add(square(cy.abs_diff(y))) <= square(radius))
+    }
+
+    /// Returns the number of births and deaths it took to get from this grid to
+    /// the next generation, which must be the same size.
+    pub fn births_and_deaths(&self, next: &Grid) -> (usize, usize) {
+        self.cells.iter().zip(&next.cells).fold((0, 0), |(births, deaths), (&before, &after)| match (before, after) {
+            (false, true) => (births + 1, deaths),
+            (true, false) => (births, deaths + 1),
+            _ => (births, deaths),
+        })
+    }
+
+    /// Returns the top-left corner and size of the smallest region that
+    /// contains every alive cell, or `None` if there are none.
+    pub fn bounds(&self) -> Option<((usize, usize), (usize, usize))> {
+        let alive =
+            || self.cells.iter().enumerate().filter(|(_, &alive)| alive).map(|(i, _)| (i % self.width, i / self.width));
+        let (min_x, max_x) = (alive().map(|(x, _)| x).min()?, alive().map(|(x, _)| x).max()?);
+        let (min_y, max_y) = (alive().map(|(_, y)| y).min()?, alive().map(|(_, y)| y).max()?);
+        Some(((min_x, min_y), (max_x - min_x + 1, max_y - min_y + 1)))
+    }
+
+    /// Returns the average coordinates of the alive cells, or `None` if there
+    /// are none.
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        let (mut sum_x, mut sum_y, mut count) = (0.0, 0.0, 0usize);
+        for (i, _) in self.cells.iter().enumerate().filter(|(_, &alive)| alive) {
+            sum_x += (i % self.width) as f64;
+            sum_y += (i / self.width) as f64;
+            count += 1;
+        }
+        (count > 0).then(|| (sum_x / count as f64, sum_y / count as f64))
+    }
+
+    /// Returns the index of the cell that each cell of the grid would take its
+    /// state from if every cell moved by the given offset, in the same order
+    /// as the cells. Cells moved past an edge come back in through the edge
+    /// it is joined to if the boundary wraps around, and are lost otherwise.
+    pub fn translation_sources(&self, dx: i64, dy: i64) -> Vec<Option<usize>> {
+        let (width, height) = (self.width as i64, self.height as i64);
+        (0..self.width * self.height)
+            .map(|index| {
+                let (x, y) = ((index % self.width) as i64 - dx, (index / self.width) as i64 - dy);
+                let source = if self.boundary.wraps() {
+                    self.boundary.resolve(x, y, self.width, self.height)
+                } else {
+                    ((0..width).contains(&x) && (0..height).contains(&y)).then_some((x as usize, y as usize))
+                };
+                source.map(|(x, y)| y * self.width + x)
+            })
+            .collect()
+    }
+
+    /// Returns the grid with the state of each cell taken from the given
+    /// source, as returned by [`Grid::translation_sources`].
+    pub fn translated(&self, sources: &[Option<usize>]) -> Grid {
+        let mut next = Grid::new(self.width, self.height).with_boundary(self.boundary);
+        for (cell, source) in next.cells.iter_mut().zip(sources) {
+            *cell = source.is_some_and(|source| self.cells[source]);
+        }
+        next
+    }
+
+    /// Returns whether any alive cell is within `margin` cells of an edge of
+    /// the grid.
+    pub fn near_edge(&self, margin: usize) -> bool {
+        self.bounds().is_some_and(|((x, y), (width, height))| {
+            x < margin || y < margin || x + width + margin > self.width || y + height + margin > self.height
+        })
+    }
+
+    /// Returns a copy of the region of the grid with its top-left corner at the
+    /// given coordinates, which must fit inside the grid.
+    pub fn crop(&self, x0: usize, y0: usize, width: usize, height: usize) -> Grid {
+        let mut region = Grid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                region.set(x, y, self.get(x0 + x, y0 + y));
+            }
+        }
+        region
+    }
+
+    /// Returns a grid of the given size with the same boundary, holding a copy
+    /// of this grid with its top-left corner at the given coordinates.
+    pub fn resized(&self, width: usize, height: usize, x0: usize, y0: usize) -> Grid {
+        let mut resized = Grid::new(width, height).with_boundary(self.boundary);
+        resized.paste(self, x0, y0);
+        resized
+    }
+
+    /// Copy the state of another grid into this one, with its top-left corner
+    /// at the given coordinates. Cells that fall outside of this grid are
+    /// discarded.
+    pub fn paste(&mut self, other: &Grid, x0: usize, y0: usize) {
+        for y in 0..other.height.min(self.height.saturating_sub(y0)) {
+            for x in 0..other.width.min(self.width.saturating_sub(x0)) {
+                self.set(x0 + x, y0 + y, other.get(x, y));
+            }
+        }
+    }
+
+    /// Return the coordinates of a given cell's eight neighbors, ordered
+    /// clockwise starting from the top-left. Neighbors outside of the grid are
+    /// resolved according to its boundary, and are `None` if it doesn't map
+    /// them to a cell.
+    pub fn neighbor_coords(&self, x: i32, y: i32) -> [Option<(usize, usize)>; 8] {
+        const OFFSETS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0)];
+
+        OFFSETS
+            .map(|offset| self.boundary.resolve((x + offset.0).into(), (y + offset.1).into(), self.width, self.height))
+    }
+
+    /// Return the states of a given cell's eight neighbors, ordered clockwise
+    /// starting from the top-left. Neighbors that the grid's boundary doesn't
+    /// map to a cell are dead.
+    pub fn neighbors(&self, x: i32, y: i32) -> [bool; 8] {
+        self.neighbor_coords(x, y).map(|coords| coords.is_some_and(|(x, y)| self.get(x, y)))
+    }
+
+    /// Return the number of alive cells out of a given cell's up-to eight
+    /// neighbors.
+    pub fn alive_neighbors(&self, x: i32, y: i32) -> u8 {
+        self.neighbors(x, y).iter().filter(|&&alive| alive).count() as u8
+    }
+
+    /// Compute the next generation of the grid based on the given rule.
+    /// Life-like rules are computed for 64 cells at a time on bit-packed
+    /// rows, unless the grid's edges are joined with a shift or a twist.
+    pub fn step(&self, rule: &dyn Rule) -> Grid {
+        match rule.as_life_like() {
+            Some(rule) if self.boundary.is_separable() => step_packed(self, &rule),
+            _ => self.step_each(rule),
+        }
+    }
+
+    /// Compute the next generation of the grid where each cell follows the
+    /// rule of its zone, given for each cell as an index into `rules` in the
+    /// same order as the cells. Cells at the border between zones see their
+    /// neighbors in other zones like any others.
+    pub fn step_zoned(&self, rules: &[&dyn Rule], zones: &[u8]) -> Grid {
+        // Stepping the whole grid with each rule keeps the fast paths of the
+        // engines, which pays for the cells that are thrown away.
+        let steps: Vec<Grid> = rules.iter().map(|rule| self.step(*rule)).collect();
+        let mut next = Grid::new(self.width, self.height).with_boundary(self.boundary);
+        for (index, cell) in next.cells.iter_mut().enumerate() {
+            *cell = steps[zones[index] as usize].cells[index];
+        }
+        next
+    }
+
+    /// Compute the next generation of the grid based on the given rule, where
+    /// the live cells of another grid of the same size also count as live
+    /// neighbors. A neighbor is alive if it is alive in either grid, so the
+    /// other grid drives this one without being changed by it.
+    pub fn step_coupled(&self, rule: &dyn Rule, other: &Grid) -> Grid {
+        let mut next = Grid::new(self.width, self.height).with_boundary(self.boundary);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut neighbors = self.neighbors(x as i32, y as i32);
+                let driving = other.neighbors(x as i32, y as i32);
+                for (neighbor, driving) in neighbors.iter_mut().zip(driving) {
+                    *neighbor |= driving;
+                }
+                if rule.next_state(self.get(x, y), &neighbors) {
+                    next.set(x, y, true);
+                }
+            }
+        }
+        next
+    }
+
+    /// Compute the next generation of a universe whose cells have more than
+    /// two states, given the state of each cell in the same order as the
+    /// cells of the grid. Returns the next states along with the grid of the
+    /// cells that are in any state but 0. Neighbors that the grid's boundary
+    /// doesn't map to a cell are in state 0.
+    pub fn step_states(&self, rule: &dyn MultiStateRule, states: &[u8]) -> (Grid, Vec<u8>) {
+        let mut next = Grid::new(self.width, self.height).with_boundary(self.boundary);
+        let mut next_states = vec![0; states.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let neighbors = self
+                    .neighbor_coords(x as i32, y as i32)
+                    .map(|coords| coords.map_or(0, |(x, y)| states[y * self.width + x]));
+                let state = rule.transition(states[y * self.width + x], &neighbors);
+                next_states[y * self.width + x] = state;
+                next.set(x, y, state != 0);
+            }
+        }
+        (next, next_states)
+    }
+
+    /// Compute the next generation of the grid based on the given rule, by
+    /// asking it for the next state of each cell one at a time.
+    pub fn step_each(&self, rule: &dyn Rule) -> Grid {
+        let mut next = Grid::new(self.width, self.height).with_boundary(self.boundary);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if rule.next_state(self.get(x, y), &self.neighbors(x as i32, y as i32)) {
+                    next.set(x, y, true);
+                }
+            }
+        }
+        next
+    }
+
+    /// Compute the next generation of the grid, deciding the next state of each
+    /// cell with a fallible transition function. The function is given the
+    /// cell's coordinates, whether it is alive, and its number of alive
+    /// neighbors.
+    pub fn try_step<E>(
+        &self,
+        mut transition: impl FnMut(usize, usize, bool, u8) -> Result<bool, E>,
+    ) -> Result<Grid, E> {
+        let mut next = Grid::new(self.width, self.height).with_boundary(self.boundary);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let alive = self.get(x, y);
+                let alive_neighbors = self.alive_neighbors(x as i32, y as i32);
+                if transition(x, y, alive, alive_neighbors)? {
+                    next.set(x, y, true);
+                }
+            }
+        }
+        Ok(next)
+    }
+}
+
+/// A cellular automaton that decides the next state of a cell from its
+/// current state and the states of its neighbors.
+///
+/// Implement this to add new automata without touching the tick loop, and add
+/// them to a `Registry` to make them selectable by name.
+pub trait Rule: Send + Sync {
+    /// Returns whether a cell will be alive in the next generation, given
+    /// whether it is alive now and the states of its eight neighbors, ordered
+    /// clockwise starting from the top-left.
+    fn next_state(&self, alive: bool, neighbors: &[bool; 8]) -> bool;
+
+    /// Returns the rule as a rulestring that other Life programs understand,
+    /// if it can be written as one.
+    fn rulestring(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the rule as a Life-like rule, if it only depends on how many of
+    /// a cell's neighbors are alive, so that it can be computed for many cells
+    /// at once.
+    fn as_life_like(&self) -> Option<LifeLike> {
+        None
+    }
+
+    /// Returns the rule over every state of a cell, if it has more than two.
+    /// [`Rule::next_state`] then gives the rule as it is for cells in states 0
+    /// and 1, with a cell alive if it ends up in any state but 0.
+    fn as_multi_state(&self) -> Option<&dyn MultiStateRule> {
+        None
+    }
+}
+
+/// A cellular automaton whose cells have more than two states, numbered from
+/// 0, where a cell in any state but 0 counts as alive on the grid.
+pub trait MultiStateRule: Send + Sync {
+    /// Returns the number of states a cell can be in.
+    fn states(&self) -> usize;
+
+    /// Returns the next state of a cell, given its current state and those of
+    /// its eight neighbors, ordered clockwise starting from the top-left.
+    fn transition(&self, state: u8, neighbors: &[u8; 8]) -> u8;
+}
+
+/// A "Life-like" rule, where the next state of a cell depends only on its
+/// current state and how many of its neighbors are alive.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LifeLike {
+    /// Whether a dead cell with the given number of alive neighbors is born.
+    birth: [bool; 9],
+    /// Whether an alive cell with the given number of alive neighbors
+    /// survives.
+    survival: [bool; 9],
+}
+
+impl LifeLike {
+    /// The rules of Conway's Game of Life, B3/S23.
+    pub const LIFE: LifeLike = LifeLike::new(&[3], &[2, 3]);
+
+    /// Create a rule from the neighbor counts that cause births and survivals.
+    pub const fn new(birth: &[u8], survival: &[u8]) -> Self {
+        let mut rule = Self { birth: [false; 9], survival: [false; 9] };
+        let mut i = 0;
+        while i < birth.len() {
+            rule.birth[birth[i] as usize] = true;
+            i += 1;
+        }
+        let mut i = 0;
+        while i < survival.len() {
+            rule.survival[survival[i] as usize] = true;
+            i += 1;
+        }
+        rule
+    }
+
+    /// Create a rule from whether a dead cell is born, and whether an alive
+    /// cell survives, with each number of alive neighbors from 0 to 8.
+    pub const fn from_conditions(birth: [bool; 9], survival: [bool; 9]) -> Self {
+        Self { birth, survival }
+    }
+
+    /// Returns whether a dead cell is born, and whether an alive cell
+    /// survives, with each number of alive neighbors from 0 to 8.
+    pub fn conditions(&self) -> ([bool; 9], [bool; 9]) {
+        (self.birth, self.survival)
+    }
+
+    /// Returns whether a cell will be alive in the next generation, given
+    /// whether it is alive now and how many of its neighbors are alive.
+    pub fn next_state_for_count(&self, alive: bool, alive_neighbors: u8) -> bool {
+        let counts = if alive { &self.survival } else { &self.birth };
+        counts[alive_neighbors as usize]
+    }
+}
+
+impl Rule for LifeLike {
+    fn next_state(&self, alive: bool, neighbors: &[bool; 8]) -> bool {
+        let alive_neighbors = neighbors.iter().filter(|&&alive| alive).count() as u8;
+        self.next_state_for_count(alive, alive_neighbors)
+    }
+
+    fn rulestring(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+
+    fn as_life_like(&self) -> Option<LifeLike> {
+        Some(*self)
+    }
+}
+
+impl fmt::Display for LifeLike {
+    /// Format the rule as a rulestring in B/S notation, e.g. `B3/S23`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |counts: &[bool; 9]| -> String {
+            (0..9).filter(|&count| counts[count]).map(|count| char::from(b'0' + count as u8)).collect()
+        };
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survival))
+    }
+}
+
+impl FromStr for LifeLike {
+    type Err = ParseRuleError;
+
+    /// Parse a rulestring in B/S notation, e.g. `B36/S23`. Letters are case
+    /// insensitive and either half may be empty, as in `B2/S`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseRuleError(s.to_string());
+        let (birth, survival) = s.split_once('/').ok_or_else(invalid)?;
+        let birth = birth.strip_prefix(['B', 'b']).ok_or_else(invalid)?;
+        let survival = survival.strip_prefix(['S', 's']).ok_or_else(invalid)?;
+
+        let counts = |digits: &str| -> Result<[bool; 9], ParseRuleError> {
+            let mut counts = [false; 9];
+            for digit in digits.chars() {
+                let count = digit.to_digit(10).filter(|&count| count <= 8).ok_or_else(invalid)?;
+                counts[count as usize] = true;
+            }
+            Ok(counts)
+        };
+        Ok(Self { birth: counts(birth)?, survival: counts(survival)? })
+    }
+}
+
+/// A rule was neither registered nor a valid rulestring.
+#[derive(Debug)]
+pub struct ParseRuleError(pub(crate) String);
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is not a registered rule or a valid B/S rulestring", self.0)
+    }
+}
+
+impl ::core::error::Error for ParseRuleError {}
+
+/// The number of cells packed into each word of a row.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A grid with each row packed into words, one bit per cell with the leftmost
+/// cell in the lowest bit of the first word. Bits past the end of a row are
+/// always zero.
+struct Packed {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl Packed {
+    fn new(grid: &Grid) -> Self {
+        let (width, height) = (grid.width(), grid.height());
+        let words_per_row = width.div_ceil(WORD_BITS);
+        let mut words = vec![0; words_per_row * height];
+        for y in 0..height {
+            for x in (0..width).filter(|&x| grid.get(x, y)) {
+                words[y * words_per_row + x / WORD_BITS] |= 1 << (x % WORD_BITS);
+            }
+        }
+        Self { width, height, words_per_row, words }
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.words_per_row..(y + 1) * self.words_per_row]
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.row(y)[x / WORD_BITS] & (1 << (x % WORD_BITS)) != 0
+    }
+}
+
+/// Compute the next generation of the grid based on a Life-like rule, adding up
+/// the neighbors of a whole word of cells at a time. The grid's boundary must
+/// be [separable](Boundary::is_separable).
+///
+/// Each of the eight neighbors of the cells in a word is itself a word, the
+/// rows above and below and the row itself shifted by a cell either way. They
+/// are summed with a bit-sliced adder, which keeps the count for every cell in
+/// four words holding one binary digit of it each, and the rule is then
+/// applied to the counts with a mask for each one that it births or keeps
+/// cells with.
+fn step_packed(grid: &Grid, rule: &LifeLike) -> Grid {
+    let boundary = grid.boundary();
+    let mut next = Grid::new(grid.width(), grid.height()).with_boundary(boundary);
+    if grid.width() == 0 || grid.height() == 0 {
+        return next;
+    }
+    let packed = Packed::new(grid);
+    let (width, height, words_per_row) = (packed.width, packed.height, packed.words_per_row);
+    let births: Vec<u8> = (0..=8).filter(|&count| rule.next_state_for_count(false, count)).collect();
+    let survivals: Vec<u8> = (0..=8).filter(|&count| rule.next_state_for_count(true, count)).collect();
+    let dead_row = vec![0; words_per_row];
+
+    let mut shifted = [vec![0; words_per_row], vec![0; words_per_row]];
+    for y in 0..height {
+        let mut counts = vec![[0; 4]; words_per_row];
+        for dy in -1..=1 {
+            // The rows above and below, and the cells past the left and right
+            // edges, are resolved by the boundary, and are dead if it doesn't
+            // map them to cells.
+            let source = resolve(boundary, y as i64 + dy, height);
+            let cells = source.map_or(dead_row.as_slice(), |y| packed.row(y));
+            let edge = |x: i64| source.zip(resolve(boundary, x, width)).is_some_and(|(y, x)| packed.get(x, y));
+            let [west, east] = &mut shifted;
+            shift_west(cells, west, edge(-1));
+            shift_east(cells, east, width, edge(width as i64));
+            for (i, count) in counts.iter_mut().enumerate() {
+                add(count, west[i]);
+                add(count, east[i]);
+                if dy != 0 {
+                    add(count, cells[i]);
+                }
+            }
+        }
+
+        for (i, count) in counts.iter().enumerate() {
+            let alive = packed.row(y)[i];
+            let born = births.iter().fold(0, |mask, &n| mask | equals(count, n));
+            let survived = survivals.iter().fold(0, |mask, &n| mask | equals(count, n));
+            let mut word = ((!alive & born) | (alive & survived)) & valid(i, width);
+            while word != 0 {
+                next.set(i * WORD_BITS + word.trailing_zeros() as usize, y, true);
+                word &= word - 1;
+            }
+        }
+    }
+    next
+}
+
+/// Returns the index a coordinate along a dimension `len` cells long refers to,
+/// in the same way as [`Grid::neighbor_coords`] does.
+fn resolve(boundary: Boundary, coordinate: i64, len: usize) -> Option<usize> {
+    let len = len as i64;
+    if (0..len).contains(&coordinate) {
+        return Some(coordinate as usize);
+    }
+    match boundary {
+        Boundary::Dead => None,
+        Boundary::Wrap => Some(coordinate.rem_euclid(len) as usize),
+        // Only one cell past the edge is ever resolved, which reflects onto
+        // the edge cell itself.
+        Boundary::Mirror => Some(coordinate.clamp(0, len - 1) as usize),
+        _ => unreachable!("only separable boundaries are stepped on packed rows"),
+    }
+}
+
+/// Fill `out` with the state of the cell to the west of each cell of the row,
+/// where the cell west of the first one has the given state.
+fn shift_west(row: &[u64], out: &mut [u64], first: bool) {
+    let mut carry = first as u64;
+    for (word, out) in row.iter().zip(out) {
+        *out = (word << 1) | carry;
+        carry = word >> (WORD_BITS - 1);
+    }
+}
+
+/// Fill `out` with the state of the cell to the east of each cell of the row,
+/// which is `width` cells long, where the cell east of the last one has the
+/// given state.
+fn shift_east(row: &[u64], out: &mut [u64], width: usize, last: bool) {
+    let mut carry = 0;
+    for (word, out) in row.iter().zip(out.iter_mut()).rev() {
+        *out = (word >> 1) | carry;
+        carry = word << (WORD_BITS - 1);
+    }
+    out[(width - 1) / WORD_BITS] |= (last as u64) << ((width - 1) % WORD_BITS);
+}
+
+/// Add a word of single bits to the four-bit counts sliced across `count`,
+/// rippling the carry up through its digits.
+fn add(count: &mut [u64; 4], mut carry: u64) {
+    for digit in count {
+        let next = *digit & carry;
+        *digit ^= carry;
+        carry = next;
+    }
+}
+
+/// Returns a mask of the cells whose sliced count is `n`.
+fn equals(count: &[u64; 4], n: u8) -> u64 {
+    count.iter().enumerate().fold(!0, |mask, (bit, &digit)| mask & if n & (1 << bit) != 0 { digit } else { !digit })
+}
+
+/// Returns a mask of the bits of the given word of a row that hold cells.
+fn valid(word: usize, width: usize) -> u64 {
+    let cells = (width - word * WORD_BITS).min(WORD_BITS);
+    if cells == WORD_BITS {
+        !0
+    } else {
+        (1 << cells) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundaries_round_trip_through_their_names() {
+        let boundaries = [
+            Boundary::Dead,
+            Boundary::Wrap,
+            Boundary::Mirror,
+            Boundary::Shifted { edges: Edges::TopBottom, shift: 3 },
+            Boundary::Shifted { edges: Edges::TopBottom, shift: -2 },
+            Boundary::Shifted { edges: Edges::LeftRight, shift: 5 },
+            Boundary::Shifted { edges: Edges::LeftRight, shift: -1 },
+            Boundary::Klein { twisted: Edges::TopBottom },
+            Boundary::Klein { twisted: Edges::LeftRight },
+            Boundary::CrossSurface,
+            Boundary::Sphere,
+        ];
+        for boundary in boundaries {
+            let name = boundary.to_string();
+            assert!(name.parse::<Boundary>() == Ok(boundary), "{name} parsed to something else");
+        }
+    }
+
+    #[test]
+    fn disks_with_huge_radii_cover_the_whole_grid() {
+        let grid = Grid::new(5, 4);
+        assert_eq!(grid.disk(2, 1, usize::MAX).count(), 20);
+        assert_eq!(grid.disk(usize::MAX - 1, 1, 1).count(), 0);
+        assert_eq!(grid.disk(2, 1, 1).count(), 5);
+    }
+
+    #[test]
+    fn golly_topologies_without_shifts_are_planes_and_tori() {
+        assert!("P".parse::<Boundary>() == Ok(Boundary::Dead));
+        assert!("T".parse::<Boundary>() == Ok(Boundary::Wrap));
+        assert!("T+0".parse::<Boundary>() == Ok(Boundary::Wrap));
+    }
+
+    #[test]
+    fn invalid_boundaries_are_rejected() {
+        for name in ["", "torus", "T3", "T+1,+1", "T+x", "K", "Dead"] {
+            assert!(name.parse::<Boundary>().is_err(), "{name} parsed");
+        }
+    }
+
+    /// A xorshift generator, so that the random grids are the same every run.
+    fn xorshift(mut state: u32) -> impl FnMut() -> u32 {
+        move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        }
+    }
+
+    #[test]
+    fn packed_stepping_matches_stepping_each_cell() {
+        let rules =
+            [LifeLike::LIFE, LifeLike::new(&[3, 6], &[2, 3]), LifeLike::new(&[0, 2], &[8]), LifeLike::new(&[], &[])];
+        // Widths on both sides of the 64 cells in a word, and degenerate grids.
+        let sizes = [(1, 1), (2, 3), (63, 5), (64, 64), (65, 9), (130, 17), (7, 1), (1, 7)];
+        let mut random = xorshift(0x9E37_79B9);
+        for boundary in [Boundary::Dead, Boundary::Wrap, Boundary::Mirror] {
+            for (width, height) in sizes {
+                for chance in [0.1, 0.35, 0.9] {
+                    let mut grid = Grid::new(width, height).with_boundary(boundary);
+                    grid.fill_random(chance, &mut random);
+                    for rule in &rules {
+                        let mut packed = grid.clone();
+                        let mut each = grid.clone();
+                        // A few generations, so that errors at the edges have
+                        // time to spread.
+                        for _ in 0..4 {
+                            packed = packed.step(rule);
+                            each = each.step_each(rule);
+                            assert!(
+                                packed == each,
+                                "{rule} on a {width}x{height} grid with a {boundary} boundary stepped differently"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use cgol2::grid::Grid;
+
+/// Below this, what is left of a birth or death isn't drawn any more.
+const VISIBLE: f32 = 1.0 / 64.0;
+
+/// Where cells were born and died in recent generations, so that the active
+/// fronts of a pattern can be told apart from its still ash.
+pub struct Delta {
+    /// How strongly each cell's last birth is shown, from 1 in the generation
+    /// it was born down towards 0, in the same order as the cells of the grid.
+    pub births: Vec<f32>,
+    /// How strongly each cell's last death is shown, in the same way.
+    pub deaths: Vec<f32>,
+    /// The fraction of each birth and death that is still shown a generation
+    /// later, where 0 only shows those of the last generation.
+    decay: f32,
+}
+
+impl Delta {
+    /// Start tracking births and deaths in a grid with the given number of
+    /// cells.
+    pub fn new(cells: usize, decay: f32) -> Self {
+        Self { births: vec![0.0; cells], deaths: vec![0.0; cells], decay }
+    }
+
+    /// Fade the earlier births and deaths, and record those of the step from
+    /// one generation to the next.
+    pub fn record(&mut self, before: &Grid, after: &Grid) {
+        let width = after.width();
+        for (index, (birth, death)) in self.births.iter_mut().zip(&mut self.deaths).enumerate() {
+            let (x, y) = (index % width, index / width);
+            match (before.get(x, y), after.get(x, y)) {
+                (false, true) => (*birth, *death) = (1.0, 0.0),
+                (true, false) => (*birth, *death) = (0.0, 1.0),
+                _ => {
+                    *birth = fade(*birth * self.decay);
+                    *death = fade(*death * self.decay);
+                },
+            }
+        }
+    }
+
+    /// Forget every birth and death.
+    pub fn clear(&mut self) {
+        self.births.fill(0.0);
+        self.deaths.fill(0.0);
+    }
+
+    /// Returns how strongly the birth of the cell at the given coordinates of
+    /// a grid that is `width` cells wide is shown.
+    pub fn birth(&self, x: usize, y: usize, width: usize) -> f32 {
+        self.births[y * width + x]
+    }
+
+    /// Returns how strongly the death of the cell at the given coordinates is
+    /// shown, like [`Delta::birth`].
+    pub fn death(&self, x: usize, y: usize, width: usize) -> f32 {
+        self.deaths[y * width + x]
+    }
+}
+
+/// Returns the strength, or 0 once it is too faint to see.
+fn fade(strength: f32) -> f32 {
+    if strength < VISIBLE {
+        0.0
+    } else {
+        strength
+    }
+}
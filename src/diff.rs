@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use cgol2::grid::Grid;
+use cgol2::snapshot;
+use clap::builder::RangedU64ValueParser;
+use clap::Args;
+
+use crate::error::Error;
+use crate::image::{self, Style};
+use crate::render::CellShape;
+use crate::theme::StyleArgs;
+use crate::{load_pattern, ColorMode};
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Snapshot or pattern file of the first state, or `-` to read a pattern
+    /// from standard input.
+    before: PathBuf,
+
+    /// Snapshot or pattern file of the second state.
+    after: PathBuf,
+
+    /// Print every cell that differs, as `+X,Y` for cells only alive in the
+    /// second state and `-X,Y` for cells only alive in the first.
+    #[arg(long)]
+    list: bool,
+
+    /// Draw the states overlaid and save them as a PNG image, with cells
+    /// alive in both in the theme's alive color, cells only alive in the
+    /// second in its birth color, and cells only alive in the first in its
+    /// death color.
+    #[arg(long)]
+    out: Option<PathBuf>,
+
+    #[command(flatten)]
+    style: StyleArgs,
+
+    /// Number of pixels along the height and width of each cell.
+    #[arg(long, default_value_t = 4, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    cell_size: usize,
+}
+
+/// Load a state from a snapshot, along with the generation it was taken at,
+/// or else from a pattern file or the name of a bundled pattern.
+pub fn load_state(path: &Path) -> Result<(Grid, Option<u64>), Error> {
+    if snapshot::is_snapshot(path) {
+        let (grid, generation) =
+            snapshot::read(path).map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err })?;
+        return Ok((grid, Some(generation)));
+    }
+    Ok((load_pattern(path)?.cells, None))
+}
+
+/// Returns whether the cell at the given coordinates is alive, treating cells
+/// outside of the grid as dead.
+pub fn alive(grid: &Grid, x: usize, y: usize) -> bool {
+    x < grid.width() && y < grid.height() && grid.get(x, y)
+}
+
+/// Compare two states cell by cell with their top-left corners aligned, and
+/// print how many cells were added and removed on the way from the first to
+/// the second. Returns whether the states are identical, so that the exit
+/// status can tell scripts whether an engine change altered a result.
+pub fn run(args: DiffArgs) -> Result<bool, Error> {
+    let (before, before_generation) = load_state(&args.before)?;
+    let (after, after_generation) = load_state(&args.after)?;
+    for (path, grid, generation) in
+        [(&args.before, &before, before_generation), (&args.after, &after, after_generation)]
+    {
+        let generation = generation.map_or_else(String::new, |generation| format!(" at generation {generation}"));
+        println!(
+            "{}: {} live cells in {}x{}{generation}",
+            path.display(),
+            grid.population(),
+            grid.width(),
+            grid.height()
+        );
+    }
+
+    let (width, height) = (before.width().max(after.width()), before.height().max(after.height()));
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+    for y in 0..height {
+        for x in 0..width {
+            match (alive(&before, x, y), alive(&after, x, y)) {
+                (true, true) => unchanged += 1,
+                (false, true) => changes.push((x, y, true)),
+                (true, false) => changes.push((x, y, false)),
+                (false, false) => {},
+            }
+        }
+    }
+    let added = changes.iter().filter(|&&(_, _, added)| added).count();
+    println!("{unchanged} unchanged, {added} added, {} removed", changes.len() - added);
+    if let (Some(x0), Some(x1)) = (changes.iter().map(|&(x, ..)| x).min(), changes.iter().map(|&(x, ..)| x).max()) {
+        let (y0, y1) = (changes[0].1, changes[changes.len() - 1].1);
+        println!("The differences lie within ({x0}, {y0}) to ({x1}, {y1})");
+    } else {
+        println!("The states are identical");
+    }
+    if args.list {
+        for &(x, y, added) in &changes {
+            println!("{}{x},{y}", if added { '+' } else { '-' });
+        }
+    }
+
+    if let Some(out) = &args.out {
+        let theme = args.style.theme_source().load()?;
+        let mut overlay = Grid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                overlay.set(x, y, alive(&before, x, y) || alive(&after, x, y));
+            }
+        }
+        let style = Style {
+            theme: &theme,
+            color_mode: &ColorMode::Monochrome,
+            cell_shape: CellShape::Square,
+            cell_size: args.cell_size,
+        };
+        image::save(out, &overlay, ((0, 0), (width, height)), &style, |x, y| {
+            match (alive(&before, x, y), alive(&after, x, y)) {
+                (true, true) => theme.alive,
+                (false, _) => theme.birth,
+                (true, false) => theme.death,
+            }
+        })?;
+    }
+    Ok(changes.is_empty())
+}
@@ -0,0 +1,88 @@
+use clap::ValueEnum;
+
+use crate::grid::{Boundary, Grid};
+use crate::pattern::Pattern;
+use crate::rule::{LifeLike, Rule};
+
+/// The size of the universe that reference runs evolve their pattern in. Its
+/// edges wrap around, so that engines are checked on how they join the edges
+/// as well as on the cells between them.
+pub const REFERENCE_UNIVERSE: (usize, usize) = (64, 64);
+
+/// A run of a bundled pattern, with the digest that every engine must arrive
+/// at after it.
+pub struct Reference {
+    /// The name of the pattern in the [library](crate::library).
+    pub pattern: &'static str,
+    pub generations: u64,
+    /// The digest returned by [`Engine::run_reference`].
+    pub digest: u64,
+}
+
+/// The known-good reference runs, covering still lifes, oscillators,
+/// spaceships crossing the edges, a gun, and methuselahs.
+pub const REFERENCES: [Reference; 7] = [
+    Reference { pattern: "beehive", generations: 10, digest: 0x894E_D451_7ED9_1CDA },
+    Reference { pattern: "pulsar", generations: 100, digest: 0xE070_8D7C_E90A_5DE5 },
+    Reference { pattern: "glider", generations: 300, digest: 0x9174_60E2_4516_0124 },
+    Reference { pattern: "LWSS", generations: 300, digest: 0xD2B2_B7E7_D218_961E },
+    Reference { pattern: "Gosper glider gun", generations: 200, digest: 0xA2FD_1864_8623_2C3E },
+    Reference { pattern: "R-pentomino", generations: 500, digest: 0x5B38_384F_9AE5_AFAF },
+    Reference { pattern: "diehard", generations: 130, digest: 0x0657_5400_E217_FCE5 },
+];
+
+/// An implementation of the simulation that can compute the next generation of
+/// a grid.
+#[derive(ValueEnum, strum::Display, Clone, Copy)]
+#[strum(serialize_all = "lowercase")]
+pub enum Engine {
+    /// Count the neighbors of every cell one at a time.
+    Naive,
+    /// Count the neighbors of 64 cells at a time on bit-packed rows, with
+    /// bitwise adders. Rules that aren't Life-like, and grids whose edges are
+    /// joined with a shift or a twist, fall back to counting them one at a
+    /// time.
+    Packed,
+}
+
+impl Engine {
+    /// Compute the next generation of the grid based on the given rule.
+    pub fn step(self, grid: &Grid, rule: &dyn Rule) -> Grid {
+        match self {
+            Self::Naive => grid.step_each(rule),
+            Self::Packed => grid.step(rule),
+        }
+    }
+
+    /// Evolve a pattern under Conway's Game of Life for the given number of
+    /// generations, from the center of a [`REFERENCE_UNIVERSE`], and return a
+    /// digest of the result. Unlike [`Grid::digest`], the digest is the same
+    /// on every platform and version of Rust, so it can be checked against
+    /// the known-good values in [`REFERENCES`].
+    pub fn run_reference(self, pattern: &Pattern, generations: u64) -> u64 {
+        let (width, height) = REFERENCE_UNIVERSE;
+        let mut grid = Grid::new(width, height).with_boundary(Boundary::Wrap);
+        grid.paste(
+            &pattern.cells,
+            width.saturating_sub(pattern.cells.width()) / 2,
+            height.saturating_sub(pattern.cells.height()) / 2,
+        );
+        for _ in 0..generations {
+            grid = self.step(&grid, &LifeLike::LIFE);
+        }
+        stable_digest(&grid)
+    }
+}
+
+/// Returns the FNV-1a hash of the size of the grid followed by the coordinates
+/// of its live cells, row by row.
+fn stable_digest(grid: &Grid) -> u64 {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01B3;
+    let live = (0..grid.height()).flat_map(|y| (0..grid.width()).filter(move |&x| grid.get(x, y)).map(move |x| (x, y)));
+    std::iter::once((grid.width(), grid.height()))
+        .chain(live)
+        .flat_map(|(a, b)| [a as u64, b as u64])
+        .flat_map(u64::to_le_bytes)
+        .fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
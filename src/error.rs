@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+use std::{fmt, io};
+
+use cgol2::pattern::ParsePatternError;
+use cgol2::rule::ParseRuleError;
+use cgol2::rule_file::ParseRuleFileError;
+
+/// Everything that can go wrong while running the simulation.
+pub enum Error {
+    /// The boards don't fit in the window, because there are more of them
+    /// along a dimension than there are cells.
+    BoardLayout { columns: usize, rows: usize, width: usize, height: usize },
+    /// The window couldn't be created.
+    CreateWindow(winit::error::OsError),
+    /// The pixel buffer couldn't be created.
+    CreatePixels(pixels::Error),
+    /// The pixel buffer or its surface couldn't be resized.
+    Resize(pixels::TextureError),
+    /// The pixel buffer couldn't be drawn to the window.
+    Render(pixels::Error),
+    /// A file given on the command line couldn't be read.
+    ReadFile { path: PathBuf, source: io::Error },
+    /// An output file couldn't be written.
+    WriteFile { path: PathBuf, source: io::Error },
+    /// An image couldn't be encoded and written to a file.
+    WriteImage { path: PathBuf, source: png::EncodingError },
+    /// An image given on the command line couldn't be read or decoded.
+    ReadImage { path: PathBuf, source: png::DecodingError },
+    /// A recording couldn't be replayed because it is malformed.
+    InvalidRecording { path: PathBuf, message: String },
+    /// The format to write a pattern file in wasn't given and couldn't be told
+    /// from its extension.
+    UnknownFormat { path: PathBuf },
+    /// A pattern file is malformed.
+    Pattern { path: PathBuf, source: ParsePatternError },
+    /// A theme file is malformed.
+    Theme { path: PathBuf, source: toml::de::Error },
+    /// A rule given on the command line couldn't be resolved.
+    Rule(ParseRuleError),
+    /// A rule file is malformed or describes an unsupported rule.
+    RuleFile { path: PathBuf, source: ParseRuleFileError },
+    /// A weighted kernel file is malformed.
+    Kernel { path: PathBuf, source: toml::de::Error },
+    /// A config file is malformed.
+    Config { path: PathBuf, source: toml::de::Error },
+    /// A file couldn't be watched for changes.
+    Watch { path: PathBuf, source: notify::Error },
+    /// A rule that isn't life-like was given to something that only supports
+    /// life-like rules.
+    NotLifeLike { rule: String },
+    /// An option was given to a subcommand that doesn't support it.
+    Unsupported { option: &'static str, command: &'static str },
+    /// The remote control server couldn't be started.
+    ControlServer(io::Error),
+    /// The metrics server couldn't be started.
+    MetricsServer(io::Error),
+    /// The JSON-RPC server couldn't be started, or reading requests or
+    /// writing responses failed.
+    RpcServer(io::Error),
+    /// A frame couldn't be drawn to the terminal.
+    Terminal(io::Error),
+    /// A Lua script failed to load or one of its hooks raised an error.
+    Script(mlua::Error),
+    /// A MIDI mapping file is malformed.
+    #[cfg(feature = "midi")]
+    MidiMap { path: PathBuf, source: toml::de::Error },
+    /// The MIDI output port couldn't be found or opened, a message couldn't
+    /// be sent to it, or a mapping is out of range.
+    #[cfg(feature = "midi")]
+    Midi(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BoardLayout { columns, rows, width, height } => {
+                write!(f, "a {columns}x{rows} layout of boards doesn't fit in the {width}x{height} grid")
+            },
+            Self::CreateWindow(err) => write!(f, "failed to create window: {err}"),
+            Self::CreatePixels(err) => write!(f, "failed to create pixel buffer: {err}"),
+            Self::Resize(err) => write!(f, "failed to resize pixel buffer: {err}"),
+            Self::Render(err) => write!(f, "failed to render frame: {err}"),
+            Self::ReadFile { path, source } => write!(f, "failed to read {}: {source}", path.display()),
+            Self::WriteFile { path, source } => write!(f, "failed to write {}: {source}", path.display()),
+            Self::WriteImage { path, source } => write!(f, "failed to write image {}: {source}", path.display()),
+            Self::ReadImage { path, source } => write!(f, "failed to read image {}: {source}", path.display()),
+            Self::InvalidRecording { path, message } => write!(f, "invalid recording {}: {message}", path.display()),
+            Self::UnknownFormat { path } => {
+                write!(f, "can't tell which format to write {} in; try --to", path.display())
+            },
+            Self::Pattern { path, source } => write!(f, "invalid pattern {}: {source}", path.display()),
+            Self::Theme { path, source } => write!(f, "invalid theme {}: {source}", path.display()),
+            Self::Rule(err) => write!(f, "{err}"),
+            Self::RuleFile { path, source } => write!(f, "invalid rule file {}: {source}", path.display()),
+            Self::Kernel { path, source } => write!(f, "invalid kernel {}: {source}", path.display()),
+            Self::Config { path, source } => write!(f, "invalid config {}: {source}", path.display()),
+            Self::Watch { path, source } => write!(f, "failed to watch {} for changes: {source}", path.display()),
+            Self::NotLifeLike { rule } => write!(f, "`{rule}` is not a life-like rule"),
+            Self::Unsupported { option, command } => write!(f, "`--{option}` isn't supported by `{command}`"),
+            Self::ControlServer(err) => write!(f, "failed to start remote control server: {err}"),
+            Self::MetricsServer(err) => write!(f, "failed to start metrics server: {err}"),
+            Self::RpcServer(err) => write!(f, "JSON-RPC server failed: {err}"),
+            Self::Terminal(err) => write!(f, "failed to draw to the terminal: {err}"),
+            Self::Script(err) => write!(f, "script error: {err}"),
+            #[cfg(feature = "midi")]
+            Self::MidiMap { path, source } => write!(f, "invalid MIDI map {}: {source}", path.display()),
+            #[cfg(feature = "midi")]
+            Self::Midi(message) => write!(f, "MIDI output failed: {message}"),
+        }
+    }
+}
+
+// Returning an error from `main` prints its `Debug` representation, so make it
+// the same human-readable message as `Display`.
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::BoardLayout { .. }
+            | Self::InvalidRecording { .. }
+            | Self::UnknownFormat { .. }
+            | Self::NotLifeLike { .. }
+            | Self::Unsupported { .. } => None,
+            Self::CreateWindow(err) => Some(err),
+            Self::CreatePixels(err) | Self::Render(err) => Some(err),
+            Self::Resize(err) => Some(err),
+            Self::ReadFile { source, .. } | Self::WriteFile { source, .. } => Some(source),
+            Self::WriteImage { source, .. } => Some(source),
+            Self::ReadImage { source, .. } => Some(source),
+            Self::Pattern { source, .. } => Some(source),
+            Self::Theme { source, .. } | Self::Kernel { source, .. } | Self::Config { source, .. } => Some(source),
+            Self::Watch { source, .. } => Some(source),
+            Self::RuleFile { source, .. } => Some(source),
+            Self::ControlServer(err) | Self::MetricsServer(err) | Self::RpcServer(err) | Self::Terminal(err) => {
+                Some(err)
+            },
+            Self::Rule(err) => Some(err),
+            Self::Script(err) => Some(err),
+            #[cfg(feature = "midi")]
+            Self::MidiMap { source, .. } => Some(source),
+            #[cfg(feature = "midi")]
+            Self::Midi(_) => None,
+        }
+    }
+}
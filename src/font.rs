@@ -0,0 +1,107 @@
+use cgol2::grid::Grid;
+
+/// The width of each glyph, in font pixels.
+pub const GLYPH_WIDTH: usize = 3;
+/// The height of each glyph, in font pixels.
+pub const GLYPH_HEIGHT: usize = 5;
+
+/// Returns the rows of a tiny bitmap glyph for a character, from top to
+/// bottom, where the highest of the three low bits of each row is its leftmost
+/// pixel.
+///
+/// Only uppercase letters, digits, and common punctuation have glyphs, so
+/// lowercase letters are drawn as uppercase and anything else as `?`.
+pub fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '?' => [0b111, 0b001, 0b010, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '|' => [0b010, 0b010, 0b010, 0b010, 0b010],
+        _ => glyph('?'),
+    }
+}
+
+/// Rasterize text into the cells of a grid, with each pixel of its glyphs
+/// becoming a square of `scale` by `scale` live cells. Glyphs are separated
+/// by a column of dead pixels, and lines by a row of them.
+pub fn rasterize(text: &str, scale: usize) -> Grid {
+    let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+    let columns = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let width = (columns * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale;
+    let height = (lines.len() * (GLYPH_HEIGHT + 1)).saturating_sub(1) * scale;
+    let mut grid = Grid::new(width, height);
+    for (row, line) in lines.iter().enumerate() {
+        for (column, &c) in line.iter().enumerate() {
+            for (dy, bits) in glyph(c).into_iter().enumerate() {
+                for dx in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - dx)) == 0 {
+                        continue;
+                    }
+                    let x = (column * (GLYPH_WIDTH + 1) + dx) * scale;
+                    let y = (row * (GLYPH_HEIGHT + 1) + dy) * scale;
+                    for (x, y) in (y..y + scale).flat_map(|y| (x..x + scale).map(move |x| (x, y))) {
+                        grid.set(x, y, true);
+                    }
+                }
+            }
+        }
+    }
+    grid
+}
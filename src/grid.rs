@@ -0,0 +1,12 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+pub use crate::core::{Boundary, Edges, Grid};
+
+impl Grid {
+    /// Returns a hash of the state of the grid, used to detect repeated states.
+    pub fn digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
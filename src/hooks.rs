@@ -0,0 +1,126 @@
+use std::process::Command;
+use std::str::FromStr;
+
+/// Population records run their hooks at most once every this many
+/// generations, so that a growing pattern doesn't start a program every tick.
+const RECORD_COOLDOWN: u64 = 100;
+
+/// Something notable that can happen to a universe.
+#[derive(Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumString, strum::VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum Event {
+    /// Every cell died.
+    Extinct,
+    /// The universe settled into a still life.
+    Stable,
+    /// The universe settled into an oscillator with a period of at least 2.
+    Cycle,
+    /// The population rose above its highest so far.
+    Record,
+}
+
+/// A command to run through the shell whenever an event happens.
+#[derive(Clone)]
+pub struct Hook {
+    event: Event,
+    command: String,
+}
+
+impl FromStr for Hook {
+    type Err = String;
+
+    /// Parse a hook like `extinct:./notify.sh`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (event, command) = s.split_once(':').ok_or_else(|| format!("expected `EVENT:COMMAND`, got `{s}`"))?;
+        let event = event.trim().parse().map_err(|_| {
+            format!("unknown event `{event}`; the events are {}", <Event as strum::VariantNames>::VARIANTS.join(", "))
+        })?;
+        if command.trim().is_empty() {
+            return Err("the command to run is empty".to_string());
+        }
+        Ok(Self { event, command: command.to_string() })
+    }
+}
+
+/// Runs the hooks of a universe as their events happen. Extinction and
+/// settling down only count when the universe wasn't already extinct or
+/// settled in the generation before.
+pub struct Hooks {
+    hooks: Vec<Hook>,
+    /// The state of the universe in the last generation, so that events only
+    /// happen when it changes.
+    last: Option<Event>,
+    /// The generation whose population record last ran the hooks.
+    last_record: Option<u64>,
+}
+
+impl Hooks {
+    pub fn new(hooks: Vec<Hook>) -> Self {
+        Self { hooks, last: None, last_record: None }
+    }
+
+    /// Forget the earlier generations, as when the universe starts over.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.last_record = None;
+    }
+
+    /// Run the hooks of the events that happened in the latest generation,
+    /// given its population, the period it has settled into if any, and
+    /// whether it set a population record.
+    pub fn observe(&mut self, generation: u64, population: usize, period: Option<usize>, record: bool, rule: &str) {
+        let state = match period {
+            _ if population == 0 => Some(Event::Extinct),
+            Some(1) => Some(Event::Stable),
+            Some(_) => Some(Event::Cycle),
+            None => None,
+        };
+        let mut events = Vec::new();
+        if state != self.last {
+            events.extend(state);
+        }
+        self.last = state;
+        if record && self.last_record.is_none_or(|last| generation >= last + RECORD_COOLDOWN) {
+            self.last_record = Some(generation);
+            events.push(Event::Record);
+        }
+
+        for event in events {
+            for hook in self.hooks.iter().filter(|hook| hook.event == event) {
+                log::debug!("Running the {event} hook at generation {generation}");
+                let mut command = shell(&hook.command);
+                command
+                    .env("CGOL_EVENT", event.to_string())
+                    .env("CGOL_GENERATION", generation.to_string())
+                    .env("CGOL_POPULATION", population.to_string())
+                    .env("CGOL_PERIOD", period.map_or_else(String::new, |period| period.to_string()))
+                    .env("CGOL_RULE", rule);
+                spawn(command, &hook.command);
+            }
+        }
+    }
+}
+
+/// Returns a command that runs the given command line through the shell.
+fn shell(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut shell = Command::new(shell);
+    shell.arg(flag).arg(command);
+    shell
+}
+
+/// Start a command without waiting for it, so that slow hooks don't hold up
+/// the simulation, and log it if it fails.
+fn spawn(mut command: Command, name: &str) {
+    let name = name.to_string();
+    match command.spawn() {
+        Ok(mut child) => {
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => log::warn!("Hook `{name}` failed with {status}"),
+                Ok(_) => {},
+                Err(err) => log::warn!("Failed to wait for hook `{name}`: {err}"),
+            });
+        },
+        Err(err) => log::warn!("Failed to run hook `{name}`: {err}"),
+    }
+}
@@ -0,0 +1,263 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use cgol2::grid::{Boundary, Grid};
+use cgol2::parse_dimensions;
+use cgol2::soup::Picture;
+use clap::builder::RangedU64ValueParser;
+use clap::Args;
+
+use crate::error::Error;
+use crate::lineage::Lineage;
+use crate::render::{Canvas, CellShape, PixelRect, Rgb};
+use crate::simulation::Simulation;
+use crate::theme::{StyleArgs, Theme};
+use crate::{cycle_color, load_pattern, resolve_rule, ColorMode};
+
+#[derive(Args)]
+pub struct ImageArgs {
+    /// Pattern file to start from, or `-` to read the pattern from standard
+    /// input.
+    #[arg(long)]
+    pattern: PathBuf,
+
+    /// Number of generations to evolve the pattern for.
+    #[arg(long, default_value_t = 0)]
+    generations: u64,
+
+    /// File to write the PNG image to.
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Name of a registered rule, or a rulestring in B/S notation like
+    /// `B36/S23` or Hensel notation like `B2-a/S12`. Defaults to the rule in
+    /// the pattern file, or else `life`.
+    #[arg(long)]
+    rule: Option<String>,
+
+    /// Size of the universe in cells, given as `WIDTHxHEIGHT`, with the
+    /// pattern at its center. The whole universe is drawn. Defaults to a
+    /// universe large enough that nothing can reach its edges, and the image
+    /// only covers the live cells.
+    #[arg(long, value_parser = parse_dimensions)]
+    universe: Option<(usize, usize)>,
+
+    /// How the neighbors of cells at the edges of the universe are treated:
+    /// `dead`, `wrap`, `mirror`, or a topology in Golly's notation without
+    /// the sizes, such as `T+3` for a torus whose top and bottom edges are
+    /// joined three cells apart, `K*` for a Klein bottle, `C` for a
+    /// cross-surface, or `S` for a sphere.
+    #[arg(long, default_value_t = Boundary::Dead)]
+    boundary: Boundary,
+
+    /// Number of dead cells to leave around the live cells, if the image only
+    /// covers them.
+    #[arg(long, default_value_t = 2)]
+    margin: usize,
+
+    #[arg(long, default_value_t = ColorMode::Monochrome)]
+    color_mode: ColorMode,
+
+    /// Shape to draw each live cell as.
+    #[arg(long, default_value_t = CellShape::Square)]
+    cell_shape: CellShape,
+
+    #[command(flatten)]
+    style: StyleArgs,
+
+    /// Number of pixels along the height and width of each cell.
+    #[arg(long, default_value_t = 4, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    cell_size: usize,
+}
+
+/// Evolve a pattern for `--generations` generations, then draw it the same way
+/// the window does and save it as a PNG image.
+pub fn run(args: ImageArgs) -> Result<(), Error> {
+    let pattern = load_pattern(&args.pattern)?;
+    let rule_name = args.rule.clone().or(pattern.rule.clone()).unwrap_or_else(|| "life".to_string());
+    let rule = resolve_rule(&rule_name)?;
+
+    // Nothing travels faster than one cell per generation, so this much room
+    // on each side keeps the pattern from ever reaching the edges.
+    let room = usize::try_from(args.generations).unwrap_or(usize::MAX).saturating_add(args.margin);
+    let (width, height) = args.universe.unwrap_or((
+        pattern.cells.width().saturating_add(room.saturating_mul(2)),
+        pattern.cells.height().saturating_add(room.saturating_mul(2)),
+    ));
+    let mut grid = Grid::new(width, height).with_boundary(args.boundary);
+    grid.paste(
+        &pattern.cells,
+        width.saturating_sub(pattern.cells.width()) / 2,
+        height.saturating_sub(pattern.cells.height()) / 2,
+    );
+
+    let mut ages = vec![0u32; width * height];
+    let mut lineage = matches!(args.color_mode, ColorMode::Lineage).then(|| Lineage::new(&grid, 0));
+    for _ in 0..args.generations {
+        let next = grid.step(rule.as_ref());
+        if let Some(lineage) = &mut lineage {
+            lineage.inherit(&grid, &next);
+        }
+        grid = next;
+        for (index, age) in ages.iter_mut().enumerate() {
+            *age = if grid.get(index % width, index / width) { age.saturating_add(1) } else { 0 };
+        }
+    }
+    log::info!(
+        "{} has {} live cells after {} generations",
+        args.pattern.display(),
+        grid.population(),
+        args.generations
+    );
+
+    // Frame either the whole universe, or just the live cells and the margin
+    // around them.
+    let ((x, y), (columns, rows)) = match args.universe {
+        Some(universe) => ((0, 0), universe),
+        None => {
+            let ((x, y), (w, h)) = grid.bounds().unwrap_or(((width / 2, height / 2), (1, 1)));
+            let margin = args.margin as isize;
+            ((x as isize - margin, y as isize - margin), (w + 2 * args.margin, h + 2 * args.margin))
+        },
+    };
+
+    let theme = args.style.theme_source().load()?;
+
+    let style =
+        Style { theme: &theme, color_mode: &args.color_mode, cell_shape: args.cell_shape, cell_size: args.cell_size };
+    let cycle = cycle_color(args.generations, grid.population(), width * height);
+    save(&args.out, &grid, ((x, y), (columns, rows)), &style, |x, y| {
+        let genome = lineage.as_ref().and_then(|lineage| lineage.genome(x, y, width));
+        args.color_mode.cell_color(&theme, ages[y * width + x], genome, cycle)
+    })
+}
+
+/// How the cells of an image are drawn.
+pub struct Style<'a> {
+    pub theme: &'a Theme,
+    pub color_mode: &'a ColorMode,
+    pub cell_shape: CellShape,
+    /// Number of pixels along the height and width of each cell.
+    pub cell_size: usize,
+}
+
+/// Draw a region of the grid, given by its top-left corner and its size in
+/// cells, the same way the window does and save it as a PNG image. Live cells
+/// are drawn in the color returned for their coordinates.
+pub fn save(
+    path: &Path,
+    grid: &Grid,
+    region: ((isize, isize), (usize, usize)),
+    style: &Style,
+    color: impl Fn(usize, usize) -> Rgb,
+) -> Result<(), Error> {
+    let frame = draw(grid, region, style, color);
+    let write = || encode_png(BufWriter::new(File::create(path)?), &frame);
+    write().map_err(|err| Error::WriteImage { path: path.to_path_buf(), source: err })
+}
+
+/// A buffer of RGBA pixels.
+pub struct Frame {
+    pub pixels: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Draw a region of the grid, given by its top-left corner and its size in
+/// cells, the same way the window does, into a new frame. Live cells are
+/// drawn in the color returned for their coordinates.
+pub fn draw(
+    grid: &Grid,
+    ((x, y), (columns, rows)): ((isize, isize), (usize, usize)),
+    style: &Style,
+    color: impl Fn(usize, usize) -> Rgb,
+) -> Frame {
+    let (width, height) = (columns * style.cell_size, rows * style.cell_size);
+    let mut pixels = vec![0; width * height * 4];
+    let mut canvas = Canvas::new(&mut pixels, width, height);
+    let tile = PixelRect { x: 0, y: 0, w: width, h: height };
+    canvas.fill_rect(tile, style.theme.background);
+    canvas
+        .draw_cells(tile, grid, (x, y), style.cell_size, style.cell_shape, |x, y| grid.get(x, y).then(|| color(x, y)));
+    if let Some(grid_color) = style.theme.grid {
+        canvas.draw_grid_lines(tile, style.cell_size, style.theme.grid_width, grid_color);
+    }
+    Frame { pixels, width, height }
+}
+
+/// Writes an image of each board every few generations, for assembling into
+/// a time-lapse.
+pub struct FrameExport {
+    every: u64,
+    dir: PathBuf,
+    /// Whether the images are named after the board they are of, because
+    /// there is more than one.
+    boards: bool,
+}
+
+impl FrameExport {
+    /// Start exporting images to a directory, creating it if needed.
+    pub fn create(every: u64, dir: &Path, boards: usize) -> Result<Self, Error> {
+        std::fs::create_dir_all(dir).map_err(|err| Error::WriteFile { path: dir.to_path_buf(), source: err })?;
+        Ok(Self { every, dir: dir.to_path_buf(), boards: boards > 1 })
+    }
+
+    /// Save an image of the whole universe of a board if its generation is a
+    /// multiple of the export interval. Images are named after the generation,
+    /// like `00001200.png`, or `2-00001200.png` for the third board.
+    pub fn export(&self, board: usize, simulation: &Simulation, style: &Style) -> Result<(), Error> {
+        if !simulation.generation.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        let name = if self.boards {
+            format!("{board}-{:08}.png", simulation.generation)
+        } else {
+            format!("{:08}.png", simulation.generation)
+        };
+        let grid = &simulation.grid;
+        let region = ((0, 0), (grid.width(), grid.height()));
+        let cycle = simulation.cycle_color();
+        save(&self.dir.join(name), grid, region, style, |x, y| {
+            simulation.cell_color(x, y, style.theme, style.color_mode, cycle)
+        })
+    }
+}
+
+/// Encode a frame as a PNG image.
+pub fn encode_png(out: impl Write, frame: &Frame) -> Result<(), png::EncodingError> {
+    let too_large = || png::EncodingError::LimitsExceeded;
+    let (width, height) =
+        (u32::try_from(frame.width).map_err(|_| too_large())?, u32::try_from(frame.height).map_err(|_| too_large())?);
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&frame.pixels)?;
+    Ok(())
+}
+
+/// Read a PNG file as a grayscale picture. Transparent pixels count as dark.
+pub fn read_picture(path: &Path) -> Result<Picture, png::DecodingError> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let channels = info.color_type.samples();
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in buffer.chunks(info.line_size).take(height) {
+        for pixel in row.chunks(channels).take(width) {
+            let value = |channel: usize| pixel[channel] as f64 / 255.0;
+            // Rec. 709 luma, with the alpha channel mixing it with black.
+            let (luma, alpha) = match info.color_type {
+                png::ColorType::Grayscale | png::ColorType::Indexed => (value(0), 1.0),
+                png::ColorType::GrayscaleAlpha => (value(0), value(1)),
+                png::ColorType::Rgb => (0.2126 * value(0) + 0.7152 * value(1) + 0.0722 * value(2), 1.0),
+                png::ColorType::Rgba => (0.2126 * value(0) + 0.7152 * value(1) + 0.0722 * value(2), value(3)),
+            };
+            pixels.push(luma * alpha);
+        }
+    }
+    Ok(Picture { width, height, pixels })
+}
@@ -0,0 +1,53 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod chunk;
+/// The grid, its boundaries, and the rules and engine that step it, without
+/// anything from `std`, so that they build with `no_std` and `alloc` for
+/// running the simulation on microcontrollers. Disable the default `std`
+/// feature to build only this module.
+pub mod core;
+#[cfg(feature = "std")]
+pub mod engine;
+#[cfg(feature = "std")]
+pub mod grid;
+#[cfg(feature = "std")]
+pub mod library;
+#[cfg(feature = "std")]
+pub mod pattern;
+#[cfg(feature = "std")]
+pub mod rule;
+#[cfg(feature = "std")]
+pub mod rule_file;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod soup;
+#[cfg(feature = "std")]
+pub mod spaceship;
+
+/// Parse a probability in the range [0, 1].
+#[cfg(feature = "std")]
+pub fn parse_probability(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("{value} is not in the range 0..=1"));
+    }
+    Ok(value)
+}
+
+/// Parse dimensions in the form `WIDTHxHEIGHT`, e.g. `64x64`.
+#[cfg(feature = "std")]
+pub fn parse_dimensions(s: &str) -> Result<(usize, usize), String> {
+    let (width, height) = s.split_once('x').ok_or_else(|| format!("expected `WIDTHxHEIGHT`, got `{s}`"))?;
+    let width: usize = width.parse().map_err(|_| format!("`{width}` is not a valid width"))?;
+    let height: usize = height.parse().map_err(|_| format!("`{height}` is not a valid height"))?;
+    if width == 0 || height == 0 {
+        return Err(format!("dimensions must be non-zero, got `{s}`"));
+    }
+    Ok((width, height))
+}
@@ -0,0 +1,67 @@
+use crate::pattern::Pattern;
+
+/// A pattern bundled with the program, along with what is known about it.
+pub struct Entry {
+    pub name: &'static str,
+    /// Who found the pattern.
+    pub discoverer: &'static str,
+    /// How many generations the pattern takes to repeat its shape, if it
+    /// ever does. Methuselahs have none.
+    pub period: Option<u32>,
+    rle: &'static str,
+}
+
+/// The bundled patterns, from still lifes through oscillators, spaceships, and
+/// guns to methuselahs.
+pub const PATTERNS: [Entry; 15] = [
+    Entry { name: "block", discoverer: "John Conway", period: Some(1), rle: "2o$2o!" },
+    Entry { name: "beehive", discoverer: "John Conway", period: Some(1), rle: "b2o$o2bo$b2o!" },
+    Entry { name: "blinker", discoverer: "John Conway", period: Some(2), rle: "3o!" },
+    Entry { name: "toad", discoverer: "Simon Norton", period: Some(2), rle: "b3o$3o!" },
+    Entry { name: "beacon", discoverer: "John Conway", period: Some(2), rle: "2o$o$3bo$2b2o!" },
+    Entry {
+        name: "pulsar",
+        discoverer: "John Conway",
+        period: Some(3),
+        rle: "2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+    Entry { name: "pentadecathlon", discoverer: "John Conway", period: Some(15), rle: "2bo4bo$2ob4ob2o$2bo4bo!" },
+    Entry { name: "glider", discoverer: "Richard K. Guy", period: Some(4), rle: "bo$2bo$3o!" },
+    Entry { name: "LWSS", discoverer: "John Conway", period: Some(4), rle: "bo2bo$o$o3bo$4o!" },
+    Entry { name: "MWSS", discoverer: "John Conway", period: Some(4), rle: "3bo$bo3bo$o$o4bo$5o!" },
+    Entry { name: "HWSS", discoverer: "John Conway", period: Some(4), rle: "3b2o$bo4bo$o$o5bo$6o!" },
+    Entry {
+        name: "Gosper glider gun",
+        discoverer: "Bill Gosper",
+        period: Some(30),
+        rle: "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    },
+    Entry { name: "R-pentomino", discoverer: "John Conway", period: None, rle: "b2o$2o$bo!" },
+    Entry { name: "acorn", discoverer: "Charles Corderman", period: None, rle: "bo$3bo$2o2b3o!" },
+    Entry { name: "diehard", discoverer: "unknown", period: None, rle: "6bo$2o$bo3b3o!" },
+];
+
+impl Entry {
+    /// Returns the pattern, named after the entry.
+    pub fn pattern(&self) -> Pattern {
+        let mut pattern = Pattern::parse_rle(self.rle).expect("bundled patterns are valid RLE");
+        pattern.name = Some(self.name.to_string());
+        pattern
+    }
+
+    /// Returns the pattern in RLE format.
+    pub fn rle(&self) -> &'static str {
+        self.rle
+    }
+}
+
+/// Look up a bundled pattern by name. Names are matched regardless of case,
+/// spaces, hyphens, and underscores, so `gosper-glider-gun` finds the Gosper
+/// glider gun.
+pub fn find(name: &str) -> Option<&'static Entry> {
+    let normalize = |name: &str| {
+        name.chars().filter(|c| !matches!(c, ' ' | '-' | '_')).map(|c| c.to_ascii_lowercase()).collect::<String>()
+    };
+    let name = normalize(name);
+    PATTERNS.iter().find(|entry| normalize(entry.name) == name)
+}
@@ -0,0 +1,85 @@
+use cgol2::grid::Grid;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::render::Rgb;
+
+/// The most that each color component of a newborn cell's genome can drift
+/// from the average of its parents'.
+const MUTATION: i16 = 6;
+
+/// The colors that live cells inherit from the cells that gave birth to them,
+/// so that each family of cells shares a color.
+pub struct Lineage {
+    /// The genome of each cell, in the same order as the cells of the grid.
+    /// Dead cells have none.
+    pub genomes: Vec<Option<Rgb>>,
+    /// Mutations are drawn from their own generator, so that tracking lineage
+    /// doesn't change how noise or injections play out.
+    rng: StdRng,
+}
+
+impl Lineage {
+    /// Give every live cell of the grid a random genome.
+    pub fn new(grid: &Grid, seed: u64) -> Self {
+        let mut lineage = Self { genomes: vec![None; grid.width() * grid.height()], rng: StdRng::seed_from_u64(seed) };
+        lineage.adopt(grid);
+        lineage
+    }
+
+    /// Returns the genome of the cell at the given coordinates of a grid that
+    /// is `width` cells wide, if it is alive.
+    pub fn genome(&self, x: usize, y: usize, width: usize) -> Option<Rgb> {
+        self.genomes[y * width + x]
+    }
+
+    /// Give a random genome to every live cell of the grid that doesn't have
+    /// one, such as cells that were painted, and forget the genomes of dead
+    /// cells.
+    pub fn adopt(&mut self, grid: &Grid) {
+        let width = grid.width();
+        for (index, genome) in self.genomes.iter_mut().enumerate() {
+            if !grid.get(index % width, index / width) {
+                *genome = None;
+            } else if genome.is_none() {
+                *genome = Some(Rgb(self.rng.gen(), self.rng.gen(), self.rng.gen()));
+            }
+        }
+    }
+
+    /// Update the genomes for the step from one generation to the next.
+    /// Survivors keep their genome, and each newborn cell gets the average of
+    /// its live neighbors' genomes with a slight mutation.
+    pub fn inherit(&mut self, before: &Grid, after: &Grid) {
+        let width = after.width();
+        let mut genomes = vec![None; self.genomes.len()];
+        for (index, genome) in genomes.iter_mut().enumerate() {
+            let (x, y) = (index % width, index / width);
+            if !after.get(x, y) {
+                continue;
+            }
+            if before.get(x, y) {
+                *genome = self.genomes[index];
+                continue;
+            }
+            let parents: Vec<Rgb> = before
+                .neighbor_coords(x as i32, y as i32)
+                .into_iter()
+                .flatten()
+                .filter_map(|(x, y)| self.genomes[y * width + x])
+                .collect();
+            *genome = (!parents.is_empty()).then(|| {
+                let mut component = |component: fn(&Rgb) -> u8| {
+                    let sum: u32 = parents.iter().map(|parent| component(parent) as u32).sum();
+                    let average = (sum / parents.len() as u32) as i16;
+                    (average + self.rng.gen_range(-MUTATION..=MUTATION)).clamp(0, 255) as u8
+                };
+                Rgb(component(|rgb| rgb.0), component(|rgb| rgb.1), component(|rgb| rgb.2))
+            });
+        }
+        self.genomes = genomes;
+        // Cells born without live neighbors, e.g. from noise, start new
+        // families.
+        self.adopt(after);
+    }
+}
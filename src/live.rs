@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use winit::event_loop::EventLoopProxy;
+
+use crate::error::Error;
+use crate::render::Rgb;
+use crate::run::RunArgs;
+use crate::theme::ThemeSource;
+use crate::{ColorMode, UserEvent};
+
+/// Settings of a run in the window that are read from the file given to
+/// `--config`, and applied again whenever it changes.
+///
+/// Config files are TOML documents with any of the keys below. Settings that
+/// are left out keep the values given by flags, or those they were changed to
+/// while running.
+///
+/// ```toml
+/// rule = "highlife"
+/// color_mode = "age"
+/// frames_per_second = 30
+/// steps_per_frame = 4
+/// alive_color = "#33ff66"
+/// dead_color = "#000000"
+/// grid_color = "#202020"
+/// theme = "deuteranopia"
+/// high_contrast = true
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LiveConfig {
+    pub rule: Option<String>,
+    pub color_mode: Option<ColorMode>,
+    pub frames_per_second: Option<u32>,
+    pub steps_per_frame: Option<u64>,
+    pub alive_color: Option<Rgb>,
+    pub dead_color: Option<Rgb>,
+    pub grid_color: Option<Rgb>,
+    pub theme: Option<String>,
+    pub high_contrast: Option<bool>,
+}
+
+impl LiveConfig {
+    /// Apply the settings that boards are created with to the arguments of a
+    /// run, over those given as flags. The colors are applied to the theme
+    /// instead, with [`LiveConfig::theme`].
+    pub fn apply_to(&self, args: &mut RunArgs) {
+        if let Some(rule) = &self.rule {
+            args.simulation.rule = rule.clone();
+            args.simulation.rule_file = None;
+            args.simulation.kernel = None;
+        }
+        if let Some(color_mode) = &self.color_mode {
+            args.color_mode = color_mode.clone();
+        }
+        // Neither applies while the speed adapts to `--target-fps`.
+        if args.target_fps.is_none() {
+            args.fps = self.frames_per_second.or(args.fps);
+            args.steps_per_frame = self.steps_per_frame.unwrap_or(args.steps_per_frame);
+        }
+    }
+
+    /// Returns the theme with the theme and colors of the config over those
+    /// given as flags.
+    pub fn theme(&self, flags: &ThemeSource) -> ThemeSource {
+        ThemeSource {
+            name: self.theme.clone().unwrap_or_else(|| flags.name.clone()),
+            high_contrast: self.high_contrast.unwrap_or(flags.high_contrast),
+            alive: self.alive_color.or(flags.alive),
+            background: self.dead_color.or(flags.background),
+            grid: self.grid_color.or(flags.grid),
+            ..flags.clone()
+        }
+    }
+
+    /// Load a config file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err })?;
+        toml::from_str(&text).map_err(|err| Error::Config { path: path.to_path_buf(), source: err })
+    }
+}
+
+/// Watch the given files, sending an event to the event loop whenever any of
+/// them changes. Files are watched through their directories, since editors
+/// often save by replacing the file rather than writing to it. The files stop
+/// being watched once the returned watcher is dropped.
+pub fn watch(files: Vec<PathBuf>, proxy: EventLoopProxy<UserEvent>) -> Result<RecommendedWatcher, Error> {
+    // Paths in events are absolute, so the files are compared by name.
+    let names: Vec<_> = files.iter().filter_map(|file| file.file_name().map(|name| name.to_owned())).collect();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+        let changed = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            && event.paths.iter().any(|path| path.file_name().is_some_and(|name| names.iter().any(|n| n == name)));
+        if changed {
+            // The event loop has exited if this fails, so there is nothing
+            // left to reload.
+            let _ = proxy.send_event(UserEvent::Reload);
+        }
+    })
+    .map_err(|err| Error::Watch { path: files.first().cloned().unwrap_or_default(), source: err })?;
+    for file in &files {
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|err| Error::Watch { path: file.clone(), source: err })?;
+    }
+    Ok(watcher)
+}
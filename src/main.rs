@@ -1,218 +1,325 @@
-use std::time::Duration;
-
-use clap::{Parser, ValueEnum};
-use pixels::{Pixels, SurfaceTexture};
-use rand::Rng;
-use winit::dpi::PhysicalSize;
-use winit::event_loop::EventLoop;
-use winit::window::WindowBuilder;
+mod app;
+mod bench;
+mod browser;
+mod camera;
+mod config;
+mod console;
+mod convert;
+mod delta;
+mod diff;
+mod error;
+mod font;
+mod hooks;
+mod image;
+mod lineage;
+mod live;
+mod methuselah;
+mod metrics;
+#[cfg(feature = "midi")]
+mod midi;
+mod minimap;
+mod overlay;
+mod panel;
+mod predecessor;
+mod profile;
+mod recording;
+mod remote;
+mod render;
+mod rpc;
+mod run;
+mod script;
+mod search;
+mod selection;
+mod sim;
+mod simulation;
+mod species;
+mod state;
+mod stats;
+mod terminal;
+mod theme;
+mod verify;
+mod worker;
+mod zones;
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use cgol2::library;
+use cgol2::pattern::Pattern;
+use cgol2::rule::{Registry, Rule, Weighted};
+use cgol2::rule_file::RuleFile;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::bench::BenchArgs;
+use crate::config::SimulationArgs;
+use crate::convert::ConvertArgs;
+use crate::diff::DiffArgs;
+use crate::error::Error;
+use crate::image::ImageArgs;
+use crate::predecessor::PredecessorArgs;
+#[cfg(feature = "count-allocations")]
+use crate::profile::CountingAllocator;
+use crate::render::Rgb;
+use crate::rpc::RpcArgs;
+use crate::run::RunArgs;
+use crate::search::SearchArgs;
+use crate::sim::SimArgs;
+use crate::terminal::TerminalArgs;
+use crate::theme::Theme;
+use crate::verify::VerifyArgs;
+
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 /// A "cell" in the grid has this number of pixels along its height and width,
 /// and each cell is offset by a multiple of this number.
-const PIXELS_PER_CELL: usize = 16;
-
-const WINDOW_WIDTH: usize = 800;
-const WINDOW_HEIGHT: usize = 640;
-
-/// A color with red, green, and blue components.
-#[derive(Clone, Copy)]
-struct Rgb(u8, u8, u8);
-
-impl Rgb {
-    /// Plain black.
-    const BLACK: Rgb = Rgb(0, 0, 0);
-    /// Plain white.
-    const WHITE: Rgb = Rgb(255, 255, 255);
-
-    /// Generate a random color.
-    fn random() -> Self {
-        let mut rng = rand::thread_rng();
-        let r = rng.gen_range(0..=255);
-        let g = rng.gen_range(0..=255);
-        let b = rng.gen_range(0..=255);
-        Self(r, g, b)
-    }
+pub const PIXELS_PER_CELL: usize = 16;
+
+/// The number of generations it takes a cell to reach the end of the age color
+/// ramp.
+const AGE_RAMP_GENERATIONS: u32 = 50;
+
+/// In the cycle color mode, the number of generations the hue takes to go
+/// once around the color wheel.
+const HUE_CYCLE_GENERATIONS: f64 = 1000.0;
+
+/// The largest radius the brush for painting cells can have, in cells.
+pub const MAX_BRUSH_RADIUS: usize = 64;
+
+/// The most generations that can be computed for each frame.
+pub const MAX_STEPS_PER_FRAME: u64 = 1024;
+
+pub const WINDOW_WIDTH: usize = 800;
+pub const WINDOW_HEIGHT: usize = 640;
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
-/// A location in grid space.
-struct GridCoords {
-    x: usize,
-    y: usize,
+/// Events sent to the event loop from other threads.
+enum UserEvent {
+    /// A command from a remote control client.
+    Remote(remote::Request),
+    /// The config file or theme file changed.
+    Reload,
 }
 
-/// A location in pixel space.
-struct PixelCoords {
-    x: usize,
-    y: usize,
+#[derive(Subcommand)]
+enum Command {
+    /// Run the simulation in a window. Press `?` or F1 to list the keys and
+    /// the current settings.
+    Run(Box<RunArgs>),
+    /// Run a single universe without a window until it ends, and report how
+    /// it ended.
+    Sim(Box<SimArgs>),
+    /// Run a single universe inside the terminal until it ends, drawing it
+    /// with the Sixel or Kitty graphics protocol, e.g. over SSH.
+    Terminal(Box<TerminalArgs>),
+    /// Evolve many random soups without a window and report the unusual ones.
+    ///
+    /// Soups are generated from the settings of the initial random
+    /// configuration, with consecutive seeds from `--seed`, in a 16x16 region
+    /// unless `--soup-size` is given, and given up on after 10000 generations
+    /// unless `--max-generations` is. Settings that place cells other than at
+    /// random or change how the universe evolves past its rule aren't
+    /// supported.
+    Search(Box<SearchArgs>),
+    /// Measure how many generations per second each engine can compute.
+    Bench(BenchArgs),
+    /// Check that every engine evolves the bundled reference patterns to the
+    /// known-good states. Exits with status 1 if any of them doesn't.
+    Verify(VerifyArgs),
+    /// List the registered rules.
+    Rules,
+    /// List the patterns bundled with the program, which can be given by name
+    /// wherever a pattern file can.
+    Patterns,
+    /// Convert a pattern file between RLE, plaintext, Life 1.06, and
+    /// macrocell formats.
+    Convert(ConvertArgs),
+    /// Evolve a pattern without a window and save the final generation as a
+    /// PNG image.
+    Render(ImageArgs),
+    /// Compare two snapshots or pattern files cell by cell, and report the
+    /// cells that were added and removed. Exits with status 1 if they differ.
+    Diff(DiffArgs),
+    /// Search for a state that evolves into a pattern in one generation, to
+    /// tell whether the pattern is a Garden of Eden.
+    Predecessor(PredecessorArgs),
+    /// Serve a JSON-RPC API over stdin and stdout, or TCP, for driving a
+    /// universe without a window from other programs.
+    Rpc(Box<RpcArgs>),
 }
 
-impl PixelCoords {
-    /// Returns pixel coordinates at the origin of the screen (0, 0).
-    fn origin() -> Self {
-        Self { x: 0, y: 0 }
-    }
+/// Returns the number of physical pixels along the height and width of a cell
+/// that is `cell_size` logical pixels wide.
+fn physical_cell_size(cell_size: usize, scale_factor: f64) -> usize {
+    ((cell_size as f64 * scale_factor).round() as usize).max(1)
+}
+
+#[derive(ValueEnum, strum::Display, Clone, PartialEq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+enum ColorMode {
+    /// Cells will be rendered in the `--alive-color`.
+    Monochrome,
+    /// Cells will be rendered with a random color.
+    Random,
+    /// Cells will be colored by how many generations they have been alive,
+    /// using the theme's `age` ramp.
+    Age,
+    /// Cells will be colored by their family, where newborn cells take the
+    /// average color of their parents with a slight mutation, and the initial
+    /// cells get random colors.
+    Lineage,
+    /// Cells will share a color whose hue slowly cycles with the generation,
+    /// and shifts with the population.
+    Cycle,
 }
 
-/// Draw a filled cell in the pixel buffer.
-fn fill_cell(frame: &mut [u8], buffer_width: usize, coords: GridCoords, rgb: Rgb) {
-    let pixel_coords = PixelCoords { x: coords.x * PIXELS_PER_CELL, y: coords.y * PIXELS_PER_CELL };
-    fill_rect(frame, buffer_width, pixel_coords, PIXELS_PER_CELL, PIXELS_PER_CELL, rgb);
+#[derive(ValueEnum, strum::Display, Clone, Copy)]
+#[strum(serialize_all = "lowercase")]
+enum OnEnd {
+    /// Refill the board with a random configuration from a new seed.
+    Restart,
+    /// Pause the board, leaving its final state on screen.
+    Pause,
+    /// Exit once every board has ended.
+    Exit,
 }
 
-/// Draw a filled rectangle in the pixel buffer.
-fn fill_rect(frame: &mut [u8], buffer_width: usize, coords: PixelCoords, w: usize, h: usize, rgb: Rgb) {
-    for y in coords.y..coords.y + h {
-        for x in coords.x..coords.x + w {
-            let idx = (y * buffer_width + x) * 4;
-            frame[idx + 0] = rgb.0;
-            frame[idx + 1] = rgb.1;
-            frame[idx + 2] = rgb.2;
-            frame[idx + 3] = 0xFF;
+impl ColorMode {
+    /// Returns the color to draw a live cell with, given how many consecutive
+    /// generations it has been alive for, the genome it has inherited if
+    /// lineage is tracked, and the color of its board's cycle.
+    fn cell_color(&self, theme: &Theme, age: u32, genome: Option<Rgb>, cycle: Rgb) -> Rgb {
+        match self {
+            Self::Monochrome => theme.alive,
+            Self::Random => theme.legible(Rgb::random()),
+            Self::Age => match theme.ramp("age") {
+                Some(ramp) => ramp.sample(age.min(AGE_RAMP_GENERATIONS) as f64 / AGE_RAMP_GENERATIONS as f64),
+                None => theme.alive,
+            },
+            Self::Lineage => genome.map_or(theme.alive, |genome| theme.legible(genome)),
+            Self::Cycle => theme.legible(cycle),
         }
     }
 }
 
-/// Return the number of alive cells out of a given cell's up-to eight
-/// neighbors.
-fn alive_neighbors(grid: &Vec<Vec<bool>>, x: i32, y: i32) -> u8 {
-    const OFFSETS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0)];
-
-    let grid_width = grid[0].len();
-    let grid_height = grid.len();
-    let mut alive = 0;
-    for offset in OFFSETS {
-        // Ensure that x and y for this offset are in range, otherwise skip it.
-        let Some(x) = usize::try_from(x + offset.0).ok() else { continue };
-        let Some(y) = usize::try_from(y + offset.1).ok() else { continue };
-        if x >= grid_width || y >= grid_height {
-            continue;
-        }
+/// Returns the color that all cells of a board share in the cycle color mode,
+/// given its generation, and its population out of the cells in the universe.
+/// A full turn of the hue takes [`HUE_CYCLE_GENERATIONS`], and filling half of
+/// the universe shifts it by a full turn too.
+fn cycle_color(generation: u64, population: usize, cells: usize) -> Rgb {
+    let density = population as f64 / cells.max(1) as f64;
+    Rgb::from_hsv(generation as f64 / HUE_CYCLE_GENERATIONS + 2.0 * density, 0.65, 1.0)
+}
 
-        if grid[y][x] {
-            alive += 1;
-        }
-    }
-    alive
+/// Look up a rule by name in the registry of built-in rules, or parse it as a
+/// rulestring.
+fn resolve_rule(name: &str) -> Result<Arc<dyn Rule>, Error> {
+    Registry::with_builtins().resolve(name).map_err(Error::Rule)
 }
 
-/// Generate and fill a random configuration of the grid.
-fn random_configuration(grid: &mut Vec<Vec<bool>>, chance: f64) {
-    let mut rng = rand::thread_rng();
-    for r in grid {
-        for c in r {
-            if rng.gen_bool(chance) {
-                *c = true;
-            }
-        }
+/// Resolve the rule a simulation evolves by, loading it from `--rule-file` or
+/// `--kernel` if either was given. Returns the rule along with its name.
+fn simulation_rule(config: &SimulationArgs) -> Result<(Arc<dyn Rule>, String), Error> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path).map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err })
+    };
+    if let Some(path) = &config.rule_file {
+        let file =
+            RuleFile::parse(&read(path)?).map_err(|err| Error::RuleFile { path: path.to_path_buf(), source: err })?;
+        let name = file.name.clone();
+        return Ok((Arc::new(file), name));
+    }
+    if let Some(path) = &config.kernel {
+        let rule: Weighted =
+            toml::from_str(&read(path)?).map_err(|err| Error::Kernel { path: path.to_path_buf(), source: err })?;
+        return Ok((Arc::new(rule), path.display().to_string()));
     }
+    Ok((resolve_rule(&config.rule)?, config.rule.clone()))
 }
 
-#[derive(Parser)]
-#[command(version, about, long_about = None)]
-struct Cli {
-    #[arg(long, default_value_t = ColorMode::Monochrome)]
-    color_mode: ColorMode,
+/// Read a file given on the command line, or standard input if the path is
+/// `-`, so that patterns can be piped in.
+fn read_input(path: &Path) -> Result<String, Error> {
+    let text = if path == Path::new("-") { io::read_to_string(io::stdin()) } else { std::fs::read_to_string(path) };
+    text.map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err })
+}
 
-    #[arg(long, default_value_t = 0.25)]
-    alive_random_chance: f64,
+/// Load a pattern file, or a pattern from standard input if the path is `-`,
+/// detecting which format it is in. The names of bundled patterns load those
+/// patterns instead.
+fn load_pattern(path: &Path) -> Result<Pattern, Error> {
+    if let Some(entry) = path.to_str().and_then(library::find) {
+        return Ok(entry.pattern());
+    }
+    let text = read_input(path)?;
+    Pattern::parse(&text).map_err(|err| Error::Pattern { path: path.to_path_buf(), source: err })
 }
 
-#[derive(ValueEnum, strum::Display, Clone)]
-#[strum(serialize_all = "lowercase")]
-enum ColorMode {
-    /// Cells will be rendered as plain white.
-    Monochrome,
-    /// Cells will be rendered with a random color.
-    Random,
+/// Print every registered rule along with its description.
+fn list_rules() {
+    let registry = Registry::with_builtins();
+    let width = registry.iter().map(|registration| registration.name.len()).max().unwrap_or(0);
+    for registration in registry.iter() {
+        println!("{:<width$}  {}", registration.name, registration.description);
+    }
 }
 
-fn main() {
-    env_logger::init();
-    let args = Cli::parse();
-    let event_loop = EventLoop::new();
-    let size = PhysicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64);
-    let window = WindowBuilder::new().with_inner_size(size).build(&event_loop).unwrap();
-
-    let pixel_buffer_width = WINDOW_WIDTH;
-    let pixel_buffer_height = WINDOW_HEIGHT;
-
-    // In order to properly render the game, we *need* the screen size in each
-    // direction to be a multiple of the PIXELS_PER_CELL value.
-    assert!(size.width as usize % PIXELS_PER_CELL == 0, "screen width must be a multiple of {PIXELS_PER_CELL}",);
-    assert!(size.height as usize % PIXELS_PER_CELL == 0, "screen height must be a multiple of {PIXELS_PER_CELL}",);
-
-    let grid_width = pixel_buffer_width / PIXELS_PER_CELL;
-    let grid_height = pixel_buffer_height / PIXELS_PER_CELL;
-    let surface_texture = SurfaceTexture::new(pixel_buffer_width as u32, pixel_buffer_height as u32, &window);
-    let mut pixels = Pixels::new(pixel_buffer_width as u32, pixel_buffer_height as u32, surface_texture).unwrap();
-
-    // The "grid" is a 2-dimensional state object that stores the alive / dead
-    // status of each of its cells.
-    let mut grid = vec![vec![false; grid_width]; grid_height];
-    random_configuration(&mut grid, args.alive_random_chance);
-
-    let sleep_duration = Duration::from_millis(100);
-
-    let color_gen = match args.color_mode {
-        ColorMode::Monochrome => || Rgb::WHITE,
-        ColorMode::Random => || Rgb::random(),
-    };
+/// Print every bundled pattern along with its size, period, and discoverer.
+fn list_patterns() {
+    let width = library::PATTERNS.iter().map(|entry| entry.name.len()).max().unwrap_or(0);
+    for entry in &library::PATTERNS {
+        let cells = entry.pattern().cells;
+        let size = format!("{}x{}", cells.width(), cells.height());
+        let period = entry.period.map_or_else(|| "-".to_string(), |period| format!("p{period}"));
+        println!("{:<width$}  {size:<5}  {period:<3}  {}", entry.name, entry.discoverer);
+    }
+}
 
-    std::thread::spawn(move || loop {
-        log::trace!("Tick");
-        let frame = pixels.frame_mut();
-
-        // Clear the screen with black.
-        fill_rect(
-            frame,
-            pixel_buffer_width,
-            PixelCoords::origin(),
-            pixel_buffer_width,
-            pixel_buffer_height,
-            Rgb::BLACK,
-        );
-
-        // Draw the current state of the grid.
-        for y in 0..grid_height {
-            for x in 0..grid_width {
-                if grid[y][x] {
-                    fill_cell(frame, pixel_buffer_width, GridCoords { x, y }, color_gen());
-                }
+fn main() -> Result<(), Error> {
+    env_logger::init();
+    // The run subcommand needs to know which flags were given, so that they
+    // override the settings saved from the last run.
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    match cli.command {
+        Command::Run(args) => run::run(*args, matches.subcommand_matches("run").expect("the subcommand is run")),
+        Command::Sim(args) => sim::run(*args),
+        Command::Terminal(args) => terminal::run(*args),
+        Command::Search(args) => search::run(*args),
+        Command::Bench(args) => bench::run(args),
+        Command::Verify(args) => {
+            if !verify::run(args)? {
+                std::process::exit(1);
             }
-        }
-
-        pixels.render().unwrap();
-
-        // Tick the game. Update the state of the grid based on the rules of Conway's
-        // Game of Life.
-        let mut alive_count = 0;
-        let mut next_grid = vec![vec![false; grid_width]; grid_height];
-        for y in 0..grid_height {
-            for x in 0..grid_width {
-                let alive = grid[y][x];
-                let alive_neighbors = alive_neighbors(&grid, x as i32, y as i32);
-
-                // Increment the alive count so we don't exit the game prematurely.
-                if alive {
-                    alive_count += 1;
-                }
-
-                match (alive, alive_neighbors) {
-                    (true, 2..=3) | (false, 3) => next_grid[y][x] = true,
-                    _ => {},
-                };
+            Ok(())
+        },
+        Command::Rules => {
+            list_rules();
+            Ok(())
+        },
+        Command::Patterns => {
+            list_patterns();
+            Ok(())
+        },
+        Command::Convert(args) => convert::run(args),
+        Command::Render(args) => image::run(args),
+        Command::Diff(args) => {
+            if !diff::run(args)? {
+                std::process::exit(1);
             }
-        }
-
-        // If there are no cells left alive, there is nothing left to do but exit!
-        if alive_count == 0 {
-            std::process::exit(0);
-        }
-
-        grid = next_grid;
-        // TODO: Don't sleep, use a timer.
-        std::thread::sleep(sleep_duration);
-    });
-
-    event_loop.run(|_, _, _| {});
+            Ok(())
+        },
+        Command::Predecessor(args) => predecessor::run(args),
+        Command::Rpc(args) => rpc::run(*args),
+    }
 }
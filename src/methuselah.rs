@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use cgol2::grid::Grid;
+use cgol2::spaceship::{find_spaceships, Kind, Spaceship};
+use serde::Serialize;
+
+/// The longest period of the ash that a pattern is recognized as having
+/// stabilized into.
+const MAX_PERIOD: usize = 30;
+
+/// Spaceships flying away from the rest of the pattern with at least this
+/// many dead cells between them and every other live cell have escaped, and
+/// the pattern is considered to have stabilized without them.
+const ESCAPE_CLEARANCE: usize = 4;
+
+/// Escaped spaceships are removed from the universe once they are this far
+/// clear of the rest, or reach an edge, so that they can't crash into
+/// anything there or come back around.
+const REMOVAL_CLEARANCE: usize = 16;
+
+/// How long a pattern took to stabilize, and what it left behind.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct Metrics {
+    /// The generation the pattern first took the form of the still life or
+    /// oscillator it settled into, if it has settled.
+    pub lifespan: Option<u64>,
+    /// The period of what the pattern settled into.
+    pub period: Option<usize>,
+    /// The number of live cells, not counting the escaped spaceships.
+    pub population: usize,
+    /// The number of gliders that escaped.
+    pub gliders: usize,
+    /// The number of other spaceships that escaped.
+    pub spaceships: usize,
+}
+
+impl fmt::Display for Metrics {
+    /// Formats the metrics like `stable at generation 1103 with 86 cells,
+    /// 6 gliders escaped`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.lifespan {
+            Some(lifespan) => write!(f, "stable at generation {lifespan} with {} cells", self.population)?,
+            None => write!(f, "not stable yet with {} cells", self.population)?,
+        }
+        write!(f, ", {} gliders escaped", self.gliders)?;
+        if self.spaceships > 0 {
+            write!(f, ", {} other spaceships", self.spaceships)?;
+        }
+        Ok(())
+    }
+}
+
+/// Times how long a pattern, such as a methuselah like the R-pentomino, takes
+/// to stabilize, counting and removing the spaceships that escape from it.
+/// Spaceships are only recognized in Conway's Game of Life.
+#[derive(Default)]
+pub struct Methuselah {
+    /// Digests of the most recent generations without their escaped
+    /// spaceships.
+    recent: VecDeque<u64>,
+    /// The gliders and other spaceships removed from the universe so far.
+    removed: (usize, usize),
+    metrics: Metrics,
+}
+
+impl Methuselah {
+    /// Returns the metrics of the pattern so far, which stop changing once it
+    /// has stabilized.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Returns whether the pattern has settled into a still life or an
+    /// oscillator, apart from the spaceships that escaped.
+    pub fn is_stable(&self) -> bool {
+        self.metrics.lifespan.is_some()
+    }
+
+    /// Forget the recent generations, whose digests can't match those of a
+    /// grid that has changed size.
+    pub fn forget(&mut self) {
+        self.recent.clear();
+    }
+
+    /// Record a generation, removing the escaped spaceships that are well
+    /// clear of the rest or have reached an edge. `life` is whether the grid
+    /// evolves according to Conway's Game of Life, so that its spaceships can
+    /// be recognized.
+    pub fn observe(&mut self, grid: &mut Grid, generation: u64, life: bool) {
+        if self.is_stable() {
+            return;
+        }
+        let escaped = if life { escaped_spaceships(grid) } else { Vec::new() };
+        let (mut gliders, mut spaceships) = self.removed;
+        let mut rest = (!escaped.is_empty()).then(|| grid.clone());
+        for spaceship in &escaped {
+            let count = if spaceship.kind == Kind::Glider { &mut gliders } else { &mut spaceships };
+            *count += 1;
+            if let Some(rest) = &mut rest {
+                erase(rest, spaceship);
+            }
+            if clear(grid, spaceship, REMOVAL_CLEARANCE) || at_edge(grid, spaceship) {
+                log::debug!("Removing an escaped {} at generation {generation}", spaceship.kind);
+                erase(grid, spaceship);
+                let removed = if spaceship.kind == Kind::Glider { &mut self.removed.0 } else { &mut self.removed.1 };
+                *removed += 1;
+            }
+        }
+        let rest = rest.as_ref().unwrap_or(&*grid);
+
+        if self.recent.len() > MAX_PERIOD {
+            self.recent.pop_front();
+        }
+        let digest = rest.digest();
+        let period = self.recent.iter().rev().position(|&recent| recent == digest).map(|steps| steps + 1);
+        self.recent.push_back(digest);
+        // Patterns that die out are stable from the generation they do, and
+        // the run doesn't last long enough to see the empty grid repeat.
+        let lifespan = match period {
+            Some(period) => Some(generation.saturating_sub(period as u64)),
+            None => (rest.population() == 0).then_some(generation),
+        };
+        self.metrics = Metrics {
+            lifespan,
+            period: lifespan.map(|_| period.unwrap_or(1)),
+            population: rest.population(),
+            gliders,
+            spaceships,
+        };
+    }
+}
+
+/// Returns the spaceships that are flying away from every other live cell,
+/// with enough dead cells between them to have escaped.
+fn escaped_spaceships(grid: &Grid) -> Vec<Spaceship> {
+    let spaceships: Vec<Spaceship> =
+        find_spaceships(grid).into_iter().filter(|spaceship| clear(grid, spaceship, ESCAPE_CLEARANCE)).collect();
+    let Some(centroid) = grid.centroid().filter(|_| !spaceships.is_empty()) else { return Vec::new() };
+    let population = grid.population() as f64;
+    spaceships
+        .into_iter()
+        .filter(|spaceship| {
+            let cells = cells(grid, spaceship);
+            let others = population - cells.len() as f64;
+            if others == 0.0 {
+                return true;
+            }
+            let center = |coord: fn(&(usize, usize)) -> usize| {
+                cells.iter().map(coord).sum::<usize>() as f64 / cells.len() as f64
+            };
+            let (x, y) = (center(|&(x, _)| x), center(|&(_, y)| y));
+            // The centroid of the other cells, from that of every cell.
+            let rest_x = (centroid.0 * population - x * cells.len() as f64) / others;
+            let rest_y = (centroid.1 * population - y * cells.len() as f64) / others;
+            let (dx, dy) = spaceship.velocity;
+            (x - rest_x) * dx as f64 + (y - rest_y) * dy as f64 > 0.0
+        })
+        .collect()
+}
+
+/// Returns the live cells of a spaceship.
+fn cells(grid: &Grid, spaceship: &Spaceship) -> Vec<(usize, usize)> {
+    (spaceship.y..spaceship.y + spaceship.height)
+        .flat_map(|y| (spaceship.x..spaceship.x + spaceship.width).map(move |x| (x, y)))
+        .filter(|&(x, y)| grid.get(x, y))
+        .collect()
+}
+
+/// Returns whether there are no live cells within the given distance of a
+/// spaceship's bounding box, other than its own.
+fn clear(grid: &Grid, spaceship: &Spaceship, distance: usize) -> bool {
+    let columns = spaceship.x.saturating_sub(distance)..(spaceship.x + spaceship.width + distance).min(grid.width());
+    let rows = spaceship.y.saturating_sub(distance)..(spaceship.y + spaceship.height + distance).min(grid.height());
+    let own = |x: usize, y: usize| {
+        (spaceship.x..spaceship.x + spaceship.width).contains(&x)
+            && (spaceship.y..spaceship.y + spaceship.height).contains(&y)
+    };
+    rows.flat_map(|y| columns.clone().map(move |x| (x, y))).all(|(x, y)| own(x, y) || !grid.get(x, y))
+}
+
+/// Returns whether a spaceship's bounding box touches an edge of the grid.
+fn at_edge(grid: &Grid, spaceship: &Spaceship) -> bool {
+    spaceship.x == 0
+        || spaceship.y == 0
+        || spaceship.x + spaceship.width >= grid.width()
+        || spaceship.y + spaceship.height >= grid.height()
+}
+
+/// Kill the cells of a spaceship.
+fn erase(grid: &mut Grid, spaceship: &Spaceship) {
+    for (x, y) in cells(grid, spaceship) {
+        grid.set(x, y, false);
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+
+/// How long to wait on a client that isn't sending its request or reading the
+/// response, so that it can't hold up the clients after it.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Values describing the running simulation, shared with the metrics server.
+///
+/// Floating point values are stored as their bit patterns so that they can be
+/// updated atomically.
+#[derive(Default)]
+pub struct Metrics {
+    generation: AtomicU64,
+    population: AtomicU64,
+    tick_seconds: AtomicU64,
+    frames: AtomicU64,
+    fps: AtomicU64,
+}
+
+impl Metrics {
+    /// Record the outcome of a tick.
+    pub fn record_tick(&self, generation: u64, population: usize, duration: Duration) {
+        self.generation.store(generation, Ordering::Relaxed);
+        self.population.store(population as u64, Ordering::Relaxed);
+        self.tick_seconds.store(duration.as_secs_f64().to_bits(), Ordering::Relaxed);
+    }
+
+    /// Record that a frame was rendered.
+    pub fn record_frame(&self) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the most recently measured frame rate.
+    pub fn record_fps(&self, fps: f64) {
+        self.fps.store(fps.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Format the metrics in the Prometheus text exposition format.
+    fn render(&self) -> String {
+        let load_f64 = |value: &AtomicU64| f64::from_bits(value.load(Ordering::Relaxed));
+        let metrics = [
+            (
+                "cgol_generation",
+                "gauge",
+                "Generation of the universe, which starts over when it is reseeded.",
+                self.generation.load(Ordering::Relaxed) as f64,
+            ),
+            ("cgol_population", "gauge", "Number of live cells.", self.population.load(Ordering::Relaxed) as f64),
+            ("cgol_tick_seconds", "gauge", "Time taken to compute the last generation.", load_f64(&self.tick_seconds)),
+            ("cgol_frames_total", "counter", "Number of frames rendered.", self.frames.load(Ordering::Relaxed) as f64),
+            ("cgol_fps", "gauge", "Frames rendered per second.", load_f64(&self.fps)),
+        ];
+
+        let mut body = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(body, "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}");
+        }
+        body
+    }
+}
+
+/// Measures the frame rate by counting frames over one second windows.
+pub struct FrameCounter {
+    window_start: Instant,
+    frames: u32,
+}
+
+impl FrameCounter {
+    pub fn new() -> Self {
+        Self { window_start: Instant::now(), frames: 0 }
+    }
+
+    /// Count a frame, returning the frame rate if a measurement window just
+    /// ended.
+    pub fn frame(&mut self) -> Option<f64> {
+        self.frames += 1;
+        let elapsed = self.window_start.elapsed();
+        if elapsed < Duration::from_secs(1) {
+            return None;
+        }
+        let fps = self.frames as f64 / elapsed.as_secs_f64();
+        *self = Self::new();
+        Some(fps)
+    }
+}
+
+/// Start an HTTP server on the given address that serves the metrics at
+/// `/metrics`.
+pub fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr).map_err(Error::MetricsServer)?;
+    log::info!("Serving metrics at http://{addr}/metrics");
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| respond(stream, &metrics));
+            if let Err(err) = result {
+                log::debug!("Failed to serve metrics request: {err}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Answer a single HTTP request.
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Skip the headers, which don't matter for this.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header != "\r\n" && header != "\n" {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: \
+         close\r\n\r\n{body}",
+        body.len(),
+    )
+}
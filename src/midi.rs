@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+use std::path::Path;
+
+use cgol2::grid::Grid;
+use midir::{MidiOutput, MidiOutputConnection};
+use serde::Deserialize;
+
+use crate::error::Error;
+
+/// The name the program's MIDI client and connection are shown with.
+const CLIENT_NAME: &str = env!("CARGO_BIN_NAME");
+
+/// The status bytes of note messages, to be combined with a channel.
+const NOTE_ON: u8 = 0x90;
+const NOTE_OFF: u8 = 0x80;
+
+/// The highest note number and velocity in MIDI.
+const MAX_DATA: u8 = 127;
+
+/// A scale that the columns of the universe are tuned to.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Scale {
+    Chromatic,
+    Major,
+    Minor,
+    Pentatonic,
+}
+
+impl Scale {
+    /// Returns the notes in each octave of the scale, as semitones above its
+    /// root.
+    fn degrees(self) -> &'static [u8] {
+        match self {
+            Self::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Self::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Self::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Self::Pentatonic => &[0, 2, 4, 7, 9],
+        }
+    }
+}
+
+/// How generations of the first board are turned into notes, read from the
+/// TOML file given with `--midi-map`. Every setting is optional, e.g.
+///
+/// ```toml
+/// channel = 1
+/// low_note = 36
+/// high_note = 84
+/// scale = "pentatonic"
+/// max_notes = 4
+/// min_velocity = 40
+/// max_velocity = 120
+/// full_density = 0.25
+/// ```
+///
+/// The columns of the universe are split evenly between the notes of the
+/// scale from `low_note` to `high_note`, left to right, and each generation
+/// plays the notes of the columns where the most cells were born. Notes are
+/// held until the next generation, and get louder as the population grows.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mapping {
+    /// The channel to play on, from 1 to 16.
+    pub channel: u8,
+    /// The lowest note, which is also the root of the scale.
+    pub low_note: u8,
+    pub high_note: u8,
+    pub scale: Scale,
+    /// The most notes to play at once.
+    pub max_notes: usize,
+    /// The velocity of notes when almost every cell is dead.
+    pub min_velocity: u8,
+    /// The velocity of notes once `full_density` of the cells are alive.
+    pub max_velocity: u8,
+    pub full_density: f64,
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Self {
+            channel: 1,
+            low_note: 36,
+            high_note: 84,
+            scale: Scale::Pentatonic,
+            max_notes: 4,
+            min_velocity: 40,
+            max_velocity: 120,
+            full_density: 0.25,
+        }
+    }
+}
+
+impl Mapping {
+    /// Load a mapping file, checking that its settings are within the ranges
+    /// MIDI allows.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err })?;
+        let mapping: Self =
+            toml::from_str(&text).map_err(|err| Error::MidiMap { path: path.to_path_buf(), source: err })?;
+        let invalid = |message: &str| Err(Error::Midi(format!("invalid MIDI map {}: {message}", path.display())));
+        if !(1..=16).contains(&mapping.channel) {
+            return invalid("`channel` must be from 1 to 16");
+        }
+        if mapping.low_note > mapping.high_note || mapping.high_note > MAX_DATA {
+            return invalid("`low_note` and `high_note` must be in order and at most 127");
+        }
+        if mapping.min_velocity > mapping.max_velocity || mapping.max_velocity > MAX_DATA {
+            return invalid("`min_velocity` and `max_velocity` must be in order and at most 127");
+        }
+        if mapping.full_density <= 0.0 {
+            return invalid("`full_density` must be positive");
+        }
+        Ok(mapping)
+    }
+
+    /// Returns the notes of the scale from the lowest note to the highest.
+    fn notes(&self) -> Vec<u8> {
+        let degrees = self.scale.degrees();
+        (self.low_note..=self.high_note).filter(|note| degrees.contains(&((note - self.low_note) % 12))).collect()
+    }
+}
+
+/// Plays the births of each generation as notes on a MIDI output port, so that
+/// the universe can drive a synthesizer as a generative sequencer.
+pub struct Midi {
+    connection: MidiOutputConnection,
+    mapping: Mapping,
+    /// The note of each band of columns, from left to right.
+    notes: Vec<u8>,
+    /// The last generation that was played, which births are counted from.
+    previous: Option<Grid>,
+    /// The notes that are still sounding.
+    playing: Vec<u8>,
+}
+
+impl Midi {
+    /// Connect to the first output port whose name contains the given text,
+    /// ignoring case.
+    pub fn connect(port: &str, mapping: Mapping) -> Result<Self, Error> {
+        let output = MidiOutput::new(CLIENT_NAME).map_err(|err| Error::Midi(err.to_string()))?;
+        let ports = output.ports();
+        let names: Vec<String> = ports.iter().map(|port| output.port_name(port).unwrap_or_default()).collect();
+        let Some(index) = names.iter().position(|name| name.to_lowercase().contains(&port.to_lowercase())) else {
+            let available = if names.is_empty() { "none".to_string() } else { names.join(", ") };
+            return Err(Error::Midi(format!("no MIDI output port matches `{port}`; the ports are {available}")));
+        };
+        log::info!("Sending MIDI to {}", names[index]);
+        let connection = output.connect(&ports[index], CLIENT_NAME).map_err(|err| Error::Midi(err.to_string()))?;
+        let notes = mapping.notes();
+        Ok(Self { connection, mapping, notes, previous: None, playing: Vec::new() })
+    }
+
+    /// End the notes of the last generation played, and play the columns
+    /// where the most cells were born since then. The first generation, and
+    /// the first one after the universe changes size, only sets the grid that
+    /// births are counted from.
+    pub fn play(&mut self, grid: &Grid) -> Result<(), Error> {
+        self.silence()?;
+        let previous = self.previous.replace(grid.clone());
+        let size = |grid: &Grid| (grid.width(), grid.height());
+        let Some(previous) = previous.filter(|previous| size(previous) == size(grid)) else {
+            return Ok(());
+        };
+
+        let mut births = vec![0; self.notes.len()];
+        for y in 0..grid.height() {
+            for x in (0..grid.width()).filter(|&x| grid.get(x, y) && !previous.get(x, y)) {
+                births[x * self.notes.len() / grid.width()] += 1;
+            }
+        }
+        let density = grid.population() as f64 / (grid.width() * grid.height()) as f64;
+        let loudness = (density / self.mapping.full_density).min(1.0);
+        let (min, max) = (self.mapping.min_velocity, self.mapping.max_velocity);
+        let velocity = (min as f64 + (max - min) as f64 * loudness).round() as u8;
+
+        let mut bands: Vec<usize> = (0..births.len()).filter(|&band| births[band] > 0).collect();
+        bands.sort_by_key(|&band| Reverse(births[band]));
+        bands.truncate(self.mapping.max_notes);
+        for band in bands {
+            let note = self.notes[band];
+            self.send(NOTE_ON, note, velocity.max(1))?;
+            self.playing.push(note);
+        }
+        Ok(())
+    }
+
+    /// End every note that is still sounding.
+    pub fn silence(&mut self) -> Result<(), Error> {
+        for note in std::mem::take(&mut self.playing) {
+            self.send(NOTE_OFF, note, 0)?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, status: u8, note: u8, velocity: u8) -> Result<(), Error> {
+        let status = status | (self.mapping.channel - 1);
+        self.connection.send(&[status, note, velocity]).map_err(|err| Error::Midi(err.to_string()))
+    }
+}
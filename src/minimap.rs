@@ -0,0 +1,75 @@
+use cgol2::grid::Grid;
+
+use crate::render::{Canvas, PixelCoords, PixelRect};
+use crate::theme::Theme;
+
+/// The largest fraction of a board's region of the window the minimap covers
+/// along each dimension.
+const MAX_FRACTION: usize = 4;
+/// The gap between the minimap and the edges of the board's region, in pixels.
+const MARGIN: usize = 4;
+
+/// A small overview of a whole universe, drawn in the bottom-right corner of
+/// its board's region of the window.
+pub struct Minimap {
+    /// Where the minimap is drawn, in physical pixels.
+    pub rect: PixelRect,
+    /// The number of cells along each dimension that share a pixel.
+    scale: usize,
+}
+
+impl Minimap {
+    /// Lay out the minimap of a universe of the given size within a board's
+    /// region of the window.
+    pub fn new(tile: PixelRect, universe: (usize, usize)) -> Self {
+        let max_width = (tile.w / MAX_FRACTION).max(1);
+        let max_height = (tile.h / MAX_FRACTION).max(1);
+        let scale = universe.0.div_ceil(max_width).max(universe.1.div_ceil(max_height)).max(1);
+        let (w, h) = (universe.0.div_ceil(scale), universe.1.div_ceil(scale));
+        let x = (tile.x + tile.w).saturating_sub(w + MARGIN).max(tile.x);
+        let y = (tile.y + tile.h).saturating_sub(h + MARGIN).max(tile.y);
+        Self { rect: PixelRect { x, y, w, h }, scale }
+    }
+
+    /// Draw the universe, with a rectangle around the cells in the view,
+    /// given as the top-left visible cell and the number of visible cells.
+    pub fn draw(&self, canvas: &mut Canvas, grid: &Grid, view: ((isize, isize), (usize, usize)), theme: &Theme) {
+        let (x, y) = (self.rect.x.saturating_sub(1), self.rect.y.saturating_sub(1));
+        let border = PixelRect { x, y, w: self.rect.w + 2, h: self.rect.h + 2 };
+        canvas.stroke_rect(border, theme.divider);
+        canvas.fill_rect(self.rect, theme.background);
+
+        // Each pixel shows whether any of the cells it covers is alive.
+        for my in 0..self.rect.h {
+            for mx in 0..self.rect.w {
+                let (x0, y0) = (mx * self.scale, my * self.scale);
+                let alive = (y0..(y0 + self.scale).min(grid.height()))
+                    .any(|y| (x0..(x0 + self.scale).min(grid.width())).any(|x| grid.get(x, y)));
+                if alive {
+                    let coords = PixelCoords { x: self.rect.x + mx, y: self.rect.y + my };
+                    canvas.fill_rect(PixelRect { x: coords.x, y: coords.y, w: 1, h: 1 }, theme.alive);
+                }
+            }
+        }
+
+        let ((x, y), (columns, rows)) = view;
+        let to_pixel = |cell: isize, origin: usize| origin as isize + cell.div_euclid(self.scale as isize);
+        let left = to_pixel(x, self.rect.x).max(self.rect.x as isize) as usize;
+        let top = to_pixel(y, self.rect.y).max(self.rect.y as isize) as usize;
+        let right = (to_pixel(x + columns as isize, self.rect.x) as usize).min(self.rect.x + self.rect.w);
+        let bottom = (to_pixel(y + rows as isize, self.rect.y) as usize).min(self.rect.y + self.rect.h);
+        let viewport = PixelRect { x: left, y: top, w: right.saturating_sub(left), h: bottom.saturating_sub(top) };
+        canvas.stroke_rect(viewport, theme.viewport);
+    }
+
+    /// Returns the universe coordinates of the cell shown at the given pixel,
+    /// if it is on the minimap.
+    pub fn cell_at(&self, coords: PixelCoords) -> Option<(isize, isize)> {
+        if !self.rect.contains(coords) {
+            return None;
+        }
+        let x = (coords.x - self.rect.x) * self.scale + self.scale / 2;
+        let y = (coords.y - self.rect.y) * self.scale + self.scale / 2;
+        Some((x as isize, y as isize))
+    }
+}
@@ -0,0 +1,43 @@
+use crate::physical_cell_size;
+use crate::render::{self, Canvas, PixelCoords, PixelRect};
+use crate::theme::Theme;
+
+/// The logical size of each pixel of the font that overlays are drawn in.
+const TEXT_SCALE: usize = 2;
+/// The padding around the text of a panel, in logical pixels.
+const PADDING: usize = 4;
+
+/// Draws panels of text over the boards, sized for the window's display.
+pub struct Overlay {
+    /// The physical size of each pixel of the font.
+    text_scale: usize,
+    /// The physical padding around the text of a panel.
+    pub padding: usize,
+}
+
+impl Overlay {
+    pub fn new(scale_factor: f64) -> Self {
+        Self {
+            text_scale: physical_cell_size(TEXT_SCALE, scale_factor),
+            padding: physical_cell_size(PADDING, scale_factor),
+        }
+    }
+
+    /// Returns the width and height of the panel that the given text is drawn
+    /// on, in physical pixels.
+    pub fn size(&self, text: &str) -> (usize, usize) {
+        let (width, height) = render::text_size(text, self.text_scale);
+        (width + 2 * self.padding, height + 2 * self.padding)
+    }
+
+    /// Draw text on a bordered panel with its top-left corner at the given
+    /// pixel.
+    pub fn draw(&self, canvas: &mut Canvas, origin: PixelCoords, text: &str, theme: &Theme) {
+        let (w, h) = self.size(text);
+        let panel = PixelRect { x: origin.x, y: origin.y, w, h };
+        canvas.fill_rect(panel, theme.hud_background);
+        canvas.stroke_rect(panel, theme.divider);
+        let text_origin = PixelCoords { x: origin.x + self.padding, y: origin.y + self.padding };
+        canvas.draw_text(text_origin, text, self.text_scale, theme.hud_text);
+    }
+}
@@ -0,0 +1,236 @@
+use cgol2::rule::{LifeLike, Registry};
+use clap::ValueEnum;
+use egui::{ClippedPrimitive, Context, TexturesDelta};
+use egui_wgpu::renderer::{Renderer, ScreenDescriptor};
+use pixels::{wgpu, Pixels, PixelsContext};
+use winit::window::Window;
+
+use crate::{ColorMode, MAX_STEPS_PER_FRAME};
+
+/// The width of the panel, in logical pixels.
+const PANEL_WIDTH: f32 = 220.0;
+
+/// The settings that the panel can change, which the app updates from it
+/// after every frame.
+pub struct Controls {
+    pub frames_per_second: u32,
+    pub steps_per_frame: u64,
+    /// Whether the number of generations for each frame is chosen by
+    /// `--target-fps`, so that it can't be changed from the panel.
+    pub adaptive: bool,
+    /// The probability that each cell starts alive when boards are reseeded.
+    pub chance: f64,
+    pub color_mode: ColorMode,
+    pub paused: bool,
+    /// The rule of the first board, or its name if it isn't one that a
+    /// rulestring can describe.
+    pub rulestring: String,
+    /// The rule of the first board, if it only depends on how many neighbors
+    /// are alive, so that it can be edited condition by condition.
+    pub life_like: Option<LifeLike>,
+}
+
+/// Something to do that was requested with one of the panel's buttons.
+pub enum Action {
+    TogglePause,
+    Step,
+    Reseed,
+    ChangeRule(String),
+    CopyRule(String),
+    LoadPattern(String),
+}
+
+/// A side panel drawn with egui over the window, for changing the settings
+/// of the simulation without the keyboard.
+pub struct Panel {
+    context: Context,
+    state: egui_winit::State,
+    screen: ScreenDescriptor,
+    renderer: Renderer,
+    paint_jobs: Vec<ClippedPrimitive>,
+    textures: TexturesDelta,
+    /// The rule typed into the rule field, which may be a registered name or a
+    /// rulestring.
+    rule: String,
+    /// The path of a pattern file typed into the pattern field.
+    pattern: String,
+}
+
+impl Panel {
+    pub fn new(window: &Window, pixels: &Pixels, rule: String) -> Self {
+        let size = window.inner_size();
+        let scale_factor = window.scale_factor() as f32;
+        let mut state = egui_winit::State::new(window);
+        state.set_max_texture_side(pixels.device().limits().max_texture_dimension_2d as usize);
+        state.set_pixels_per_point(scale_factor);
+        Self {
+            context: Context::default(),
+            state,
+            screen: ScreenDescriptor { size_in_pixels: [size.width, size.height], pixels_per_point: scale_factor },
+            renderer: Renderer::new(pixels.device(), pixels.render_texture_format(), None, 1),
+            paint_jobs: Vec::new(),
+            textures: TexturesDelta::default(),
+            rule,
+            pattern: String::new(),
+        }
+    }
+
+    /// Pass an event to the panel, returning whether it was meant for the
+    /// panel rather than the boards beneath it, and whether the panel needs
+    /// to be redrawn.
+    pub fn handle_event(&mut self, event: &winit::event::WindowEvent<'_>) -> (bool, bool) {
+        let response = self.state.on_event(&self.context, event);
+        (response.consumed, response.repaint)
+    }
+
+    /// Resize the panel's surface to the size of the window, in physical
+    /// pixels.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.screen.size_in_pixels = [width, height];
+        }
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.screen.pixels_per_point = scale_factor as f32;
+    }
+
+    /// Lay out the panel for the next frame, updating the controls from it and
+    /// returning the actions requested with its buttons.
+    pub fn prepare(&mut self, window: &Window, controls: &mut Controls) -> Vec<Action> {
+        let mut actions = Vec::new();
+        let input = self.state.take_egui_input(window);
+        let output = self.context.clone().run(input, |context| self.ui(context, controls, &mut actions));
+        self.textures.append(output.textures_delta);
+        self.state.handle_platform_output(window, &self.context, output.platform_output);
+        self.paint_jobs = self.context.tessellate(output.shapes);
+        actions
+    }
+
+    fn ui(&mut self, context: &Context, controls: &mut Controls, actions: &mut Vec<Action>) {
+        egui::SidePanel::left("controls").resizable(false).default_width(PANEL_WIDTH).show(context, |ui| {
+            ui.heading("Simulation");
+            ui.horizontal(|ui| {
+                if ui.button(if controls.paused { "Resume" } else { "Pause" }).clicked() {
+                    actions.push(Action::TogglePause);
+                }
+                if ui.button("Step").clicked() {
+                    actions.push(Action::Step);
+                }
+                if ui.button("Reseed").clicked() {
+                    actions.push(Action::Reseed);
+                }
+            });
+            ui.add(egui::Slider::new(&mut controls.frames_per_second, 1..=60).text("frames per second"));
+            ui.add_enabled(
+                !controls.adaptive,
+                egui::Slider::new(&mut controls.steps_per_frame, 1..=MAX_STEPS_PER_FRAME)
+                    .logarithmic(true)
+                    .text("generations per frame"),
+            );
+            ui.add(egui::Slider::new(&mut controls.chance, 0.0..=1.0).text("density on reseed"));
+
+            ui.separator();
+            ui.heading("Rule");
+            egui::ComboBox::from_id_source("rule").selected_text(self.rule.as_str()).show_ui(ui, |ui| {
+                for registration in Registry::with_builtins().iter() {
+                    if ui.selectable_label(self.rule == registration.name, &registration.name).clicked() {
+                        self.rule = registration.name.clone();
+                        actions.push(Action::ChangeRule(self.rule.clone()));
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.rule).desired_width(PANEL_WIDTH / 2.0));
+                if ui.button("Apply").clicked() {
+                    actions.push(Action::ChangeRule(self.rule.clone()));
+                }
+            });
+            self.rule_editor(ui, controls, actions);
+
+            ui.separator();
+            ui.heading("Colors");
+            egui::ComboBox::from_id_source("color mode").selected_text(controls.color_mode.to_string()).show_ui(
+                ui,
+                |ui| {
+                    for color_mode in ColorMode::value_variants() {
+                        let selected = controls.color_mode == *color_mode;
+                        if ui.selectable_label(selected, color_mode.to_string()).clicked() {
+                            controls.color_mode = color_mode.clone();
+                        }
+                    }
+                },
+            );
+
+            ui.separator();
+            ui.heading("Pattern");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.pattern).hint_text("path").desired_width(PANEL_WIDTH / 2.0),
+                );
+                if ui.button("Load").clicked() {
+                    actions.push(Action::LoadPattern(self.pattern.clone()));
+                }
+            });
+        });
+    }
+
+    /// Lay out a checkbox for each birth and survival condition of the
+    /// current rule, which change the rule as soon as they are toggled, and
+    /// its rulestring with a button to copy it.
+    fn rule_editor(&mut self, ui: &mut egui::Ui, controls: &Controls, actions: &mut Vec<Action>) {
+        let Some(rule) = controls.life_like else {
+            ui.label(format!("{} can't be edited by neighbor counts", controls.rulestring));
+            return;
+        };
+        let (mut birth, mut survival) = rule.conditions();
+        let mut changed = false;
+        egui::Grid::new("rule conditions").spacing([2.0, 2.0]).show(ui, |ui| {
+            ui.label("");
+            for count in 0..9 {
+                ui.label(count.to_string());
+            }
+            ui.end_row();
+            for (name, conditions) in [("born", &mut birth), ("survives", &mut survival)] {
+                ui.label(name);
+                for condition in conditions.iter_mut() {
+                    changed |= ui.checkbox(condition, "").changed();
+                }
+                ui.end_row();
+            }
+        });
+        if changed {
+            self.rule = LifeLike::from_conditions(birth, survival).to_string();
+            actions.push(Action::ChangeRule(self.rule.clone()));
+        }
+        ui.horizontal(|ui| {
+            ui.monospace(&controls.rulestring);
+            if ui.button("Copy").clicked() {
+                actions.push(Action::CopyRule(controls.rulestring.clone()));
+            }
+        });
+    }
+
+    /// Draw the panel over the frame that was just scaled to the window.
+    pub fn render(&mut self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView, context: &PixelsContext) {
+        for (id, delta) in &self.textures.set {
+            self.renderer.update_texture(&context.device, &context.queue, *id, delta);
+        }
+        self.renderer.update_buffers(&context.device, &context.queue, encoder, &self.paint_jobs, &self.screen);
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("panel"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer.render(&mut pass, &self.paint_jobs, &self.screen);
+        }
+        for id in &std::mem::take(&mut self.textures).free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
@@ -0,0 +1,743 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::grid::Grid;
+
+/// The largest number of cells that a parsed pattern's grid may have, so that
+/// sparse patterns spread over a huge area don't exhaust memory.
+const MAX_PATTERN_CELLS: usize = 1 << 28;
+
+/// A file format that patterns can be read from and written to.
+#[derive(ValueEnum, strum::Display, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase")]
+pub enum Format {
+    /// Run-length encoded, usually with the `.rle` extension.
+    Rle,
+    /// Rows of `.` and `O` characters, usually with the `.cells` extension.
+    Plaintext,
+    /// A `#Life 1.06` header followed by the coordinates of each live cell,
+    /// usually with the `.lif` extension.
+    Life106,
+    /// Golly's quadtree format, usually with the `.mc` extension.
+    Macrocell,
+}
+
+impl Format {
+    /// Returns the format that files with the extension of the given path are
+    /// usually in, if it is a known one.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "rle" => Some(Self::Rle),
+            "cells" => Some(Self::Plaintext),
+            "lif" | "life" => Some(Self::Life106),
+            "mc" => Some(Self::Macrocell),
+            _ => None,
+        }
+    }
+
+    /// Returns the format that the text looks like it is in, based on its
+    /// header.
+    pub fn detect(text: &str) -> Self {
+        let first_line = text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+        if first_line.starts_with("#Life 1.06") {
+            Self::Life106
+        } else if first_line.starts_with("[M2]") {
+            Self::Macrocell
+        } else if is_plaintext(text) {
+            Self::Plaintext
+        } else {
+            Self::Rle
+        }
+    }
+}
+
+/// A pattern of cells loaded from one of the common Life file formats.
+#[derive(Clone)]
+pub struct Pattern {
+    /// The name of the pattern, if the file gave one.
+    pub name: Option<String>,
+    /// The rule the pattern was designed for, if the file gave one.
+    pub rule: Option<String>,
+    /// The cells of the pattern, in a grid just large enough to hold them.
+    pub cells: Grid,
+    /// The walls of the pattern, in a grid the size of `cells`, if it has any.
+    /// Walls count as live neighbors but never change, and are written as
+    /// state `B` in RLE and `#` in plaintext.
+    pub walls: Option<Grid>,
+}
+
+impl Pattern {
+    /// Parse a pattern, detecting which format it is in.
+    pub fn parse(text: &str) -> Result<Self, ParsePatternError> {
+        Self::parse_as(text, Format::detect(text))
+    }
+
+    /// Parse a pattern in the given format.
+    pub fn parse_as(text: &str, format: Format) -> Result<Self, ParsePatternError> {
+        match format {
+            Format::Rle => Self::parse_rle(text),
+            Format::Plaintext => Self::parse_plaintext(text),
+            Format::Life106 => Self::parse_life106(text),
+            Format::Macrocell => Self::parse_macrocell(text),
+        }
+    }
+
+    /// Write the pattern in the given format.
+    pub fn write_as(&self, format: Format) -> String {
+        match format {
+            Format::Rle => self.to_rle(),
+            Format::Plaintext => self.to_plaintext(),
+            Format::Life106 => self.to_life106(),
+            Format::Macrocell => self.to_macrocell(),
+        }
+    }
+
+    /// Parse a pattern in run-length encoded (RLE) format, e.g.
+    /// `x = 3, y = 3\nbo$2bo$3o!`. The header line is optional.
+    pub fn parse_rle(text: &str) -> Result<Self, ParsePatternError> {
+        let mut name = None;
+        let mut rule = None;
+        let mut width = 0;
+        let mut height = 0;
+        let mut alive = Vec::new();
+        let mut walls = Vec::new();
+        let (mut x, mut y): (usize, usize) = (0, 0);
+        // The widest any row reaches, so that runs can't spread the pattern
+        // over more cells than a grid may have.
+        let mut reach = 0;
+        let mut count: Option<usize> = None;
+
+        'lines: for line in text.lines().map(str::trim) {
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(comment_name) = comment.strip_prefix('N') {
+                    name = Some(comment_name.trim().to_string());
+                }
+                continue;
+            }
+            if line.starts_with('x') && line.contains('=') {
+                for setting in line.split(',') {
+                    let Some((key, value)) = setting.split_once('=') else { continue };
+                    let value = value.trim();
+                    match key.trim() {
+                        "x" => width = value.parse().map_err(|_| ParsePatternError::new("invalid RLE width"))?,
+                        "y" => height = value.parse().map_err(|_| ParsePatternError::new("invalid RLE height"))?,
+                        "rule" => rule = Some(value.to_string()),
+                        _ => {},
+                    }
+                }
+                continue;
+            }
+
+            for c in line.chars() {
+                match c {
+                    '0'..='9' => {
+                        let digit = c.to_digit(10).expect("character is a digit") as usize;
+                        let next = count.unwrap_or(0).checked_mul(10).and_then(|count| count.checked_add(digit));
+                        count = Some(next.ok_or_else(|| ParsePatternError::new("RLE run count is too large"))?);
+                        continue;
+                    },
+                    'b' | '.' => x = x.saturating_add(count.unwrap_or(1)),
+                    '$' => {
+                        y = y.saturating_add(count.unwrap_or(1));
+                        x = 0;
+                    },
+                    '!' => break 'lines,
+                    'B' => push_run(&mut walls, (&mut x, y), count.unwrap_or(1), &mut reach)?,
+                    // Any other letter is a live cell; multi-state files use
+                    // `A`-`X` and `o` for states other than dead, and state 2
+                    // is taken to be a wall.
+                    c if c.is_ascii_alphabetic() => push_run(&mut alive, (&mut x, y), count.unwrap_or(1), &mut reach)?,
+                    c if c.is_whitespace() => {},
+                    c => return Err(ParsePatternError::new(format!("unexpected character `{c}` in RLE"))),
+                }
+                count = None;
+            }
+        }
+
+        let (cells, walls) = grids_from_runs(width, height, &alive, &walls)?;
+        Ok(Self { name, rule, cells, walls })
+    }
+
+    /// Parse a pattern in plaintext format, where each line is a row of `.`
+    /// (dead), `O` (alive), and `#` (wall) characters, and lines starting with
+    /// `!` are comments.
+    pub fn parse_plaintext(text: &str) -> Result<Self, ParsePatternError> {
+        let mut name = None;
+        let mut alive = Vec::new();
+        let mut walls = Vec::new();
+        let mut y = 0;
+        for line in text.lines() {
+            if let Some(comment) = line.strip_prefix('!') {
+                if let Some(comment_name) = comment.strip_prefix("Name:") {
+                    name = Some(comment_name.trim().to_string());
+                }
+                continue;
+            }
+            for (x, c) in line.trim_end().chars().enumerate() {
+                match c {
+                    '.' => {},
+                    'O' | 'o' | '*' => alive.push((x, y, 1)),
+                    '#' => walls.push((x, y, 1)),
+                    c => return Err(ParsePatternError::new(format!("unexpected character `{c}` in plaintext"))),
+                }
+            }
+            y += 1;
+        }
+        let (cells, walls) = grids_from_runs(0, 0, &alive, &walls)?;
+        Ok(Self { name, rule: None, cells, walls })
+    }
+
+    /// Parse a pattern in Life 1.06 format, where each line after the
+    /// `#Life 1.06` header holds the coordinates of a live cell, e.g. `-1 4`.
+    pub fn parse_life106(text: &str) -> Result<Self, ParsePatternError> {
+        let mut alive = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let coordinate = |part: Option<&str>| {
+                part.and_then(|part| part.parse::<i64>().ok())
+                    .ok_or_else(|| ParsePatternError::new(format!("invalid Life 1.06 cell `{line}`")))
+            };
+            let mut parts = line.split_whitespace();
+            alive.push((coordinate(parts.next())?, coordinate(parts.next())?));
+        }
+        Ok(Self { name: None, rule: None, cells: grid_from_offset_cells(&alive)?, walls: None })
+    }
+
+    /// Parse a pattern in Golly's macrocell format, which describes the
+    /// universe as a quadtree. Each line after the `[M2]` header is a node:
+    /// either an 8x8 leaf drawn with `.`, `*`, and `$`, or a level followed by
+    /// the line numbers of its four children, where 0 is an empty child.
+    pub fn parse_macrocell(text: &str) -> Result<Self, ParsePatternError> {
+        let mut rule = None;
+        let mut nodes = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if line.starts_with('[') {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(rulestring) = comment.strip_prefix('R') {
+                    rule = Some(rulestring.trim().to_string());
+                }
+                continue;
+            }
+            nodes.push(parse_macrocell_node(line, &nodes)?);
+        }
+        let Some(root) = nodes.len().checked_sub(1) else {
+            return Err(ParsePatternError::new("macrocell file has no nodes"));
+        };
+
+        // The live cells are drawn straight into a grid of their bounds, so
+        // that huge quadtrees built from a few repeated nodes are rejected
+        // before they are expanded.
+        let bounds = macrocell_bounds(&nodes);
+        let Some([min_x, min_y, max_x, max_y]) = bounds[root] else {
+            return Ok(Self { name: None, rule, cells: Grid::new(0, 0), walls: None });
+        };
+        let span = |min: u64, max: u64| usize::try_from(max - min).ok().and_then(|span| span.checked_add(1));
+        let (Some(width), Some(height)) = (span(min_x, max_x), span(min_y, max_y)) else {
+            return Err(ParsePatternError::new("the pattern's cells are too far apart"));
+        };
+        check_size(width, height)?;
+        let mut cells = Grid::new(width, height);
+        draw_macrocell(&nodes, &bounds, root + 1, (0, 0), (min_x, min_y), &mut cells);
+        Ok(Self { name: None, rule, cells, walls: None })
+    }
+
+    /// Returns whether the cell at the given coordinates is a wall.
+    pub fn is_wall(&self, x: usize, y: usize) -> bool {
+        self.walls.as_ref().is_some_and(|walls| walls.get(x, y))
+    }
+
+    /// Write the pattern in plaintext format, with its name as a comment.
+    pub fn to_plaintext(&self) -> String {
+        let mut plaintext = String::new();
+        if let Some(name) = &self.name {
+            let _ = writeln!(plaintext, "!Name: {name}");
+        }
+        for y in 0..self.cells.height() {
+            let row: String = (0..self.cells.width())
+                .map(|x| match (self.cells.get(x, y), self.is_wall(x, y)) {
+                    (true, _) => 'O',
+                    (false, true) => '#',
+                    (false, false) => '.',
+                })
+                .collect();
+            let _ = writeln!(plaintext, "{}", row.trim_end_matches('.'));
+        }
+        plaintext
+    }
+
+    /// Write the pattern in Life 1.06 format, with the top-left corner of the
+    /// pattern at the origin. Walls can't be written in Life 1.06.
+    pub fn to_life106(&self) -> String {
+        let mut life106 = "#Life 1.06\n".to_string();
+        for y in 0..self.cells.height() {
+            for x in (0..self.cells.width()).filter(|&x| self.cells.get(x, y)) {
+                let _ = writeln!(life106, "{x} {y}");
+            }
+        }
+        life106
+    }
+
+    /// Write the pattern in Golly's macrocell format. Identical nodes are only
+    /// written once. Walls can't be written in macrocell format.
+    pub fn to_macrocell(&self) -> String {
+        let mut macrocell = "[M2] (cgol2)\n".to_string();
+        if let Some(rule) = &self.rule {
+            let _ = writeln!(macrocell, "#R {rule}");
+        }
+        // The smallest nodes are 8x8 leaves, at level 3.
+        let size = self.cells.width().max(self.cells.height()).next_power_of_two().max(8);
+        let level = size.trailing_zeros();
+        let mut writer = MacrocellWriter { grid: &self.cells, lines: Vec::new(), ids: HashMap::new() };
+        if writer.node(level, 0, 0) == 0 {
+            // An empty universe still needs a root node.
+            writer.lines.push(format!("{} 0 0 0 0", level.max(4)));
+        }
+        for line in writer.lines {
+            let _ = writeln!(macrocell, "{line}");
+        }
+        macrocell
+    }
+
+    /// Write the pattern in run-length encoded (RLE) format, with a header
+    /// giving its size and rule, and lines wrapped at 70 characters.
+    pub fn to_rle(&self) -> String {
+        const LINE_WIDTH: usize = 70;
+
+        let mut rle = String::new();
+        if let Some(name) = &self.name {
+            let _ = writeln!(rle, "#N {name}");
+        }
+        let _ = write!(rle, "x = {}, y = {}", self.cells.width(), self.cells.height());
+        if let Some(rule) = &self.rule {
+            let _ = write!(rle, ", rule = {rule}");
+        }
+        rle.push('\n');
+
+        // Dead cells at the ends of rows and rows at the end of the pattern are
+        // left out, since they're implied by the size in the header.
+        let run = |count: usize, tag: char| if count == 1 { tag.to_string() } else { format!("{count}{tag}") };
+        let mut items = Vec::new();
+        let mut last_row = None;
+        for y in 0..self.cells.height() {
+            let row: Vec<char> = (0..self.cells.width())
+                .map(|x| match (self.cells.get(x, y), self.is_wall(x, y)) {
+                    (true, _) => 'o',
+                    (false, true) => 'B',
+                    (false, false) => 'b',
+                })
+                .collect();
+            let Some(end) = row.iter().rposition(|&tag| tag != 'b') else { continue };
+            let skipped = last_row.map_or(y, |last_row| y - last_row);
+            if skipped > 0 {
+                items.push(run(skipped, '$'));
+            }
+            for chunk in row[..=end].chunk_by(|a, b| a == b) {
+                items.push(run(chunk.len(), chunk[0]));
+            }
+            last_row = Some(y);
+        }
+        items.push("!".to_string());
+
+        let mut line_length = 0;
+        for item in items {
+            if line_length + item.len() > LINE_WIDTH {
+                rle.push('\n');
+                line_length = 0;
+            }
+            line_length += item.len();
+            rle.push_str(&item);
+        }
+        rle.push('\n');
+        rle
+    }
+
+    /// Returns the pattern rotated a quarter turn clockwise.
+    pub fn rotated_clockwise(&self) -> Self {
+        let height = self.cells.height();
+        self.transformed(height, self.cells.width(), |x, y| (height - 1 - y, x))
+    }
+
+    /// Returns the pattern mirrored so that its left and right sides swap.
+    pub fn flipped_horizontally(&self) -> Self {
+        let width = self.cells.width();
+        self.transformed(width, self.cells.height(), |x, y| (width - 1 - x, y))
+    }
+
+    /// Returns the pattern mirrored so that its top and bottom swap.
+    pub fn flipped_vertically(&self) -> Self {
+        let height = self.cells.height();
+        self.transformed(self.cells.width(), height, |x, y| (x, height - 1 - y))
+    }
+
+    /// Returns a copy of the pattern in a grid of the given size, with each
+    /// cell moved to the coordinates that `map` gives for it.
+    fn transformed(&self, width: usize, height: usize, map: impl Fn(usize, usize) -> (usize, usize)) -> Self {
+        let transform = |from: &Grid| {
+            let mut to = Grid::new(width, height);
+            for y in 0..from.height() {
+                for x in 0..from.width() {
+                    let (to_x, to_y) = map(x, y);
+                    to.set(to_x, to_y, from.get(x, y));
+                }
+            }
+            to
+        };
+        let walls = self.walls.as_ref().map(transform);
+        Self { name: self.name.clone(), rule: self.rule.clone(), cells: transform(&self.cells), walls }
+    }
+}
+
+/// A node of a macrocell quadtree.
+enum MacrocellNode {
+    /// An 8x8 square of cells, as rows where bit `x` is the cell in column `x`.
+    Leaf([u8; 8]),
+    /// A square of `2^level` cells made of four quadrants, given as the line
+    /// numbers of their nodes in the order northwest, northeast, southwest, and
+    /// southeast, where 0 is an empty quadrant.
+    Inner { level: u32, children: [usize; 4] },
+}
+
+impl MacrocellNode {
+    fn level(&self) -> u32 {
+        match self {
+            Self::Leaf(_) => 3,
+            Self::Inner { level, .. } => *level,
+        }
+    }
+}
+
+/// Parse a line of a macrocell file, given the nodes on the lines before it.
+fn parse_macrocell_node(line: &str, nodes: &[MacrocellNode]) -> Result<MacrocellNode, ParsePatternError> {
+    if line.starts_with(['.', '*', '$']) {
+        let mut rows = [0; 8];
+        let (mut x, mut y) = (0, 0);
+        for c in line.chars() {
+            match c {
+                '.' => x += 1,
+                '*' if x < 8 && y < 8 => {
+                    rows[y] |= 1 << x;
+                    x += 1;
+                },
+                '$' => (x, y) = (0, y + 1),
+                _ => return Err(ParsePatternError::new(format!("invalid macrocell leaf `{line}`"))),
+            }
+        }
+        return Ok(MacrocellNode::Leaf(rows));
+    }
+
+    let invalid = || ParsePatternError::new(format!("invalid macrocell node `{line}`"));
+    let numbers: Vec<usize> =
+        line.split_whitespace().map(str::parse).collect::<Result<_, _>>().map_err(|_| invalid())?;
+    let [level, nw, ne, sw, se] = numbers[..] else { return Err(invalid()) };
+    let level = u32::try_from(level).ok().filter(|level| (4..64).contains(level)).ok_or_else(invalid)?;
+    let children = [nw, ne, sw, se];
+    // Children must come before their parent, and be one level smaller.
+    for child in children.into_iter().filter(|&child| child != 0) {
+        if nodes.get(child - 1).is_none_or(|node| node.level() != level - 1) {
+            return Err(invalid());
+        }
+    }
+    Ok(MacrocellNode::Inner { level, children })
+}
+
+/// Returns the bounds of the live cells of each node, as their smallest and
+/// largest columns and rows from its top-left corner, or `None` for nodes with
+/// no live cells. Children come before their parents, so a single pass finds
+/// them all.
+fn macrocell_bounds(nodes: &[MacrocellNode]) -> Vec<Option<[u64; 4]>> {
+    let mut bounds: Vec<Option<[u64; 4]>> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let node_bounds = match node {
+            MacrocellNode::Leaf(rows) => {
+                let occupied = || (0..8).filter(|&y| rows[y] != 0);
+                occupied().next().map(|min_y| {
+                    let columns = rows.iter().fold(0, |columns, row| columns | row);
+                    let max_y = occupied().next_back().unwrap_or(min_y);
+                    [columns.trailing_zeros(), min_y as u32, 7 - columns.leading_zeros(), max_y as u32].map(u64::from)
+                })
+            },
+            MacrocellNode::Inner { level, children } => {
+                let half = 1u64 << (level - 1);
+                let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+                children
+                    .iter()
+                    .zip(offsets)
+                    .filter_map(|(&child, (dx, dy))| {
+                        let [min_x, min_y, max_x, max_y] = bounds[child.checked_sub(1)?]?;
+                        Some([min_x + dx, min_y + dy, max_x + dx, max_y + dy])
+                    })
+                    .reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])])
+            },
+        };
+        bounds.push(node_bounds);
+    }
+    bounds
+}
+
+/// Set the live cells of the node on the given line in `grid`, where the
+/// node's top-left corner is at `origin` and the grid's is at `corner`. Nodes
+/// without live cells aren't visited.
+fn draw_macrocell(
+    nodes: &[MacrocellNode],
+    bounds: &[Option<[u64; 4]>],
+    id: usize,
+    origin: (u64, u64),
+    corner: (u64, u64),
+    grid: &mut Grid,
+) {
+    let Some(index) = id.checked_sub(1).filter(|&index| bounds[index].is_some()) else { return };
+    match &nodes[index] {
+        MacrocellNode::Leaf(rows) => {
+            for (y, row) in rows.iter().enumerate() {
+                for x in (0..8).filter(|x| row & (1 << x) != 0) {
+                    grid.set((origin.0 + x - corner.0) as usize, (origin.1 + y as u64 - corner.1) as usize, true);
+                }
+            }
+        },
+        MacrocellNode::Inner { level, children } => {
+            let half = 1u64 << (level - 1);
+            let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+            for (&child, (dx, dy)) in children.iter().zip(offsets) {
+                draw_macrocell(nodes, bounds, child, (origin.0 + dx, origin.1 + dy), corner, grid);
+            }
+        },
+    }
+}
+
+/// Writes the nodes of a grid's quadtree as the lines of a macrocell file.
+struct MacrocellWriter<'a> {
+    grid: &'a Grid,
+    lines: Vec<String>,
+    /// The line number of each node that has been written, by its line.
+    ids: HashMap<String, usize>,
+}
+
+impl MacrocellWriter<'_> {
+    /// Write the node of the given level with its top-left corner at the given
+    /// cell, after any of its children that haven't been written yet, and
+    /// return its line number, or 0 if it is empty.
+    fn node(&mut self, level: u32, x: usize, y: usize) -> usize {
+        let line = if level == 3 {
+            let alive = |dx: usize, dy: usize| {
+                let (x, y) = (x + dx, y + dy);
+                x < self.grid.width() && y < self.grid.height() && self.grid.get(x, y)
+            };
+            let rows: Vec<String> = (0..8)
+                .map(|dy| (0..8).map(|dx| if alive(dx, dy) { '*' } else { '.' }).collect::<String>())
+                .map(|row| format!("{}$", row.trim_end_matches('.')))
+                .collect();
+            let leaf = rows.concat().trim_end_matches('$').to_string();
+            if leaf.is_empty() {
+                return 0;
+            }
+            format!("{leaf}$")
+        } else {
+            let half = 1 << (level - 1);
+            let children = [
+                self.node(level - 1, x, y),
+                self.node(level - 1, x + half, y),
+                self.node(level - 1, x, y + half),
+                self.node(level - 1, x + half, y + half),
+            ];
+            if children == [0; 4] {
+                return 0;
+            }
+            let [nw, ne, sw, se] = children;
+            format!("{level} {nw} {ne} {sw} {se}")
+        };
+        if let Some(&id) = self.ids.get(&line) {
+            return id;
+        }
+        self.lines.push(line.clone());
+        self.ids.insert(line, self.lines.len());
+        self.lines.len()
+    }
+}
+
+/// Build a grid just large enough to hold the given live cells, whose
+/// coordinates may be negative, with the top-left live cell at the origin.
+fn grid_from_offset_cells(alive: &[(i64, i64)]) -> Result<Grid, ParsePatternError> {
+    if alive.is_empty() {
+        return Ok(Grid::new(0, 0));
+    }
+    let (min_x, max_x) = alive.iter().fold((i64::MAX, i64::MIN), |(min, max), &(x, _)| (min.min(x), max.max(x)));
+    let (min_y, max_y) = alive.iter().fold((i64::MAX, i64::MIN), |(min, max), &(_, y)| (min.min(y), max.max(y)));
+    // The cells may be further apart than any grid could be wide.
+    let span = |min: i64, max: i64| usize::try_from(max.abs_diff(min)).ok().and_then(|span| span.checked_add(1));
+    let (Some(width), Some(height)) = (span(min_x, max_x), span(min_y, max_y)) else {
+        return Err(ParsePatternError::new("the pattern's cells are too far apart"));
+    };
+    check_size(width, height)?;
+    let alive: Vec<(usize, usize, usize)> =
+        alive.iter().map(|&(x, y)| (x.abs_diff(min_x) as usize, y.abs_diff(min_y) as usize, 1)).collect();
+    grid_from_runs(width, height, &alive)
+}
+
+/// Returns whether the text looks like a plaintext pattern rather than RLE.
+fn is_plaintext(text: &str) -> bool {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    lines.all(|line| line.starts_with('!') || line.chars().all(|c| matches!(c, '.' | 'O' | '*' | '#')))
+}
+
+/// Returns an error if a pattern of the given size would have more cells than
+/// a parsed pattern's grid may.
+fn check_size(width: usize, height: usize) -> Result<(), ParsePatternError> {
+    if width.saturating_mul(height) > MAX_PATTERN_CELLS {
+        return Err(ParsePatternError::new(format!("a {width}x{height} pattern is too large")));
+    }
+    Ok(())
+}
+
+/// Add a run of cells of an RLE row to `runs`, starting at `x` and moving it
+/// past them, unless the whole pattern would then be too large. `reach` is
+/// the widest any row has reached so far. Runs are kept whole rather than as
+/// their cells, so that they take no more memory than the text did.
+fn push_run(
+    runs: &mut Vec<(usize, usize, usize)>,
+    (x, y): (&mut usize, usize),
+    count: usize,
+    reach: &mut usize,
+) -> Result<(), ParsePatternError> {
+    *reach = x.saturating_add(count).max(*reach);
+    check_size(*reach, y.saturating_add(1))?;
+    runs.push((*x, y, count));
+    *x += count;
+    Ok(())
+}
+
+/// Build a grid containing the given runs of live cells, each given by the
+/// column and row of its first cell and its length along the row. The grid
+/// is at least the given size, and grows to fit every cell, unless it would
+/// have more cells than a parsed pattern may.
+fn grid_from_runs(width: usize, height: usize, alive: &[(usize, usize, usize)]) -> Result<Grid, ParsePatternError> {
+    let width = alive.iter().map(|&(x, _, len)| x.saturating_add(len)).max().unwrap_or(0).max(width);
+    let height = alive.iter().map(|&(_, y, _)| y.saturating_add(1)).max().unwrap_or(0).max(height);
+    check_size(width, height)?;
+    let mut grid = Grid::new(width, height);
+    for &(x, y, len) in alive {
+        for x in x..x + len {
+            grid.set(x, y, true);
+        }
+    }
+    Ok(grid)
+}
+
+/// Build grids of the same size containing the given runs of live cells and
+/// walls, like [`grid_from_runs`], with no grid of walls if there are none.
+fn grids_from_runs(
+    width: usize,
+    height: usize,
+    alive: &[(usize, usize, usize)],
+    walls: &[(usize, usize, usize)],
+) -> Result<(Grid, Option<Grid>), ParsePatternError> {
+    let width = walls.iter().map(|&(x, _, len)| x.saturating_add(len)).max().unwrap_or(0).max(width);
+    let height = walls.iter().map(|&(_, y, _)| y.saturating_add(1)).max().unwrap_or(0).max(height);
+    let cells = grid_from_runs(width, height, alive)?;
+    let walls = (!walls.is_empty()).then(|| grid_from_runs(cells.width(), cells.height(), walls)).transpose()?;
+    Ok((cells, walls))
+}
+
+/// A pattern file was malformed.
+#[derive(Debug)]
+pub struct ParsePatternError(String);
+
+impl ParsePatternError {
+    fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for ParsePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pattern: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParsePatternError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [Format; 4] = [Format::Rle, Format::Plaintext, Format::Life106, Format::Macrocell];
+
+    /// Returns a pattern whose live cells reach every edge of its grid, so
+    /// that formats that only keep the live cells keep its size too. It is
+    /// wide enough for RLE lines to wrap and for macrocell to need several
+    /// levels of nodes.
+    fn sample() -> Pattern {
+        let mut cells = Grid::new(83, 21);
+        for (x, y) in [(0, 0), (82, 20), (1, 0), (2, 1), (0, 2), (1, 2), (2, 2), (40, 10), (41, 10), (42, 10)] {
+            cells.set(x, y, true);
+        }
+        for x in (5..80).step_by(3) {
+            cells.set(x, x % 17, true);
+        }
+        Pattern { name: None, rule: None, cells, walls: None }
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let pattern = sample();
+        for format in FORMATS {
+            let text = pattern.write_as(format);
+            assert!(Format::detect(&text) == format, "{format} was detected as {}", Format::detect(&text));
+            let parsed = Pattern::parse_as(&text, format).unwrap();
+            assert!(parsed.cells == pattern.cells, "{format} changed the cells");
+        }
+    }
+
+    #[test]
+    fn conversions_between_formats_keep_the_cells() {
+        let pattern = sample();
+        for from in FORMATS {
+            let converted = Pattern::parse(&pattern.write_as(from)).unwrap();
+            for to in FORMATS {
+                let parsed = Pattern::parse(&converted.write_as(to)).unwrap();
+                assert!(parsed.cells == pattern.cells, "converting from {from} to {to} changed the cells");
+            }
+        }
+    }
+
+    #[test]
+    fn walls_round_trip_through_rle_and_plaintext() {
+        let mut pattern = sample();
+        let mut walls = Grid::new(pattern.cells.width(), pattern.cells.height());
+        walls.set(10, 5, true);
+        walls.set(11, 5, true);
+        pattern.walls = Some(walls);
+        for format in [Format::Rle, Format::Plaintext] {
+            let parsed = Pattern::parse_as(&pattern.write_as(format), format).unwrap();
+            assert!(parsed.cells == pattern.cells && parsed.walls == pattern.walls, "{format} changed the walls");
+        }
+    }
+
+    #[test]
+    fn oversized_patterns_are_rejected() {
+        assert!(Pattern::parse_rle("x = 4000000000, y = 4000000000\no!").is_err());
+        assert!(Pattern::parse_rle("99999999999999999999999o!").is_err());
+        assert!(Pattern::parse_rle("16385o268435455$o!").is_err());
+    }
+
+    #[test]
+    fn life106_cells_at_the_ends_of_the_coordinates_are_rejected() {
+        let text = "#Life 1.06\n-9223372036854775808 0\n9223372036854775807 0\n";
+        assert!(Pattern::parse_life106(text).is_err());
+        let text = "#Life 1.06\n-1 -1\n1 1\n";
+        assert!(Pattern::parse_life106(text).unwrap().cells.width() == 3);
+    }
+
+    #[test]
+    fn huge_macrocell_quadtrees_are_rejected_before_expanding() {
+        let mut text = String::from("[M2]\n**$**$\n");
+        for level in 4..=25 {
+            let child = level - 3;
+            text += &format!("{level} {child} {child} {child} {child}\n");
+        }
+        assert!(Pattern::parse_macrocell(&text).is_err());
+    }
+}
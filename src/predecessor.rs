@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use cgol2::analysis::{self, Predecessor};
+use cgol2::pattern::Pattern;
+use cgol2::rule::LifeLike;
+use clap::Args;
+
+use crate::error::Error;
+use crate::{load_pattern, resolve_rule};
+
+#[derive(Args)]
+pub struct PredecessorArgs {
+    /// Pattern file to find a predecessor of, or `-` to read the pattern from
+    /// standard input.
+    pattern: PathBuf,
+
+    /// Name of a registered life-like rule, or a rulestring in B/S notation.
+    /// Defaults to the rule in the pattern file, or else `life`.
+    #[arg(long)]
+    rule: Option<String>,
+
+    /// Give up after assigning this many cells of candidate predecessors.
+    #[arg(long, default_value_t = 100_000_000)]
+    max_nodes: u64,
+}
+
+/// Search for a predecessor of a pattern, and print it as RLE if there is one.
+pub fn run(args: PredecessorArgs) -> Result<(), Error> {
+    let pattern = load_pattern(&args.pattern)?;
+    let rule_name = args.rule.clone().or(pattern.rule.clone()).unwrap_or_else(|| "life".to_string());
+    let rule: LifeLike = resolve_rule(&rule_name)?
+        .rulestring()
+        .and_then(|rulestring| rulestring.parse().ok())
+        .ok_or_else(|| Error::NotLifeLike { rule: rule_name.clone() })?;
+
+    let name = pattern.name.clone().unwrap_or_else(|| args.pattern.display().to_string());
+    match analysis::find_predecessor(&pattern.cells, &rule, args.max_nodes) {
+        Predecessor::Found(cells) => {
+            let predecessor = Pattern {
+                name: Some(format!("Predecessor of {name}")),
+                rule: Some(rule.to_string()),
+                cells,
+                walls: None,
+            };
+            print!("{}", predecessor.to_rle());
+        },
+        Predecessor::None => println!("{name} has no predecessor, so it is a Garden of Eden"),
+        Predecessor::GaveUp => {
+            println!("Gave up after {} nodes without deciding whether {name} has a predecessor", args.max_nodes);
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+#[cfg(feature = "count-allocations")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "count-allocations")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The number of allocations made since the program started.
+#[cfg(feature = "count-allocations")]
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting how many allocations are made for the
+/// profiling overlay.
+#[cfg(feature = "count-allocations")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "count-allocations")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc`.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc_zeroed`.
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::realloc`.
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::dealloc`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+/// Returns the number of allocations made since the program started, or 0 if
+/// they aren't counted.
+fn allocations() -> u64 {
+    #[cfg(feature = "count-allocations")]
+    return ALLOCATIONS.load(Ordering::Relaxed);
+    #[cfg(not(feature = "count-allocations"))]
+    0
+}
+
+/// Where the time of a frame went.
+#[derive(Default, Clone, Copy)]
+struct Frame {
+    /// Counting neighbors to compute the next generation of every board,
+    /// including waiting for the worker to finish it.
+    neighbors: Duration,
+    /// Replacing each grid with the next one, and updating the ages and the
+    /// rest of what is tracked about the cells.
+    swap: Duration,
+    /// Filling the frame with the background color.
+    clear: Duration,
+    /// Drawing the cells of every board, and everything drawn over them.
+    drawing: Duration,
+    /// Scaling the frame to the window, drawing the panel, and presenting it.
+    present: Duration,
+    /// The number of allocations made, which are only counted with the
+    /// `count-allocations` feature.
+    allocations: u64,
+}
+
+/// Measures where the time of each frame goes, for the profiling overlay.
+pub struct Profiler {
+    current: Frame,
+    last: Frame,
+    /// The number of allocations made before the current frame started.
+    allocations: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self { current: Frame::default(), last: Frame::default(), allocations: allocations() }
+    }
+
+    /// Add the time a tick of one board took to the current frame, given the
+    /// whole tick and the part of it that went to counting neighbors.
+    pub fn record_tick(&mut self, tick: Duration, neighbors: Duration) {
+        self.current.neighbors += neighbors;
+        self.current.swap += tick.saturating_sub(neighbors);
+    }
+
+    pub fn record_clear(&mut self, duration: Duration) {
+        self.current.clear += duration;
+    }
+
+    pub fn record_drawing(&mut self, duration: Duration) {
+        self.current.drawing += duration;
+    }
+
+    /// Finish the current frame with the time it took to present it, so that
+    /// it is the one described by the overlay.
+    pub fn finish_frame(&mut self, present: Duration) {
+        self.current.present += present;
+        let allocations = allocations();
+        self.current.allocations = allocations - self.allocations;
+        self.allocations = allocations;
+        self.last = std::mem::take(&mut self.current);
+    }
+
+    /// Returns the breakdown of the last frame, for the overlay.
+    pub fn text(&self) -> String {
+        let Frame { neighbors, swap, clear, drawing, present, allocations } = self.last;
+        let total = neighbors + swap + clear + drawing + present;
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let mut text = format!(
+            "frame {:.2} ms\nneighbors {:.2} ms\nswap {:.2} ms\nclear {:.2} ms\ndraw {:.2} ms\npresent {:.2} ms",
+            ms(total),
+            ms(neighbors),
+            ms(swap),
+            ms(clear),
+            ms(drawing),
+            ms(present)
+        );
+        if cfg!(feature = "count-allocations") {
+            text.push_str(&format!("\n{allocations} allocations"));
+        }
+        text
+    }
+}
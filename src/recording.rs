@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::remote::Command;
+
+/// One line of a recording, which is written as newline-delimited JSON.
+///
+/// Events are stamped with the number of timed ticks that had happened when
+/// they occurred, so that a replay can apply them at the same point.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Entry {
+    /// The first line, with everything needed to start the run again.
+    Start {
+        seed: u64,
+        /// The command line the run was started with, including the program
+        /// name.
+        args: Vec<String>,
+    },
+    /// A remote command changed a board.
+    Command { ticks: u64, board: usize, command: Command },
+    /// A board was restarted from a new random seed.
+    Restart { ticks: u64, board: usize, seed: u64 },
+}
+
+/// Writes every event that influences a run, so that it can be replayed.
+pub struct Recorder {
+    /// Where the recording is written, for error messages.
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Create a recording at the given path, truncating it, starting with the
+    /// seed and command line of the run.
+    pub fn create(path: &Path, seed: u64, args: Vec<String>) -> Result<Self, Error> {
+        let file = File::create(path).map_err(|err| Error::WriteFile { path: path.to_path_buf(), source: err })?;
+        let mut recorder = Self { path: path.to_path_buf(), writer: BufWriter::new(file) };
+        recorder.record(&Entry::Start { seed, args })?;
+        Ok(recorder)
+    }
+
+    /// Write an event as a single line.
+    pub fn record(&mut self, entry: &Entry) -> Result<(), Error> {
+        serde_json::to_writer(&mut self.writer, entry)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(self.writer))
+            .map_err(|err| self.error(err))
+    }
+
+    /// Write out anything that is still buffered.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush().map_err(|err| self.error(err))
+    }
+
+    fn error(&self, source: io::Error) -> Error {
+        Error::WriteFile { path: self.path.clone(), source }
+    }
+}
+
+/// The events of a recording that have yet to be replayed.
+pub struct Replay {
+    entries: VecDeque<Entry>,
+}
+
+impl Replay {
+    /// Read a recording, returning its seed and command line along with the
+    /// rest of its events.
+    pub fn load(path: &Path) -> Result<(u64, Vec<String>, Self), Error> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err })?;
+        let invalid = |message: String| Error::InvalidRecording { path: path.to_path_buf(), message };
+
+        let mut entries = VecDeque::new();
+        for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let entry = serde_json::from_str(line).map_err(|err| invalid(format!("line {}: {err}", index + 1)))?;
+            entries.push_back(entry);
+        }
+        match entries.pop_front() {
+            Some(Entry::Start { seed, args }) => Ok((seed, args, Self { entries })),
+            _ => Err(invalid("it doesn't start with the seed and command line".to_string())),
+        }
+    }
+
+    /// Remove and return the next command if it happened after the given
+    /// number of ticks, along with the board it applies to.
+    pub fn next_command(&mut self, ticks: u64) -> Option<(usize, Command)> {
+        if !matches!(self.entries.front(), Some(Entry::Command { ticks: at, .. }) if *at <= ticks) {
+            return None;
+        }
+        let Some(Entry::Command { board, command, .. }) = self.entries.pop_front() else { unreachable!() };
+        Some((board, command))
+    }
+
+    /// Remove and return the seed that the next restart used, if the next
+    /// event is a restart.
+    pub fn next_restart(&mut self) -> Option<u64> {
+        if !matches!(self.entries.front(), Some(Entry::Restart { .. })) {
+            return None;
+        }
+        let Some(Entry::Restart { seed, .. }) = self.entries.pop_front() else { unreachable!() };
+        Some(seed)
+    }
+}
@@ -0,0 +1,355 @@
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::Instant;
+
+use cgol2::pattern::Pattern;
+use cgol2::soup::random_fill;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tungstenite::Message;
+use winit::event_loop::EventLoopProxy;
+
+use crate::app::App;
+use crate::error::Error;
+use crate::{resolve_rule, UserEvent, MAX_BRUSH_RADIUS};
+
+/// A command sent by a remote client, as a JSON object whose `command` field
+/// names the command, e.g. `{"command": "set-cell", "x": 3, "y": 4}`.
+///
+/// Commands apply to the first board unless the object has a `board` field
+/// with the index of another one.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Command {
+    /// Suspend timed ticks.
+    Pause,
+    /// Resume timed ticks.
+    Resume,
+    /// Advance the simulation by a number of generations, even while paused.
+    Step {
+        #[serde(default = "default_generations")]
+        generations: u64,
+    },
+    /// Set the state of a single cell.
+    SetCell {
+        x: usize,
+        y: usize,
+        #[serde(default = "default_alive")]
+        alive: bool,
+    },
+    /// Set the state of every cell within a radius of a cell, so that a radius
+    /// of 0 sets just that cell. Killing cells also knocks down their walls.
+    /// The radius is at most that of the largest brush.
+    Paint {
+        x: usize,
+        y: usize,
+        #[serde(default)]
+        radius: usize,
+        #[serde(default = "default_alive")]
+        alive: bool,
+    },
+    /// Turn every cell within a radius of a cell into a wall, which counts as
+    /// a live neighbor but never changes. The radius is at most that of the
+    /// largest brush.
+    BuildWalls {
+        x: usize,
+        y: usize,
+        #[serde(default)]
+        radius: usize,
+    },
+    /// Bring the cells at the given coordinates to life, e.g. to stamp a
+    /// pattern onto the grid.
+    Stamp { cells: Vec<[usize; 2]> },
+    /// Replace a rectangular region with random cells, each alive with the
+    /// given probability.
+    FillRandom { x: usize, y: usize, width: usize, height: usize, chance: f64 },
+    /// Kill every cell, leaving the walls standing.
+    Clear,
+    /// Refill the grid with a random configuration from the given seed, or a
+    /// random one, and start counting generations again. Compared boards are
+    /// reseeded together.
+    Reseed {
+        #[serde(default = "rand::random")]
+        seed: u64,
+    },
+    /// Clear the grid and place an RLE or plaintext pattern on it, replacing
+    /// the walls with the pattern's. The pattern is centered unless a position
+    /// for its top-left corner is given.
+    LoadPattern { pattern: String, x: Option<usize>, y: Option<usize> },
+    /// Switch to a registered rule or B/S rulestring.
+    ChangeRule { rule: String },
+    /// Return the generation, population, rule, live cells, and walls.
+    QueryState,
+}
+
+/// A command along with the board it applies to.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    board: usize,
+    #[serde(flatten)]
+    command: Command,
+}
+
+fn default_generations() -> u64 {
+    1
+}
+
+fn default_alive() -> bool {
+    true
+}
+
+/// A command waiting to be run on the event loop, along with a channel to send
+/// the response back to the client on.
+pub struct Request {
+    command: Command,
+    board: usize,
+    reply: mpsc::Sender<Value>,
+}
+
+impl Request {
+    /// Run the command against its board and send the response to the client.
+    pub fn handle(self, app: &mut App) -> Result<(), Error> {
+        let command = self.command.clone();
+        let response = match self.command.run(app, self.board) {
+            Ok(response) => {
+                // Queries don't change anything, so there's no need to replay them.
+                if !matches!(command, Command::QueryState) {
+                    app.record_command(self.board, command)?;
+                }
+                response
+            },
+            Err(CommandError::Client(message)) => json!({ "ok": false, "error": message }),
+            Err(CommandError::Simulation(err)) => {
+                let _ = self.reply.send(json!({ "ok": false, "error": err.to_string() }));
+                return Err(*err);
+            },
+        };
+        // The client may have disconnected while waiting, which is fine.
+        let _ = self.reply.send(response);
+        Ok(())
+    }
+}
+
+/// Why a command failed.
+enum CommandError {
+    /// The command was invalid, which is reported to the client.
+    Client(String),
+    /// The simulation itself failed, which stops it. It is boxed to keep
+    /// results of commands small.
+    Simulation(Box<Error>),
+}
+
+impl From<Error> for CommandError {
+    fn from(err: Error) -> Self {
+        Self::Simulation(Box::new(err))
+    }
+}
+
+impl Command {
+    /// Run a command from a recording, which succeeded when it was recorded.
+    pub fn replay(self, app: &mut App, board: usize) -> Result<(), Error> {
+        match self.run(app, board) {
+            Ok(_) => Ok(()),
+            Err(CommandError::Client(message)) => {
+                log::warn!("Replayed command failed, so the replay has diverged: {message}");
+                Ok(())
+            },
+            Err(CommandError::Simulation(err)) => Err(*err),
+        }
+    }
+
+    /// Run a command issued from the window, recording it like a remote one.
+    pub fn issue(self, app: &mut App, board: usize) -> Result<(), Error> {
+        if let Err(message) = self.try_issue(app, board)? {
+            log::warn!("{message}");
+        }
+        Ok(())
+    }
+
+    /// Run a command issued from the window, recording it like a remote one,
+    /// and return why it was rejected if it was invalid.
+    pub fn try_issue(self, app: &mut App, board: usize) -> Result<Result<(), String>, Error> {
+        match self.clone().run(app, board) {
+            Ok(_) => app.record_command(board, self).map(Ok),
+            Err(CommandError::Client(message)) => Ok(Err(message)),
+            Err(CommandError::Simulation(err)) => Err(*err),
+        }
+    }
+
+    /// Run the command on the board with the given index, returning the
+    /// response to send to the client.
+    fn run(self, app: &mut App, board: usize) -> Result<Value, CommandError> {
+        let boards = app.boards.len();
+        if board >= boards {
+            return Err(CommandError::Client(format!("there is no board {board}; there are {boards} boards")));
+        }
+        if let Self::Step { generations } = self {
+            let start = Instant::now();
+            for _ in 0..generations {
+                app.step_board(board)?;
+            }
+            app.record_metrics(start.elapsed());
+            return Ok(json!({ "ok": true }));
+        }
+        if let Self::Reseed { seed } = self {
+            app.reseed_board(board, seed)?;
+            return Ok(json!({ "ok": true }));
+        }
+
+        let simulation = &mut app.boards[board].simulation;
+        match self {
+            Self::Pause => simulation.paused = true,
+            Self::Resume => simulation.paused = false,
+            Self::Step { .. } | Self::Reseed { .. } => unreachable!("stepping and reseeding are handled above"),
+            Self::SetCell { x, y, alive } => {
+                let grid = &mut simulation.grid;
+                if x >= grid.width() || y >= grid.height() {
+                    let (width, height) = (grid.width(), grid.height());
+                    return Err(CommandError::Client(format!("({x}, {y}) is outside of the {width}x{height} grid")));
+                }
+                grid.set(x, y, alive);
+            },
+            Self::Paint { x, y, radius, alive } => {
+                let grid = &mut simulation.grid;
+                if x >= grid.width() || y >= grid.height() {
+                    let (width, height) = (grid.width(), grid.height());
+                    return Err(CommandError::Client(format!("({x}, {y}) is outside of the {width}x{height} grid")));
+                }
+                let cells: Vec<_> = grid.disk(x, y, radius.min(MAX_BRUSH_RADIUS)).collect();
+                for &(x, y) in &cells {
+                    grid.set(x, y, alive);
+                }
+                if !alive {
+                    simulation.demolish_walls(cells);
+                }
+            },
+            Self::BuildWalls { x, y, radius } => {
+                let grid = &simulation.grid;
+                if x >= grid.width() || y >= grid.height() {
+                    let (width, height) = (grid.width(), grid.height());
+                    return Err(CommandError::Client(format!("({x}, {y}) is outside of the {width}x{height} grid")));
+                }
+                let cells: Vec<_> = grid.disk(x, y, radius.min(MAX_BRUSH_RADIUS)).collect();
+                simulation.build_walls(cells);
+            },
+            Self::Stamp { cells } => {
+                let grid = &mut simulation.grid;
+                if let Some([x, y]) = cells.iter().find(|[x, y]| *x >= grid.width() || *y >= grid.height()) {
+                    let (width, height) = (grid.width(), grid.height());
+                    return Err(CommandError::Client(format!("({x}, {y}) is outside of the {width}x{height} grid")));
+                }
+                for [x, y] in cells {
+                    grid.set(x, y, true);
+                }
+            },
+            Self::FillRandom { x, y, width, height, chance } => {
+                if !(0.0..=1.0).contains(&chance) {
+                    return Err(CommandError::Client(format!("{chance} is not in the range 0..=1")));
+                }
+                random_fill(&mut simulation.grid, (x, y), (width, height), chance, &mut simulation.rng);
+            },
+            Self::Clear => simulation.grid.clear(),
+            Self::LoadPattern { pattern, x, y } => {
+                let pattern = Pattern::parse(&pattern).map_err(|err| CommandError::Client(err.to_string()))?;
+                simulation.load(pattern, x, y);
+            },
+            Self::ChangeRule { rule } => {
+                simulation.rule = resolve_rule(&rule).map_err(|err| CommandError::Client(err.to_string()))?;
+                simulation.rule_name = rule;
+            },
+            Self::QueryState => {
+                let grid = &simulation.grid;
+                let cells: Vec<[usize; 2]> = (0..grid.height())
+                    .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+                    .filter(|&(x, y)| grid.get(x, y))
+                    .map(|(x, y)| [x, y])
+                    .collect();
+                let walls: Vec<[usize; 2]> = simulation.walls.as_ref().map_or_else(Vec::new, |walls| {
+                    (0..walls.height())
+                        .flat_map(|y| (0..walls.width()).map(move |x| (x, y)))
+                        .filter(|&(x, y)| walls.get(x, y))
+                        .map(|(x, y)| [x, y])
+                        .collect()
+                });
+                return Ok(json!({
+                    "ok": true,
+                    "board": board,
+                    "boards": boards,
+                    "generation": simulation.generation,
+                    "population": cells.len(),
+                    "paused": simulation.paused,
+                    "rule": simulation.rule_name,
+                    "width": grid.width(),
+                    "height": grid.height(),
+                    "cells": cells,
+                    "walls": walls,
+                }));
+            },
+        }
+        Ok(json!({ "ok": true }))
+    }
+}
+
+/// Start a WebSocket server on the given local port that forwards commands to
+/// the event loop.
+///
+/// The server only listens on the loopback interface, since anyone who can
+/// connect can control the simulation.
+pub fn serve(port: u16, proxy: EventLoopProxy<UserEvent>) -> Result<(), Error> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(Error::ControlServer)?;
+    log::info!("Listening for remote control connections on port {port}");
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let proxy = proxy.clone();
+                    std::thread::spawn(move || handle_connection(stream, proxy));
+                },
+                Err(err) => log::warn!("Failed to accept remote control connection: {err}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Read commands from a single client until it disconnects.
+fn handle_connection(stream: TcpStream, proxy: EventLoopProxy<UserEvent>) {
+    let peer = stream.peer_addr().map_or_else(|_| "unknown peer".to_string(), |addr| addr.to_string());
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(err) => return log::warn!("WebSocket handshake with {peer} failed: {err}"),
+    };
+    log::info!("Remote control client {peer} connected");
+
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(err) => {
+                log::debug!("Remote control connection to {peer} failed: {err}");
+                break;
+            },
+        };
+
+        let response = match serde_json::from_str::<Envelope>(&text) {
+            Ok(Envelope { board, command }) => {
+                let (reply, response) = mpsc::channel();
+                if proxy.send_event(UserEvent::Remote(Request { command, board, reply })).is_err() {
+                    // The event loop has exited.
+                    break;
+                }
+                match response.recv() {
+                    Ok(response) => response,
+                    Err(_) => break,
+                }
+            },
+            Err(err) => json!({ "ok": false, "error": err.to_string() }),
+        };
+        if socket.send(Message::Text(response.to_string())).is_err() {
+            break;
+        }
+    }
+    log::info!("Remote control client {peer} disconnected");
+}
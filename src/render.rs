@@ -0,0 +1,424 @@
+use std::fmt;
+use std::str::FromStr;
+
+use cgol2::grid::Grid;
+use clap::ValueEnum;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+/// The gap between neighboring glyphs, and between lines of text, in font
+/// pixels.
+const GLYPH_SPACING: usize = 1;
+
+/// Grid lines aren't drawn between cells smaller than this many physical
+/// pixels, since they would hide the cells.
+const MIN_GRID_LINE_CELL_SIZE: usize = 4;
+
+/// A color with red, green, and blue components.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Generate a random color.
+    pub fn random() -> Self {
+        let mut rng = rand::thread_rng();
+        let r = rng.gen_range(0..=255);
+        let g = rng.gen_range(0..=255);
+        let b = rng.gen_range(0..=255);
+        Self(r, g, b)
+    }
+
+    /// Convert a color from HSV, with the hue in turns so that any value wraps
+    /// around the color wheel, and the saturation and value between 0 and 1.
+    pub fn from_hsv(hue: f64, saturation: f64, value: f64) -> Self {
+        let (saturation, value) = (saturation.clamp(0.0, 1.0), value.clamp(0.0, 1.0));
+        let sector = hue.rem_euclid(1.0) * 6.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        let component = |c: f64| ((c + m) * 255.0).round() as u8;
+        Self(component(r), component(g), component(b))
+    }
+
+    /// Returns the color the given fraction of the way from this one to the
+    /// other.
+    pub fn blend(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        Self(mix(self.0, other.0), mix(self.1, other.1), mix(self.2, other.2))
+    }
+
+    /// Returns the color for the GPU, which blends in linear rather than sRGB
+    /// space.
+    pub fn to_linear(self) -> pixels::wgpu::Color {
+        let linear = |component: u8| {
+            let c = component as f64 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        pixels::wgpu::Color { r: linear(self.0), g: linear(self.1), b: linear(self.2), a: 1.0 }
+    }
+}
+
+impl fmt::Display for Rgb {
+    /// Format the color as a hex color like `#33ff66`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    /// Parse a hex color like `#33ff66`, or its short form `#3f6`. The `#` is
+    /// optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("`{s}` is not a hex color like `#33ff66`");
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let component = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+        match hex.len() {
+            6 => Ok(Self(component(&hex[0..2])?, component(&hex[2..4])?, component(&hex[4..6])?)),
+            // Each digit of the short form is repeated, so `#3f6` is `#33ff66`.
+            3 => Ok(Self(component(&hex[0..1])? * 17, component(&hex[1..2])? * 17, component(&hex[2..3])? * 17)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// A location in grid space.
+pub struct GridCoords {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// A location in pixel space.
+#[derive(Clone, Copy)]
+pub struct PixelCoords {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// A rectangle in pixel space.
+#[derive(Clone, Copy)]
+pub struct PixelRect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+impl PixelRect {
+    /// Returns whether the rectangle contains the given pixel.
+    pub fn contains(&self, coords: PixelCoords) -> bool {
+        (self.x..self.x + self.w).contains(&coords.x) && (self.y..self.y + self.h).contains(&coords.y)
+    }
+
+    /// Returns the part of this rectangle that is also inside the other one.
+    fn intersect(&self, other: &PixelRect) -> PixelRect {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let right = (self.x + self.w).min(other.x + other.w);
+        let bottom = (self.y + self.h).min(other.y + other.h);
+        PixelRect { x, y, w: right.saturating_sub(x), h: bottom.saturating_sub(y) }
+    }
+}
+
+/// The shape each live cell is drawn as, within its square slot.
+#[derive(ValueEnum, strum::Display, Clone, Copy)]
+#[strum(serialize_all = "lowercase")]
+pub enum CellShape {
+    /// Fill the whole slot.
+    Square,
+    /// A filled circle touching the edges of the slot.
+    Circle,
+    /// A filled diamond whose corners touch the edges of the slot.
+    Diamond,
+}
+
+impl CellShape {
+    /// Returns whether the pixel at the given offset from the center of a
+    /// slot that is `2 * radius` pixels wide is part of the shape.
+    fn contains(self, dx: f64, dy: f64, radius: f64) -> bool {
+        match self {
+            Self::Square => true,
+            Self::Circle => dx * dx + dy * dy <= radius * radius,
+            Self::Diamond => dx.abs() + dy.abs() <= radius,
+        }
+    }
+}
+
+/// Returns the width and height of text when drawn with each font pixel
+/// `scale` pixels wide and tall.
+pub fn text_size(text: &str, scale: usize) -> (usize, usize) {
+    let columns = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    let rows = text.lines().count();
+    let size = |count: usize, glyph: usize| (count * (glyph + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING) * scale;
+    (size(columns, GLYPH_WIDTH), size(rows, GLYPH_HEIGHT))
+}
+
+/// A pixel buffer to draw into, along with a rectangle that drawing is clipped
+/// to.
+pub struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: usize,
+    height: usize,
+    clip: PixelRect,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(frame: &'a mut [u8], width: usize, height: usize) -> Self {
+        Self { frame, width, height, clip: PixelRect { x: 0, y: 0, w: width, h: height } }
+    }
+
+    /// Clip all further drawing to the given rectangle, or to the whole buffer
+    /// if there is none.
+    pub fn set_clip(&mut self, clip: Option<PixelRect>) {
+        let whole = PixelRect { x: 0, y: 0, w: self.width, h: self.height };
+        self.clip = clip.map_or(whole, |clip| clip.intersect(&whole));
+    }
+
+    /// Draw a filled rectangle.
+    pub fn fill_rect(&mut self, rect: PixelRect, rgb: Rgb) {
+        let rect = rect.intersect(&self.clip);
+        for y in rect.y..rect.y + rect.h {
+            for x in rect.x..rect.x + rect.w {
+                self.set_pixel(PixelCoords { x, y }, rgb);
+            }
+        }
+    }
+
+    /// Draw the one pixel wide outline of a rectangle.
+    pub fn stroke_rect(&mut self, rect: PixelRect, rgb: Rgb) {
+        let PixelRect { x, y, w, h } = rect;
+        self.fill_rect(PixelRect { x, y, w, h: 1 }, rgb);
+        self.fill_rect(PixelRect { x, y: (y + h).saturating_sub(1), w, h: 1 }, rgb);
+        self.fill_rect(PixelRect { x, y, w: 1, h }, rgb);
+        self.fill_rect(PixelRect { x: (x + w).saturating_sub(1), y, w: 1, h }, rgb);
+    }
+
+    /// Draw a filled cell that is `size` pixels wide and tall with its
+    /// top-left corner at the given pixel.
+    pub fn fill_cell(&mut self, origin: PixelCoords, size: usize, shape: CellShape, rgb: Rgb) {
+        if let CellShape::Square = shape {
+            return self.fill_rect(PixelRect { x: origin.x, y: origin.y, w: size, h: size }, rgb);
+        }
+
+        // Test the center of each pixel, so that the shape is symmetric.
+        let radius = size as f64 / 2.0;
+        for y in 0..size {
+            for x in 0..size {
+                let coords = PixelCoords { x: origin.x + x, y: origin.y + y };
+                if self.clip.contains(coords)
+                    && shape.contains(x as f64 + 0.5 - radius, y as f64 + 0.5 - radius, radius)
+                {
+                    self.set_pixel(coords, rgb);
+                }
+            }
+        }
+    }
+
+    /// Draw the cells of a grid that fall inside a tile, where `origin` is the
+    /// cell at the tile's top-left corner and each cell is `size` pixels wide
+    /// and tall. `color` is called with the coordinates of each cell to pick
+    /// the color it is drawn in, if it is drawn at all.
+    pub fn draw_cells(
+        &mut self,
+        tile: PixelRect,
+        grid: &Grid,
+        origin: (isize, isize),
+        size: usize,
+        shape: CellShape,
+        mut color: impl FnMut(usize, usize) -> Option<Rgb>,
+    ) {
+        let (columns, rows) = (tile.w.div_ceil(size), tile.h.div_ceil(size));
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (origin.0 + column as isize, origin.1 + row as isize);
+                if x < 0 || y < 0 || x as usize >= grid.width() || y as usize >= grid.height() {
+                    continue;
+                }
+                let Some(rgb) = color(x as usize, y as usize) else { continue };
+                let coords = PixelCoords { x: tile.x + column * size, y: tile.y + row * size };
+                self.fill_cell(coords, size, shape, rgb);
+            }
+        }
+    }
+
+    /// Draw the cells of a grid that fall inside a tile like
+    /// [`Canvas::draw_cells`], but with the view anywhere between whole cells
+    /// and zoom levels, as while zooming is animated. `origin` is the universe
+    /// position at the tile's top-left corner, and each cell is `size` pixels
+    /// wide and tall, rounded so that every cell reaches the next.
+    pub fn draw_cells_scaled(
+        &mut self,
+        tile: PixelRect,
+        grid: &Grid,
+        origin: (f64, f64),
+        size: f64,
+        shape: CellShape,
+        mut color: impl FnMut(usize, usize) -> Option<Rgb>,
+    ) {
+        let pixel =
+            |cell: usize, origin: f64, start: usize| start as isize + ((cell as f64 - origin) * size).round() as isize;
+        let visible = |origin: f64, pixels: usize, cells: usize| {
+            let start = origin.floor().max(0.0) as usize;
+            start.min(cells)..((origin + pixels as f64 / size).ceil().max(0.0) as usize).min(cells)
+        };
+        for y in visible(origin.1, tile.h, grid.height()) {
+            let (top, bottom) = (pixel(y, origin.1, tile.y), pixel(y + 1, origin.1, tile.y));
+            for x in visible(origin.0, tile.w, grid.width()) {
+                let Some(rgb) = color(x, y) else { continue };
+                let (left, right) = (pixel(x, origin.0, tile.x), pixel(x + 1, origin.0, tile.x));
+                if left >= 0 && top >= 0 {
+                    let size = (right - left).max(bottom - top).max(1) as usize;
+                    self.fill_cell(PixelCoords { x: left as usize, y: top as usize }, size, shape, rgb);
+                } else {
+                    // Cells that hang off the top or left of the buffer are
+                    // drawn as the part of their square that is on it.
+                    let (x, y) = (left.max(0), top.max(0));
+                    let (w, h) = ((right - x).max(0) as usize, (bottom - y).max(0) as usize);
+                    self.fill_rect(PixelRect { x: x as usize, y: y as usize, w, h }, rgb);
+                }
+            }
+        }
+    }
+
+    /// Draw the cells of a grid that fall inside a tile when zoomed out past
+    /// one pixel per cell, where `origin` is the cell at the tile's top-left
+    /// corner and each block of `size` pixels covers `cells` cells wide and
+    /// tall. Blocks with live cells are drawn in the color `shade` picks for
+    /// the fraction of their cells that are alive.
+    pub fn draw_density(
+        &mut self,
+        tile: PixelRect,
+        grid: &Grid,
+        origin: (isize, isize),
+        size: usize,
+        cells: usize,
+        mut shade: impl FnMut(f64) -> Rgb,
+    ) {
+        // The range of cells along a dimension of the grid that a block covers.
+        let covered = |start: isize, len: usize| {
+            let end = (start + cells as isize).clamp(0, len as isize) as usize;
+            start.clamp(0, len as isize) as usize..end
+        };
+        let (columns, rows) = (tile.w.div_ceil(size), tile.h.div_ceil(size));
+        for row in 0..rows {
+            let ys = covered(origin.1 + (row * cells) as isize, grid.height());
+            for column in 0..columns {
+                let xs = covered(origin.0 + (column * cells) as isize, grid.width());
+                let total = xs.len() * ys.len();
+                let alive = ys.clone().map(|y| xs.clone().filter(|&x| grid.get(x, y)).count()).sum::<usize>();
+                if alive == 0 {
+                    continue;
+                }
+                let rect = PixelRect { x: tile.x + column * size, y: tile.y + row * size, w: size, h: size };
+                self.fill_rect(rect, shade(alive as f64 / total as f64));
+            }
+        }
+    }
+
+    /// Draw lines `width` pixels thick between the cells of a tile, where each
+    /// cell is `size` pixels wide and tall, unless the cells are too small for
+    /// lines to fit.
+    pub fn draw_grid_lines(&mut self, tile: PixelRect, size: usize, width: usize, rgb: Rgb) {
+        if size < MIN_GRID_LINE_CELL_SIZE {
+            return;
+        }
+        // Lines never cover more than half of a cell.
+        let width = width.clamp(1, size / 2);
+        for column in 0..tile.w.div_ceil(size) {
+            let x = tile.x + column * size;
+            self.fill_rect(PixelRect { x, y: tile.y, w: width.min(tile.x + tile.w - x), h: tile.h }, rgb);
+        }
+        for row in 0..tile.h.div_ceil(size) {
+            let y = tile.y + row * size;
+            self.fill_rect(PixelRect { x: tile.x, y, w: tile.w, h: width.min(tile.y + tile.h - y) }, rgb);
+        }
+    }
+
+    /// Draw a one pixel wide line between two pixels, which may be outside of
+    /// the buffer.
+    pub fn draw_line(&mut self, from: (isize, isize), to: (isize, isize), rgb: Rgb) {
+        let steps = from.0.abs_diff(to.0).max(from.1.abs_diff(to.1)).max(1);
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = from.0 as f64 + (to.0 - from.0) as f64 * t;
+            let y = from.1 as f64 + (to.1 - from.1) as f64 * t;
+            let (x, y) = (x.round(), y.round());
+            if x < 0.0 || y < 0.0 {
+                continue;
+            }
+            let coords = PixelCoords { x: x as usize, y: y as usize };
+            if self.clip.contains(coords) {
+                self.set_pixel(coords, rgb);
+            }
+        }
+    }
+
+    /// Draw a line between two pixels with an arrowhead at the second one.
+    pub fn draw_arrow(&mut self, from: (isize, isize), to: (isize, isize), rgb: Rgb) {
+        self.draw_line(from, to, rgb);
+        let (dx, dy) = ((from.0 - to.0) as f64, (from.1 - to.1) as f64);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        // Each side of the head points back along the line, turned 30 degrees.
+        let head = (length / 3.0).max(3.0) / length;
+        let (sin, cos) = std::f64::consts::FRAC_PI_6.sin_cos();
+        for sin in [sin, -sin] {
+            let side = ((dx * cos - dy * sin) * head, (dx * sin + dy * cos) * head);
+            self.draw_line(to, (to.0 + side.0 as isize, to.1 + side.1 as isize), rgb);
+        }
+    }
+
+    /// Draw text with its top-left corner at the given pixel, with each font
+    /// pixel `scale` pixels wide and tall. Each line of the text is drawn
+    /// below the last.
+    pub fn draw_text(&mut self, origin: PixelCoords, text: &str, scale: usize, rgb: Rgb) {
+        for (row, line) in text.lines().enumerate() {
+            let y = origin.y + row * (GLYPH_HEIGHT + GLYPH_SPACING) * scale;
+            for (column, c) in line.chars().enumerate() {
+                let x = origin.x + column * (GLYPH_WIDTH + GLYPH_SPACING) * scale;
+                for (dy, bits) in font::glyph(c).into_iter().enumerate() {
+                    for dx in (0..GLYPH_WIDTH).filter(|dx| bits & (1 << (GLYPH_WIDTH - 1 - dx)) != 0) {
+                        let rect = PixelRect { x: x + dx * scale, y: y + dy * scale, w: scale, h: scale };
+                        self.fill_rect(rect, rgb);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Set the color of a single pixel, which must be inside the clipping
+    /// rectangle.
+    fn set_pixel(&mut self, coords: PixelCoords, rgb: Rgb) {
+        let idx = (coords.y * self.width + coords.x) * 4;
+        self.frame[idx..idx + 4].copy_from_slice(&[rgb.0, rgb.1, rgb.2, 0xFF]);
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{SocketAddr, TcpListener};
+
+use cgol2::parse_dimensions;
+use cgol2::pattern::Pattern;
+use clap::Args;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::config::SimulationArgs;
+use crate::error::Error;
+use crate::simulation::Simulation;
+use crate::{simulation_rule, PIXELS_PER_CELL, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// The request wasn't valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// The request wasn't a JSON-RPC request object.
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The parameters were malformed, or referred to cells outside of the grid.
+const INVALID_PARAMS: i64 = -32602;
+/// The simulation failed, e.g. because a script hook raised an error.
+const SIMULATION_ERROR: i64 = -32000;
+
+#[derive(Args)]
+pub struct RpcArgs {
+    #[command(flatten)]
+    simulation: SimulationArgs,
+
+    /// Size of the universe in cells, given as `WIDTHxHEIGHT`. Defaults to the
+    /// size of the grid in the window, so that runs match `run`.
+    #[arg(long, value_parser = parse_dimensions)]
+    universe: Option<(usize, usize)>,
+
+    /// Accept connections on this address, like `127.0.0.1:7070`, instead of
+    /// reading requests from stdin and writing responses to stdout. Clients
+    /// are served one at a time, and all of them drive the same universe.
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+}
+
+/// A JSON-RPC 2.0 request. Requests without an `id` are notifications, which
+/// get no response.
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Parameters of `load`, which clears the grid and places an RLE or plaintext
+/// pattern on it, centered unless a position for its top-left corner is given.
+#[derive(Deserialize)]
+struct Load {
+    pattern: String,
+    x: Option<usize>,
+    y: Option<usize>,
+}
+
+/// Parameters of `step`, which advances the universe by `n` generations.
+#[derive(Deserialize)]
+struct Step {
+    #[serde(default = "default_generations")]
+    n: u64,
+}
+
+/// Parameters of `get_cells`, which returns the live cells within a region of
+/// the grid, or the whole grid if it isn't given.
+#[derive(Deserialize)]
+struct GetCells {
+    #[serde(default)]
+    x: usize,
+    #[serde(default)]
+    y: usize,
+    width: Option<usize>,
+    height: Option<usize>,
+}
+
+/// Parameters of `set_cells`, which sets the state of each of the cells.
+#[derive(Deserialize)]
+struct SetCells {
+    cells: Vec<[usize; 2]>,
+    #[serde(default = "default_alive")]
+    alive: bool,
+}
+
+fn default_generations() -> u64 {
+    1
+}
+
+fn default_alive() -> bool {
+    true
+}
+
+/// Why a request failed, as a JSON-RPC error code and message.
+type RpcError = (i64, String);
+
+/// Serve a JSON-RPC API for driving a universe without a window, with one
+/// request and one response per line.
+///
+/// The methods are `load`, `step`, `get_cells`, `set_cells`, and
+/// `population`, and take their parameters by name, e.g.
+/// `{"jsonrpc": "2.0", "id": 1, "method": "step", "params": {"n": 10}}`.
+pub fn run(args: RpcArgs) -> Result<(), Error> {
+    let config = &args.simulation;
+    let universe = args.universe.unwrap_or((WINDOW_WIDTH / PIXELS_PER_CELL, WINDOW_HEIGHT / PIXELS_PER_CELL));
+    let seed = config.seed.unwrap_or_else(rand::random);
+    log::info!("Using seed {seed}");
+    let (rule, rule_name) = simulation_rule(config)?;
+    let mut simulation = Simulation::new(config, universe, rule, rule_name, config.noise)?;
+    simulation.reseed(seed, &config.soup()?)?;
+
+    let Some(addr) = args.listen else {
+        return serve(&mut simulation, io::stdin().lock(), io::stdout().lock()).map_err(Error::RpcServer);
+    };
+    let listener = TcpListener::bind(addr).map_err(Error::RpcServer)?;
+    log::info!("Listening for JSON-RPC connections on {addr}");
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                log::warn!("Failed to accept JSON-RPC connection: {err}");
+                continue;
+            },
+        };
+        let peer = stream.peer_addr().map_or_else(|_| "unknown peer".to_string(), |addr| addr.to_string());
+        log::info!("JSON-RPC client {peer} connected");
+        let reader = stream.try_clone().map(BufReader::new);
+        if let Err(err) = reader.and_then(|reader| serve(&mut simulation, reader, BufWriter::new(stream))) {
+            log::debug!("JSON-RPC connection to {peer} failed: {err}");
+        }
+        log::info!("JSON-RPC client {peer} disconnected");
+    }
+    Ok(())
+}
+
+/// Answer requests read from one client until it closes its end.
+fn serve(simulation: &mut Simulation, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, result) = match serde_json::from_str::<Value>(&line) {
+            Ok(value) => match serde_json::from_value::<Request>(value) {
+                Ok(request) => {
+                    let result = call(simulation, &request.method, request.params);
+                    // Notifications don't get a response, even if they fail.
+                    let Some(id) = request.id else { continue };
+                    (id, result)
+                },
+                Err(err) => (Value::Null, Err((INVALID_REQUEST, err.to_string()))),
+            },
+            Err(err) => (Value::Null, Err((PARSE_ERROR, err.to_string()))),
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => {
+                json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+            },
+        };
+        writeln!(writer, "{response}")?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Run a method on the universe, returning its result.
+fn call(simulation: &mut Simulation, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "load" => {
+            let Load { pattern, x, y } = params_of(params)?;
+            let pattern = Pattern::parse(&pattern).map_err(|err| (INVALID_PARAMS, err.to_string()))?;
+            simulation.load(pattern, x, y);
+            Ok(json!({ "population": simulation.grid.population() }))
+        },
+        "step" => {
+            let Step { n } = params_of(params)?;
+            for _ in 0..n {
+                simulation.tick().map_err(|err| (SIMULATION_ERROR, err.to_string()))?;
+                if simulation.needs_expansion() {
+                    simulation.expand();
+                }
+            }
+            Ok(json!({ "generation": simulation.generation, "population": simulation.grid.population() }))
+        },
+        "get_cells" => {
+            let GetCells { x, y, width, height } = params_of(params)?;
+            let grid = &simulation.grid;
+            let x_end = width.map_or(grid.width(), |width| x.saturating_add(width)).min(grid.width());
+            let y_end = height.map_or(grid.height(), |height| y.saturating_add(height)).min(grid.height());
+            let cells: Vec<[usize; 2]> = (y..y_end)
+                .flat_map(|y| (x..x_end).map(move |x| (x, y)))
+                .filter(|&(x, y)| grid.get(x, y))
+                .map(|(x, y)| [x, y])
+                .collect();
+            Ok(json!({ "width": grid.width(), "height": grid.height(), "cells": cells }))
+        },
+        "set_cells" => {
+            let SetCells { cells, alive } = params_of(params)?;
+            let grid = &mut simulation.grid;
+            if let Some([x, y]) = cells.iter().find(|[x, y]| *x >= grid.width() || *y >= grid.height()) {
+                let (width, height) = (grid.width(), grid.height());
+                return Err((INVALID_PARAMS, format!("({x}, {y}) is outside of the {width}x{height} grid")));
+            }
+            for [x, y] in cells {
+                grid.set(x, y, alive);
+            }
+            Ok(json!({ "population": grid.population() }))
+        },
+        "population" => Ok(json!(simulation.grid.population())),
+        _ => Err((METHOD_NOT_FOUND, format!("unknown method `{method}`"))),
+    }
+}
+
+/// Parse the parameters of a method, treating missing ones as an empty object
+/// so that methods whose parameters all have defaults can leave them out.
+fn params_of<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|err| (INVALID_PARAMS, err.to_string()))
+}
@@ -0,0 +1,359 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rand::Rng;
+use serde::Deserialize;
+
+pub use crate::core::{LifeLike, MultiStateRule, ParseRuleError, Rule};
+
+impl LifeLike {
+    /// Returns the rule with one randomly chosen birth or survival condition
+    /// toggled. Births with no alive neighbors are never toggled, since they
+    /// would fill the empty space around every pattern.
+    pub fn mutated(self, rng: &mut impl Rng) -> Self {
+        let (mut birth, mut survival) = self.conditions();
+        match rng.gen_range(1..18) {
+            count @ 1..=8 => birth[count] = !birth[count],
+            count => survival[count - 9] = !survival[count - 9],
+        }
+        Self::from_conditions(birth, survival)
+    }
+}
+
+/// A rule given by the next state of a cell for every combination of its own
+/// state and the states of its neighbors, so that it can express any
+/// two-state rule on the eight neighbors.
+#[derive(Clone)]
+pub struct LookupRule {
+    /// The next state of a cell, indexed by its state in the ninth bit and
+    /// the states of its neighbors in the eight bits below it, in the same
+    /// order as [`Rule::next_state`] takes them.
+    table: [bool; 512],
+}
+
+impl LookupRule {
+    /// Build the table by calling `next_state` for every neighborhood.
+    pub fn from_fn(mut next_state: impl FnMut(bool, &[bool; 8]) -> bool) -> Self {
+        let mut table = [false; 512];
+        for (index, next) in table.iter_mut().enumerate() {
+            let neighbors = std::array::from_fn(|neighbor| index & (1 << neighbor) != 0);
+            *next = next_state(index & 1 << 8 != 0, &neighbors);
+        }
+        Self { table }
+    }
+}
+
+impl Rule for LookupRule {
+    fn next_state(&self, alive: bool, neighbors: &[bool; 8]) -> bool {
+        let index = neighbors.iter().rev().fold(alive as usize, |index, &alive| index << 1 | alive as usize);
+        self.table[index]
+    }
+}
+
+/// One arrangement of live neighbors for each letter of Hensel notation, for
+/// each number of live neighbors up to four. The neighbors are ordered
+/// clockwise starting from the top. The letters for five to seven neighbors
+/// stand for the complements of the ones for three to one.
+const HENSEL: [&[(char, [bool; 8])]; 5] = {
+    const O: bool = true;
+    const X: bool = false;
+    [
+        &[],
+        &[('c', [X, O, X, X, X, X, X, X]), ('e', [O, X, X, X, X, X, X, X])],
+        &[
+            ('c', [X, O, X, O, X, X, X, X]),
+            ('e', [O, X, O, X, X, X, X, X]),
+            ('k', [O, X, X, O, X, X, X, X]),
+            ('a', [O, O, X, X, X, X, X, X]),
+            ('i', [O, X, X, X, O, X, X, X]),
+            ('n', [X, O, X, X, X, O, X, X]),
+        ],
+        &[
+            ('c', [X, O, X, O, X, O, X, X]),
+            ('e', [O, X, O, X, O, X, X, X]),
+            ('k', [O, X, O, X, X, O, X, X]),
+            ('a', [O, O, O, X, X, X, X, X]),
+            ('i', [O, O, X, X, X, X, X, O]),
+            ('n', [O, O, X, O, X, X, X, X]),
+            ('y', [O, X, X, O, X, O, X, X]),
+            ('q', [O, O, X, X, X, O, X, X]),
+            ('j', [O, O, X, X, X, X, O, X]),
+            ('r', [O, O, X, X, O, X, X, X]),
+        ],
+        &[
+            ('c', [X, O, X, O, X, O, X, O]),
+            ('e', [O, X, O, X, O, X, O, X]),
+            ('k', [O, O, X, O, X, X, O, X]),
+            ('a', [O, O, O, O, X, X, X, X]),
+            ('i', [O, O, X, O, O, X, X, X]),
+            ('n', [O, O, X, O, X, X, X, O]),
+            ('y', [O, O, X, O, X, O, X, X]),
+            ('q', [O, O, O, X, X, O, X, X]),
+            ('j', [O, O, X, X, O, X, O, X]),
+            ('r', [O, O, O, X, O, X, X, X]),
+            ('t', [O, O, X, X, O, X, X, O]),
+            ('w', [O, O, X, X, X, O, O, X]),
+            ('z', [O, O, X, X, O, O, X, X]),
+        ],
+    ]
+};
+
+/// Returns the letters of Hensel notation for the given number of live
+/// neighbors.
+fn hensel_letters(count: usize) -> impl Iterator<Item = char> {
+    HENSEL[count.min(8 - count)].iter().map(|&(letter, _)| letter)
+}
+
+/// Returns the letter of Hensel notation for an arrangement of live neighbors,
+/// ordered clockwise starting from the top, or `None` if all arrangements with
+/// as many live neighbors are alike, i.e. for none or all of them.
+fn hensel_letter(neighbors: [bool; 8]) -> Option<char> {
+    let count = neighbors.iter().filter(|&&alive| alive).count();
+    let neighbors = if count > 4 { neighbors.map(|alive| !alive) } else { neighbors };
+    HENSEL[count.min(8 - count)].iter().find_map(|&(letter, arrangement)| {
+        // Rotate by quarter turns and reflect across the vertical axis.
+        let symmetric = (0..8).any(|symmetry: usize| {
+            (0..8).all(|position: usize| {
+                let moved = (position + symmetry / 2 * 2) % 8;
+                let moved = if symmetry % 2 == 1 { (8 - moved) % 8 } else { moved };
+                arrangement[position] == neighbors[moved]
+            })
+        });
+        symmetric.then_some(letter)
+    })
+}
+
+/// An isotropic non-totalistic rule, where the next state of a cell depends on
+/// its current state and the arrangement of its live neighbors, up to
+/// rotations and reflections.
+#[derive(Clone)]
+pub struct Isotropic {
+    rule: LookupRule,
+    /// The rulestring the rule was parsed from, in Hensel notation.
+    rulestring: String,
+}
+
+impl Rule for Isotropic {
+    fn next_state(&self, alive: bool, neighbors: &[bool; 8]) -> bool {
+        self.rule.next_state(alive, neighbors)
+    }
+
+    fn rulestring(&self) -> Option<String> {
+        Some(self.rulestring.clone())
+    }
+}
+
+impl FromStr for Isotropic {
+    type Err = ParseRuleError;
+
+    /// Parse a rulestring in Hensel notation, e.g. `B2-a/S12`, where each
+    /// neighbor count can be followed by the letters of the arrangements it
+    /// applies to, or by `-` and the letters of the ones it doesn't. A count
+    /// without letters applies to every arrangement.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseRuleError(s.to_string());
+        let (birth, survival) = s.split_once('/').ok_or_else(invalid)?;
+        let birth = birth.strip_prefix(['B', 'b']).ok_or_else(invalid)?;
+        let survival = survival.strip_prefix(['S', 's']).ok_or_else(invalid)?;
+
+        // Whether each arrangement of live neighbors, as a bitmask of the
+        // neighbors clockwise from the top, is in the half of the rulestring.
+        let arrangements = |half: &str| -> Result<[bool; 256], ParseRuleError> {
+            let mut arrangements = [false; 256];
+            let mut chars = half.chars().peekable();
+            while let Some(digit) = chars.next() {
+                let count = digit.to_digit(10).filter(|&count| count <= 8).ok_or_else(invalid)? as usize;
+                let excluded = chars.next_if_eq(&'-').is_some();
+                let mut letters = Vec::new();
+                while let Some(letter) = chars.next_if(char::is_ascii_lowercase) {
+                    letters.push(letter);
+                }
+                if (excluded && letters.is_empty())
+                    || !letters.iter().all(|&letter| hensel_letters(count).any(|l| l == letter))
+                {
+                    return Err(invalid());
+                }
+                for (mask, included) in arrangements.iter_mut().enumerate() {
+                    let neighbors = std::array::from_fn(|position| mask & (1 << position) != 0);
+                    if mask.count_ones() as usize != count {
+                        continue;
+                    }
+                    let lettered = hensel_letter(neighbors).is_some_and(|letter| letters.contains(&letter));
+                    *included |= letters.is_empty() || lettered != excluded;
+                }
+            }
+            Ok(arrangements)
+        };
+        let (births, survivals) = (arrangements(birth)?, arrangements(survival)?);
+        let rule = LookupRule::from_fn(|alive, neighbors| {
+            // The neighbors start from the top-left, rather than the top.
+            let mask =
+                (0..8).filter(|&position| neighbors[(position + 1) % 8]).fold(0, |mask, position| mask | 1 << position);
+            if alive {
+                survivals[mask]
+            } else {
+                births[mask]
+            }
+        });
+        Ok(Self { rule, rulestring: format!("B{birth}/S{survival}") })
+    }
+}
+
+/// A rule where each cell weighs its own state and its neighbors' states by a
+/// 3x3 kernel, and the weighted sum of the live ones decides its next state.
+///
+/// It is loaded from TOML like:
+///
+/// ```toml
+/// kernel = [[1, 2, 1], [2, 0, 2], [1, 2, 1]]
+/// birth = [[5, 6]]
+/// survival = [[4, 7], [9, 9]]
+/// ```
+///
+/// where the center of the kernel is the weight of the cell itself, and
+/// `birth` and `survival` are inclusive ranges of the sums at which dead cells
+/// are born and live cells survive.
+#[derive(Clone, Deserialize)]
+pub struct Weighted {
+    kernel: [[f64; 3]; 3],
+    birth: Vec<[f64; 2]>,
+    survival: Vec<[f64; 2]>,
+}
+
+impl Rule for Weighted {
+    fn next_state(&self, alive: bool, neighbors: &[bool; 8]) -> bool {
+        // The row and column of each neighbor in the kernel.
+        const POSITIONS: [(usize, usize); 8] = [(0, 0), (0, 1), (0, 2), (1, 2), (2, 2), (2, 1), (2, 0), (1, 0)];
+
+        let sum: f64 = POSITIONS
+            .iter()
+            .zip(neighbors)
+            .chain([(&(1, 1), &alive)])
+            .filter(|(_, &alive)| alive)
+            .map(|(&(row, column), _)| self.kernel[row][column])
+            .sum();
+        let ranges = if alive { &self.survival } else { &self.birth };
+        ranges.iter().any(|&[min, max]| (min..=max).contains(&sum))
+    }
+}
+
+/// A rule that has been registered under a name.
+pub struct Registration {
+    /// The name used to select the rule, e.g. `highlife`.
+    pub name: String,
+    /// A short, human-readable description of the rule.
+    pub description: String,
+    /// The rule itself.
+    pub rule: Arc<dyn Rule>,
+}
+
+/// A collection of rules that can be selected by name.
+#[derive(Default)]
+pub struct Registry {
+    registrations: Vec<Registration>,
+}
+
+impl Registry {
+    /// Create a registry containing the built-in Life-like rules.
+    pub fn with_builtins() -> Self {
+        const BUILTINS: [(&str, &str, LifeLike); 6] = [
+            ("life", "Conway's Game of Life", LifeLike::LIFE),
+            ("highlife", "Like Life, with a self-replicating pattern", LifeLike::new(&[3, 6], &[2, 3])),
+            ("seeds", "Every live cell dies each generation", LifeLike::new(&[2], &[])),
+            (
+                "day-and-night",
+                "Symmetric under swapping alive and dead",
+                LifeLike::new(&[3, 6, 7, 8], &[3, 4, 6, 7, 8]),
+            ),
+            ("maze", "Grows maze-like corridors", LifeLike::new(&[3], &[1, 2, 3, 4, 5])),
+            ("replicator", "Every pattern eventually replicates", LifeLike::new(&[1, 3, 5, 7], &[1, 3, 5, 7])),
+        ];
+
+        let mut registry = Self::default();
+        for (name, description, rule) in BUILTINS {
+            registry.register(name, format!("{rule} - {description}"), rule);
+        }
+        registry
+    }
+
+    /// Add a rule to the registry. A rule registered with the same name as an
+    /// existing one replaces it.
+    pub fn register(&mut self, name: impl Into<String>, description: impl Into<String>, rule: impl Rule + 'static) {
+        let registration = Registration { name: name.into(), description: description.into(), rule: Arc::new(rule) };
+        match self.registrations.iter_mut().find(|existing| existing.name == registration.name) {
+            Some(existing) => *existing = registration,
+            None => self.registrations.push(registration),
+        }
+    }
+
+    /// Look up a rule by its registered name, falling back to parsing it as a
+    /// B/S rulestring, which may be in Hensel notation.
+    pub fn resolve(&self, name: &str) -> Result<Arc<dyn Rule>, ParseRuleError> {
+        if let Some(registration) = self.registrations.iter().find(|registration| registration.name == name) {
+            return Ok(Arc::clone(&registration.rule));
+        }
+        if let Ok(rule) = name.parse::<LifeLike>() {
+            return Ok(Arc::new(rule));
+        }
+        Ok(Arc::new(name.parse::<Isotropic>()?))
+    }
+
+    /// Returns every registered rule, in the order they were registered.
+    pub fn iter(&self) -> impl Iterator<Item = &Registration> {
+        self.registrations.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the neighbors with the given ones alive, in the order
+    /// [`Rule::next_state`] takes them, clockwise from the top-left.
+    fn neighbors(alive: &[usize]) -> [bool; 8] {
+        std::array::from_fn(|position| alive.contains(&position))
+    }
+
+    /// Every neighborhood, as the state of the cell and its neighbors.
+    fn neighborhoods() -> impl Iterator<Item = (bool, [bool; 8])> {
+        (0..512).map(|index: usize| (index & 1 << 8 != 0, std::array::from_fn(|position| index & 1 << position != 0)))
+    }
+
+    #[test]
+    fn totalistic_hensel_rule_is_life_like() {
+        let isotropic: Isotropic = "B3/S23".parse().unwrap();
+        for (alive, neighbors) in neighborhoods() {
+            assert_eq!(isotropic.next_state(alive, &neighbors), LifeLike::LIFE.next_state(alive, &neighbors));
+        }
+    }
+
+    #[test]
+    fn hensel_letters_select_arrangements() {
+        let adjacent: Isotropic = "B2a/S".parse().unwrap();
+        // The top and top-right neighbors are next to each other, while the
+        // top-right and bottom-right ones are corners with an edge between.
+        assert!(adjacent.next_state(false, &neighbors(&[1, 2])));
+        assert!(!adjacent.next_state(false, &neighbors(&[2, 4])));
+        // Rotations and reflections of an arrangement have the same letter.
+        assert!(adjacent.next_state(false, &neighbors(&[6, 7])));
+        assert!(adjacent.next_state(false, &neighbors(&[0, 7])));
+    }
+
+    #[test]
+    fn hensel_exclusion_removes_arrangements() {
+        let all: Isotropic = "B2/S".parse().unwrap();
+        let adjacent: Isotropic = "B2a/S".parse().unwrap();
+        let excluded: Isotropic = "B2-a/S".parse().unwrap();
+        for (alive, neighbors) in neighborhoods() {
+            let expected = all.next_state(alive, &neighbors) && !adjacent.next_state(alive, &neighbors);
+            assert_eq!(excluded.next_state(alive, &neighbors), expected);
+        }
+    }
+
+    #[test]
+    fn invalid_hensel_rulestrings_are_rejected() {
+        for rulestring in ["B2-/S", "B2z/S", "B9/S", "B3S23", "3/23"] {
+            assert!(rulestring.parse::<Isotropic>().is_err(), "{rulestring} parsed");
+        }
+    }
+}
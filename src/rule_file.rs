@@ -0,0 +1,665 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+
+use crate::rule::{LookupRule, MultiStateRule, Rule};
+
+/// Where each neighbor in a Golly rule table's Moore neighborhood (N, NE, E,
+/// SE, S, SW, W, NW) is in the neighbors given to [`crate::rule::Rule`].
+const TABLE_MOORE: [usize; 8] = [1, 2, 3, 4, 5, 6, 7, 0];
+
+/// Where each neighbor in a Golly rule table's von Neumann neighborhood (N, E,
+/// S, W) is in the neighbors given to [`crate::rule::Rule`].
+const TABLE_VON_NEUMANN: [usize; 4] = [1, 3, 5, 7];
+
+/// Where each neighbor in a Golly rule tree's Moore neighborhood (NW, NE, SW,
+/// SE, N, W, E, S) is in the neighbors given to [`crate::rule::Rule`].
+const TREE_MOORE: [usize; 8] = [0, 2, 6, 4, 1, 7, 3, 5];
+
+/// Where each neighbor in a Golly rule tree's von Neumann neighborhood (N, W,
+/// E, S) is in the neighbors given to [`crate::rule::Rule`].
+const TREE_VON_NEUMANN: [usize; 4] = [1, 7, 3, 5];
+
+/// The most states a rule file can give cells, since each state is a byte.
+const MAX_STATES: usize = 256;
+
+/// The most neighborhoods whose next state a table remembers at once, after
+/// which it forgets them and starts over.
+const CACHE_LIMIT: usize = 1 << 20;
+
+/// A rule loaded from a Golly `.rule` file.
+///
+/// Both `@TABLE` and `@TREE` sections are supported, for the Moore and von
+/// Neumann neighborhoods, with any number of states up to 256. A rule with two
+/// states steps the grid of dead and alive cells like any other, and one with
+/// more is reached through [`Rule::as_multi_state`], so that the states of the
+/// cells can be tracked alongside the grid.
+pub struct RuleFile {
+    /// The name from the `@RULE` line.
+    pub name: String,
+    states: usize,
+    transitions: Transitions,
+    /// The rule for cells in states 0 and 1, which is all of it for a rule
+    /// with two states.
+    lookup: LookupRule,
+}
+
+impl RuleFile {
+    /// Parse the contents of a `.rule` file. A table is used over a tree if
+    /// the file has both.
+    pub fn parse(text: &str) -> Result<Self, ParseRuleFileError> {
+        let mut name = None;
+        let mut sections: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut section = None;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if let Some(header) = line.strip_prefix('@') {
+                let (header, rest) = header.split_once(char::is_whitespace).unwrap_or((header, ""));
+                if header == "RULE" {
+                    name = Some(rest.trim().to_string());
+                }
+                section = Some(header);
+                sections.entry(header).or_default();
+            } else if let (Some(section), false) = (section, line.is_empty()) {
+                sections.entry(section).or_default().push(line);
+            }
+        }
+
+        let name = name.filter(|name| !name.is_empty()).ok_or_else(|| ParseRuleFileError::new("missing @RULE name"))?;
+        let (states, transitions) = match (sections.get("TABLE"), sections.get("TREE")) {
+            (Some(table), _) => parse_table(table)?,
+            (None, Some(tree)) => parse_tree(tree)?,
+            (None, None) => return Err(ParseRuleFileError::new("missing @TABLE or @TREE section")),
+        };
+        let lookup =
+            LookupRule::from_fn(|alive, neighbors| transitions.next(u8::from(alive), &neighbors.map(u8::from)) != 0);
+        Ok(Self { name, states, transitions, lookup })
+    }
+}
+
+impl Rule for RuleFile {
+    fn next_state(&self, alive: bool, neighbors: &[bool; 8]) -> bool {
+        self.lookup.next_state(alive, neighbors)
+    }
+
+    fn as_multi_state(&self) -> Option<&dyn MultiStateRule> {
+        (self.states > 2).then_some(self)
+    }
+}
+
+impl MultiStateRule for RuleFile {
+    fn states(&self) -> usize {
+        self.states
+    }
+
+    fn transition(&self, state: u8, neighbors: &[u8; 8]) -> u8 {
+        // Cells left in states the rule doesn't have, as by an earlier rule
+        // with more of them, count as being in state 0.
+        let known = |state: u8| if (state as usize) < self.states { state } else { 0 };
+        self.transitions.next(known(state), &neighbors.map(known))
+    }
+}
+
+/// The two ways a rule file can give the next state of a cell.
+enum Transitions {
+    Table(Table),
+    /// A decision tree over the states of the neighbors and then the cell
+    /// itself, as the level of each node and the node (or at the first level,
+    /// the state) that each state of its variable leads to.
+    Tree {
+        neighbors: &'static [usize],
+        nodes: Vec<(usize, Vec<usize>)>,
+        root: usize,
+    },
+}
+
+impl Transitions {
+    /// Returns the next state of a cell, given its state and those of its
+    /// neighbors in the order [`Rule::next_state`] takes them.
+    fn next(&self, state: u8, neighbors: &[u8; 8]) -> u8 {
+        match self {
+            Self::Table(table) => table.next(state, neighbors),
+            Self::Tree { neighbors: order, nodes, root } => {
+                let mut node = *root;
+                // The root is at the highest level, so the walk reaches the
+                // first level, and a state, at the cell itself.
+                for state in order.iter().map(|&neighbor| neighbors[neighbor]).chain([state]) {
+                    let (level, children) = &nodes[node];
+                    node = children[state as usize];
+                    if *level == 1 {
+                        return node as u8;
+                    }
+                }
+                0
+            },
+        }
+    }
+}
+
+/// The transitions of a `@TABLE` section, tried in order for each
+/// neighborhood.
+struct Table {
+    /// Where each neighbor of the table's neighborhood is in the neighbors
+    /// given to [`Rule::next_state`].
+    neighborhood: &'static [usize],
+    transitions: Vec<Transition>,
+    /// Whether every transition is symmetric under any rearrangement of the
+    /// neighbors, so that only how many of them are in each state matters.
+    permuted: bool,
+    /// The next state of each neighborhood looked up so far, by the state of
+    /// the cell and then of its neighbors in the table's order.
+    cache: Mutex<HashMap<[u8; 9], u8>>,
+}
+
+impl Table {
+    /// Returns the next state of a cell from the first transition that
+    /// matches its neighborhood, or its current state if none do.
+    fn next(&self, state: u8, neighbors: &[u8; 8]) -> u8 {
+        let len = self.neighborhood.len() + 1;
+        let mut cells = [0; 9];
+        cells[0] = state;
+        for (position, &neighbor) in self.neighborhood.iter().enumerate() {
+            cells[position + 1] = neighbors[neighbor];
+        }
+        if self.permuted {
+            cells[1..len].sort_unstable();
+        }
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(&next) = cache.get(&cells) {
+            return next;
+        }
+        let next = self.transitions.iter().find_map(|transition| transition.apply(&cells[..len])).unwrap_or(state);
+        if cache.len() >= CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(cells, next);
+        next
+    }
+}
+
+/// A transition of a table, such as `0,a,1,0,0,0,0,0,0,1`.
+struct Transition {
+    /// The states that each position matches: the cell, and then its
+    /// neighbors in the table's order.
+    inputs: Vec<Vec<u8>>,
+    /// The variable at each position that stands for the same state
+    /// everywhere it appears in the transition, as an index into the states
+    /// they are bound to.
+    bindings: Vec<Option<usize>>,
+    /// Every rearrangement of the neighbors that the table's symmetries make
+    /// equivalent, as the position each neighbor moves to, or `None` for every
+    /// rearrangement.
+    symmetries: Option<Vec<Vec<usize>>>,
+    next: Next,
+}
+
+/// The state a transition gives the cell.
+enum Next {
+    State(u8),
+    /// The state bound to a variable of the transition.
+    Variable(usize),
+}
+
+impl Transition {
+    /// Returns the next state of a cell if the transition matches it, given the
+    /// state of the cell and then of its neighbors in the table's order.
+    fn apply(&self, cells: &[u8]) -> Option<u8> {
+        let mut bound = vec![None; self.inputs.len()];
+        let matched = match &self.symmetries {
+            Some(symmetries) => symmetries.iter().any(|symmetry| {
+                bound.fill(None);
+                self.accepts(0, cells[0], &mut bound)
+                    && (1..self.inputs.len())
+                        .all(|position| self.accepts(position, cells[symmetry[position - 1] + 1], &mut bound))
+            }),
+            None => self.accepts(0, cells[0], &mut bound) && self.permuted(cells, 1, 0, &mut bound),
+        };
+        matched.then(|| match self.next {
+            Next::State(state) => state,
+            Next::Variable(variable) => {
+                bound[variable].expect("the variables of the next state are bound by the cells")
+            },
+        })
+    }
+
+    /// Returns whether the neighbors can be rearranged to match the positions
+    /// of the transition from the given one on, without using those in
+    /// `used` again, binding the variables of the transition on the way.
+    fn permuted(&self, cells: &[u8], position: usize, used: u32, bound: &mut Vec<Option<u8>>) -> bool {
+        if position == self.inputs.len() {
+            return true;
+        }
+        (1..cells.len()).any(|cell| {
+            if used & 1 << cell != 0 {
+                return false;
+            }
+            let before = bound.clone();
+            let matched = self.accepts(position, cells[cell], bound)
+                && self.permuted(cells, position + 1, used | 1 << cell, bound);
+            if !matched {
+                *bound = before;
+            }
+            matched
+        })
+    }
+
+    /// Returns whether a position of the transition matches a state, binding
+    /// its variable to the state if it hasn't been bound yet.
+    fn accepts(&self, position: usize, state: u8, bound: &mut [Option<u8>]) -> bool {
+        if !self.inputs[position].contains(&state) {
+            return false;
+        }
+        match self.bindings[position] {
+            Some(variable) => *bound[variable].get_or_insert(state) == state,
+            None => true,
+        }
+    }
+}
+
+/// Check that a rule has a number of states that cells can be in.
+fn check_states(states: usize) -> Result<(), ParseRuleFileError> {
+    match states {
+        2..=MAX_STATES => Ok(()),
+        states => {
+            Err(ParseRuleFileError::new(format!("rules must have from 2 to {MAX_STATES} states, but it has {states}")))
+        },
+    }
+}
+
+/// Compile the lines of a `@TABLE` section, returning the number of states
+/// along with the transitions. Neighborhoods that no transition matches keep
+/// their cell's state, and where several transitions match, the first one
+/// wins.
+fn parse_table(lines: &[&str]) -> Result<(usize, Transitions), ParseRuleFileError> {
+    let mut states = None;
+    let mut neighborhood: &[usize] = &TABLE_MOORE;
+    let mut symmetries = "none";
+    let mut variables: HashMap<&str, Vec<u8>> = HashMap::new();
+    let mut transitions = Vec::new();
+
+    for &line in lines {
+        if let Some(declaration) = line.strip_prefix("var ") {
+            let states = states.ok_or_else(|| ParseRuleFileError::new("n_states must come before variables"))?;
+            let (name, values) = declaration
+                .split_once('=')
+                .ok_or_else(|| ParseRuleFileError::new(format!("expected `var NAME={{...}}`, got `{line}`")))?;
+            let values = values
+                .trim()
+                .strip_prefix('{')
+                .and_then(|values| values.strip_suffix('}'))
+                .ok_or_else(|| ParseRuleFileError::new(format!("expected braces around the values of `{line}`")))?;
+            let mut expanded = Vec::new();
+            for value in values.split(',').map(str::trim) {
+                expanded.extend(resolve_value(value, states, &variables)?);
+            }
+            variables.insert(name.trim(), expanded);
+        } else if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" => {
+                    let count = value
+                        .parse()
+                        .map_err(|_| ParseRuleFileError::new(format!("`{value}` is not a number of states")))?;
+                    check_states(count)?;
+                    states = Some(count);
+                },
+                "neighborhood" if !transitions.is_empty() => {
+                    return Err(ParseRuleFileError::new("the neighborhood must come before transitions"));
+                },
+                "neighborhood" => {
+                    neighborhood = match value {
+                        "Moore" => &TABLE_MOORE,
+                        "vonNeumann" => &TABLE_VON_NEUMANN,
+                        _ => return Err(ParseRuleFileError::new(format!("unsupported neighborhood `{value}`"))),
+                    }
+                },
+                "symmetries" => symmetries = value,
+                key => return Err(ParseRuleFileError::new(format!("unknown table setting `{key}`"))),
+            }
+        } else {
+            let states = states.ok_or_else(|| ParseRuleFileError::new("n_states must come before transitions"))?;
+            let symmetries = symmetries_of(symmetries, neighborhood.len())?;
+            transitions.push(parse_transition(line, states, neighborhood.len(), symmetries, &variables)?);
+        }
+    }
+    let states = states.ok_or_else(|| ParseRuleFileError::new("missing n_states"))?;
+    let permuted = transitions.iter().all(|transition| transition.symmetries.is_none());
+    Ok((states, Transitions::Table(Table { neighborhood, transitions, permuted, cache: Mutex::default() })))
+}
+
+/// Returns the states a value in a transition or variable stands for, which is
+/// either a state or the name of a variable.
+fn resolve_value(
+    value: &str,
+    states: usize,
+    variables: &HashMap<&str, Vec<u8>>,
+) -> Result<Vec<u8>, ParseRuleFileError> {
+    match value.parse::<u8>() {
+        Ok(state) if (state as usize) < states => Ok(vec![state]),
+        Ok(state) => Err(ParseRuleFileError::new(format!("state {state} is out of range"))),
+        Err(_) => {
+            variables.get(value).cloned().ok_or_else(|| ParseRuleFileError::new(format!("unknown variable `{value}`")))
+        },
+    }
+}
+
+/// Returns every rearrangement of a neighborhood with `len` neighbors that the
+/// named symmetry makes equivalent, as the position each neighbor moves to.
+/// `None` stands for every rearrangement.
+fn symmetries_of(name: &str, len: usize) -> Result<Option<Vec<Vec<usize>>>, ParseRuleFileError> {
+    let rotations = |count: usize| -> Vec<Vec<usize>> {
+        (0..count)
+            .map(|rotation| (0..len).map(|position| (position + rotation * len / count) % len).collect())
+            .collect()
+    };
+    let reflected = |arrangements: Vec<Vec<usize>>| -> Vec<Vec<usize>> {
+        let reflections: Vec<Vec<usize>> = arrangements
+            .iter()
+            .map(|arrangement| arrangement.iter().map(|&position| (len - position) % len).collect())
+            .collect();
+        arrangements.into_iter().chain(reflections).collect()
+    };
+    Ok(Some(match (name, len) {
+        ("none", _) => rotations(1),
+        ("rotate2", _) => rotations(2),
+        ("rotate4", _) => rotations(4),
+        ("rotate8", 8) => rotations(8),
+        ("reflect_horizontal", _) => reflected(rotations(1)),
+        ("rotate4reflect", _) => reflected(rotations(4)),
+        ("rotate8reflect", 8) => reflected(rotations(8)),
+        ("permute", _) => return Ok(None),
+        _ => return Err(ParseRuleFileError::new(format!("unsupported symmetries `{name}` for this neighborhood"))),
+    }))
+}
+
+/// Parse a transition, such as `0,a,1,0,0,0,0,0,0,1`, for a neighborhood of
+/// `neighbors` cells.
+fn parse_transition(
+    line: &str,
+    states: usize,
+    neighbors: usize,
+    symmetries: Option<Vec<Vec<usize>>>,
+    variables: &HashMap<&str, Vec<u8>>,
+) -> Result<Transition, ParseRuleFileError> {
+    // States can be run together without commas when there are few of them.
+    let values: Vec<&str> =
+        if line.contains(',') { line.split(',').map(str::trim).collect() } else { line.matches(|_| true).collect() };
+    if values.len() != neighbors + 2 {
+        return Err(ParseRuleFileError::new(format!("expected {} states in transition `{line}`", neighbors + 2)));
+    }
+
+    // A variable that appears several times in a transition stands for the
+    // same state each time.
+    let mut repeated: Vec<&str> = values
+        .iter()
+        .copied()
+        .filter(|value| variables.contains_key(value) && values.iter().filter(|other| other == &value).count() > 1)
+        .collect();
+    repeated.sort_unstable();
+    repeated.dedup();
+    let binding = |value: &str| repeated.iter().position(|&name| name == value);
+
+    let (inputs, next) = values.split_at(neighbors + 1);
+    let next = match (binding(next[0]), resolve_value(next[0], states, variables)?.as_slice()) {
+        (Some(variable), _) => Next::Variable(variable),
+        (None, &[state]) => Next::State(state),
+        (None, _) => {
+            return Err(ParseRuleFileError::new(format!(
+                "the next state of `{line}` must be a state or a variable that the cells bind"
+            )))
+        },
+    };
+    Ok(Transition {
+        inputs: inputs.iter().map(|value| resolve_value(value, states, variables)).collect::<Result<_, _>>()?,
+        bindings: inputs.iter().map(|value| binding(value)).collect(),
+        symmetries,
+        next,
+    })
+}
+
+/// Compile the lines of a `@TREE` section, which describe a decision tree
+/// over the states of the neighbors and then the cell itself, returning the
+/// number of states along with the tree.
+fn parse_tree(lines: &[&str]) -> Result<(usize, Transitions), ParseRuleFileError> {
+    let mut states = None;
+    let mut neighbors = None;
+    let mut expected_nodes = None;
+    let mut nodes: Vec<(usize, Vec<usize>)> = Vec::new();
+
+    for &line in lines {
+        if let Some((key, value)) = line.split_once('=') {
+            let value: usize =
+                value.trim().parse().map_err(|_| ParseRuleFileError::new(format!("`{line}` is not a number")))?;
+            match key.trim() {
+                "num_states" => {
+                    check_states(value)?;
+                    states = Some(value);
+                },
+                "num_neighbors" => {
+                    neighbors = match value {
+                        8 => Some(&TREE_MOORE[..]),
+                        4 => Some(&TREE_VON_NEUMANN[..]),
+                        _ => return Err(ParseRuleFileError::new(format!("unsupported number of neighbors {value}"))),
+                    }
+                },
+                "num_nodes" => expected_nodes = Some(value),
+                key => return Err(ParseRuleFileError::new(format!("unknown tree setting `{key}`"))),
+            }
+            continue;
+        }
+
+        let states = states.ok_or_else(|| ParseRuleFileError::new("num_states must come before nodes"))?;
+        let numbers: Vec<usize> = line
+            .split_whitespace()
+            .map(|number| number.parse().map_err(|_| ParseRuleFileError::new(format!("invalid node `{line}`"))))
+            .collect::<Result<_, _>>()?;
+        let Some((&level, children)) = numbers.split_first().filter(|(_, children)| children.len() == states) else {
+            return Err(ParseRuleFileError::new(format!("expected a level and {states} children in node `{line}`")));
+        };
+        let valid = match level {
+            0 => false,
+            1 => children.iter().all(|&state| state < states),
+            _ => {
+                children.iter().all(|&child| nodes.get(child).is_some_and(|(child_level, _)| child_level + 1 == level))
+            },
+        };
+        if !valid {
+            return Err(ParseRuleFileError::new(format!("node `{line}` has invalid children")));
+        }
+        nodes.push((level, children.to_vec()));
+    }
+
+    let states = states.ok_or_else(|| ParseRuleFileError::new("missing num_states"))?;
+    let neighbors = neighbors.ok_or_else(|| ParseRuleFileError::new("missing num_neighbors"))?;
+    if let Some(expected) = expected_nodes.filter(|&expected| expected != nodes.len()) {
+        return Err(ParseRuleFileError::new(format!("expected {expected} nodes, found {}", nodes.len())));
+    }
+    let root = nodes.len().checked_sub(1).filter(|&root| nodes[root].0 == neighbors.len() + 1);
+    let root = root.ok_or_else(|| ParseRuleFileError::new("the last node must be the root of the tree"))?;
+    Ok((states, Transitions::Tree { neighbors, nodes, root }))
+}
+
+/// A rule file was malformed or describes a rule that isn't supported.
+#[derive(Debug)]
+pub struct ParseRuleFileError(String);
+
+impl ParseRuleFileError {
+    fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for ParseRuleFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRuleFileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::rule::{LifeLike, Rule};
+
+    /// Every neighborhood, as the state of the cell and its neighbors.
+    fn neighborhoods() -> impl Iterator<Item = (bool, [bool; 8])> {
+        (0..512).map(|index: usize| (index & 1 << 8 != 0, std::array::from_fn(|position| index & 1 << position != 0)))
+    }
+
+    #[test]
+    fn table_of_life() {
+        let text = "\
+@RULE LifeTable
+# Births and survivals, then every other live cell dies.
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+0,1,1,1,0,0,0,0,0,1
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+1,a,b,c,d,e,f,g,h,0
+";
+        let file = RuleFile::parse(text).unwrap();
+        assert_eq!(file.name, "LifeTable");
+        for (alive, neighbors) in neighborhoods() {
+            assert_eq!(file.next_state(alive, &neighbors), LifeLike::LIFE.next_state(alive, &neighbors));
+        }
+    }
+
+    #[test]
+    fn tree_of_parity() {
+        // The cell becomes the parity of itself and its four orthogonal
+        // neighbors, which doesn't depend on the order the tree visits them.
+        let text = "\
+@RULE Parity
+@TREE
+num_states=2
+num_neighbors=4
+num_nodes=9
+1 0 1
+1 1 0
+2 0 1
+2 1 0
+3 2 3
+3 3 2
+4 4 5
+4 5 4
+5 6 7
+";
+        let file = RuleFile::parse(text).unwrap();
+        for (alive, neighbors) in neighborhoods() {
+            let parity =
+                [alive, neighbors[1], neighbors[3], neighbors[5], neighbors[7]].iter().filter(|&&on| on).count();
+            assert_eq!(file.next_state(alive, &neighbors), parity % 2 == 1);
+        }
+    }
+
+    /// Wireworld, with electron heads in state 1, tails in state 2, and
+    /// conductors in state 3.
+    const WIREWORLD: &str = "\
+@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+var i={0,2,3}
+var j={i}
+var k={i}
+var l={i}
+var m={i}
+var n={i}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,i,j,k,l,m,n,a,1
+3,1,1,i,j,k,l,m,n,1
+";
+
+    #[test]
+    fn table_of_wireworld() {
+        let file = RuleFile::parse(WIREWORLD).unwrap();
+        let rule = file.as_multi_state().expect("Wireworld has four states");
+        assert_eq!(rule.states(), 4);
+        assert_eq!(rule.transition(1, &[3, 3, 0, 0, 0, 0, 0, 0]), 2);
+        assert_eq!(rule.transition(2, &[1, 1, 1, 0, 0, 0, 0, 0]), 3);
+        assert_eq!(rule.transition(3, &[0, 0, 0, 1, 0, 0, 0, 2]), 1);
+        assert_eq!(rule.transition(3, &[1, 0, 0, 0, 0, 3, 3, 1]), 1);
+        assert_eq!(rule.transition(3, &[1, 1, 0, 1, 0, 0, 0, 0]), 3);
+        assert_eq!(rule.transition(3, &[2, 3, 3, 0, 0, 0, 0, 0]), 3);
+        assert_eq!(rule.transition(0, &[1, 1, 0, 0, 0, 0, 0, 0]), 0);
+    }
+
+    #[test]
+    fn electrons_flow_along_wires() {
+        let file = RuleFile::parse(WIREWORLD).unwrap();
+        let rule = file.as_multi_state().unwrap();
+        let grid = Grid::new(6, 1);
+        let (next, states) = grid.step_states(rule, &[2, 1, 3, 3, 3, 0]);
+        assert_eq!(states, [3, 2, 1, 3, 3, 0]);
+        assert!((0..6).all(|x| next.get(x, 0) == (states[x] != 0)));
+    }
+
+    #[test]
+    fn tree_of_cycling_states() {
+        // Every cell moves on to the next of three states, whatever its
+        // neighbors are.
+        let text = "\
+@RULE Cycle
+@TREE
+num_states=3
+num_neighbors=4
+num_nodes=5
+1 1 2 0
+2 0 0 0
+3 1 1 1
+4 2 2 2
+5 3 3 3
+";
+        let file = RuleFile::parse(text).unwrap();
+        let rule = file.as_multi_state().unwrap();
+        for state in 0..3 {
+            assert_eq!(rule.transition(state, &[2, 1, 0, 2, 1, 0, 2, 1]), (state + 1) % 3);
+        }
+        // States the rule doesn't have count as state 0.
+        assert_eq!(rule.transition(7, &[9; 8]), 1);
+        // Dead and alive cells move on to states that are both alive.
+        assert!(file.next_state(false, &[true; 8]));
+        assert!(file.next_state(true, &[false; 8]));
+    }
+
+    #[test]
+    fn two_state_rules_step_the_grid_alone() {
+        let text = "@RULE Parity\n@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:permute\n0,1,0,0,0,1\n";
+        let file = RuleFile::parse(text).unwrap();
+        assert!(file.as_multi_state().is_none());
+        assert!(file.next_state(false, &[false, true, false, false, false, false, false, false]));
+        assert!(!file.next_state(false, &[true, false, false, false, false, false, false, false]));
+    }
+
+    #[test]
+    fn invalid_numbers_of_states_are_rejected() {
+        assert!(RuleFile::parse("@RULE One\n@TABLE\nn_states:1\n").is_err());
+        assert!(RuleFile::parse("@RULE Many\n@TABLE\nn_states:257\n").is_err());
+        assert!(RuleFile::parse("@RULE Unbound\n@TABLE\nn_states:3\nvar a={1,2}\n0,0,0,0,0,0,0,0,0,a\n").is_err());
+        assert!(RuleFile::parse("@TABLE\nn_states:2\n").is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use cgol2::{parse_dimensions, parse_probability};
+use clap::builder::RangedU64ValueParser;
+use clap::{ArgGroup, ArgMatches, Args, Parser};
+use winit::event_loop::{ControlFlow, EventLoopBuilder};
+
+use crate::app::App;
+use crate::camera::Follow;
+use crate::config::SimulationArgs;
+use crate::error::Error;
+use crate::live::LiveConfig;
+use crate::recording::Replay;
+use crate::render::CellShape;
+use crate::state::State;
+use crate::theme::StyleArgs;
+use crate::{
+    remote, resolve_rule, simulation_rule, Cli, ColorMode, Command, OnEnd, UserEvent, MAX_BRUSH_RADIUS,
+    MAX_STEPS_PER_FRAME, PIXELS_PER_CELL, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("compare").multiple(true)))]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub simulation: SimulationArgs,
+
+    #[arg(long, default_value_t = ColorMode::Monochrome)]
+    pub color_mode: ColorMode,
+
+    /// Shape to draw each live cell as.
+    #[arg(long, default_value_t = CellShape::Square)]
+    pub cell_shape: CellShape,
+
+    #[command(flatten)]
+    pub style: StyleArgs,
+
+    /// Number of pixels along the height and width of each cell. If it doesn't
+    /// evenly divide the window, the grid is centered in it with a margin.
+    #[arg(long, default_value_t = PIXELS_PER_CELL, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub cell_size: usize,
+
+    /// Pan the view to keep the live cells of the first board centered as
+    /// they move, so that drifting patterns stay in view, or also zoom out to
+    /// fit all of them as they grow. Press `T` to switch between following
+    /// the centroid, the bounding box, fitting them, and nothing, and `A` to
+    /// fit them once.
+    #[arg(long, value_name = "WHAT")]
+    pub follow: Option<Follow>,
+
+    /// Start in borderless fullscreen mode. Press F11 to toggle it at runtime.
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Show a side panel with controls for the speed, density, rule, and
+    /// color mode, and buttons to pause, step, reseed, and load patterns. The
+    /// birth and survival conditions of Life-like rules can be toggled there
+    /// while the simulation runs.
+    #[arg(long)]
+    pub panel: bool,
+
+    /// Run as a screensaver: fullscreen with the cursor and HUD hidden,
+    /// restarting boards when their runs end with a random built-in rule and
+    /// color mode, and exiting on any key press, click, or mouse movement.
+    #[arg(long, conflicts_with_all = ["on_end", "record", "replay"])]
+    pub screensaver: bool,
+
+    /// RLE or plaintext pattern file to stamp onto boards, `-` to read the
+    /// pattern from standard input, or the name of a bundled pattern. Press
+    /// `P` to start placing it, `R` to rotate it, `F` and `V` to flip it, and
+    /// click to stamp it. Press `B` to browse the bundled patterns instead,
+    /// and patterns can also be pasted from the clipboard with `Ctrl+V`.
+    #[arg(long)]
+    pub stamp: Option<PathBuf>,
+
+    /// Accept JSON commands over WebSocket connections to this local port.
+    #[arg(long)]
+    pub control_port: Option<u16>,
+
+    /// Serve Prometheus metrics over HTTP at this address, e.g.
+    /// `127.0.0.1:9184`.
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Play the births in each generation of the first board as notes on the
+    /// MIDI output port whose name contains this text, so that the board can
+    /// drive a synthesizer.
+    #[cfg(feature = "midi")]
+    #[arg(long, value_name = "PORT")]
+    pub midi: Option<String>,
+
+    /// TOML file with the channel, range of notes, scale, and velocities to
+    /// play with `--midi`.
+    #[cfg(feature = "midi")]
+    #[arg(long, value_name = "PATH", requires = "midi")]
+    pub midi_map: Option<PathBuf>,
+
+    /// Radius in cells of the brush that cells are painted with using the
+    /// mouse, which can be changed with the scroll wheel while Ctrl is held.
+    #[arg(long, default_value_t = 0, value_parser = RangedU64ValueParser::<usize>::new().range(..=MAX_BRUSH_RADIUS as u64))]
+    pub brush_radius: usize,
+
+    /// Number of generations to compute for each frame that is drawn, to fast
+    /// forward through long-lived patterns. Press `,` and `.` to halve and
+    /// double it at runtime.
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<u64>::new().range(1..=MAX_STEPS_PER_FRAME))]
+    pub steps_per_frame: u64,
+
+    /// Draw this many frames per second, computing as many generations for
+    /// each frame as fit in the time left over from drawing it. The number of
+    /// generations adjusts as the load changes.
+    #[arg(long, conflicts_with = "steps_per_frame", value_parser = RangedU64ValueParser::<u32>::new().range(1..))]
+    pub target_fps: Option<u32>,
+
+    /// Draw this many frames per second, computing `--steps-per-frame`
+    /// generations for each. Defaults to 10.
+    #[arg(long, conflicts_with = "target_fps", value_parser = RangedU64ValueParser::<u32>::new().range(1..))]
+    pub fps: Option<u32>,
+
+    /// TOML file of settings to apply over the flags: `rule`, `color_mode`,
+    /// `frames_per_second`, `steps_per_frame`, `alive_color`, `dead_color`,
+    /// and `grid_color`. The file is watched, and changes to it are applied
+    /// without restarting, as are changes to a `--theme` file.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Size of each board's universe in cells, given as `WIDTHxHEIGHT`.
+    /// Defaults to filling the window; larger universes can be explored by
+    /// zooming with the mouse wheel or `+`/`-` and panning with the arrow keys.
+    #[arg(long, value_parser = parse_dimensions)]
+    pub universe: Option<(usize, usize)>,
+
+    /// Run several independent boards side by side, given as
+    /// `COLUMNSxROWS`. Each board is seeded differently.
+    #[arg(long, default_value = "1x1", value_parser = parse_dimensions)]
+    pub boards: (usize, usize),
+
+    /// Compare two boards side by side, started from the same seed, where the
+    /// second one uses this rule instead of `--rule`.
+    #[arg(long, group = "compare", conflicts_with = "boards")]
+    pub compare_rule: Option<String>,
+
+    /// Compare two boards side by side, started from the same seed, where the
+    /// second one uses this noise instead of `--noise`.
+    #[arg(long, group = "compare", conflicts_with = "boards", value_parser = parse_probability)]
+    pub compare_noise: Option<f64>,
+
+    /// What to do when a board goes extinct, settles into still lifes and
+    /// oscillators, or reaches `--max-generations`. Without this, the
+    /// simulation exits once every board is extinct.
+    #[arg(long)]
+    pub on_end: Option<OnEnd>,
+
+    /// Mark dead cells that have been alive before in the theme's history
+    /// color, to show how far a pattern has reached. Press `H` to toggle it at
+    /// runtime.
+    #[arg(long)]
+    pub history: bool,
+
+    /// Tint cells that were just born in the theme's birth color and mark
+    /// those that just died in its death color, so that the active fronts of
+    /// a pattern stand out from its still ash. Press `D` to toggle it at
+    /// runtime.
+    #[arg(long)]
+    pub delta: bool,
+
+    /// Fraction of each birth and death that is still shown in the delta view
+    /// a generation later, so that they fade out over several generations
+    /// rather than only showing those of the last one.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_probability)]
+    pub delta_decay: f64,
+
+    /// Outline gliders and other common spaceships, with an arrow in the
+    /// direction they're moving, when running Conway's Game of Life. Press `G`
+    /// to toggle it at runtime.
+    #[arg(long)]
+    pub spaceships: bool,
+
+    /// When comparing boards, draw the cells that are alive on only one of
+    /// them in red.
+    #[arg(long, requires = "compare")]
+    pub highlight_divergence: bool,
+
+    /// When comparing boards, draw both in one full-size board rather than
+    /// side by side, with cells alive on the first in the theme's first layer
+    /// color, those alive on the second in its second, and those alive on
+    /// both in the two combined.
+    #[arg(long, requires = "compare", conflicts_with = "highlight_divergence")]
+    pub overlay: bool,
+
+    /// When comparing boards, count the live cells of the first board as
+    /// live neighbors of the second's cells too, so that the first drives the
+    /// second without being changed by it.
+    #[arg(long, requires = "compare")]
+    pub couple: bool,
+
+    /// Compare every board against this snapshot or pattern file, drawing
+    /// cells that are only alive on the board in the theme's birth color and
+    /// cells that are only alive in the file in its death color. Patterns are
+    /// placed at the center of the universe, like `--pattern`.
+    #[arg(long, value_name = "PATH")]
+    pub diff_against: Option<PathBuf>,
+
+    /// Record the seed, settings, and every remote command to this file, so
+    /// that the run can be reproduced with `--replay`.
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Reproduce a run recorded with `--record`. Every other setting is taken
+    /// from the recording.
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// Start with the default window size, color mode, speed, and rule rather
+    /// than those of the last run. They are restored for any that aren't given
+    /// as flags otherwise, except when recording or replaying, so that
+    /// recordings replay the same way.
+    #[arg(long)]
+    pub fresh: bool,
+}
+
+impl RunArgs {
+    /// Returns whether two boards are being compared side by side.
+    pub fn comparing(&self) -> bool {
+        self.compare_rule.is_some() || self.compare_noise.is_some()
+    }
+}
+
+/// Ensure that every board in the layout is at least one cell wide and tall.
+fn check_board_layout(width: usize, height: usize, (columns, rows): (usize, usize)) -> Result<(), Error> {
+    if columns > width || rows > height {
+        return Err(Error::BoardLayout { columns, rows, width, height });
+    }
+    Ok(())
+}
+
+/// Report an error that happened inside the event loop and stop it.
+///
+/// The event loop never returns, so there is no way to hand the error back to
+/// `main`.
+fn exit_with_error(control_flow: &mut ControlFlow, err: Error) {
+    eprintln!("Error: {err}");
+    *control_flow = ControlFlow::ExitWithCode(1);
+}
+
+/// Open a window and run the simulation in it until it exits. `matches` are
+/// those the arguments were parsed from, which tell which flags were given.
+pub fn run(mut args: RunArgs, matches: &ArgMatches) -> Result<(), Error> {
+    let mut replay = None;
+    if let Some(path) = &args.replay {
+        let (seed, recorded_args, events) = Replay::load(path)?;
+        let invalid = |err: clap::Error| Error::InvalidRecording { path: path.clone(), message: err.to_string() };
+        args = match Cli::try_parse_from(recorded_args).map_err(invalid)?.command {
+            Command::Run(args) => *args,
+            _ => return Err(Error::InvalidRecording { path: path.clone(), message: "it isn't of a run".to_string() }),
+        };
+        args.simulation.seed = Some(seed);
+        // Commands from remote clients would make the replay diverge.
+        args.record = None;
+        args.control_port = None;
+        replay = Some(events);
+    }
+    // The screensaver shuffles its settings, and replays take theirs from the
+    // recording, so neither is worth saving.
+    let state_path = State::path().filter(|_| replay.is_none() && !args.screensaver);
+    let saved = match &state_path {
+        Some(path) if !args.fresh && args.record.is_none() => State::load(path),
+        _ => State::default(),
+    };
+    saved.restore(&mut args, matches);
+    let config = args.config.as_deref().map(LiveConfig::load).transpose()?.unwrap_or_default();
+    config.apply_to(&mut args);
+    if args.screensaver {
+        args.fullscreen = true;
+        args.on_end = Some(OnEnd::Restart);
+    }
+    // Choose the seed up front, so that it can be recorded.
+    args.simulation.seed = Some(args.simulation.seed.unwrap_or_else(rand::random));
+    let rule = simulation_rule(&args.simulation)?;
+    let compare_rule = args.compare_rule.as_deref().map(resolve_rule).transpose()?;
+
+    // Cells that don't fit evenly into the window leave a margin around the
+    // grid, rather than being cut off.
+    let cell_size = args.cell_size;
+    check_board_layout(WINDOW_WIDTH / cell_size, WINDOW_HEIGHT / cell_size, args.boards)?;
+
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+    let mut app = App::new(&args, rule, compare_rule, replay, (&saved, state_path), config, &event_loop)?;
+
+    if let Some(port) = args.control_port {
+        remote::serve(port, event_loop.create_proxy())?;
+    }
+
+    event_loop.run(move |event, _, control_flow| {
+        if let Err(err) = app.handle_event(event, control_flow) {
+            exit_with_error(control_flow, err);
+        }
+    });
+}
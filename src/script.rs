@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use cgol2::grid::Grid;
+use cgol2::rule::Rule;
+use mlua::{Function, IntoLuaMulti, Lua, UserData, UserDataMethods};
+
+use crate::error::Error;
+
+/// A Lua script that can hook into the simulation.
+///
+/// Scripts may define any of the following global functions:
+///
+/// - `on_init(grid)`, called once after the initial configuration is generated.
+/// - `on_tick(grid, generation)`, called after every tick.
+/// - `transition(alive, neighbors, x, y)`, which returns whether the cell at
+///   the given coordinates is alive in the next generation, replacing the rules
+///   of Conway's Game of Life.
+///
+/// The `grid` passed to hooks has `width()`, `height()`, `get(x, y)` and
+/// `set(x, y, alive)` methods. Coordinates start at 0, like everywhere else.
+pub struct Script {
+    lua: Lua,
+}
+
+impl Script {
+    /// Load and run the script at the given path, which defines its hooks.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let source =
+            std::fs::read_to_string(path).map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err })?;
+        let lua = Lua::new();
+        lua.load(source).set_name(path.display().to_string()).exec().map_err(Error::Script)?;
+        Ok(Self { lua })
+    }
+
+    /// Call the script's `on_init` hook, if it has one.
+    pub fn on_init(&self, grid: &mut Grid) -> Result<(), Error> {
+        self.call_grid_hook("on_init", grid, ())
+    }
+
+    /// Call the script's `on_tick` hook, if it has one.
+    pub fn on_tick(&self, grid: &mut Grid, generation: u64) -> Result<(), Error> {
+        self.call_grid_hook("on_tick", grid, generation)
+    }
+
+    /// Compute the next generation of the grid using the script's `transition`
+    /// function, or the given rule if it doesn't have one.
+    pub fn step(&self, grid: &Grid, rule: &dyn Rule) -> Result<Grid, Error> {
+        let Some(transition) = self.function("transition")? else { return Ok(grid.step(rule)) };
+        grid.try_step(|x, y, alive, alive_neighbors| transition.call((alive, alive_neighbors, x, y)))
+            .map_err(Error::Script)
+    }
+
+    /// Call a hook that takes the grid as its first argument, followed by any
+    /// extra arguments.
+    fn call_grid_hook<'lua>(
+        &'lua self,
+        name: &str,
+        grid: &mut Grid,
+        args: impl IntoLuaMulti<'lua>,
+    ) -> Result<(), Error> {
+        let Some(hook) = self.function(name)? else { return Ok(()) };
+        self.lua
+            .scope(|scope| {
+                let grid = scope.create_nonstatic_userdata(LuaGrid(grid))?;
+                hook.call::<_, ()>((grid, args))
+            })
+            .map_err(Error::Script)
+    }
+
+    /// Look up a global function defined by the script.
+    fn function(&self, name: &str) -> Result<Option<Function<'_>>, Error> {
+        self.lua.globals().get(name).map_err(Error::Script)
+    }
+}
+
+/// The view of a grid that is handed to Lua hooks.
+struct LuaGrid<'a>(&'a mut Grid);
+
+impl LuaGrid<'_> {
+    /// Ensure that the given coordinates are inside the grid.
+    fn check_bounds(&self, x: usize, y: usize) -> mlua::Result<()> {
+        if x >= self.0.width() || y >= self.0.height() {
+            let (width, height) = (self.0.width(), self.0.height());
+            return Err(mlua::Error::runtime(format!("({x}, {y}) is outside of the {width}x{height} grid")));
+        }
+        Ok(())
+    }
+}
+
+impl UserData for LuaGrid<'_> {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("width", |_, this, ()| Ok(this.0.width()));
+        methods.add_method("height", |_, this, ()| Ok(this.0.height()));
+        methods.add_method("get", |_, this, (x, y): (usize, usize)| {
+            this.check_bounds(x, y)?;
+            Ok(this.0.get(x, y))
+        });
+        methods.add_method_mut("set", |_, this, (x, y, alive): (usize, usize, bool)| {
+            this.check_bounds(x, y)?;
+            this.0.set(x, y, alive);
+            Ok(())
+        });
+    }
+}
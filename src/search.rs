@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use cgol2::analysis::{census, common_name};
+use cgol2::grid::Grid;
+use cgol2::parse_dimensions;
+use cgol2::rule::Rule;
+use cgol2::soup::random_soup;
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::config::SimulationArgs;
+use crate::error::Error;
+use crate::{simulation_rule, PIXELS_PER_CELL, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// The size of the random soup placed at the center of the universe, unless
+/// `--soup-size` is given.
+const SOUP_SIZE: (usize, usize) = (16, 16);
+
+/// The generations after which a soup that hasn't stabilized is given up on,
+/// unless `--max-generations` is given.
+const MAX_GENERATIONS: u64 = 10_000;
+
+#[derive(Args)]
+pub struct SearchArgs {
+    #[command(flatten)]
+    simulation: SimulationArgs,
+
+    /// Number of soups to evolve.
+    #[arg(long, default_value_t = 1000)]
+    soups: u64,
+
+    /// Size of the universe that each soup evolves in. Defaults to the size of
+    /// the grid in the GUI, so that hits replay identically there.
+    #[arg(long, value_parser = parse_dimensions)]
+    universe: Option<(usize, usize)>,
+
+    /// Report soups that take at least this many generations to stabilize.
+    #[arg(long, default_value_t = 1000)]
+    min_lifetime: u64,
+
+    /// Report soups that stabilize with at least this many live cells.
+    #[arg(long, default_value_t = 100)]
+    min_population: usize,
+
+    /// Number of worker threads. Defaults to the available parallelism.
+    #[arg(long)]
+    threads: Option<NonZeroUsize>,
+
+    /// After searching, print how many of each object the soups that settled
+    /// left behind, by apgcode, in the same way as apgsearch's census.
+    #[arg(long)]
+    census: bool,
+}
+
+/// The result of evolving a single soup until it stabilized.
+struct Outcome {
+    seed: u64,
+    /// The generation at which the soup entered a repeating cycle, or `None` if
+    /// it didn't stabilize within the maximum number of generations.
+    lifetime: Option<u64>,
+    /// The period of the cycle the soup stabilized into.
+    period: Option<u64>,
+    /// The number of live cells when the search stopped.
+    population: usize,
+    /// Whether any live cell ever reached the edge of the universe, which
+    /// usually means a glider or other spaceship escaped the soup.
+    escaped: bool,
+    /// The objects the soup settled into, by apgcode, if a census was asked
+    /// for.
+    census: Vec<(String, usize)>,
+}
+
+impl Outcome {
+    /// Returns the reasons this soup is interesting, if any.
+    fn reasons(&self, args: &SearchArgs) -> Vec<&'static str> {
+        let mut reasons = Vec::new();
+        if self.lifetime.is_none_or(|lifetime| lifetime >= args.min_lifetime) {
+            reasons.push("long-lived");
+        }
+        if self.population >= args.min_population {
+            reasons.push("high population");
+        }
+        if self.escaped {
+            reasons.push("escaped");
+        }
+        reasons
+    }
+}
+
+/// Search for random soups with unusual properties, printing each hit along
+/// with the command to replay it in the GUI.
+pub fn run(args: SearchArgs) -> Result<(), Error> {
+    if let Some(option) = unsupported(&args.simulation) {
+        return Err(Error::Unsupported { option, command: "search" });
+    }
+    let (rule, _) = simulation_rule(&args.simulation)?;
+    let base_seed = args.simulation.seed.unwrap_or_else(rand::random);
+    log::info!("Using base seed {base_seed}");
+    let threads = args.threads.or_else(|| std::thread::available_parallelism().ok()).map_or(1, NonZeroUsize::get);
+
+    let next = AtomicU64::new(0);
+    let hits = AtomicU64::new(0);
+    let census = Mutex::new(HashMap::<String, u64>::new());
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= args.soups {
+                    break;
+                }
+                if index > 0 && index.is_multiple_of(100) {
+                    log::debug!("Searched {index} soups");
+                }
+
+                let outcome = evolve(base_seed.wrapping_add(index), rule.as_ref(), &args);
+                if !outcome.census.is_empty() {
+                    let mut census = census.lock().unwrap_or_else(PoisonError::into_inner);
+                    for (code, count) in &outcome.census {
+                        *census.entry(code.clone()).or_default() += *count as u64;
+                    }
+                }
+                let reasons = outcome.reasons(&args);
+                if !reasons.is_empty() {
+                    hits.fetch_add(1, Ordering::Relaxed);
+                    report(&outcome, &reasons, &args);
+                }
+            });
+        }
+    });
+
+    log::info!("Searched {} soups, found {} hits", args.soups, hits.into_inner());
+    if args.census {
+        print_census(census.into_inner().unwrap_or_else(PoisonError::into_inner));
+    }
+    Ok(())
+}
+
+/// Returns the first option given that search can't honor, because it places
+/// cells other than at random or changes how the universe evolves past its
+/// rule, if any.
+fn unsupported(config: &SimulationArgs) -> Option<&'static str> {
+    let given = [
+        ("noise", config.noise > 0.0),
+        ("expand", config.expand),
+        ("place", !config.place.is_empty()),
+        ("seed-image", config.seed_image.is_some()),
+        ("pattern", config.pattern.is_some()),
+        ("seed-text", config.seed_text.is_some()),
+        ("species", config.species.is_some()),
+        ("drift", config.drift.is_some()),
+        ("zones", config.zones.is_some()),
+        ("mutate-rule-every", config.mutate_rule_every.is_some()),
+        ("inject", config.inject.is_some()),
+        ("script", config.script.is_some()),
+        ("methuselah", config.methuselah),
+        ("exec-on", !config.exec_on.is_empty()),
+        ("stats-stream", config.stats_stream.is_some()),
+        ("log-csv", config.log_csv.is_some()),
+        ("export-every", config.export_every.is_some()),
+    ];
+    given.into_iter().find(|&(_, given)| given).map(|(option, _)| option)
+}
+
+/// Evolve the soup with the given seed until it settles into a repeating cycle.
+fn evolve(seed: u64, rule: &dyn Rule, args: &SearchArgs) -> Outcome {
+    let config = &args.simulation;
+    let (width, height) = universe(args);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = Grid::new(width, height).with_boundary(config.boundary);
+    random_soup(
+        &mut grid,
+        Some(config.soup_size.unwrap_or(SOUP_SIZE)),
+        config.alive_random_chance,
+        config.fill,
+        config.fill_kind,
+        config.symmetry,
+        &mut rng,
+    );
+
+    // A soup that fills the whole universe touches the edge from the start, so
+    // escapes can't be detected.
+    let detect_escapes = !touches_edge(&grid);
+    let mut escaped = false;
+    let mut seen = HashMap::new();
+    for generation in 0..=config.max_generations.unwrap_or(MAX_GENERATIONS) {
+        if let Some(previous) = seen.insert(grid.digest(), generation) {
+            let census = if args.census { census(&grid, rule) } else { Vec::new() };
+            return Outcome {
+                seed,
+                lifetime: Some(previous),
+                period: Some(generation - previous),
+                population: grid.population(),
+                escaped,
+                census,
+            };
+        }
+        escaped |= detect_escapes && touches_edge(&grid);
+        grid = grid.step(rule);
+    }
+
+    Outcome { seed, lifetime: None, period: None, population: grid.population(), escaped, census: Vec::new() }
+}
+
+/// Returns the size of the universe each soup evolves in, which defaults to
+/// that of the window's grid, as in `run`.
+fn universe(args: &SearchArgs) -> (usize, usize) {
+    args.universe.unwrap_or((WINDOW_WIDTH / PIXELS_PER_CELL, WINDOW_HEIGHT / PIXELS_PER_CELL))
+}
+
+/// Print a hit to stdout.
+fn report(outcome: &Outcome, reasons: &[&str], args: &SearchArgs) {
+    let config = &args.simulation;
+    let lifetime = match outcome.lifetime {
+        Some(lifetime) => lifetime.to_string(),
+        None => format!(">{}", config.max_generations.unwrap_or(MAX_GENERATIONS)),
+    };
+    let period = outcome.period.map_or_else(|| "?".to_string(), |period| period.to_string());
+    let (soup_width, soup_height) = config.soup_size.unwrap_or(SOUP_SIZE);
+    let (width, height) = universe(args);
+    let rule = match (&config.rule_file, &config.kernel) {
+        (Some(path), _) => format!("--rule-file '{}'", path.display()),
+        (None, Some(path)) => format!("--kernel '{}'", path.display()),
+        (None, None) => format!("--rule {}", config.rule),
+    };
+
+    // Lock stdout so that reports from different workers don't interleave.
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(
+        stdout,
+        "seed {}: lifetime {lifetime}, period {period}, final population {} [{}]",
+        outcome.seed,
+        outcome.population,
+        reasons.join(", "),
+    );
+    let _ = writeln!(
+        stdout,
+        "  replay: {} run --seed {} {rule} --alive-random-chance {} --fill {} --fill-kind {} --symmetry {} \
+         --soup-size {soup_width}x{soup_height} --universe {width}x{height} --boundary '{}'",
+        env!("CARGO_BIN_NAME"),
+        outcome.seed,
+        config.alive_random_chance,
+        config.fill,
+        config.fill_kind,
+        config.symmetry,
+        config.boundary,
+    );
+}
+
+/// Print the objects left by every soup that settled, from the most common.
+fn print_census(census: HashMap<String, u64>) {
+    let mut census: Vec<(String, u64)> = census.into_iter().collect();
+    census.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let width = census.iter().map(|(code, _)| code.len()).max().unwrap_or(0);
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "Census:");
+    for (code, count) in census {
+        let name = common_name(&code).unwrap_or("");
+        let _ = writeln!(stdout, "{}", format!("  {code:width$}  {count:>8}  {name}").trim_end());
+    }
+}
+
+/// Returns whether any live cell is on the outermost edge of the grid.
+fn touches_edge(grid: &Grid) -> bool {
+    let (width, height) = (grid.width(), grid.height());
+    (0..width).any(|x| grid.get(x, 0) || grid.get(x, height - 1))
+        || (0..height).any(|y| grid.get(0, y) || grid.get(width - 1, y))
+}
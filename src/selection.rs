@@ -0,0 +1,38 @@
+/// A rectangular region of cells on one board, selected by dragging between
+/// two of its corners.
+#[derive(Clone, Copy)]
+pub struct Selection {
+    /// The index of the board the region is on.
+    pub board: usize,
+    /// The cell where the drag started.
+    anchor: (usize, usize),
+    /// The cell opposite the anchor, where the drag is or ended.
+    corner: (usize, usize),
+}
+
+impl Selection {
+    /// Select a single cell, which the selection can then be extended from.
+    pub fn new(board: usize, x: usize, y: usize) -> Self {
+        Self { board, anchor: (x, y), corner: (x, y) }
+    }
+
+    /// Move the corner opposite the cell the selection was started from.
+    pub fn extend_to(&mut self, x: usize, y: usize) {
+        self.corner = (x, y);
+    }
+
+    /// Move the whole region by the given number of cells.
+    pub fn translate(&mut self, dx: usize, dy: usize) {
+        self.anchor = (self.anchor.0 + dx, self.anchor.1 + dy);
+        self.corner = (self.corner.0 + dx, self.corner.1 + dy);
+    }
+
+    /// Returns the coordinates of the top-left cell of the region, and its
+    /// width and height in cells.
+    pub fn bounds(&self) -> ((usize, usize), (usize, usize)) {
+        let (x, y) = (self.anchor.0.min(self.corner.0), self.anchor.1.min(self.corner.1));
+        let width = self.anchor.0.abs_diff(self.corner.0) + 1;
+        let height = self.anchor.1.abs_diff(self.corner.1) + 1;
+        ((x, y), (width, height))
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+
+use cgol2::chunk::World;
+use cgol2::{parse_dimensions, snapshot};
+use clap::builder::RangedU64ValueParser;
+use clap::Args;
+
+use crate::config::SimulationArgs;
+use crate::error::Error;
+use crate::image::Style;
+use crate::render::CellShape;
+use crate::simulation::Simulation;
+use crate::stats::{CsvLog, StatsStream};
+use crate::theme::Theme;
+use crate::{simulation_rule, ColorMode, PIXELS_PER_CELL, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+#[derive(Args)]
+pub struct SimArgs {
+    #[command(flatten)]
+    simulation: SimulationArgs,
+
+    /// Size of the universe in cells, given as `WIDTHxHEIGHT`. Defaults to the
+    /// size of the grid in the window, so that runs match `run`.
+    #[arg(long, value_parser = parse_dimensions)]
+    universe: Option<(usize, usize)>,
+
+    /// Periodically save the universe to this snapshot file, and once more
+    /// when the run ends. Snapshots are memory-mapped and take one bit per
+    /// cell, so that huge universes can be checkpointed.
+    #[arg(long, value_name = "PATH")]
+    snapshot: Option<PathBuf>,
+
+    /// Number of generations between snapshots, or between saves of the
+    /// `--world`.
+    #[arg(long, default_value_t = 1000, value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    snapshot_every: u64,
+
+    /// Continue from a snapshot saved with `--snapshot`, instead of a random
+    /// configuration. The universe is the size of the snapshot.
+    #[arg(long, value_name = "PATH", conflicts_with = "universe")]
+    restore: Option<PathBuf>,
+
+    /// Keep the universe in a chunked world in this directory, saving it
+    /// periodically and once more when the run ends. Only the chunks that
+    /// changed are written. If the directory already holds a world, the run
+    /// continues from it, and the universe is the part of the world that has
+    /// ever held a live cell.
+    #[arg(long, value_name = "DIR", conflicts_with = "restore")]
+    world: Option<PathBuf>,
+}
+
+/// The most chunks of a `--world` to hold in memory at once.
+const WORLD_CACHE_CHUNKS: usize = 4096;
+
+/// Evolve a universe until it goes extinct, stagnates, or reaches
+/// `--max-generations`, then print how it ended.
+pub fn run(args: SimArgs) -> Result<(), Error> {
+    let config = &args.simulation;
+    let universe = args.universe.unwrap_or((WINDOW_WIDTH / PIXELS_PER_CELL, WINDOW_HEIGHT / PIXELS_PER_CELL));
+    let seed = config.seed.unwrap_or_else(rand::random);
+    log::info!("Using seed {seed}");
+
+    let (rule, rule_name) = simulation_rule(config)?;
+    let mut restored = args
+        .restore
+        .as_deref()
+        .map(|path| snapshot::read(path).map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err }))
+        .transpose()?;
+    // The cell of the world at the universe's top-left corner.
+    let mut origin = (0, 0);
+    let mut world = match &args.world {
+        Some(dir) if dir.join("index").exists() => {
+            let read_error = |err| Error::ReadFile { path: dir.clone(), source: err };
+            let mut world = World::open(dir, WORLD_CACHE_CHUNKS).map_err(read_error)?;
+            if let Some(((left, top), (right, bottom))) = world.bounds() {
+                let (width, height) = ((right - left + 1) as usize, (bottom - top + 1) as usize);
+                restored = Some((world.load(left, top, width, height).map_err(read_error)?, world.generation));
+                origin = (left, top);
+            }
+            Some(world)
+        },
+        Some(dir) => Some(
+            World::create(dir, WORLD_CACHE_CHUNKS)
+                .map_err(|err| Error::WriteFile { path: dir.clone(), source: err })?,
+        ),
+        None => None,
+    };
+    let universe = restored.as_ref().map_or(universe, |(grid, _)| (grid.width(), grid.height()));
+    let mut simulation = Simulation::new(config, universe, rule, rule_name, config.noise)?;
+    match restored {
+        Some((grid, generation)) => {
+            log::info!("Restored generation {generation}");
+            simulation.restore(grid, generation, seed);
+        },
+        None => simulation.reseed(seed, &config.soup()?)?,
+    }
+    let mut save_snapshot = |simulation: &Simulation, origin: (i64, i64)| -> Result<(), Error> {
+        if let (Some(world), Some(dir)) = (&mut world, &args.world) {
+            log::debug!("Saving generation {} to the world", simulation.generation);
+            world.generation = simulation.generation;
+            world
+                .store(&simulation.grid, origin.0, origin.1)
+                .and_then(|()| world.flush())
+                .map_err(|err| Error::WriteFile { path: dir.clone(), source: err })?;
+        }
+        let Some(path) = &args.snapshot else { return Ok(()) };
+        log::debug!("Saving a snapshot of generation {}", simulation.generation);
+        snapshot::write(path, &simulation.grid, simulation.generation)
+            .map_err(|err| Error::WriteFile { path: path.clone(), source: err })
+    };
+    let mut stats_stream = config.stats_stream.as_deref().map(StatsStream::create).transpose()?;
+    let mut csv_log = config.log_csv.as_deref().map(CsvLog::open).transpose()?;
+    let frame_export = config.frame_export(1)?;
+    // Images are drawn the way a default run in the window draws its board.
+    let theme = Theme::classic();
+    let style = Style {
+        theme: &theme,
+        color_mode: &ColorMode::Monochrome,
+        cell_shape: CellShape::Square,
+        cell_size: PIXELS_PER_CELL,
+    };
+    let end = loop {
+        if let Some(end) = simulation.end(config.max_generations, true) {
+            break end;
+        }
+        let stats = simulation.tick()?;
+        if simulation.needs_expansion() {
+            let (dx, dy) = simulation.expand();
+            origin = (origin.0 - dx as i64, origin.1 - dy as i64);
+        }
+        if let Some(stats_stream) = &mut stats_stream {
+            stats_stream.write(0, &stats)?;
+        }
+        if let Some(csv_log) = &mut csv_log {
+            csv_log.write(0, &stats)?;
+        }
+        if let Some(frame_export) = &frame_export {
+            frame_export.export(0, &simulation, &style)?;
+        }
+        if simulation.generation.is_multiple_of(args.snapshot_every) {
+            save_snapshot(&simulation, origin)?;
+        }
+    };
+    save_snapshot(&simulation, origin)?;
+    if let Some(stats_stream) = &mut stats_stream {
+        stats_stream.flush()?;
+    }
+    if let Some(csv_log) = &mut csv_log {
+        csv_log.flush()?;
+    }
+
+    // Keep stdout parseable when the statistics are streamed to it.
+    let summary = format!(
+        "Seed {seed} {end} at generation {} with {} live cells",
+        simulation.generation,
+        simulation.grid.population()
+    );
+    let mut report = simulation.report();
+    if let Some(methuselah) = &simulation.methuselah {
+        let metrics = serde_json::to_string(methuselah.metrics()).expect("metrics serialize to JSON");
+        report.push('\n');
+        report.push_str(&metrics);
+    }
+    if config.stats_stream.as_deref() == Some(Path::new("-")) {
+        eprintln!("{summary}\n{report}");
+    } else {
+        println!("{summary}\n{report}");
+    }
+    Ok(())
+}
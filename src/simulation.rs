@@ -0,0 +1,759 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+use cgol2::analysis::{census, common_name};
+use cgol2::grid::Grid;
+use cgol2::pattern::Pattern;
+use cgol2::rule::Rule;
+use cgol2::soup::{apply_noise, picture_soup, random_soup, Fill, FillKind, Injection, Picture, Symmetry};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::config::{Drift, SimulationArgs};
+use crate::delta::Delta;
+use crate::error::Error;
+use crate::hooks::Hooks;
+use crate::lineage::Lineage;
+use crate::methuselah::Methuselah;
+use crate::render::Rgb;
+use crate::script::Script;
+use crate::species::{Species, SPECIES};
+use crate::stats::{RunSummary, TickStats};
+use crate::theme::Theme;
+use crate::worker::{Pending, Worker};
+use crate::zones::Zones;
+use crate::{cycle_color, ColorMode};
+
+/// The longest period of oscillation that is detected as stagnation.
+const STAGNATION_PERIOD: usize = 30;
+
+/// Expanding universes grow when live cells come within this many cells of an
+/// edge.
+const EXPANSION_MARGIN: usize = 4;
+
+/// Mixed into the seed of each universe for the mutations of its lineage.
+const LINEAGE_SEED_SALT: u64 = 0x6C69_6E65_6167_6573;
+
+/// Mixed into the seed of each universe for where its species' colonies
+/// start.
+const SPECIES_SEED_SALT: u64 = 0x7370_6563_6965_7321;
+/// Mixed into the seed of each universe for how its rule mutates, so that
+/// mutations don't change how noise or injections play out.
+const MUTATION_SEED_SALT: u64 = 0x6D75_7461_7469_6F6E;
+
+/// Expanding universes stop growing once they would have more cells than this.
+const MAX_EXPANDED_CELLS: usize = 1 << 24;
+
+/// How the initial configuration of a universe is generated.
+pub struct Soup {
+    /// The size of the region at the center of the grid to fill, or the
+    /// whole grid if none is given.
+    pub size: Option<(usize, usize)>,
+    /// Probability that any given cell in the region starts alive.
+    pub chance: f64,
+    pub fill: Fill,
+    pub fill_kind: FillKind,
+    pub symmetry: Symmetry,
+    /// Patterns to paste over the random cells, walls and all, along with the
+    /// coordinates of their top-left corners.
+    pub patterns: Vec<(Pattern, (usize, usize))>,
+    /// A picture to seed the region from in place of random cells.
+    pub picture: Option<Picture>,
+    /// Whether the picture is dithered rather than thresholded.
+    pub dither: bool,
+    /// Cells of a pattern or text to center on the grid in place of random
+    /// cells.
+    pub centered: Option<Grid>,
+    /// The walls of the centered pattern, if it has any, which replace those
+    /// of the universe.
+    pub walls: Option<Grid>,
+}
+
+/// Why the run of a universe ended.
+#[derive(Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum End {
+    #[strum(serialize = "went extinct")]
+    Extinct,
+    #[strum(serialize = "stagnated")]
+    Stagnant,
+    #[strum(serialize = "reached the generation limit")]
+    GenerationLimit,
+}
+
+/// The state of a running universe, along with everything that influences how
+/// it advances from one generation to the next.
+pub struct Simulation {
+    pub grid: Grid,
+    /// The cells that are walls, if any have been built. Walls count as live
+    /// neighbors but never change, and cells brought to life on them die at
+    /// the next tick.
+    pub walls: Option<Grid>,
+    /// The number of ticks since the initial configuration.
+    pub generation: u64,
+    /// The rule used to compute each generation.
+    pub rule: Arc<dyn Rule>,
+    /// The name or rulestring the rule was selected with.
+    pub rule_name: String,
+    /// Whether timed ticks are suspended. The simulation can still be stepped
+    /// manually while paused.
+    pub paused: bool,
+    pub rng: StdRng,
+    /// Probability that any given cell flips its state after each tick.
+    pub noise: f64,
+    pub injection: Option<Injection>,
+    pub script: Option<Script>,
+    /// Whether the universe grows when live cells come close to its edges.
+    pub expand: bool,
+    /// Digests of the most recent generations, used to detect stagnation.
+    pub recent: VecDeque<u64>,
+    /// How many consecutive generations each cell has been alive for, in the
+    /// same order as the cells of the grid.
+    pub ages: Vec<u32>,
+    /// Whether each cell has ever been alive since the initial configuration,
+    /// in the same order as the cells of the grid.
+    pub history: Vec<bool>,
+    /// The colors that live cells have inherited, if lineage is tracked.
+    pub lineage: Option<Lineage>,
+    /// The species of live cells, if rival species compete.
+    pub species: Option<Species>,
+    /// The state of each cell, in the same order as the cells of the grid, if
+    /// the rule has more than two. The live cells of the grid are those in any
+    /// state but 0.
+    pub states: Option<Vec<u8>>,
+    /// Where cells were recently born and died, if the delta view is shown.
+    pub delta: Option<Delta>,
+    /// How the rule mutates, if it does.
+    pub mutation: Option<Mutation>,
+    /// The zones that follow rules other than the universe's, if it is
+    /// divided into any.
+    pub zones: Option<Zones>,
+    /// How far every cell moves, and how often, if the universe drifts.
+    pub drift: Option<Drift>,
+    /// How long the pattern takes to stabilize, if it is being timed.
+    pub methuselah: Option<Methuselah>,
+    /// The universe whose live cells also count as live neighbors of this
+    /// one's cells, as it was before this universe's next tick, if it is
+    /// coupled to one.
+    pub coupling: Option<Grid>,
+    /// Commands to run when notable events happen, if there are any.
+    hooks: Option<Hooks>,
+    /// Statistics about the run since the initial configuration.
+    pub summary: RunSummary,
+    /// The next generation, if it is being computed in the background.
+    pending: Option<Pending>,
+}
+
+impl Simulation {
+    /// Create an empty universe of the given size that evolves according to
+    /// the settings, but with the given rule and noise. Call `reseed` to fill
+    /// it.
+    pub fn new(
+        args: &SimulationArgs,
+        (width, height): (usize, usize),
+        rule: Arc<dyn Rule>,
+        rule_name: String,
+        noise: f64,
+    ) -> Result<Self, Error> {
+        let mutation = match (args.mutate_rule_every, rule.as_life_like()) {
+            (Some(every), Some(_)) => Some(Mutation::new(every, &rule_name)),
+            (Some(_), None) => {
+                log::warn!("Not mutating {rule_name}, since it isn't a Life-like rule");
+                None
+            },
+            (None, _) => None,
+        };
+        Ok(Self {
+            grid: Grid::new(width, height).with_boundary(args.boundary),
+            walls: None,
+            generation: 0,
+            rule,
+            rule_name,
+            paused: false,
+            rng: StdRng::seed_from_u64(0),
+            noise,
+            injection: args.inject.clone(),
+            script: args.script.as_deref().map(Script::load).transpose()?,
+            expand: args.expand,
+            recent: VecDeque::new(),
+            ages: vec![0; width * height],
+            history: vec![false; width * height],
+            lineage: None,
+            species: args.species.map(|count| Species::new(&Grid::new(width, height), count, 0)),
+            states: None,
+            delta: None,
+            mutation,
+            zones: args.zones((width, height))?,
+            drift: args.drift,
+            methuselah: args.methuselah.then(Methuselah::default),
+            coupling: None,
+            hooks: (!args.exec_on.is_empty()).then(|| Hooks::new(args.exec_on.clone())),
+            summary: RunSummary::new(0),
+            pending: None,
+        })
+    }
+
+    /// Replace the grid with a configuration generated from the seed,
+    /// and start counting generations from the beginning.
+    pub fn reseed(&mut self, seed: u64, soup: &Soup) -> Result<(), Error> {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.grid.clear();
+        match (&soup.picture, &soup.centered) {
+            (Some(picture), _) => picture_soup(&mut self.grid, soup.size, picture, soup.dither),
+            (None, Some(cells)) => {
+                // Cells that don't fit are cut off evenly on both sides.
+                let width = cells.width().min(self.grid.width());
+                let height = cells.height().min(self.grid.height());
+                let (x0, y0) = ((cells.width() - width) / 2, (cells.height() - height) / 2);
+                let (x, y) = ((self.grid.width() - width) / 2, (self.grid.height() - height) / 2);
+                self.grid.paste(&cells.crop(x0, y0, width, height), x, y);
+                if let Some(walls) = &soup.walls {
+                    let mut built = Grid::new(self.grid.width(), self.grid.height());
+                    built.paste(&walls.crop(x0, y0, width, height), x, y);
+                    self.walls = Some(built);
+                }
+            },
+            (None, None) => random_soup(
+                &mut self.grid,
+                soup.size,
+                soup.chance,
+                soup.fill,
+                soup.fill_kind,
+                soup.symmetry,
+                &mut self.rng,
+            ),
+        }
+        for (pattern, (x, y)) in &soup.patterns {
+            self.grid.paste(&pattern.cells, *x, *y);
+            if let Some(walls) = &pattern.walls {
+                let (width, height) = (self.grid.width(), self.grid.height());
+                self.walls.get_or_insert_with(|| Grid::new(width, height)).paste(walls, *x, *y);
+            }
+        }
+        if let Some(script) = &self.script {
+            script.on_init(&mut self.grid)?;
+        }
+        if let Some(walls) = &self.walls {
+            kill_walled_cells(&mut self.grid, walls);
+        }
+        self.generation = 0;
+        self.start_tracking();
+        if self.lineage.is_some() {
+            self.track_lineage(seed);
+        }
+        self.start_mutating(seed);
+        self.found_colonies(seed);
+        Ok(())
+    }
+
+    /// Replace the grid with one restored from a snapshot, continuing from
+    /// the generation it was taken at. The grid keeps this universe's
+    /// boundary, and random events are drawn from the seed.
+    pub fn restore(&mut self, grid: Grid, generation: u64, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.grid = grid.with_boundary(self.grid.boundary());
+        self.generation = generation;
+        self.start_tracking();
+        if self.lineage.is_some() {
+            self.track_lineage(seed);
+        }
+        self.found_colonies(seed);
+        self.start_mutating(seed);
+    }
+
+    /// Forget everything tracked about the cells in earlier generations, so
+    /// that tracking starts over from the current grid.
+    fn start_tracking(&mut self) {
+        self.recent.clear();
+        self.ages = vec![0; self.grid.width() * self.grid.height()];
+        self.history = vec![false; self.grid.width() * self.grid.height()];
+        self.record_history();
+        self.states = None;
+        self.adopt_states();
+        if let Some(delta) = &mut self.delta {
+            delta.clear();
+        }
+        self.summary = RunSummary::new(self.grid.population());
+        if let Some(hooks) = &mut self.hooks {
+            hooks.reset();
+        }
+        let life = self.is_life();
+        if let Some(methuselah) = &mut self.methuselah {
+            *methuselah = Methuselah::default();
+            methuselah.observe(&mut self.grid, self.generation, life);
+        }
+    }
+
+    /// Returns how many consecutive generations the cell at the given
+    /// coordinates has been alive for.
+    pub fn age(&self, x: usize, y: usize) -> u32 {
+        self.ages[y * self.grid.width() + x]
+    }
+
+    /// Returns the color of the live cell at the given coordinates: that of its
+    /// state if the rule has more than two, that of its species if rival
+    /// species compete, or else the one the color mode gives it.
+    pub fn cell_color(&self, x: usize, y: usize, theme: &Theme, color_mode: &ColorMode, cycle: Rgb) -> Rgb {
+        let width = self.grid.width();
+        // States are colored like species, from state 1 on.
+        let state = self.states.as_ref().map(|states| states[y * width + x]).filter(|&state| state > 0);
+        if let Some(state) = state {
+            return theme.species_color(state as usize - 1);
+        }
+        match self.species.as_ref().and_then(|species| species.species(x, y, width)) {
+            Some(species) => theme.species_color(species as usize),
+            None => {
+                let genome = self.lineage.as_ref().and_then(|lineage| lineage.genome(x, y, width));
+                color_mode.cell_color(theme, self.age(x, y), genome, cycle)
+            },
+        }
+    }
+
+    /// Returns the color that the cells share in the cycle color mode, which
+    /// only changes between generations, so it is worked out once per frame
+    /// rather than for every cell.
+    pub fn cycle_color(&self) -> Rgb {
+        cycle_color(self.generation, self.grid.population(), self.grid.width() * self.grid.height())
+    }
+
+    /// Returns whether the cell at the given coordinates has ever been alive.
+    pub fn was_alive(&self, x: usize, y: usize) -> bool {
+        self.history[y * self.grid.width() + x]
+    }
+
+    /// Start tracking the colors that cells inherit, with every live cell
+    /// starting its own family.
+    pub fn track_lineage(&mut self, seed: u64) {
+        self.lineage = Some(Lineage::new(&self.grid, seed ^ LINEAGE_SEED_SALT));
+    }
+
+    /// Split the live cells into new colonies of the same number of species,
+    /// if rival species compete.
+    fn found_colonies(&mut self, seed: u64) {
+        if let Some(count) = self.species.as_ref().map(Species::count) {
+            self.species = Some(Species::new(&self.grid, count, seed ^ SPECIES_SEED_SALT));
+        }
+    }
+
+    /// Start the timeline of mutated rules over from the current rule, if it
+    /// mutates.
+    fn start_mutating(&mut self, seed: u64) {
+        if let Some(mutation) = &mut self.mutation {
+            mutation.rng = StdRng::seed_from_u64(seed ^ MUTATION_SEED_SALT);
+            mutation.timeline = vec![(self.generation, self.rule_name.clone())];
+        }
+    }
+
+    /// Toggle a random condition of the rule, if it mutates and this is a
+    /// generation it mutates at.
+    fn mutate_rule(&mut self) {
+        let Some(mutation) = self.mutation.as_mut().filter(|mutation| self.generation.is_multiple_of(mutation.every))
+        else {
+            return;
+        };
+        let Some(rule) = self.rule.as_life_like() else { return };
+        let rule = rule.mutated(&mut mutation.rng);
+        log::info!("The rule mutated from {} to {rule} at generation {}", self.rule_name, self.generation);
+        self.rule = Arc::new(rule);
+        self.rule_name = rule.to_string();
+        mutation.timeline.push((self.generation, self.rule_name.clone()));
+    }
+
+    /// Move every cell by the drift, along with everything tracked about it,
+    /// if the universe drifts and this is a generation it drifts at.
+    fn drift(&mut self) {
+        let Some(drift) = self.drift.filter(|drift| self.generation.is_multiple_of(drift.every)) else { return };
+        let sources = self.grid.translation_sources(drift.dx, drift.dy);
+        self.grid = self.grid.translated(&sources);
+        if let Some(walls) = &mut self.walls {
+            *walls = walls.translated(&sources);
+        }
+        self.ages = translated(&self.ages, &sources);
+        self.history = translated(&self.history, &sources);
+        if let Some(lineage) = &mut self.lineage {
+            lineage.genomes = translated(&lineage.genomes, &sources);
+        }
+        if let Some(species) = &mut self.species {
+            species.species = translated(&species.species, &sources);
+        }
+        if let Some(states) = &mut self.states {
+            *states = translated(states, &sources);
+        }
+        if let Some(delta) = &mut self.delta {
+            delta.births = translated(&delta.births, &sources);
+            delta.deaths = translated(&delta.deaths, &sources);
+        }
+    }
+
+    /// Bring the states of the cells in line with the grid, if the rule has
+    /// more than two: cells that were brought to life, as by painting or
+    /// noise, start in state 1, and cells that died are in state 0. Starts
+    /// tracking the states, or stops, when the rule has changed to one that
+    /// has more than two or not.
+    fn adopt_states(&mut self) {
+        if self.rule.as_multi_state().is_none() {
+            self.states = None;
+            return;
+        }
+        let len = self.grid.width() * self.grid.height();
+        let states = self.states.get_or_insert_with(|| vec![0; len]);
+        if states.len() != len {
+            *states = vec![0; len];
+        }
+        let width = self.grid.width();
+        for (index, state) in states.iter_mut().enumerate() {
+            *state = match (self.grid.get(index % width, index / width), *state) {
+                (false, _) => 0,
+                (true, 0) => 1,
+                (true, state) => state,
+            };
+        }
+    }
+
+    /// Mark every cell that is currently alive as having been alive.
+    fn record_history(&mut self) {
+        let width = self.grid.width();
+        for (index, visited) in self.history.iter_mut().enumerate() {
+            *visited |= self.grid.get(index % width, index / width);
+        }
+    }
+
+    /// Returns why the run has ended, if it has. Paused universes never end,
+    /// and stagnation only ends a run if `stagnation` is set.
+    pub fn end(&self, max_generations: Option<u64>, stagnation: bool) -> Option<End> {
+        if self.paused {
+            None
+        } else if self.is_extinct() {
+            Some(End::Extinct)
+        } else if stagnation && (self.is_stagnant() || self.methuselah.as_ref().is_some_and(Methuselah::is_stable)) {
+            Some(End::Stagnant)
+        } else if max_generations.is_some_and(|max| self.generation >= max) {
+            Some(End::GenerationLimit)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether there are no cells left alive.
+    pub fn is_extinct(&self) -> bool {
+        self.grid.population() == 0
+    }
+
+    /// Returns whether the cell at the given coordinates is a wall.
+    pub fn is_wall(&self, x: usize, y: usize) -> bool {
+        self.walls.as_ref().is_some_and(|walls| walls.get(x, y))
+    }
+
+    /// Turn the given cells into walls, killing any that are alive.
+    pub fn build_walls(&mut self, cells: impl IntoIterator<Item = (usize, usize)>) {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let walls = self.walls.get_or_insert_with(|| Grid::new(width, height));
+        for (x, y) in cells {
+            walls.set(x, y, true);
+            self.grid.set(x, y, false);
+        }
+    }
+
+    /// Replace every cell and wall with those of a pattern, with its top-left
+    /// corner at the given position, which defaults to centering it on each
+    /// axis it isn't given for.
+    pub fn load(&mut self, pattern: Pattern, x: Option<usize>, y: Option<usize>) {
+        let grid = &mut self.grid;
+        let x = x.unwrap_or(grid.width().saturating_sub(pattern.cells.width()) / 2);
+        let y = y.unwrap_or(grid.height().saturating_sub(pattern.cells.height()) / 2);
+        grid.clear();
+        grid.paste(&pattern.cells, x, y);
+        self.walls = pattern.walls.map(|walls| {
+            let mut built = Grid::new(grid.width(), grid.height());
+            built.paste(&walls, x, y);
+            built
+        });
+    }
+
+    /// Knock down the walls at the given cells, leaving them dead.
+    pub fn demolish_walls(&mut self, cells: impl IntoIterator<Item = (usize, usize)>) {
+        let Some(walls) = &mut self.walls else { return };
+        for (x, y) in cells {
+            walls.set(x, y, false);
+        }
+        if walls.population() == 0 {
+            self.walls = None;
+        }
+    }
+
+    /// Returns the grid with every wall alive, so that the walls count as live
+    /// neighbors of the cells around them, if there are any walls.
+    fn walled_grid(&self) -> Option<Grid> {
+        let walls = self.walls.as_ref()?;
+        let mut grid = self.grid.clone();
+        let width = grid.width();
+        for (index, alive) in grid.cells_mut().enumerate() {
+            *alive |= walls.get(index % width, index / width);
+        }
+        Some(grid)
+    }
+
+    /// Returns whether the grid has settled into a still life or an
+    /// oscillator, by having repeated one of its recent states.
+    pub fn is_stagnant(&self) -> bool {
+        self.period().is_some()
+    }
+
+    /// Returns the period of the still life or oscillator that the grid has
+    /// settled into, if it has repeated one of its recent states.
+    pub fn period(&self) -> Option<usize> {
+        let latest = self.recent.back()?;
+        self.recent.iter().rev().skip(1).position(|digest| digest == latest).map(|steps| steps + 1)
+    }
+
+    /// Returns whether the universe evolves according to Conway's Game of
+    /// Life.
+    fn is_life(&self) -> bool {
+        self.rule.rulestring().as_deref() == Some("B3/S23")
+    }
+
+    /// Returns a human-readable report of the run so far. Runs of Conway's
+    /// Game of Life that have settled also get a census of the objects left.
+    pub fn report(&self) -> String {
+        let mut report = self.summary.report(self.generation, self.grid.population(), self.period());
+        if self.is_stagnant() && !self.is_extinct() && self.is_life() {
+            let census = census(&self.grid, self.rule.as_ref());
+            let width = census.iter().map(|(code, _)| code.len()).max().unwrap_or(0);
+            report.push_str("\nCensus:");
+            for (code, count) in census {
+                let name = common_name(&code).unwrap_or("");
+                report.push_str(format!("\n  {code:width$}  {count:>5}  {name}").trim_end());
+            }
+        }
+        if let Some(methuselah) = &self.methuselah {
+            report.push_str(&format!("\nMethuselah: {}", methuselah.metrics()));
+        }
+        if let Some(species) = &self.species {
+            report.push_str(&format!("\nSpecies: {}", species_populations(species)));
+        }
+        if let Some(mutation) = &self.mutation {
+            report.push_str("\nRules:");
+            for (generation, rule) in &mutation.timeline {
+                report.push_str(&format!("\n  {generation:>8}  {rule}"));
+            }
+        }
+        report
+    }
+
+    /// Returns whether the universe should grow, because it is expanding and
+    /// live cells have come close to its edges.
+    pub fn needs_expansion(&self) -> bool {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        self.expand
+            && width.saturating_mul(2).saturating_mul(height.saturating_mul(2)) <= MAX_EXPANDED_CELLS
+            && self.grid.near_edge(EXPANSION_MARGIN)
+    }
+
+    /// Double the size of the universe, keeping its cells at the center, and
+    /// return how many cells they moved right and down.
+    pub fn expand(&mut self) -> (usize, usize) {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let offset = (width / 2, height / 2);
+        self.grid = self.grid.resized(width * 2, height * 2, offset.0, offset.1);
+        if let Some(walls) = &mut self.walls {
+            *walls = walls.resized(width * 2, height * 2, offset.0, offset.1);
+        }
+        self.ages = recentered(&self.ages, width, offset);
+        self.history = recentered(&self.history, width, offset);
+        if let Some(lineage) = &mut self.lineage {
+            lineage.genomes = recentered(&lineage.genomes, width, offset);
+        }
+        if let Some(species) = &mut self.species {
+            species.species = recentered(&species.species, width, offset);
+        }
+        if let Some(states) = &mut self.states {
+            *states = recentered(states, width, offset);
+        }
+        // The zones are laid out over the whole of the larger universe.
+        if let Some(zones) = &mut self.zones {
+            zones.resize((width * 2, height * 2));
+        }
+        if let Some(delta) = &mut self.delta {
+            delta.births = recentered(&delta.births, width, offset);
+            delta.deaths = recentered(&delta.deaths, width, offset);
+        }
+        // The digests of smaller grids can never match again.
+        self.recent.clear();
+        if let Some(methuselah) = &mut self.methuselah {
+            methuselah.forget();
+        }
+        log::debug!("Expanded the universe to {}x{} at generation {}", width * 2, height * 2, self.generation);
+        offset
+    }
+
+    /// Start computing the next generation on the worker, so that the next
+    /// tick can use it instead of computing it then. It is thrown away if the
+    /// grid changes before then. Scripted, zoned, and coupled generations,
+    /// those of universes with walls, and those of rules with more than two
+    /// states are always computed when ticking.
+    pub fn prefetch(&mut self, worker: &Worker) {
+        if self.script.is_none()
+            && self.zones.is_none()
+            && self.walls.is_none()
+            && self.coupling.is_none()
+            && self.rule.as_multi_state().is_none()
+        {
+            self.pending = Some(worker.step(self.grid.clone(), Arc::clone(&self.rule)));
+        }
+    }
+
+    /// Advance the simulation by one generation, returning statistics about
+    /// the tick.
+    pub fn tick(&mut self) -> Result<TickStats, Error> {
+        let start = Instant::now();
+        // Cells may have been brought to life since the last tick.
+        self.record_history();
+        if let Some(lineage) = &mut self.lineage {
+            lineage.adopt(&self.grid);
+        }
+        if let Some(species) = &mut self.species {
+            species.adopt(&self.grid);
+        }
+        self.adopt_states();
+        let step_start = Instant::now();
+        // Walls are stepped as live cells, and killed again afterwards.
+        let walled = self.walled_grid();
+        let grid = walled.as_ref().unwrap_or(&self.grid);
+        let pending = self.pending.take().filter(|_| walled.is_none());
+        // The coupled universe is ignored while the two differ in size, as
+        // for the tick in which one has expanded and the other not yet.
+        let coupling =
+            self.coupling.as_ref().filter(|other| (other.width(), other.height()) == (grid.width(), grid.height()));
+        let multi_state = self.rule.as_multi_state().zip(self.states.as_deref());
+        let mut next_grid = match (&self.script, &self.zones, coupling) {
+            (Some(script), ..) => script.step(grid, self.rule.as_ref())?,
+            (None, Some(zones), _) => zones.step(grid, self.rule.as_ref()),
+            // Walls are left out of the states, so they never change them.
+            (None, None, _) if multi_state.is_some() => {
+                let (rule, states) = multi_state.expect("the rule has more than two states");
+                let (next_grid, next_states) = self.grid.step_states(rule, states);
+                self.states = Some(next_states);
+                next_grid
+            },
+            (None, None, Some(other)) => grid.step_coupled(self.rule.as_ref(), other),
+            (None, None, None) => match pending.and_then(|pending| pending.finish(grid, &self.rule)) {
+                Some(next_grid) => next_grid,
+                None => grid.step(self.rule.as_ref()),
+            },
+        };
+        if let Some(species) = &mut self.species {
+            species.contest(&self.grid, &mut next_grid);
+        }
+        let step_duration = step_start.elapsed();
+        if self.noise > 0.0 {
+            apply_noise(&mut next_grid, self.noise, &mut self.rng);
+        }
+
+        self.generation += 1;
+        if let Some(injection) = &self.injection {
+            if self.generation.is_multiple_of(injection.every) {
+                log::debug!("Injecting random cells at generation {}", self.generation);
+                injection.apply(&mut next_grid, &mut self.rng);
+            }
+        }
+        self.mutate_rule();
+        if let Some(walls) = &self.walls {
+            kill_walled_cells(&mut next_grid, walls);
+        }
+
+        let (births, deaths) = self.grid.births_and_deaths(&next_grid);
+        if let Some(lineage) = &mut self.lineage {
+            lineage.inherit(&self.grid, &next_grid);
+        }
+        if let Some(delta) = &mut self.delta {
+            delta.record(&self.grid, &next_grid);
+        }
+        self.grid = next_grid;
+        if let Some(script) = &self.script {
+            script.on_tick(&mut self.grid, self.generation)?;
+        }
+
+        // Cells brought to life by noise, injections, or the script join the
+        // species around them.
+        if let Some(species) = &mut self.species {
+            species.adopt(&self.grid);
+        }
+        self.adopt_states();
+
+        let width = self.grid.width();
+        for (index, age) in self.ages.iter_mut().enumerate() {
+            *age = if self.grid.get(index % width, index / width) { age.saturating_add(1) } else { 0 };
+        }
+        self.record_history();
+        self.drift();
+        let life = self.is_life();
+        if let Some(methuselah) = &mut self.methuselah {
+            methuselah.observe(&mut self.grid, self.generation, life);
+        }
+
+        if self.recent.len() > STAGNATION_PERIOD {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(self.grid.digest());
+
+        let population = self.grid.population();
+        let stats = TickStats::new(self.generation, population, births, deaths, start.elapsed(), step_duration);
+        let record = self.summary.record(&stats);
+        let period = self.period();
+        if let Some(hooks) = &mut self.hooks {
+            hooks.observe(self.generation, population, period, record, &self.rule_name);
+        }
+        Ok(stats)
+    }
+}
+
+/// How the rule of a universe mutates as it runs, and the rules it has had.
+pub struct Mutation {
+    /// Number of generations between mutations.
+    every: u64,
+    /// Mutations are drawn from their own generator, reseeded along with the
+    /// universe.
+    rng: StdRng,
+    /// Each rule the universe has had since the initial configuration, along
+    /// with the generation it took over at.
+    pub timeline: Vec<(u64, String)>,
+}
+
+impl Mutation {
+    fn new(every: u64, rule_name: &str) -> Self {
+        Self { every, rng: StdRng::seed_from_u64(MUTATION_SEED_SALT), timeline: vec![(0, rule_name.to_string())] }
+    }
+}
+
+/// Kill the live cells of a grid that are on walls.
+fn kill_walled_cells(grid: &mut Grid, walls: &Grid) {
+    let width = grid.width();
+    for (index, alive) in grid.cells_mut().enumerate() {
+        *alive &= !walls.get(index % width, index / width);
+    }
+}
+
+/// Returns the population of each species, like `red 120, blue 98`.
+pub fn species_populations(species: &Species) -> String {
+    let populations = species.populations().into_iter().zip(SPECIES);
+    populations.map(|(population, (name, _))| format!("{name} {population}")).collect::<Vec<_>>().join(", ")
+}
+
+/// Returns per-cell values with each value taken from the given source, as
+/// returned by [`Grid::translation_sources`], and the cells without one set to
+/// the default.
+fn translated<T: Copy + Default>(values: &[T], sources: &[Option<usize>]) -> Vec<T> {
+    sources.iter().map(|source| source.map_or_else(T::default, |source| values[source])).collect()
+}
+
+/// Returns per-cell values for a grid that has doubled in size from `width`
+/// cells wide, with the old values moved right and down by `offset` cells and
+/// the new cells around them set to the default.
+fn recentered<T: Copy + Default>(values: &[T], width: usize, offset: (usize, usize)) -> Vec<T> {
+    let mut recentered = vec![T::default(); values.len() * 4];
+    for (index, &value) in values.iter().enumerate() {
+        let (x, y) = (index % width + offset.0, index / width + offset.1);
+        recentered[y * width * 2 + x] = value;
+    }
+    recentered
+}
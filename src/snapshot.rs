@@ -0,0 +1,108 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::path::Path;
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::grid::Grid;
+
+/// Identifies a file as a snapshot.
+const MAGIC: &[u8; 8] = b"CGOLSNAP";
+
+/// The version of the format, which changes whenever the layout does.
+const VERSION: u32 = 1;
+
+/// The size of the header: the magic bytes, the version, four bytes of
+/// padding, and then the width, height, and generation as 64-bit integers.
+const HEADER_LEN: usize = 40;
+
+/// Returns the number of bytes each row of a grid of the given width takes.
+fn row_len(width: usize) -> usize {
+    width.div_ceil(8)
+}
+
+/// Write a snapshot of a grid at the given generation, for checkpointing
+/// universes too large to comfortably hold twice in memory.
+///
+/// A snapshot is a header followed by the rows of the grid, one bit per cell
+/// with the leftmost cell in the lowest bit of each byte, and each row padded
+/// to a whole number of bytes. The file is memory-mapped and filled one row at
+/// a time, so that only pages of it, rather than a second copy of the grid,
+/// are held in memory. It is written next to the path and then moved over it,
+/// so that an interrupted write doesn't destroy the previous snapshot.
+pub fn write(path: &Path, grid: &Grid, generation: u64) -> io::Result<()> {
+    let (width, height) = (grid.width(), grid.height());
+    let row_len = row_len(width);
+    let len = height
+        .checked_mul(row_len)
+        .and_then(|cells| cells.checked_add(HEADER_LEN))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the grid is too large to snapshot"))?;
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&partial)?;
+    file.set_len(len as u64)?;
+    // SAFETY: the file was just created by this process, and nothing else is
+    // expected to modify it while it is mapped.
+    let mut map = unsafe { MmapMut::map_mut(&file)? };
+
+    map[..8].copy_from_slice(MAGIC);
+    map[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    map[16..24].copy_from_slice(&(width as u64).to_le_bytes());
+    map[24..32].copy_from_slice(&(height as u64).to_le_bytes());
+    map[32..40].copy_from_slice(&generation.to_le_bytes());
+    for (y, row) in map[HEADER_LEN..].chunks_exact_mut(row_len).enumerate() {
+        row.fill(0);
+        for x in (0..width).filter(|&x| grid.get(x, y)) {
+            row[x / 8] |= 1 << (x % 8);
+        }
+    }
+    map.flush()?;
+    drop(map);
+    std::fs::rename(&partial, path)
+}
+
+/// Returns whether the file at the path starts like a snapshot, rather than
+/// being a pattern file or missing.
+pub fn is_snapshot(path: &Path) -> bool {
+    let mut magic = [0; MAGIC.len()];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == MAGIC
+}
+
+/// Read a snapshot written by [`write`], returning the grid along with the
+/// generation it was taken at. The file is memory-mapped, so that it is never
+/// read into memory all at once.
+pub fn read(path: &Path) -> io::Result<(Grid, u64)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let file = File::open(path)?;
+    // SAFETY: the snapshot is only read, and is assumed not to be modified
+    // while it is being restored.
+    let map = unsafe { Mmap::map(&file)? };
+    if map.len() < HEADER_LEN || &map[..8] != MAGIC {
+        return Err(invalid("not a snapshot"));
+    }
+    let number = |range: std::ops::Range<usize>| -> u64 {
+        u64::from_le_bytes(map[range].try_into().expect("the header fields are eight bytes long"))
+    };
+    let version = u32::from_le_bytes(map[8..12].try_into().expect("the version is four bytes long"));
+    if version != VERSION {
+        return Err(invalid(&format!("unsupported snapshot version {version}")));
+    }
+    let (width, height) = (number(16..24) as usize, number(24..32) as usize);
+    let generation = number(32..40);
+    let row_len = row_len(width);
+    if width == 0
+        || height == 0
+        || height.checked_mul(row_len).and_then(|len| len.checked_add(HEADER_LEN)) != Some(map.len())
+    {
+        return Err(invalid("the snapshot's size doesn't match its dimensions"));
+    }
+
+    let mut grid = Grid::new(width, height);
+    for (y, row) in map[HEADER_LEN..].chunks_exact(row_len).enumerate() {
+        for x in (0..width).filter(|&x| row[x / 8] & (1 << (x % 8)) != 0) {
+            grid.set(x, y, true);
+        }
+    }
+    Ok((grid, generation))
+}
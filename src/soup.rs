@@ -0,0 +1,495 @@
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::grid::Grid;
+use crate::{parse_dimensions, parse_probability};
+
+/// The width of each band of the stripes fill, in cells.
+const STRIPE_WIDTH: usize = 8;
+
+/// The distance between the points of the lattice that noise fills are
+/// interpolated from, in cells, which is roughly the size of their clusters.
+const NOISE_SCALE: usize = 8;
+
+/// The width and height of each dot of a Poisson-disc fill, in cells.
+const DOT_SIZE: usize = 3;
+
+/// The chance of each cell of a dot being alive.
+const DOT_CHANCE: f64 = 0.5;
+
+/// The fraction of the area that randomly placed disks cover once no more fit
+/// between them, which sets how far apart the dots of a Poisson-disc fill are.
+const JAMMED_COVERAGE: f64 = 0.547;
+
+/// The range of the outer radii of the rings of a ring fill, in cells.
+const RING_RADII: std::ops::RangeInclusive<usize> = 3..=12;
+
+/// The width of each ring of a ring fill, in cells.
+const RING_WIDTH: usize = 2;
+
+/// How many rings in a row may add no live cells before a ring fill stops
+/// short of its target.
+const RING_ATTEMPTS: usize = 1000;
+
+/// Generate and fill a random configuration of the grid, where about the
+/// given chance of the cells are alive, scaled by the fill's density at each
+/// cell and arranged according to the kind of fill, then mirrored or rotated
+/// about its center according to the given symmetry.
+pub fn random_configuration(
+    grid: &mut Grid,
+    chance: f64,
+    fill: Fill,
+    kind: FillKind,
+    symmetry: Symmetry,
+    rng: &mut impl Rng,
+) {
+    let (width, height) = (grid.width(), grid.height());
+    let density = |x: usize, y: usize| fill.density(x, y, width, height);
+    match kind {
+        FillKind::Bernoulli => {
+            for (index, c) in grid.cells_mut().enumerate() {
+                if rng.gen_bool(chance * density(index % width, index / width)) {
+                    *c = true;
+                }
+            }
+        },
+        FillKind::Noise => noise_fill(grid, chance, density, rng),
+        FillKind::PoissonDisc => dot_fill(grid, chance, density, rng),
+        FillKind::Rings => ring_fill(grid, chance, density, rng),
+    }
+    symmetry.apply(grid);
+}
+
+/// Bring cells to life wherever smoothly interpolated random noise is lowest,
+/// so that live cells gather in clusters and dead cells in voids.
+fn noise_fill(grid: &mut Grid, chance: f64, density: impl Fn(usize, usize) -> f64, rng: &mut impl Rng) {
+    let (width, height) = (grid.width(), grid.height());
+    let columns = width / NOISE_SCALE + 2;
+    let lattice: Vec<f64> = (0..columns * (height / NOISE_SCALE + 2)).map(|_| rng.gen()).collect();
+    let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+    let noise = |x: usize, y: usize| {
+        let (cx, cy) = (x / NOISE_SCALE, y / NOISE_SCALE);
+        let tx = smooth((x % NOISE_SCALE) as f64 / NOISE_SCALE as f64);
+        let ty = smooth((y % NOISE_SCALE) as f64 / NOISE_SCALE as f64);
+        let at = |dx: usize, dy: usize| lattice[(cy + dy) * columns + cx + dx];
+        let top = at(0, 0) + (at(1, 0) - at(0, 0)) * tx;
+        let bottom = at(0, 1) + (at(1, 1) - at(0, 1)) * tx;
+        top + (bottom - top) * ty
+    };
+
+    // Interpolated noise is rarely near its extremes, so cells are ranked by
+    // it and the lowest-ranked ones live, which keeps to the chance whatever
+    // the distribution of the noise.
+    let values: Vec<f64> = (0..width * height).map(|index| noise(index % width, index / width)).collect();
+    let cells = values.len() as f64;
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    for (rank, index) in order.into_iter().enumerate() {
+        let (x, y) = (index % width, index / width);
+        if (rank as f64 + 0.5) / cells < chance * density(x, y) {
+            grid.set(x, y, true);
+        }
+    }
+}
+
+/// Scatter small dots of random cells, each with its center kept at least a
+/// minimum distance from every other, spaced so that about the given chance of
+/// the cells are alive, up to about a fifth of them. Dots that would fall
+/// where the fill's density is low are likely to be left out.
+fn dot_fill(grid: &mut Grid, chance: f64, density: impl Fn(usize, usize) -> f64, rng: &mut impl Rng) {
+    if chance <= 0.0 {
+        return;
+    }
+    let (width, height) = (grid.width(), grid.height());
+    // Dots that are closer than their own size would run into each other.
+    let cells_per_dot = (DOT_SIZE * DOT_SIZE) as f64 * DOT_CHANCE;
+    let spacing =
+        (JAMMED_COVERAGE * 4.0 / std::f64::consts::PI * cells_per_dot / chance).sqrt().max(DOT_SIZE as f64 + 1.0);
+
+    // The centers are kept in buckets small enough to hold one each, so that
+    // only the buckets around a candidate are searched for centers too close.
+    let bucket = ((spacing / std::f64::consts::SQRT_2) as usize).max(1);
+    let (columns, rows) = (width.div_ceil(bucket), height.div_ceil(bucket));
+    let mut buckets: Vec<Option<(usize, usize)>> = vec![None; columns * rows];
+    let reach = (spacing / bucket as f64).ceil() as usize;
+    let mut candidates: Vec<usize> = (0..width * height).collect();
+    candidates.shuffle(rng);
+    for index in candidates {
+        let (x, y) = (index % width, index / width);
+        let (column, row) = (x / bucket, y / bucket);
+        let too_close = (row.saturating_sub(reach)..(row + reach + 1).min(rows))
+            .flat_map(|row| {
+                (column.saturating_sub(reach)..(column + reach + 1).min(columns)).map(move |column| (column, row))
+            })
+            .filter_map(|(column, row)| buckets[row * columns + column])
+            .any(|(cx, cy)| ((cx.abs_diff(x).pow(2) + cy.abs_diff(y).pow(2)) as f64) < spacing * spacing);
+        if too_close || buckets[row * columns + column].is_some() {
+            continue;
+        }
+        buckets[row * columns + column] = Some((x, y));
+        if !rng.gen_bool(density(x, y)) {
+            continue;
+        }
+        let (x0, y0) = (x.saturating_sub(DOT_SIZE / 2), y.saturating_sub(DOT_SIZE / 2));
+        for y in y0..(y0 + DOT_SIZE).min(height) {
+            for x in x0..(x0 + DOT_SIZE).min(width) {
+                if rng.gen_bool(DOT_CHANCE) {
+                    grid.set(x, y, true);
+                }
+            }
+        }
+    }
+}
+
+/// Draw thin rings of random sizes at random places, each with its cells
+/// alive where the fill's density allows, until about the given chance of
+/// the cells are alive.
+fn ring_fill(grid: &mut Grid, chance: f64, density: impl Fn(usize, usize) -> f64, rng: &mut impl Rng) {
+    let (width, height) = (grid.width(), grid.height());
+    let target = (chance * (width * height) as f64) as usize;
+    let mut population = grid.population();
+    // Rings that land where the density is zero or on cells that are already
+    // alive add nothing, so give up after enough of them in a row.
+    let mut fruitless = 0;
+    while population < target && fruitless < RING_ATTEMPTS {
+        let (x, y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+        let radius = rng.gen_range(RING_RADII);
+        let inner = radius - RING_WIDTH;
+        let ring: Vec<(usize, usize)> = grid
+            .disk(x, y, radius)
+            .filter(|&(cx, cy)| cx.abs_diff(x).pow(2) + cy.abs_diff(y).pow(2) >= inner * inner)
+            .collect();
+        let before = population;
+        for (cx, cy) in ring {
+            if rng.gen_bool(density(cx, cy)) && !grid.get(cx, cy) {
+                grid.set(cx, cy, true);
+                population += 1;
+            }
+        }
+        fruitless = if population > before { 0 } else { fruitless + 1 };
+    }
+}
+
+/// Fill the grid with a random soup. If a soup size is given, only a region of
+/// that size at the center of the grid is filled, otherwise the whole grid is.
+pub fn random_soup(
+    grid: &mut Grid,
+    size: Option<(usize, usize)>,
+    chance: f64,
+    fill: Fill,
+    kind: FillKind,
+    symmetry: Symmetry,
+    rng: &mut impl Rng,
+) {
+    let (width, height) = size.unwrap_or((grid.width(), grid.height()));
+    let width = width.min(grid.width());
+    let height = height.min(grid.height());
+    let mut soup = Grid::new(width, height);
+    random_configuration(&mut soup, chance, fill, kind, symmetry, rng);
+    grid.paste(&soup, (grid.width() - width) / 2, (grid.height() - height) / 2);
+}
+
+/// A grayscale picture to seed a grid from.
+#[derive(Clone)]
+pub struct Picture {
+    pub width: usize,
+    pub height: usize,
+    /// The brightness of each pixel from 0 to 1, row by row.
+    pub pixels: Vec<f64>,
+}
+
+/// Fill the grid with a picture, where cells are alive wherever it is bright.
+/// The picture is scaled to fit the region a soup of the given size would
+/// fill without stretching it, and centered in that region.
+///
+/// Each cell takes the average brightness of the pixels it covers, which is
+/// then either thresholded at one half, or dithered with Floyd–Steinberg error
+/// diffusion so that the shading of the picture survives as the density of
+/// live cells.
+pub fn picture_soup(grid: &mut Grid, size: Option<(usize, usize)>, picture: &Picture, dither: bool) {
+    let (width, height) = size.unwrap_or((grid.width(), grid.height()));
+    let (width, height) = (width.min(grid.width()), height.min(grid.height()));
+    if picture.width == 0 || picture.height == 0 {
+        return;
+    }
+    let scale = (width as f64 / picture.width as f64).min(height as f64 / picture.height as f64);
+    let width = ((picture.width as f64 * scale).round() as usize).clamp(1, width.max(1));
+    let height = ((picture.height as f64 * scale).round() as usize).clamp(1, height.max(1));
+
+    // The pixels covered by each cell, rounded so that every cell covers at
+    // least one pixel when the picture is scaled up.
+    let span = |cell: usize, cells: usize, pixels: usize| {
+        let start = cell * pixels / cells;
+        start..((cell + 1) * pixels / cells).max(start + 1)
+    };
+    let mut brightness = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let rows = span(y, height, picture.height);
+        for x in 0..width {
+            let columns = span(x, width, picture.width);
+            let count = rows.len() * columns.len();
+            let sum: f64 = rows.clone().flat_map(|row| &picture.pixels[row * picture.width..][columns.clone()]).sum();
+            brightness.push(sum / count as f64);
+        }
+    }
+
+    let mut cells = Grid::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let value = brightness[y * width + x];
+            let alive = value >= 0.5;
+            cells.set(x, y, alive);
+            if !dither {
+                continue;
+            }
+            let error = value - if alive { 1.0 } else { 0.0 };
+            let mut spread = |dx: isize, dy: usize, weight: f64| {
+                let Some(x) = x.checked_add_signed(dx).filter(|&x| x < width) else { return };
+                if y + dy < height {
+                    brightness[(y + dy) * width + x] += error * weight;
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+    grid.paste(&cells, (grid.width() - width) / 2, (grid.height() - height) / 2);
+}
+
+/// Replace a region of the grid with random cells that are each alive with the
+/// given probability. The parts of the region outside of the grid are ignored.
+pub fn random_fill(
+    grid: &mut Grid,
+    (x0, y0): (usize, usize),
+    (width, height): (usize, usize),
+    chance: f64,
+    rng: &mut impl Rng,
+) {
+    for y in y0..y0.saturating_add(height).min(grid.height()) {
+        for x in x0..x0.saturating_add(width).min(grid.width()) {
+            grid.set(x, y, rng.gen_bool(chance));
+        }
+    }
+}
+
+/// Flip the state of each cell in the grid with the given probability.
+pub fn apply_noise(grid: &mut Grid, chance: f64, rng: &mut impl Rng) {
+    for c in grid.cells_mut() {
+        if rng.gen_bool(chance) {
+            *c = !*c;
+        }
+    }
+}
+
+/// Settings for periodically sprinkling a random patch of live cells into the
+/// grid, so that it never settles.
+#[derive(Clone)]
+pub struct Injection {
+    /// Number of generations between injections.
+    pub every: u64,
+    /// Chance that each cell in the injected region becomes alive.
+    density: f64,
+    /// Width of the injected region, in cells.
+    width: usize,
+    /// Height of the injected region, in cells.
+    height: usize,
+}
+
+impl Injection {
+    /// Fill a randomly placed region of the grid with random live cells.
+    pub fn apply(&self, grid: &mut Grid, rng: &mut impl Rng) {
+        let width = self.width.min(grid.width());
+        let height = self.height.min(grid.height());
+        let x0 = rng.gen_range(0..=grid.width() - width);
+        let y0 = rng.gen_range(0..=grid.height() - height);
+        for y in y0..y0 + height {
+            for x in x0..x0 + width {
+                if rng.gen_bool(self.density) {
+                    grid.set(x, y, true);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Injection {
+    fn default() -> Self {
+        Self { every: 200, density: 0.25, width: 16, height: 16 }
+    }
+}
+
+impl FromStr for Injection {
+    type Err = String;
+
+    /// Parse an injection from a comma-separated list of `key=value` pairs,
+    /// e.g. `every=200,density=0.05,region=64x64`. Omitted keys keep their
+    /// default values.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut injection = Self::default();
+        for pair in s.split(',').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| format!("expected `key=value`, got `{pair}`"))?;
+            match key {
+                "every" => {
+                    injection.every =
+                        value.parse().map_err(|_| format!("`{value}` is not a valid generation count"))?;
+                    if injection.every == 0 {
+                        return Err("`every` must be at least 1".to_string());
+                    }
+                },
+                "density" => injection.density = parse_probability(value)?,
+                "region" => {
+                    let (width, height) = parse_dimensions(value)?;
+                    injection.width = width;
+                    injection.height = height;
+                },
+                _ => return Err(format!("unknown injection setting `{key}`")),
+            }
+        }
+        Ok(injection)
+    }
+}
+
+/// How the chance of a cell starting alive varies across a random soup.
+#[derive(ValueEnum, strum::Display, Clone, Copy, Default)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Fill {
+    /// Every cell has the same chance.
+    #[default]
+    Uniform,
+    /// The chance rises from nothing at the left edge to the full chance at
+    /// the right edge.
+    Gradient,
+    /// The chance is full at the center and falls off to nothing at the
+    /// edges.
+    CenterBlob,
+    /// Vertical bands of cells with the full chance alternate with empty
+    /// ones.
+    Stripes,
+}
+
+impl Fill {
+    /// Returns how much of the chance applies to the cell at the given
+    /// coordinates of a grid of the given size, between 0 and 1.
+    fn density(self, x: usize, y: usize, width: usize, height: usize) -> f64 {
+        match self {
+            Self::Uniform => 1.0,
+            Self::Gradient => (x as f64 + 0.5) / width as f64,
+            Self::CenterBlob => {
+                // The distance from the center, relative to the ellipse that
+                // touches the edges.
+                let dx = (x as f64 + 0.5) / width as f64 * 2.0 - 1.0;
+                let dy = (y as f64 + 0.5) / height as f64 * 2.0 - 1.0;
+                (1.0 - (dx * dx + dy * dy)).max(0.0)
+            },
+            Self::Stripes => {
+                if (x / STRIPE_WIDTH).is_multiple_of(2) {
+                    1.0
+                } else {
+                    0.0
+                }
+            },
+        }
+    }
+}
+
+/// How the live cells of a random soup are arranged.
+#[derive(ValueEnum, strum::Display, Clone, Copy, Default)]
+#[strum(serialize_all = "kebab-case")]
+pub enum FillKind {
+    /// Each cell is alive or dead independently of the others.
+    #[default]
+    Bernoulli,
+    /// Cells are alive wherever smooth random noise is lowest, so that they
+    /// gather in clusters with voids between them.
+    Noise,
+    /// Small dots of random cells, spread evenly with a minimum distance
+    /// between them.
+    PoissonDisc,
+    /// Thin rings of random sizes, scattered at random.
+    Rings,
+}
+
+/// A linear transformation of grid coordinates relative to the center of the
+/// grid, stored as a 2x2 matrix.
+type Transform = [[i64; 2]; 2];
+
+#[derive(ValueEnum, strum::Display, Clone, Copy)]
+#[strum(serialize_all = "lowercase")]
+pub enum Symmetry {
+    /// No symmetry is applied.
+    None,
+    /// The left and right halves mirror each other.
+    Horizontal,
+    /// The top and bottom halves mirror each other.
+    Vertical,
+    /// Invariant under a half turn about the center.
+    C2,
+    /// Invariant under quarter turns about the center.
+    C4,
+    /// Invariant under quarter turns and reflections about the center.
+    D8,
+}
+
+impl Symmetry {
+    /// Returns the transformations that make up this symmetry group.
+    fn transforms(self) -> &'static [Transform] {
+        const IDENTITY: Transform = [[1, 0], [0, 1]];
+        const FLIP_X: Transform = [[-1, 0], [0, 1]];
+        const FLIP_Y: Transform = [[1, 0], [0, -1]];
+        const ROTATE_90: Transform = [[0, -1], [1, 0]];
+        const ROTATE_180: Transform = [[-1, 0], [0, -1]];
+        const ROTATE_270: Transform = [[0, 1], [-1, 0]];
+        const TRANSPOSE: Transform = [[0, 1], [1, 0]];
+        const ANTI_TRANSPOSE: Transform = [[0, -1], [-1, 0]];
+
+        match self {
+            Self::None => &[IDENTITY],
+            Self::Horizontal => &[IDENTITY, FLIP_X],
+            Self::Vertical => &[IDENTITY, FLIP_Y],
+            Self::C2 => &[IDENTITY, ROTATE_180],
+            Self::C4 => &[IDENTITY, ROTATE_90, ROTATE_180, ROTATE_270],
+            Self::D8 => &[IDENTITY, ROTATE_90, ROTATE_180, ROTATE_270, FLIP_X, FLIP_Y, TRANSPOSE, ANTI_TRANSPOSE],
+        }
+    }
+
+    /// Make the grid symmetric by copying the state of a single representative
+    /// cell to every other cell in its orbit.
+    ///
+    /// Rotations of a non-square grid move some cells out of bounds; those
+    /// images are ignored.
+    fn apply(self, grid: &mut Grid) {
+        if matches!(self, Self::None) {
+            return;
+        }
+
+        let grid_width = grid.width() as i64;
+        let grid_height = grid.height() as i64;
+        let source = grid.clone();
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                // Work in doubled coordinates so that the center of the grid is
+                // always at an integer position.
+                let dx = 2 * x as i64 - (grid_width - 1);
+                let dy = 2 * y as i64 - (grid_height - 1);
+                let representative = self
+                    .transforms()
+                    .iter()
+                    .filter_map(|[[a, b], [c, d]]| {
+                        let tx = (a * dx + b * dy + grid_width - 1) / 2;
+                        let ty = (c * dx + d * dy + grid_height - 1) / 2;
+                        let in_bounds = (0..grid_width).contains(&tx) && (0..grid_height).contains(&ty);
+                        let aligned = (a * dx + b * dy + grid_width - 1) % 2 == 0;
+                        (in_bounds && aligned).then_some((ty as usize, tx as usize))
+                    })
+                    .min()
+                    .expect("the identity transform is always in bounds");
+                grid.set(x, y, source.get(representative.1, representative.0));
+            }
+        }
+    }
+}
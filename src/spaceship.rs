@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::grid::Grid;
+use crate::pattern::Pattern;
+use crate::rule::LifeLike;
+
+/// Cells of different objects are at least this many cells apart, so that
+/// closer cells are considered part of the same object.
+const SEPARATION: usize = 2;
+
+/// Groups of cells with more than this many cells can't be a known spaceship,
+/// so they aren't compared against them.
+const MAX_SPACESHIP_CELLS: usize = 32;
+
+/// A kind of common spaceship in Conway's Game of Life.
+#[derive(Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumString, strum::IntoStaticStr)]
+#[strum(ascii_case_insensitive)]
+pub enum Kind {
+    #[strum(serialize = "glider")]
+    Glider,
+    #[strum(serialize = "LWSS")]
+    Lwss,
+    #[strum(serialize = "MWSS")]
+    Mwss,
+    #[strum(serialize = "HWSS")]
+    Hwss,
+}
+
+impl Kind {
+    /// Returns one phase of the spaceship moving right, or down and to the
+    /// right for the glider.
+    pub fn pattern(self) -> Pattern {
+        Pattern::parse_plaintext(self.plaintext()).expect("known spaceships are valid patterns")
+    }
+
+    /// Returns one phase of the spaceship moving right (or down and to the
+    /// right, for the glider) in plaintext format.
+    fn plaintext(self) -> &'static str {
+        match self {
+            Self::Glider => ".O\n..O\nOOO",
+            Self::Lwss => ".O..O\nO\nO...O\nOOOO",
+            Self::Mwss => "...O\n.O...O\nO\nO....O\nOOOOO",
+            Self::Hwss => "...OO\n.O....O\nO\nO.....O\nOOOOOO",
+        }
+    }
+}
+
+/// A spaceship found in a grid.
+#[derive(Clone, Copy)]
+pub struct Spaceship {
+    pub kind: Kind,
+    /// The coordinates of the top-left corner of the spaceship's bounding box.
+    pub x: usize,
+    pub y: usize,
+    /// The size of the spaceship's bounding box, in cells.
+    pub width: usize,
+    pub height: usize,
+    /// How many cells the spaceship moves along each axis every `period`
+    /// generations.
+    pub velocity: (isize, isize),
+    pub period: u64,
+}
+
+/// The kind, velocity, and period of the spaceship that each phase in every
+/// orientation of a known spaceship is, keyed by the coordinates of its live
+/// cells relative to the top-left corner of its bounding box.
+type Library = HashMap<Vec<(usize, usize)>, (Kind, (isize, isize), u64)>;
+
+/// Find the known spaceships among the groups of live cells in a grid, which
+/// evolves according to Conway's Game of Life.
+pub fn find_spaceships(grid: &Grid) -> Vec<Spaceship> {
+    let library = library();
+    let mut visited = vec![false; grid.width() * grid.height()];
+    let mut spaceships = Vec::new();
+    for start in 0..visited.len() {
+        let (x, y) = (start % grid.width(), start / grid.width());
+        if visited[start] || !grid.get(x, y) {
+            continue;
+        }
+        let cells = group(grid, (x, y), &mut visited);
+        if cells.len() > MAX_SPACESHIP_CELLS {
+            continue;
+        }
+        let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        let mut shape: Vec<(usize, usize)> = cells.iter().map(|&(x, y)| (x - min_x, y - min_y)).collect();
+        shape.sort_unstable();
+        if let Some(&(kind, velocity, period)) = library.get(&shape) {
+            let width = shape.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+            let height = shape.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+            spaceships.push(Spaceship { kind, x: min_x, y: min_y, width, height, velocity, period });
+        }
+    }
+    spaceships
+}
+
+/// Returns the live cells that are connected to the given cell through live
+/// cells that are no more than `SEPARATION` cells apart, marking them as
+/// visited.
+pub(crate) fn group(grid: &Grid, start: (usize, usize), visited: &mut [bool]) -> Vec<(usize, usize)> {
+    let width = grid.width();
+    let mut cells = Vec::new();
+    let mut pending = vec![start];
+    visited[start.1 * width + start.0] = true;
+    while let Some((x, y)) = pending.pop() {
+        cells.push((x, y));
+        for ny in y.saturating_sub(SEPARATION)..(y + SEPARATION + 1).min(grid.height()) {
+            for nx in x.saturating_sub(SEPARATION)..(x + SEPARATION + 1).min(width) {
+                if !visited[ny * width + nx] && grid.get(nx, ny) {
+                    visited[ny * width + nx] = true;
+                    pending.push((nx, ny));
+                }
+            }
+        }
+    }
+    cells
+}
+
+/// Returns the library of known spaceships, building it the first time.
+fn library() -> &'static Library {
+    static LIBRARY: OnceLock<Library> = OnceLock::new();
+    LIBRARY.get_or_init(build_library)
+}
+
+/// Evolve every orientation of each known spaceship through a full period,
+/// recording each phase along with how far the spaceship moves.
+fn build_library() -> Library {
+    const PERIOD: u64 = 4;
+    const MARGIN: usize = 8;
+
+    let rule: LifeLike = "B3/S23".parse().expect("Conway's Game of Life has a valid rulestring");
+    let mut library = Library::new();
+    for kind in [Kind::Glider, Kind::Lwss, Kind::Mwss, Kind::Hwss] {
+        let rotations =
+            std::iter::successors(Some(kind.pattern()), |pattern| Some(pattern.rotated_clockwise())).take(4);
+        for orientation in rotations.flat_map(|pattern| [pattern.flipped_horizontally(), pattern]) {
+            let (width, height) = (orientation.cells.width() + 2 * MARGIN, orientation.cells.height() + 2 * MARGIN);
+            let mut grid = orientation.cells.resized(width, height, MARGIN, MARGIN);
+            let mut phases = Vec::new();
+            for _ in 0..PERIOD {
+                phases.push(grid.clone());
+                grid = grid.step(&rule);
+            }
+            let (Some((start, _)), Some((end, _))) = (phases[0].bounds(), grid.bounds()) else { continue };
+            let velocity = (end.0 as isize - start.0 as isize, end.1 as isize - start.1 as isize);
+            for phase in phases {
+                let Some(((x0, y0), (w, h))) = phase.bounds() else { continue };
+                let mut shape: Vec<(usize, usize)> = (0..h)
+                    .flat_map(|y| (0..w).map(move |x| (x, y)))
+                    .filter(|&(x, y)| phase.get(x0 + x, y0 + y))
+                    .collect();
+                shape.sort_unstable();
+                library.insert(shape, (kind, velocity, PERIOD));
+            }
+        }
+    }
+    library
+}
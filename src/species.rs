@@ -0,0 +1,138 @@
+use cgol2::grid::Grid;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::render::Rgb;
+
+/// The name and color of each species, in order. There can be at most this
+/// many species.
+pub const SPECIES: [(&str, Rgb); 8] = [
+    ("red", Rgb(0xE6, 0x39, 0x46)),
+    ("blue", Rgb(0x45, 0x7B, 0xE6)),
+    ("green", Rgb(0x2E, 0xC4, 0x5B)),
+    ("yellow", Rgb(0xF4, 0xC4, 0x30)),
+    ("purple", Rgb(0xB0, 0x5C, 0xE6)),
+    ("cyan", Rgb(0x3C, 0xD4, 0xD4)),
+    ("orange", Rgb(0xF2, 0x8C, 0x28)),
+    ("pink", Rgb(0xE6, 0x6A, 0xB8)),
+];
+
+/// Rival colonies of cells, where every live cell belongs to one species.
+///
+/// Cells are only born when all of their parents are of the same species,
+/// which the newborn joins. A surviving cell joins whichever species holds the
+/// majority of it and its neighbors, keeping its own on a tie, so that
+/// colonies take over each other's cells where they meet.
+pub struct Species {
+    /// The species of each cell, as an index into [`SPECIES`], in the same
+    /// order as the cells of the grid. Dead cells have none.
+    pub species: Vec<Option<u8>>,
+    count: usize,
+}
+
+impl Species {
+    /// Split the live cells of the grid into colonies of the given number of
+    /// species, around randomly placed centers, with each cell joining the
+    /// species of the nearest one.
+    pub fn new(grid: &Grid, count: usize, seed: u64) -> Self {
+        let count = count.clamp(1, SPECIES.len());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let centers: Vec<(f64, f64)> = (0..count)
+            .map(|_| (rng.gen_range(0.0..grid.width() as f64), rng.gen_range(0.0..grid.height() as f64)))
+            .collect();
+        let width = grid.width();
+        let species = (0..width * grid.height())
+            .map(|index| {
+                let (x, y) = ((index % width) as f64 + 0.5, (index / width) as f64 + 0.5);
+                grid.get(index % width, index / width).then(|| {
+                    let distance = |&(cx, cy): &(f64, f64)| (cx - x).powi(2) + (cy - y).powi(2);
+                    let nearest = (0..count).min_by(|&a, &b| distance(&centers[a]).total_cmp(&distance(&centers[b])));
+                    nearest.unwrap_or(0) as u8
+                })
+            })
+            .collect();
+        Self { species, count }
+    }
+
+    /// Returns the number of species.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the species of the cell at the given coordinates of a grid that
+    /// is `width` cells wide, if it is alive.
+    pub fn species(&self, x: usize, y: usize, width: usize) -> Option<u8> {
+        self.species[y * width + x]
+    }
+
+    /// Returns the number of live cells of each species.
+    pub fn populations(&self) -> Vec<usize> {
+        let mut populations = vec![0; self.count];
+        for &species in self.species.iter().flatten() {
+            populations[species as usize] += 1;
+        }
+        populations
+    }
+
+    /// Give every live cell of the grid that doesn't have a species, such as
+    /// cells that were painted or flipped by noise, the most common species
+    /// among its neighbors, and forget the species of dead cells.
+    pub fn adopt(&mut self, grid: &Grid) {
+        let width = grid.width();
+        for index in 0..self.species.len() {
+            let (x, y) = (index % width, index / width);
+            if !grid.get(x, y) {
+                self.species[index] = None;
+            } else if self.species[index].is_none() {
+                let votes = self.votes(grid, x, y);
+                let majority = (0..self.count).max_by_key(|&species| (votes[species], usize::MAX - species));
+                self.species[index] = Some(majority.unwrap_or(0) as u8);
+            }
+        }
+    }
+
+    /// Resolve the step from one generation to the next between the species,
+    /// killing cells that the rule gave birth to from parents of different
+    /// species, and updating the species of the rest.
+    pub fn contest(&mut self, before: &Grid, after: &mut Grid) {
+        let width = after.width();
+        let mut species = vec![None; self.species.len()];
+        for (index, cell) in species.iter_mut().enumerate() {
+            let (x, y) = (index % width, index / width);
+            if !after.get(x, y) {
+                continue;
+            }
+            let votes = self.votes(before, x, y);
+            let mut parents = (0..self.count).filter(|&species| votes[species] > 0);
+            match self.species[index].filter(|_| before.get(x, y)) {
+                Some(own) => {
+                    let own = own as usize;
+                    let majority = (0..self.count)
+                        .max_by_key(|&species| (votes[species] + (species == own) as usize, species == own));
+                    *cell = majority.map(|species| species as u8);
+                },
+                None => match (parents.next(), parents.next()) {
+                    (Some(parent), None) => *cell = Some(parent as u8),
+                    (Some(_), Some(_)) => after.set(x, y, false),
+                    // Cells born without live neighbors join a species once
+                    // they are adopted.
+                    (None, _) => {},
+                },
+            }
+        }
+        self.species = species;
+        self.adopt(after);
+    }
+
+    /// Returns how many of the live neighbors of a cell belong to each
+    /// species.
+    fn votes(&self, grid: &Grid, x: usize, y: usize) -> [usize; SPECIES.len()] {
+        let mut votes = [0; SPECIES.len()];
+        for (x, y) in grid.neighbor_coords(x as i32, y as i32).into_iter().flatten() {
+            if let Some(species) = self.species[y * grid.width() + x].filter(|_| grid.get(x, y)) {
+                votes[species as usize] += 1;
+            }
+        }
+        votes
+    }
+}
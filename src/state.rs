@@ -0,0 +1,90 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+
+use crate::run::RunArgs;
+use crate::ColorMode;
+
+/// The settings of the last run in the window, which the next one starts with
+/// wherever it isn't given flags for them.
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    pub window: Option<WindowState>,
+    pub color_mode: Option<ColorMode>,
+    pub frames_per_second: Option<u32>,
+    pub steps_per_frame: Option<u64>,
+    /// The rule of the first board, if it is a registered rule or a
+    /// rulestring.
+    pub rule: Option<String>,
+}
+
+/// The size and position of the window, in physical pixels.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct WindowState {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl State {
+    /// Returns the path of the file the state is saved in, `state.toml` in a
+    /// directory of its own under `$XDG_STATE_HOME`, `%LOCALAPPDATA%`, or
+    /// `~/.local/state`, if any of them can be found.
+    pub fn path() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_STATE_HOME")
+            .or_else(|| std::env::var_os("LOCALAPPDATA"))
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state")))?;
+        Some(dir.join(env!("CARGO_BIN_NAME")).join("state.toml"))
+    }
+
+    /// Read the state saved by the last run. The state is only a convenience,
+    /// so a file that is missing or can't be read is treated as empty.
+    pub fn load(path: &Path) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                log::warn!("Ignoring the settings saved in {}: {err}", path.display());
+                return Self::default();
+            },
+        };
+        toml::from_str(&text).unwrap_or_else(|err| {
+            log::warn!("Ignoring the settings saved in {}: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    /// Write the state to the given path, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string(self).map_err(io::Error::other)?)
+    }
+
+    /// Apply the saved settings that weren't given on the command line to the
+    /// arguments of a run. The window's size and position are taken from the
+    /// state when the window is created instead.
+    pub fn restore(&self, args: &mut RunArgs, matches: &ArgMatches) {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        if let Some(color_mode) = self.color_mode.clone().filter(|_| !given("color_mode")) {
+            args.color_mode = color_mode;
+        }
+        if let Some(steps_per_frame) =
+            self.steps_per_frame.filter(|_| !given("steps_per_frame") && !given("target_fps"))
+        {
+            args.steps_per_frame = steps_per_frame;
+        }
+        if let Some(frames_per_second) = self.frames_per_second.filter(|_| !given("fps") && !given("target_fps")) {
+            args.fps = Some(frames_per_second);
+        }
+        if let Some(rule) = self.rule.clone().filter(|_| !["rule", "rule_file", "kernel"].iter().any(|id| given(id))) {
+            args.simulation.rule = rule;
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// Statistics about a single tick of the simulation.
+#[derive(Serialize)]
+pub struct TickStats {
+    pub generation: u64,
+    pub population: usize,
+    /// The number of cells that were dead before the tick and alive after.
+    pub births: usize,
+    /// The number of cells that were alive before the tick and dead after.
+    pub deaths: usize,
+    /// How long it took to compute the generation, in milliseconds.
+    pub tick_duration_ms: f64,
+    /// How much of the tick went to counting neighbors to compute the next
+    /// grid, rather than to replacing the grid and updating what is tracked
+    /// about its cells.
+    #[serde(skip)]
+    pub step_duration: Duration,
+}
+
+impl TickStats {
+    pub fn new(
+        generation: u64,
+        population: usize,
+        births: usize,
+        deaths: usize,
+        tick_duration: Duration,
+        step_duration: Duration,
+    ) -> Self {
+        let tick_duration_ms = tick_duration.as_secs_f64() * 1000.0;
+        Self { generation, population, births, deaths, tick_duration_ms, step_duration }
+    }
+}
+
+/// Appends the statistics of every tick to a CSV file, one row per tick.
+pub struct CsvLog {
+    /// Where the log is written, for error messages.
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl CsvLog {
+    /// The first row of a new log, naming each column.
+    const HEADER: &'static str = "board,generation,population,births,deaths,tick_duration_ms";
+
+    /// Open the log at the given path for appending, creating it with a header
+    /// row if it is new or empty.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let error = |source| Error::WriteFile { path: path.to_path_buf(), source };
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(error)?;
+        let empty = file.metadata().map_err(error)?.len() == 0;
+        let mut log = Self { path: path.to_path_buf(), writer: BufWriter::new(file) };
+        if empty {
+            writeln!(log.writer, "{}", Self::HEADER).map_err(|err| log.error(err))?;
+        }
+        Ok(log)
+    }
+
+    /// Append a row with the statistics of a tick on the given board.
+    pub fn write(&mut self, board: usize, stats: &TickStats) -> Result<(), Error> {
+        let TickStats { generation, population, births, deaths, tick_duration_ms, .. } = stats;
+        writeln!(self.writer, "{board},{generation},{population},{births},{deaths},{tick_duration_ms:.3}")
+            .map_err(|err| self.error(err))
+    }
+
+    /// Write out anything that is still buffered.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush().map_err(|err| self.error(err))
+    }
+
+    fn error(&self, source: io::Error) -> Error {
+        Error::WriteFile { path: self.path.clone(), source }
+    }
+}
+
+/// Statistics about a whole run of a universe, accumulated as it ticks.
+pub struct RunSummary {
+    started: Instant,
+    peak_population: usize,
+    /// The first generation at which the peak population was reached.
+    peak_generation: u64,
+}
+
+impl RunSummary {
+    /// Start a run from an initial configuration with the given population.
+    pub fn new(population: usize) -> Self {
+        Self { started: Instant::now(), peak_population: population, peak_generation: 0 }
+    }
+
+    /// Add the statistics of a tick to the run, returning whether its
+    /// population was the highest so far.
+    pub fn record(&mut self, stats: &TickStats) -> bool {
+        let peak = stats.population > self.peak_population;
+        if peak {
+            self.peak_population = stats.population;
+            self.peak_generation = stats.generation;
+        }
+        peak
+    }
+
+    /// Returns a human-readable report of the run, given its last generation,
+    /// final population, and the period it settled into, if any.
+    pub fn report(&self, generation: u64, population: usize, period: Option<usize>) -> String {
+        let period = match period {
+            Some(period) if population > 0 => period.to_string(),
+            _ => "none".to_string(),
+        };
+        format!(
+            "Generations: {generation}\nPeak population: {} at generation {}\nFinal population: {population}\nPeriod: \
+             {period}\nWall-clock time: {:.2?}",
+            self.peak_population,
+            self.peak_generation,
+            self.started.elapsed(),
+        )
+    }
+}
+
+/// The statistics of a tick along with the board it happened on.
+#[derive(Serialize)]
+struct BoardStats<'a> {
+    board: usize,
+    #[serde(flatten)]
+    stats: &'a TickStats,
+}
+
+/// Writes the statistics of every tick as newline-delimited JSON.
+pub struct StatsStream {
+    /// Where the stream is written, for error messages.
+    path: PathBuf,
+    writer: Box<dyn Write>,
+}
+
+impl StatsStream {
+    /// Create a stream that writes to the file at the given path, truncating
+    /// it, or to stdout if the path is `-`.
+    pub fn create(path: &Path) -> Result<Self, Error> {
+        let writer: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stdout())
+        } else {
+            let file = File::create(path).map_err(|err| Error::WriteFile { path: path.to_path_buf(), source: err })?;
+            Box::new(BufWriter::new(file))
+        };
+        Ok(Self { path: path.to_path_buf(), writer })
+    }
+
+    /// Write the statistics of a tick on the given board as a single line.
+    pub fn write(&mut self, board: usize, stats: &TickStats) -> Result<(), Error> {
+        serde_json::to_writer(&mut self.writer, &BoardStats { board, stats })
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(self.writer))
+            .map_err(|err| self.error(err))
+    }
+
+    /// Write out anything that is still buffered.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush().map_err(|err| self.error(err))
+    }
+
+    fn error(&self, source: io::Error) -> Error {
+        Error::WriteFile { path: self.path.clone(), source }
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+use cgol2::parse_dimensions;
+use clap::builder::RangedU64ValueParser;
+use clap::{Args, ValueEnum};
+
+use crate::config::SimulationArgs;
+use crate::error::Error;
+use crate::image::{self, Frame, Style};
+use crate::render::{CellShape, Rgb};
+use crate::simulation::Simulation;
+use crate::theme::StyleArgs;
+use crate::{simulation_rule, ColorMode, PIXELS_PER_CELL, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// The most colors a Sixel image is drawn with. Frames with more colors than
+/// this are drawn with a fixed palette instead.
+const SIXEL_COLORS: usize = 256;
+
+/// The number of levels of each component in the fixed palette that frames
+/// with too many colors are drawn with.
+const SIXEL_CUBE_LEVELS: u16 = 6;
+
+/// The longest chunk of image data that a Kitty graphics command can carry.
+const KITTY_CHUNK_LEN: usize = 4096;
+
+/// The image ID that every frame is sent as to terminals speaking the Kitty
+/// protocol, so that each one replaces the last.
+const KITTY_IMAGE_ID: u32 = 1;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Args)]
+pub struct TerminalArgs {
+    #[command(flatten)]
+    simulation: SimulationArgs,
+
+    /// Size of the universe in cells, given as `WIDTHxHEIGHT`. Defaults to the
+    /// size of the grid in the window.
+    #[arg(long, value_parser = parse_dimensions)]
+    universe: Option<(usize, usize)>,
+
+    /// Graphics protocol to draw frames with. Defaults to Kitty's in
+    /// terminals known to speak it, and Sixel everywhere else.
+    #[arg(long)]
+    protocol: Option<Protocol>,
+
+    /// Number of frames to draw per second.
+    #[arg(long, default_value_t = 10, value_parser = RangedU64ValueParser::<u32>::new().range(1..))]
+    fps: u32,
+
+    /// Number of generations to advance per frame.
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    steps_per_frame: u64,
+
+    #[arg(long, default_value_t = ColorMode::Monochrome)]
+    color_mode: ColorMode,
+
+    /// Shape to draw each live cell as.
+    #[arg(long, default_value_t = CellShape::Square)]
+    cell_shape: CellShape,
+
+    #[command(flatten)]
+    style: StyleArgs,
+
+    /// Number of pixels along the height and width of each cell.
+    #[arg(long, default_value_t = 8, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    cell_size: usize,
+}
+
+/// A way of drawing images inside a terminal.
+#[derive(ValueEnum, strum::Display, Clone, Copy)]
+#[strum(serialize_all = "lowercase")]
+pub enum Protocol {
+    /// Sixel graphics, which are drawn with a palette of up to 256 colors, and
+    /// are understood by xterm, foot, mlterm, WezTerm, and many others.
+    Sixel,
+    /// The Kitty graphics protocol, which sends each frame as a PNG image, and
+    /// is understood by kitty, WezTerm, Ghostty, and Konsole.
+    Kitty,
+}
+
+impl Protocol {
+    /// Guess the protocol the terminal speaks from the environment, since
+    /// asking it would mean reading its reply from standard input.
+    fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let kitty = std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || var("TERM").contains("kitty")
+            || var("TERM").contains("ghostty")
+            || matches!(var("TERM_PROGRAM").as_str(), "WezTerm" | "ghostty");
+        if kitty {
+            Self::Kitty
+        } else {
+            Self::Sixel
+        }
+    }
+
+    /// Write a frame as an image at the cursor.
+    fn write(self, out: &mut impl Write, frame: &Frame) -> io::Result<()> {
+        match self {
+            Self::Sixel => write_sixel(out, frame),
+            Self::Kitty => write_kitty(out, frame),
+        }
+    }
+}
+
+/// Run a universe inside the terminal until it ends, drawing each frame the
+/// same way images are drawn, as graphics the terminal understands, so that
+/// it can be watched over SSH. Each frame is drawn over the last one from the
+/// top-left corner of the screen.
+pub fn run(args: TerminalArgs) -> Result<(), Error> {
+    let config = &args.simulation;
+    let universe = args.universe.unwrap_or((WINDOW_WIDTH / PIXELS_PER_CELL, WINDOW_HEIGHT / PIXELS_PER_CELL));
+    let seed = config.seed.unwrap_or_else(rand::random);
+    log::info!("Using seed {seed}");
+    let (rule, rule_name) = simulation_rule(config)?;
+    let mut simulation = Simulation::new(config, universe, rule, rule_name, config.noise)?;
+    if let ColorMode::Lineage = args.color_mode {
+        simulation.track_lineage(seed);
+    }
+    simulation.reseed(seed, &config.soup()?)?;
+
+    let theme = args.style.theme_source().load()?;
+    let style =
+        Style { theme: &theme, color_mode: &args.color_mode, cell_shape: args.cell_shape, cell_size: args.cell_size };
+    let protocol = args.protocol.unwrap_or_else(Protocol::detect);
+    log::info!("Drawing with the {protocol} protocol");
+    let frame_duration = Duration::from_secs_f64(1.0 / args.fps as f64);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    out.write_all(b"\x1b[2J").map_err(Error::Terminal)?;
+    let end = loop {
+        let frame_start = Instant::now();
+        let grid = &simulation.grid;
+        let cycle = simulation.cycle_color();
+        let frame = image::draw(grid, ((0, 0), (grid.width(), grid.height())), &style, |x, y| {
+            simulation.cell_color(x, y, &theme, &args.color_mode, cycle)
+        });
+        out.write_all(b"\x1b[H")
+            .and_then(|_| protocol.write(&mut out, &frame))
+            .and_then(|_| out.flush())
+            .map_err(Error::Terminal)?;
+
+        if let Some(end) = simulation.end(config.max_generations, true) {
+            break end;
+        }
+        for _ in 0..args.steps_per_frame {
+            simulation.tick()?;
+            if simulation.needs_expansion() {
+                simulation.expand();
+            }
+            if simulation.end(config.max_generations, true).is_some() {
+                break;
+            }
+        }
+        std::thread::sleep(frame_duration.saturating_sub(frame_start.elapsed()));
+    };
+    drop(out);
+    // The cursor is left on the last row of the image.
+    println!(
+        "\nSeed {seed} {end} at generation {} with {} live cells",
+        simulation.generation,
+        simulation.grid.population()
+    );
+    Ok(())
+}
+
+/// Write a frame as a Sixel image.
+///
+/// Sixel images are drawn in bands six pixels tall. Each band is drawn once
+/// for every color in it, as a run of characters that each give which of the
+/// six pixels of a column are that color.
+fn write_sixel(out: &mut impl Write, frame: &Frame) -> io::Result<()> {
+    let (width, height) = (frame.width, frame.height);
+    let (palette, indices) = quantize(frame);
+    write!(out, "\x1bPq\"1;1;{width};{height}")?;
+    let percent = |component: u8| (component as u32 * 100 + 127) / 255;
+    for (index, &Rgb(r, g, b)) in palette.iter().enumerate() {
+        write!(out, "#{index};2;{};{};{}", percent(r), percent(g), percent(b))?;
+    }
+
+    for top in (0..height).step_by(6) {
+        let rows = top..(top + 6).min(height);
+        let mut present = [false; SIXEL_COLORS];
+        for y in rows.clone() {
+            for &index in &indices[y * width..(y + 1) * width] {
+                present[index as usize] = true;
+            }
+        }
+        let colors = (0..SIXEL_COLORS).filter(|&index| present[index]);
+        for (n, color) in colors.enumerate() {
+            // Going back to the start of the band draws the next color over
+            // the same pixels.
+            if n > 0 {
+                out.write_all(b"$")?;
+            }
+            write!(out, "#{color}")?;
+            let mut run = (0, 0);
+            for x in 0..width {
+                let bits = rows
+                    .clone()
+                    .enumerate()
+                    .filter(|&(_, y)| indices[y * width + x] as usize == color)
+                    .fold(0, |bits, (bit, _)| bits | 1 << bit);
+                let sixel = b'?' + bits;
+                if sixel == run.0 {
+                    run.1 += 1;
+                } else {
+                    write_sixel_run(out, run)?;
+                    run = (sixel, 1);
+                }
+            }
+            write_sixel_run(out, run)?;
+        }
+        out.write_all(b"-")?;
+    }
+    out.write_all(b"\x1b\\")
+}
+
+/// Write a character repeated some number of times, with Sixel's repeat
+/// introducer once it is shorter.
+fn write_sixel_run(out: &mut impl Write, (sixel, count): (u8, usize)) -> io::Result<()> {
+    match count {
+        0..=3 => out.write_all(&vec![sixel; count]),
+        _ => write!(out, "!{count}{}", sixel as char),
+    }
+}
+
+/// Returns the palette of a frame, and the index into it of the color of each
+/// pixel. Frames with more colors than fit in a Sixel palette, as when cells
+/// are colored by lineage, are drawn with evenly spaced levels of each
+/// component instead.
+fn quantize(frame: &Frame) -> (Vec<Rgb>, Vec<u8>) {
+    let pixels = || frame.pixels.chunks_exact(4).map(|pixel| (pixel[0], pixel[1], pixel[2]));
+    let mut palette: HashMap<(u8, u8, u8), u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(frame.width * frame.height);
+    for color in pixels() {
+        let next = palette.len();
+        if next == SIXEL_COLORS && !palette.contains_key(&color) {
+            break;
+        }
+        indices.push(*palette.entry(color).or_insert(next as u8));
+    }
+    if indices.len() == frame.width * frame.height {
+        let mut colors = vec![Rgb(0, 0, 0); palette.len()];
+        for ((r, g, b), index) in palette {
+            colors[index as usize] = Rgb(r, g, b);
+        }
+        return (colors, indices);
+    }
+
+    let levels = SIXEL_CUBE_LEVELS;
+    let level = |component: u8| (component as u16 * (levels - 1) + 127) / 255;
+    let value = |level: u16| (level * 255 / (levels - 1)) as u8;
+    let colors = (0..levels.pow(3))
+        .map(|index| Rgb(value(index / levels / levels), value(index / levels % levels), value(index % levels)));
+    let indices = pixels().map(|(r, g, b)| ((level(r) * levels + level(g)) * levels + level(b)) as u8).collect();
+    (colors.collect(), indices)
+}
+
+/// Write a frame as a PNG image with the Kitty graphics protocol. The image
+/// is base64-encoded and sent in chunks, and replaces the previous frame.
+fn write_kitty(out: &mut impl Write, frame: &Frame) -> io::Result<()> {
+    let mut png = Vec::new();
+    image::encode_png(&mut png, frame).map_err(io::Error::other)?;
+    let data = base64(&png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_LEN).collect();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        if index == 0 {
+            write!(out, "\x1b_Ga=T,f=100,i={KITTY_IMAGE_ID},q=2,m={more};")?;
+        } else {
+            write!(out, "\x1b_Gm={more};")?;
+        }
+        out.write_all(chunk)?;
+        out.write_all(b"\x1b\\")?;
+    }
+    Ok(())
+}
+
+/// Encode bytes in base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| group | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            encoded.push(match i <= chunk.len() {
+                true => BASE64_ALPHABET[(group >> (18 - 6 * i)) as usize & 63] as char,
+                false => '=',
+            });
+        }
+    }
+    encoded
+}
@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use clap::Args;
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::render::Rgb;
+use crate::species::SPECIES;
+
+/// The names of the built-in themes.
+const BUILTIN_THEMES: [&str; 5] = ["classic", "solarized", "neon", "deuteranopia", "protanopia"];
+
+/// The contrast ratio that high-contrast mode raises every color drawn over
+/// the background to, which is the WCAG's enhanced level for text.
+const HIGH_CONTRAST_RATIO: f64 = 7.0;
+
+/// The contrast ratio that high-contrast mode raises the lines between cells
+/// and boards to, which is the WCAG's level for graphics. They stay dimmer
+/// than cells, so that they don't compete with them.
+const HIGH_CONTRAST_LINE_RATIO: f64 = 3.0;
+
+/// The thickness of grid lines in high-contrast mode, in pixels.
+const HIGH_CONTRAST_GRID_WIDTH: usize = 2;
+
+/// The colors everything is drawn with.
+///
+/// Theme files are TOML documents with any of the keys below, where colors are
+/// hex strings like `"#33ff66"`. Keys that are left out are taken from the
+/// classic theme.
+///
+/// ```toml
+/// background = "#002b36"
+/// alive = "#93a1a1"
+///
+/// species = ["#e69f00", "#56b4e9", "#009e73"]
+///
+/// [ramps]
+/// age = ["#2aa198", "#268bd2", "#d33682"]
+/// ```
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// The color of dead cells, which is also the background.
+    pub background: Rgb,
+    /// The color of live cells in monochrome mode.
+    pub alive: Rgb,
+    /// The color of the lines between cells, if they are drawn.
+    pub grid: Option<Rgb>,
+    /// The thickness of the lines between cells, in pixels.
+    pub grid_width: usize,
+    /// The color of the lines separating boards.
+    pub divider: Rgb,
+    /// The color of the text of panels drawn over the boards, like the HUD.
+    pub hud_text: Rgb,
+    /// The color behind the text of panels drawn over the boards.
+    pub hud_background: Rgb,
+    /// The color of dead cells that have been alive before, when showing
+    /// history.
+    pub history: Rgb,
+    /// The color of the outlines and arrows marking spaceships.
+    pub spaceship: Rgb,
+    /// The color of cells that are alive on only one of two compared boards.
+    pub diverged: Rgb,
+    /// The color of the rectangle marking the visible part of the universe
+    /// on the minimap.
+    pub viewport: Rgb,
+    /// The color of the outline of the selected region.
+    pub selection: Rgb,
+    /// The color that newborn cells are tinted with in the delta view.
+    pub birth: Rgb,
+    /// The color of cells that just died in the delta view.
+    pub death: Rgb,
+    /// The color of walls.
+    pub wall: Rgb,
+    /// The colors of the first and second of two overlaid universes. Cells
+    /// alive in both are drawn in their channels added together, or
+    /// multiplied on a light background.
+    pub layers: [Rgb; 2],
+    /// The colors of rival species, in order, repeating if there are more
+    /// species than colors.
+    pub species: Vec<Rgb>,
+    /// Named color ramps that color modes sample from, e.g. `age`.
+    pub ramps: HashMap<String, Ramp>,
+    /// Whether colors that aren't known until they are drawn, like those of
+    /// the random and lineage modes, are raised to a high contrast with the
+    /// background too.
+    #[serde(skip)]
+    pub high_contrast: bool,
+}
+
+impl Theme {
+    /// White cells on a black background.
+    pub fn classic() -> Self {
+        Self {
+            background: Rgb(0x00, 0x00, 0x00),
+            alive: Rgb(0xFF, 0xFF, 0xFF),
+            grid: None,
+            grid_width: 1,
+            divider: Rgb(0x40, 0x40, 0x40),
+            hud_text: Rgb(0xFF, 0xFF, 0xFF),
+            hud_background: Rgb(0x00, 0x00, 0x00),
+            history: Rgb(0x1A, 0x2A, 0x4A),
+            spaceship: Rgb(0x66, 0xFF, 0x99),
+            diverged: Rgb(0xFF, 0x40, 0x40),
+            viewport: Rgb(0xFF, 0xE0, 0x66),
+            selection: Rgb(0x42, 0xA5, 0xF5),
+            birth: Rgb(0x4C, 0xE0, 0x6A),
+            death: Rgb(0xE0, 0x4A, 0x4A),
+            wall: Rgb(0x80, 0x80, 0x80),
+            layers: [Rgb(0xFF, 0x20, 0x20), Rgb(0x20, 0x40, 0xFF)],
+            species: default_species(),
+            ramps: HashMap::from([(
+                "age".to_string(),
+                Ramp(vec![Rgb(0xFF, 0xFF, 0xFF), Rgb(0xFF, 0xE0, 0x66), Rgb(0xFF, 0x8C, 0x42), Rgb(0xD7, 0x26, 0x3D)]),
+            )]),
+            high_contrast: false,
+        }
+    }
+
+    /// The dark variant of the Solarized palette.
+    pub fn solarized() -> Self {
+        Self {
+            background: Rgb(0x00, 0x2B, 0x36),
+            alive: Rgb(0x93, 0xA1, 0xA1),
+            grid: None,
+            grid_width: 1,
+            divider: Rgb(0x58, 0x6E, 0x75),
+            hud_text: Rgb(0x93, 0xA1, 0xA1),
+            hud_background: Rgb(0x00, 0x2B, 0x36),
+            history: Rgb(0x07, 0x36, 0x42),
+            spaceship: Rgb(0x85, 0x99, 0x00),
+            diverged: Rgb(0xDC, 0x32, 0x2F),
+            viewport: Rgb(0xB5, 0x89, 0x00),
+            selection: Rgb(0x26, 0x8B, 0xD2),
+            birth: Rgb(0x2A, 0xA1, 0x98),
+            death: Rgb(0xCB, 0x4B, 0x16),
+            wall: Rgb(0x65, 0x7B, 0x83),
+            layers: [Rgb(0xDC, 0x32, 0x2F), Rgb(0x26, 0x8B, 0xD2)],
+            species: default_species(),
+            ramps: HashMap::from([(
+                "age".to_string(),
+                Ramp(vec![Rgb(0x2A, 0xA1, 0x98), Rgb(0x26, 0x8B, 0xD2), Rgb(0x6C, 0x71, 0xC4), Rgb(0xD3, 0x36, 0x82)]),
+            )]),
+            high_contrast: false,
+        }
+    }
+
+    /// Bright colors on a deep purple background.
+    pub fn neon() -> Self {
+        Self {
+            background: Rgb(0x0D, 0x02, 0x21),
+            alive: Rgb(0x39, 0xFF, 0x14),
+            grid: None,
+            grid_width: 1,
+            divider: Rgb(0x2D, 0x1B, 0x69),
+            hud_text: Rgb(0x39, 0xFF, 0x14),
+            hud_background: Rgb(0x0D, 0x02, 0x21),
+            history: Rgb(0x24, 0x0E, 0x4A),
+            spaceship: Rgb(0xFE, 0x53, 0xBB),
+            diverged: Rgb(0xFF, 0x2A, 0x6D),
+            viewport: Rgb(0x05, 0xD9, 0xE8),
+            selection: Rgb(0xF9, 0xF0, 0x02),
+            birth: Rgb(0x05, 0xD9, 0xE8),
+            death: Rgb(0xFF, 0x2A, 0x6D),
+            wall: Rgb(0x7B, 0x61, 0xFF),
+            layers: [Rgb(0xFF, 0x2A, 0x6D), Rgb(0x3A, 0x2A, 0xFF)],
+            species: default_species(),
+            ramps: HashMap::from([(
+                "age".to_string(),
+                Ramp(vec![Rgb(0x05, 0xD9, 0xE8), Rgb(0x39, 0xFF, 0x14), Rgb(0xF9, 0xF0, 0x02), Rgb(0xFF, 0x2A, 0x6D)]),
+            )]),
+            high_contrast: false,
+        }
+    }
+
+    /// Colors told apart by deuteranopia, the most common color blindness, on
+    /// a dark background, from the Okabe-Ito palette. Nothing is told apart
+    /// by red and green alone.
+    pub fn deuteranopia() -> Self {
+        Self {
+            background: Rgb(0x10, 0x14, 0x1C),
+            alive: Rgb(0xF0, 0xE4, 0x42),
+            grid: None,
+            grid_width: 1,
+            divider: Rgb(0x3A, 0x40, 0x4C),
+            hud_text: Rgb(0xF0, 0xE4, 0x42),
+            hud_background: Rgb(0x10, 0x14, 0x1C),
+            history: Rgb(0x1C, 0x2A, 0x3C),
+            spaceship: Rgb(0x56, 0xB4, 0xE9),
+            diverged: Rgb(0xE6, 0x9F, 0x00),
+            viewport: Rgb(0xFF, 0xFF, 0xFF),
+            selection: Rgb(0x00, 0x72, 0xB2),
+            birth: Rgb(0x56, 0xB4, 0xE9),
+            death: Rgb(0xE6, 0x9F, 0x00),
+            wall: Rgb(0x80, 0x80, 0x80),
+            layers: [Rgb(0xE6, 0x9F, 0x00), Rgb(0x00, 0x72, 0xB2)],
+            species: vec![
+                Rgb(0xE6, 0x9F, 0x00),
+                Rgb(0x56, 0xB4, 0xE9),
+                Rgb(0x00, 0x9E, 0x73),
+                Rgb(0xF0, 0xE4, 0x42),
+                Rgb(0x00, 0x72, 0xB2),
+                Rgb(0xD5, 0x5E, 0x00),
+                Rgb(0xCC, 0x79, 0xA7),
+                Rgb(0xFF, 0xFF, 0xFF),
+            ],
+            ramps: HashMap::from([(
+                "age".to_string(),
+                Ramp(vec![Rgb(0xF0, 0xE4, 0x42), Rgb(0xE6, 0x9F, 0x00), Rgb(0x56, 0xB4, 0xE9), Rgb(0x00, 0x72, 0xB2)]),
+            )]),
+            high_contrast: false,
+        }
+    }
+
+    /// Colors told apart by protanopia on a dark background, from Paul Tol's
+    /// palettes. Reds look dark to protanopes, so nothing stands out by being
+    /// red, and the ramps run from light to dark through blue instead.
+    pub fn protanopia() -> Self {
+        Self {
+            background: Rgb(0x0B, 0x10, 0x20),
+            alive: Rgb(0xF5, 0xF5, 0xF5),
+            grid: None,
+            grid_width: 1,
+            divider: Rgb(0x3A, 0x42, 0x5A),
+            hud_text: Rgb(0xF5, 0xF5, 0xF5),
+            hud_background: Rgb(0x0B, 0x10, 0x20),
+            history: Rgb(0x1E, 0x25, 0x40),
+            spaceship: Rgb(0x44, 0xAA, 0x99),
+            diverged: Rgb(0xEE, 0xCC, 0x66),
+            viewport: Rgb(0xEE, 0xCC, 0x66),
+            selection: Rgb(0x33, 0x77, 0xBB),
+            birth: Rgb(0x33, 0xBB, 0xEE),
+            death: Rgb(0xEE, 0x77, 0x33),
+            wall: Rgb(0x88, 0x88, 0x88),
+            layers: [Rgb(0xCC, 0xBB, 0x44), Rgb(0x44, 0x77, 0xAA)],
+            species: vec![
+                Rgb(0x44, 0x77, 0xAA),
+                Rgb(0xCC, 0xBB, 0x44),
+                Rgb(0x66, 0xCC, 0xEE),
+                Rgb(0xEE, 0x77, 0x33),
+                Rgb(0x22, 0x88, 0x33),
+                Rgb(0xBB, 0xBB, 0xBB),
+                Rgb(0xAA, 0x33, 0x77),
+                Rgb(0xFF, 0xFF, 0xFF),
+            ],
+            ramps: HashMap::from([(
+                "age".to_string(),
+                Ramp(vec![Rgb(0xF5, 0xF5, 0xF5), Rgb(0x33, 0xBB, 0xEE), Rgb(0x00, 0x77, 0xBB), Rgb(0xEE, 0x77, 0x33)]),
+            )]),
+            high_contrast: false,
+        }
+    }
+
+    /// Look up a built-in theme by name, or load a theme file from the given
+    /// path. The built-in themes are those in [`BUILTIN_THEMES`].
+    pub fn resolve(name: &str) -> Result<Self, Error> {
+        match name {
+            "classic" => Ok(Self::classic()),
+            "solarized" => Ok(Self::solarized()),
+            "neon" => Ok(Self::neon()),
+            "deuteranopia" => Ok(Self::deuteranopia()),
+            "protanopia" => Ok(Self::protanopia()),
+            path => Self::load(Path::new(path)),
+        }
+    }
+
+    /// Load a theme file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| Error::ReadFile { path: path.to_path_buf(), source: err })?;
+        toml::from_str(&text).map_err(|err| Error::Theme { path: path.to_path_buf(), source: err })
+    }
+
+    /// Swap the colors of live and dead cells, e.g. for dark cells on a light
+    /// background.
+    pub fn invert(&mut self) {
+        std::mem::swap(&mut self.alive, &mut self.background);
+    }
+
+    /// Push the background to black or white, whichever it is closer to, and
+    /// lighten or darken every color drawn over it until it clearly stands
+    /// out, with thicker grid lines. The colors of dead cells that have been
+    /// alive before are left faint, so that they don't look alive.
+    pub fn enhance_contrast(&mut self) {
+        self.background = if luminance(self.background) < 0.18 { Rgb(0x00, 0x00, 0x00) } else { Rgb(0xFF, 0xFF, 0xFF) };
+        let background = self.background;
+        let colors = [
+            &mut self.alive,
+            &mut self.spaceship,
+            &mut self.diverged,
+            &mut self.viewport,
+            &mut self.selection,
+            &mut self.birth,
+            &mut self.death,
+            &mut self.wall,
+        ];
+        let ramps = self.ramps.values_mut().flat_map(|ramp| ramp.0.iter_mut());
+        for color in colors.into_iter().chain(&mut self.layers).chain(&mut self.species).chain(ramps) {
+            *color = contrasting(*color, background, HIGH_CONTRAST_RATIO);
+        }
+        self.hud_text = contrasting(self.hud_text, self.hud_background, HIGH_CONTRAST_RATIO);
+        for line in [&mut self.divider].into_iter().chain(self.grid.as_mut()) {
+            *line = contrasting(*line, background, HIGH_CONTRAST_LINE_RATIO);
+        }
+        self.grid_width = self.grid_width.max(HIGH_CONTRAST_GRID_WIDTH);
+        self.high_contrast = true;
+    }
+
+    /// Returns a color that is only known when it is drawn, raised to a high
+    /// contrast with the background in high-contrast mode.
+    pub fn legible(&self, color: Rgb) -> Rgb {
+        if self.high_contrast {
+            contrasting(color, self.background, HIGH_CONTRAST_RATIO)
+        } else {
+            color
+        }
+    }
+
+    /// Returns the color of a cell of two overlaid universes, given whether it
+    /// is alive in the first and in the second, or `None` if it is dead in
+    /// both.
+    pub fn overlaid(&self, first: bool, second: bool) -> Option<Rgb> {
+        let [a, b] = self.layers;
+        match (first, second) {
+            (true, true) if luminance(self.background) < 0.5 => {
+                Some(Rgb(a.0.saturating_add(b.0), a.1.saturating_add(b.1), a.2.saturating_add(b.2)))
+            },
+            (true, true) => {
+                let multiply = |a: u8, b: u8| (a as u16 * b as u16 / 255) as u8;
+                Some(Rgb(multiply(a.0, b.0), multiply(a.1, b.1), multiply(a.2, b.2)))
+            },
+            (true, false) => Some(a),
+            (false, true) => Some(b),
+            (false, false) => None,
+        }
+    }
+
+    /// Returns the color of the species with the given index.
+    pub fn species_color(&self, species: usize) -> Rgb {
+        match self.species.len() {
+            0 => self.alive,
+            len => self.species[species % len],
+        }
+    }
+
+    /// Returns the color ramp with the given name, if the theme has one.
+    pub fn ramp(&self, name: &str) -> Option<&Ramp> {
+        self.ramps.get(name)
+    }
+}
+
+/// Returns the colors that the classic themes draw species in.
+fn default_species() -> Vec<Rgb> {
+    SPECIES.iter().map(|&(_, color)| color).collect()
+}
+
+/// Returns the relative luminance of a color, from 0 for black to 1 for
+/// white, as the WCAG defines it.
+fn luminance(color: Rgb) -> f64 {
+    let linear = color.to_linear();
+    0.2126 * linear.r + 0.7152 * linear.g + 0.0722 * linear.b
+}
+
+/// Returns the WCAG contrast ratio between two colors, from 1 for the same
+/// luminance to 21 for black and white.
+fn contrast(a: Rgb, b: Rgb) -> f64 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Returns the color blended towards white or black, away from the
+/// background, just far enough to reach the given contrast ratio with it.
+fn contrasting(color: Rgb, background: Rgb, ratio: f64) -> Rgb {
+    if contrast(color, background) >= ratio {
+        return color;
+    }
+    let away = if luminance(background) < 0.18 { Rgb(0xFF, 0xFF, 0xFF) } else { Rgb(0x00, 0x00, 0x00) };
+    // Bisect for the smallest blend that is far enough.
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..12 {
+        let middle = (low + high) / 2.0;
+        if contrast(color.blend(away, middle), background) >= ratio {
+            high = middle;
+        } else {
+            low = middle;
+        }
+    }
+    color.blend(away, high)
+}
+
+/// The theme and colors to draw with, shared by every subcommand that draws
+/// universes.
+#[derive(Args, Clone)]
+pub struct StyleArgs {
+    /// Built-in theme to draw with (`classic`, `solarized`, `neon`, or the
+    /// colorblind-safe `deuteranopia` and `protanopia`), or the path to a TOML
+    /// theme file.
+    #[arg(long, default_value = "classic")]
+    pub theme: String,
+
+    /// Color of live cells in monochrome mode, as a hex color like `#33ff66`.
+    /// Overrides the theme.
+    #[arg(long)]
+    pub alive_color: Option<Rgb>,
+
+    /// Color of dead cells and the background. Overrides the theme.
+    #[arg(long)]
+    pub dead_color: Option<Rgb>,
+
+    /// Draw lines between cells in this color. Overrides the theme.
+    #[arg(long)]
+    pub grid_color: Option<Rgb>,
+
+    /// Swap the colors of live and dead cells after applying the other colors,
+    /// e.g. for black cells on a white background when printing or
+    /// projecting.
+    #[arg(long)]
+    pub invert: bool,
+
+    /// Push the background to black or white and raise every color drawn over
+    /// it, in every color mode, to a high contrast with it, with thicker grid
+    /// lines.
+    #[arg(long)]
+    pub high_contrast: bool,
+}
+
+impl StyleArgs {
+    /// Returns the theme with the colors given to override it.
+    pub fn theme_source(&self) -> ThemeSource {
+        ThemeSource {
+            name: self.theme.clone(),
+            alive: self.alive_color,
+            background: self.dead_color,
+            grid: self.grid_color,
+            invert: self.invert,
+            high_contrast: self.high_contrast,
+        }
+    }
+}
+
+/// A theme along with the colors given to override it, so that it can be
+/// loaded again when its file changes.
+#[derive(Clone)]
+pub struct ThemeSource {
+    /// The name of a built-in theme, or the path to a theme file.
+    pub name: String,
+    pub alive: Option<Rgb>,
+    pub background: Option<Rgb>,
+    pub grid: Option<Rgb>,
+    /// Whether the colors of live and dead cells are swapped after the others
+    /// are applied.
+    pub invert: bool,
+    /// Whether every color is raised to a high contrast with the background,
+    /// after the colors are swapped.
+    pub high_contrast: bool,
+}
+
+impl ThemeSource {
+    /// Load the theme and apply the colors over it.
+    pub fn load(&self) -> Result<Theme, Error> {
+        let mut theme = Theme::resolve(&self.name)?;
+        theme.alive = self.alive.unwrap_or(theme.alive);
+        theme.background = self.background.unwrap_or(theme.background);
+        theme.grid = self.grid.or(theme.grid);
+        if self.invert {
+            theme.invert();
+        }
+        if self.high_contrast {
+            theme.enhance_contrast();
+        }
+        Ok(theme)
+    }
+
+    /// Returns the path of the theme file, unless the theme is built in.
+    pub fn path(&self) -> Option<&Path> {
+        (!BUILTIN_THEMES.contains(&self.name.as_str())).then(|| Path::new(&self.name))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+/// A sequence of colors that values between 0 and 1 are mapped onto, by
+/// blending between neighboring colors.
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "Vec<Rgb>")]
+pub struct Ramp(Vec<Rgb>);
+
+impl Ramp {
+    /// Returns the color at the given position along the ramp, where 0 is the
+    /// first color and 1 is the last.
+    pub fn sample(&self, t: f64) -> Rgb {
+        let position = t.clamp(0.0, 1.0) * (self.0.len() - 1) as f64;
+        let index = (position.floor() as usize).min(self.0.len() - 1);
+        let (from, to) = (self.0[index], self.0[(index + 1).min(self.0.len() - 1)]);
+        let blend = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * position.fract()).round() as u8;
+        Rgb(blend(from.0, to.0), blend(from.1, to.1), blend(from.2, to.2))
+    }
+}
+
+impl TryFrom<Vec<Rgb>> for Ramp {
+    type Error = String;
+
+    fn try_from(colors: Vec<Rgb>) -> Result<Self, Self::Error> {
+        if colors.is_empty() {
+            return Err("a color ramp needs at least one color".to_string());
+        }
+        Ok(Self(colors))
+    }
+}
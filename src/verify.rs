@@ -0,0 +1,51 @@
+use cgol2::engine::{Engine, REFERENCES};
+use cgol2::library;
+use clap::{Args, ValueEnum};
+
+use crate::error::Error;
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Comma-separated engines to verify. Defaults to every available engine.
+    #[arg(long, value_delimiter = ',')]
+    engines: Vec<Engine>,
+
+    /// Evolve every bundled pattern for this many generations, and check that
+    /// the engines agree with each other rather than with the known-good
+    /// digests of the reference runs.
+    #[arg(long)]
+    generations: Option<u64>,
+}
+
+/// Run the reference runs on every requested engine and print whether each
+/// one arrived at the known-good digest. Returns whether they all did.
+pub fn run(args: VerifyArgs) -> Result<bool, Error> {
+    let engines = if args.engines.is_empty() { Engine::value_variants().to_vec() } else { args.engines.clone() };
+    // Without known-good digests, the first engine's digests are expected of
+    // the others.
+    let runs: Vec<(&str, u64, Option<u64>)> = match args.generations {
+        Some(generations) => library::PATTERNS.iter().map(|entry| (entry.name, generations, None)).collect(),
+        None => REFERENCES
+            .iter()
+            .map(|reference| (reference.pattern, reference.generations, Some(reference.digest)))
+            .collect(),
+    };
+
+    let mut failures = 0;
+    println!("{:<20} {:>11} {:<8} {:<16}  result", "pattern", "generations", "engine", "digest");
+    for (name, generations, known) in runs {
+        let pattern = library::find(name).expect("reference runs are of bundled patterns").pattern();
+        let mut expected = known;
+        for &engine in &engines {
+            let digest = engine.run_reference(&pattern, generations);
+            let expected = *expected.get_or_insert(digest);
+            let result = if digest == expected { "ok" } else { "MISMATCH" };
+            failures += (digest != expected) as usize;
+            println!("{name:<20} {generations:>11} {:<8} {digest:016x}  {result}", engine.to_string());
+        }
+    }
+    if failures > 0 {
+        println!("{failures} runs didn't match");
+    }
+    Ok(failures == 0)
+}
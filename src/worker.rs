@@ -0,0 +1,65 @@
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::Arc;
+use std::thread;
+
+use cgol2::grid::Grid;
+use cgol2::rule::Rule;
+
+/// A grid whose next generation is to be computed in the background.
+struct Job {
+    grid: Arc<Grid>,
+    rule: Arc<dyn Rule>,
+    next: SyncSender<Grid>,
+}
+
+/// A thread that computes the next generation of grids in the background, so
+/// that it can happen while the current generation is being drawn.
+pub struct Worker {
+    jobs: Sender<Job>,
+}
+
+impl Worker {
+    /// Start the thread. It runs until the worker is dropped.
+    pub fn spawn() -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            for job in queue {
+                // The generation is no longer wanted if the pending one was
+                // dropped.
+                let _ = job.next.send(job.grid.step(job.rule.as_ref()));
+            }
+        });
+        Self { jobs }
+    }
+
+    /// Start computing the next generation of the grid based on the given
+    /// rule.
+    pub fn step(&self, grid: Grid, rule: Arc<dyn Rule>) -> Pending {
+        let grid = Arc::new(grid);
+        let (next, receiver) = mpsc::sync_channel(1);
+        let job = Job { grid: Arc::clone(&grid), rule: Arc::clone(&rule), next };
+        // If the thread has died, the generation is computed when it's needed
+        // instead.
+        let _ = self.jobs.send(job);
+        Pending { grid, rule, next: receiver }
+    }
+}
+
+/// The next generation of a grid, which a worker is computing.
+pub struct Pending {
+    grid: Arc<Grid>,
+    rule: Arc<dyn Rule>,
+    next: Receiver<Grid>,
+}
+
+impl Pending {
+    /// Wait for the next generation to be computed, if it is that of the given
+    /// grid based on the given rule. Returns `None` if the grid was edited or
+    /// the rule was changed since it was started.
+    pub fn finish(self, grid: &Grid, rule: &Arc<dyn Rule>) -> Option<Grid> {
+        if !Arc::ptr_eq(&self.rule, rule) || *self.grid != *grid {
+            return None;
+        }
+        self.next.recv().ok()
+    }
+}
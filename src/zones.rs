@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use cgol2::grid::Grid;
+use cgol2::rule::Rule;
+use cgol2::soup::Picture;
+
+use crate::error::Error;
+use crate::image::read_picture;
+
+/// How a universe is divided into zones that follow different rules.
+#[derive(Clone)]
+pub enum Layout {
+    /// Side by side strips of equal width, from left to right.
+    Vertical,
+    /// Stacked strips of equal height, from top to bottom.
+    Horizontal,
+    /// A PNG image stretched over the universe, where darker pixels are in
+    /// earlier zones and brighter ones in later zones.
+    Mask(PathBuf),
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    /// Parse `vertical`, `horizontal`, or the path to a mask image.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vertical" => Ok(Self::Vertical),
+            "horizontal" => Ok(Self::Horizontal),
+            "" => Err("expected `vertical`, `horizontal`, or the path to a PNG mask".to_string()),
+            path => Ok(Self::Mask(PathBuf::from(path))),
+        }
+    }
+}
+
+/// A layout with any mask read from its file.
+enum Shape {
+    Vertical,
+    Horizontal,
+    Mask(Picture),
+}
+
+/// Zones of a universe that each follow their own rule. The first zone
+/// follows the universe's rule, so that it changes along with it, and the
+/// others follow rules of their own.
+pub struct Zones {
+    /// The rules of the zones after the first.
+    rules: Vec<Arc<dyn Rule>>,
+    shape: Shape,
+    /// The zone of each cell, in the same order as the cells of the grid.
+    zones: Vec<u8>,
+}
+
+impl Zones {
+    /// Lay out zones over a universe of the given size, with one zone for
+    /// each of the given rules after that of the universe, reading the mask
+    /// if there is one.
+    pub fn new(layout: &Layout, rules: Vec<Arc<dyn Rule>>, size: (usize, usize)) -> Result<Self, Error> {
+        let shape = match layout {
+            Layout::Vertical => Shape::Vertical,
+            Layout::Horizontal => Shape::Horizontal,
+            Layout::Mask(path) => {
+                Shape::Mask(read_picture(path).map_err(|err| Error::ReadImage { path: path.clone(), source: err })?)
+            },
+        };
+        let mut zones = Self { rules, shape, zones: Vec::new() };
+        zones.resize(size);
+        Ok(zones)
+    }
+
+    /// Lay the zones out again over a universe of the given size.
+    pub fn resize(&mut self, (width, height): (usize, usize)) {
+        let count = self.rules.len() + 1;
+        self.zones = (0..width * height)
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                let zone = match &self.shape {
+                    Shape::Vertical => x * count / width,
+                    Shape::Horizontal => y * count / height,
+                    Shape::Mask(mask) => {
+                        let (px, py) = (x * mask.width / width, y * mask.height / height);
+                        (mask.pixels[py * mask.width + px] * count as f64) as usize
+                    },
+                };
+                zone.min(count - 1) as u8
+            })
+            .collect();
+    }
+
+    /// Compute the next generation of the grid, with the first zone following
+    /// the given rule.
+    pub fn step(&self, grid: &Grid, rule: &dyn Rule) -> Grid {
+        let rules: Vec<&dyn Rule> = std::iter::once(rule).chain(self.rules.iter().map(|rule| rule.as_ref())).collect();
+        grid.step_zoned(&rules, &self.zones)
+    }
+}